@@ -0,0 +1,5 @@
+fn main() {
+    // The gRPC API's generated code (see src/grpc.rs)
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/agentkit.proto").expect("failed to compile proto/agentkit.proto");
+}
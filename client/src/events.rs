@@ -0,0 +1,125 @@
+use serde::Deserialize;
+
+use crate::types::{Channel, ErrorCode, MessageKind};
+
+// Events from the server's WebSocket (`/ws?global=true`). Only the payloads
+// about proofs and verifications are typed; everything else, chat included,
+// arrives as `Event::Other` with the envelope's text in `content` and the
+// message as sent in `raw`.
+
+// {"version": 1, "channel": "event", "type": "message", "content": "...", "data": {"type": "proof_start", ...}, "seq": 7}
+#[derive(Deserialize, Clone, Debug)]
+pub struct Envelope {
+    pub version: u32,
+    pub channel: Channel,
+    #[serde(rename = "type")]
+    pub kind: MessageKind,
+    pub content: String,
+    #[serde(default)]
+    pub data: Option<Event>,
+    #[serde(default)]
+    pub seq: Option<u64>,
+    // The message as received
+    #[serde(skip)]
+    pub raw: String,
+}
+
+impl Envelope {
+    pub(crate) fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        let mut envelope: Envelope = serde_json::from_str(text)?;
+        envelope.raw = text.to_string();
+        Ok(envelope)
+    }
+
+    // Proof the event refers to, if any
+    pub fn proof_id(&self) -> Option<&str> {
+        self.data.as_ref().and_then(Event::proof_id)
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    ProofStart {
+        proof_id: String,
+        function: String,
+        arguments: Vec<String>,
+        wasm_file: String,
+        step_size: u64,
+    },
+    ProofProgress {
+        proof_id: String,
+        stage: String,
+        // zkEngine's step count while proving, once it reports one
+        #[serde(default)]
+        step: Option<u64>,
+        #[serde(default)]
+        total: Option<u64>,
+    },
+    ProofComplete {
+        proof_id: String,
+        function: String,
+        arguments: Vec<String>,
+        step_size: u64,
+        time: f64,
+        size: f64,
+        hash: String,
+        // An earlier identical proof was reused rather than generated
+        #[serde(default)]
+        cached: bool,
+    },
+    ProofFailed {
+        proof_id: String,
+        error: String,
+        #[serde(default)]
+        code: ErrorCode,
+    },
+    ProofCancelled {
+        proof_id: String,
+    },
+    VerificationStart {
+        proof_id: String,
+    },
+    VerificationComplete {
+        #[serde(default)]
+        verification_id: Option<String>,
+        proof_id: String,
+        is_valid: bool,
+        #[serde(default)]
+        verification_time_secs: Option<f64>,
+        error: Option<String>,
+        #[serde(default)]
+        error_code: Option<ErrorCode>,
+        #[serde(default)]
+        cached: bool,
+    },
+    Error {
+        error: String,
+        #[serde(default)]
+        code: ErrorCode,
+    },
+    // The connection fell behind; if `complete` is false some events were
+    // lost and the client should refetch what it tracks
+    Lagged {
+        dropped: u64,
+        resync_from: u64,
+        complete: bool,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl Event {
+    pub fn proof_id(&self) -> Option<&str> {
+        match self {
+            Event::ProofStart { proof_id, .. }
+            | Event::ProofProgress { proof_id, .. }
+            | Event::ProofComplete { proof_id, .. }
+            | Event::ProofFailed { proof_id, .. }
+            | Event::ProofCancelled { proof_id }
+            | Event::VerificationStart { proof_id }
+            | Event::VerificationComplete { proof_id, .. } => Some(proof_id),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use flate2::read::GzDecoder;
+use futures_util::{Stream, StreamExt};
+use reqwest::{RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{fmt, io::Read, pin::Pin};
+use tokio_tungstenite::{connect_async, tungstenite};
+
+pub mod events;
+pub mod types;
+
+pub use events::{Envelope, Event};
+pub use types::{ApiError, ErrorCode, GenerateProofRequest, Proof, ProofStatus, Verification};
+
+// Client for a zkp-agentkit server's REST API and event stream, for Rust
+// agents that want to request proofs without speaking HTTP themselves:
+//
+//   let client = Client::new("http://localhost:8001").with_token(token);
+//   let proof = client.prove(&GenerateProofRequest::new("fib.wat", [10])).await?;
+//   let verification = client.verify(&proof.id).await?;
+//
+// Errors the server reports keep its error code (`Error::Api`), so callers
+// can branch on e.g. `ErrorCode::RateLimited`.
+
+#[derive(Debug)]
+pub enum Error {
+    // The server answered with an error
+    Api(ApiError),
+    Http(reqwest::Error),
+    // Boxed, as it is several times larger than the others
+    WebSocket(Box<tungstenite::Error>),
+    // The server's answer wasn't what this client expects
+    Decode(serde_json::Error),
+    InvalidUrl(String),
+    // The event stream ended
+    Closed,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Api(error) => write!(f, "{}", error.message),
+            Error::Http(e) => write!(f, "HTTP error: {}", e),
+            Error::WebSocket(e) => write!(f, "WebSocket error: {}", e),
+            Error::Decode(e) => write!(f, "Unexpected response: {}", e),
+            Error::InvalidUrl(url) => write!(f, "Invalid server URL {}", url),
+            Error::Closed => f.write_str("The server closed the event stream"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    // The server's error code, for errors the server reported
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::Api(error) => Some(error.code),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(e: tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(e))
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Decode(e)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+pub type EventStream = Pin<Box<dyn Stream<Item = Result<Envelope>> + Send>>;
+
+#[derive(Clone)]
+pub struct Client {
+    server: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(server: impl Into<String>) -> Self {
+        Client {
+            server: server.into().trim_end_matches('/').to_string(),
+            token: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    // API key or JWT, sent with every call
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Value> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request.send().await?;
+        let status = response.status();
+        let body: Value = match response.json().await {
+            Ok(body) => body,
+            Err(_) if !status.is_success() => Value::Null,
+            Err(e) => return Err(e.into()),
+        };
+        // Errors carry `"success": false` and their code's HTTP status
+        if body["success"] == json!(false) || !status.is_success() {
+            return Err(Error::Api(ApiError {
+                code: serde_json::from_value(body["code"].clone()).unwrap_or_default(),
+                message: body["error"].as_str()
+                    .or(status.canonical_reason())
+                    .unwrap_or("Request failed")
+                    .to_string(),
+            }));
+        }
+        Ok(body)
+    }
+
+    async fn get(&self, path: &str) -> Result<Value> {
+        self.send(self.http.get(format!("{}{}", self.server, path))).await
+    }
+
+    async fn post(&self, path: &str, body: &impl serde::Serialize) -> Result<Value> {
+        self.send(self.http.post(format!("{}{}", self.server, path)).json(body)).await
+    }
+
+    fn field<T: DeserializeOwned>(mut body: Value, field: &str) -> Result<T> {
+        Ok(serde_json::from_value(body[field].take())?)
+    }
+
+    // Start a proof; answers with its ID once it is queued
+    pub async fn generate_proof(&self, request: &GenerateProofRequest) -> Result<String> {
+        Self::field(self.post("/api/proofs/generate", request).await?, "proof_id")
+    }
+
+    pub async fn proof(&self, proof_id: &str) -> Result<Proof> {
+        Self::field(self.get(&format!("/api/proofs/{}", proof_id)).await?, "proof")
+    }
+
+    // Proofs carrying every one of `tags`, in no particular order
+    pub async fn list_proofs(&self, tags: &[&str]) -> Result<Vec<Proof>> {
+        let mut request = self.http.get(format!("{}/api/proofs", self.server));
+        if !tags.is_empty() {
+            request = request.query(&[("tag", tags.join(","))]);
+        }
+        Self::field(self.send(request).await?, "proofs")
+    }
+
+    // Verify a completed proof and wait for the result; an invalid proof is
+    // a successful call with `is_valid` false
+    pub async fn verify(&self, proof_id: &str) -> Result<Verification> {
+        Self::field(self.post(&format!("/api/proofs/{}/verify", proof_id), &json!({})).await?, "verification")
+    }
+
+    // Stop a proof that is queued, awaiting approval or proving
+    pub async fn cancel(&self, proof_id: &str) -> Result<Proof> {
+        Self::field(self.post(&format!("/api/proofs/{}/cancel", proof_id), &json!({})).await?, "proof")
+    }
+
+    // Run a finished proof again, optionally with other arguments; answers
+    // with the new proof's ID, whose `parent_id` is `proof_id`
+    pub async fn rerun(&self, proof_id: &str, arguments: Option<Vec<String>>) -> Result<String> {
+        let body = json!({ "arguments": arguments });
+        Self::field(self.post(&format!("/api/proofs/{}/rerun", proof_id), &body).await?, "proof_id")
+    }
+
+    // The proof's bundle or json export (see export.rs on the server)
+    pub async fn export(&self, proof_id: &str, format: &str) -> Result<Value> {
+        let request = self.http
+            .get(format!("{}/api/proofs/{}/export", self.server, proof_id))
+            .query(&[("format", format)]);
+        self.send(request).await
+    }
+
+    // Every global event from now on: proofs and verifications started by
+    // any client, through any API
+    pub async fn events(&self) -> Result<EventStream> {
+        let mut url = Url::parse(&format!("{}/ws", self.server)).map_err(|_| Error::InvalidUrl(self.server.clone()))?;
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme).map_err(|_| Error::InvalidUrl(self.server.clone()))?;
+        url.query_pairs_mut().append_pair("global", "true").append_pair("compress", "gzip");
+        if let Some(token) = &self.token {
+            url.query_pairs_mut().append_pair("token", token);
+        }
+        let (socket, _) = connect_async(url.as_str()).await?;
+        let events = socket.filter_map(|message| async move {
+            match message {
+                Ok(tungstenite::Message::Text(text)) => Some(Envelope::parse(&text).map_err(Error::from)),
+                // Large events arrive gzipped
+                Ok(tungstenite::Message::Binary(gzipped)) => {
+                    let mut text = String::new();
+                    Some(match GzDecoder::new(gzipped.as_slice()).read_to_string(&mut text) {
+                        Ok(_) => Envelope::parse(&text).map_err(Error::from),
+                        Err(e) => Err(Error::Decode(serde_json::Error::io(e))),
+                    })
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            }
+        });
+        Ok(Box::pin(events))
+    }
+
+    // Wait until a proof completes, fails or is cancelled. `on_change` sees
+    // the proof first as it is now and then after each status change,
+    // including each proving step.
+    pub async fn wait_for_proof(&self, proof_id: &str, mut on_change: impl FnMut(&Proof)) -> Result<Proof> {
+        // Subscribe before the first read so no change is missed
+        let mut events = self.events().await?;
+        let mut proof = self.proof(proof_id).await?;
+        on_change(&proof);
+        while !proof.status.is_final() {
+            // Any event about the proof may mean its record changed; after a
+            // lag, events may have been lost, so check anyway
+            loop {
+                let envelope = events.next().await.ok_or(Error::Closed)??;
+                if envelope.proof_id() == Some(proof.id.as_str())
+                    || matches!(envelope.data, Some(Event::Lagged { complete: false, .. }))
+                {
+                    break;
+                }
+            }
+            let previous = proof.status.clone();
+            proof = self.proof(&proof.id).await?;
+            if proof.status != previous {
+                on_change(&proof);
+            }
+        }
+        Ok(proof)
+    }
+
+    // Start a proof and wait for it to complete or fail
+    pub async fn prove(&self, request: &GenerateProofRequest) -> Result<Proof> {
+        let proof_id = self.generate_proof(request).await?;
+        self.wait_for_proof(&proof_id, |_| {}).await
+    }
+}
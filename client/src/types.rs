@@ -0,0 +1,368 @@
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+
+// Wire types. The first half is shared with the server, which uses these
+// definitions directly, so the two can't drift apart; the rest are the
+// client's view of the server's responses, holding the fields a client
+// needs and ignoring the others.
+
+// Bumped whenever a breaking change is made to the WebSocket wire format
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Error taxonomy. Every failure reported to clients carries a stable
+// snake_case `code` next to its human-readable message, in REST responses
+// (`{"success": false, "error": "...", "code": "wasm_not_found"}`), WS
+// events and stored proof and verification records, so clients can branch
+// on the code instead of parsing text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    // Proof generation
+    WasmNotFound,
+    UnknownFunction,
+    InvalidArguments,
+    // zkEngine could not be started
+    ProverUnavailable,
+    // zkEngine exited with an error
+    ProverFailed,
+    // zkEngine was killed by a signal
+    ProverCrashed,
+    Timeout,
+    // Proof lookups
+    ProofNotFound,
+    AmbiguousProofId,
+    // Another proof already has the name
+    NameTaken,
+    ProofNotReady,
+    ProofFileMissing,
+    TooLarge,
+    // Over the client's request budget
+    RateLimited,
+    // zkEngine rejected the proof: it is invalid or was generated with
+    // different parameters than it is verified with
+    VerifierMismatch,
+    // The proof file no longer matches the hash recorded when it was made
+    ArtifactTampered,
+    NotFound,
+    Unauthorized,
+    // The client's role doesn't allow the operation
+    Forbidden,
+    SessionForbidden,
+    // Turned off on this server (FEATURE_FLAGS)
+    FeatureDisabled,
+    // An approver turned the proof request down (APPROVAL_RULES)
+    ApprovalRejected,
+    StorageError,
+    Internal,
+    // Failures recorded before codes existed
+    #[default]
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn http_status(&self) -> StatusCode {
+        match self {
+            ErrorCode::ProofNotFound | ErrorCode::NotFound | ErrorCode::WasmNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::UnknownFunction | ErrorCode::InvalidArguments | ErrorCode::AmbiguousProofId => StatusCode::BAD_REQUEST,
+            ErrorCode::ProofNotReady | ErrorCode::ArtifactTampered | ErrorCode::NameTaken => StatusCode::CONFLICT,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden
+            | ErrorCode::SessionForbidden
+            | ErrorCode::FeatureDisabled
+            | ErrorCode::ApprovalRejected => StatusCode::FORBIDDEN,
+            ErrorCode::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // Best guess for messages stored before codes existed
+    pub fn classify(message: &str) -> Self {
+        match message {
+            m if m.starts_with("WASM file not found") => ErrorCode::WasmNotFound,
+            m if m.starts_with("zkEngine error") => ErrorCode::ProverFailed,
+            m if m.starts_with("Execution error") => ErrorCode::ProverUnavailable,
+            m if m.starts_with("Proof file not found") => ErrorCode::ProofFileMissing,
+            "Internal error" => ErrorCode::Internal,
+            _ => ErrorCode::Unknown,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProofMetadata {
+    pub wasm_path: String,
+    pub function: String,
+    pub arguments: Vec<String>,
+    pub step_size: u64,
+    // What the proof proves; recorded when it completes. Proofs from
+    // before statements were recorded have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement: Option<Statement>,
+}
+
+// The statement a proof proves: the module ran on these inputs and
+// produced this output
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Statement {
+    // sha256 of the exact module proven, hex; the server keeps a copy of it
+    // with the proof and checks it again before verifying
+    pub wasm_hash: String,
+    // The arguments as zkEngine received them (cities as their location
+    // codes, say), named as in the function catalog
+    pub public_inputs: Vec<PublicInput>,
+    // The output the proof claims, from zkEngine's public.json
+    pub claimed_output: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PublicInput {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProofMetrics {
+    pub generation_time_secs: f64,
+    pub file_size_mb: f64,
+    pub file_hash: String,
+    pub peak_memory_mb: Option<f64>,
+    // The size of what was proven, as zkEngine reported it; proofs from
+    // before these were recorded have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit: Option<CircuitStats>,
+}
+
+// Each count is missing when zkEngine's output didn't include it
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CircuitStats {
+    // WebAssembly instructions executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<u64>,
+    // R1CS constraints in each folding step's circuit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraints: Option<u64>,
+    // Folding steps, each covering up to `step_size` instructions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folding_rounds: Option<u64>,
+}
+
+impl CircuitStats {
+    // e.g. "1234 steps, 25 folding rounds, 10347 constraints per round"
+    pub fn describe(&self) -> String {
+        [(self.steps, "steps"), (self.folding_rounds, "folding rounds"), (self.constraints, "constraints per round")]
+            .into_iter()
+            .filter_map(|(count, label)| Some(format!("{} {}", count?, label)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+// Top-level `type` of an envelope, kept from the original untyped protocol
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    Message,
+    Error,
+    Subscription,
+}
+
+// Logical channel an envelope belongs to. Chat envelopes carry text meant
+// for people; event envelopes carry a machine-readable payload (their
+// `content` is only a human-readable summary) and bots can ignore the rest.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Chat,
+    Event,
+}
+
+// An error as the server reports it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ApiError {
+    #[serde(default)]
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofStatus {
+    // Waiting for a prover slot
+    Queued,
+    // Held for approvers (APPROVAL_RULES)
+    AwaitingApproval,
+    // zkEngine is running; step and total once it reports them
+    Proving {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+    },
+    // Being signed and recorded
+    Finalizing,
+    Complete,
+    Failed(ApiError),
+    Cancelled,
+}
+
+impl ProofStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProofStatus::Queued => "queued",
+            ProofStatus::AwaitingApproval => "awaiting_approval",
+            ProofStatus::Proving { .. } => "proving",
+            ProofStatus::Finalizing => "finalizing",
+            ProofStatus::Complete => "complete",
+            ProofStatus::Failed(_) => "failed",
+            ProofStatus::Cancelled => "cancelled",
+        }
+    }
+
+    // Complete, failed or cancelled; the status won't change again
+    pub fn is_final(&self) -> bool {
+        matches!(self, ProofStatus::Complete | ProofStatus::Failed(_) | ProofStatus::Cancelled)
+    }
+
+    // The stage for people, e.g. "proving (step 3 of 10)"
+    pub fn describe(&self) -> String {
+        match self {
+            ProofStatus::Proving { step: Some(step), total: Some(total) } => format!("proving (step {} of {})", step, total),
+            ProofStatus::Proving { step: Some(step), total: None } => format!("proving (step {})", step),
+            ProofStatus::Failed(error) => format!("failed: {}", error.message),
+            status => status.name().replace('_', " "),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Proof {
+    pub id: String,
+    // Can be used instead of the ID
+    #[serde(default)]
+    pub name: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub metadata: ProofMetadata,
+    pub metrics: ProofMetrics,
+    pub status: ProofStatus,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // What the proof was started with as `metadata`
+    #[serde(default)]
+    pub custom_metadata: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub expired: bool,
+    #[serde(default)]
+    pub revoked: bool,
+    // Every verification of this proof, oldest first
+    #[serde(default)]
+    pub verifications: Vec<VerificationSummary>,
+    // The proof this one was re-run from, and how: retry, replay or template
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub rerun: Option<String>,
+}
+
+impl Proof {
+    // The module the proof ran, e.g. "fib.wat"
+    pub fn wasm_file(&self) -> &str {
+        self.metadata.wasm_path.rsplit('/').next().unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VerificationSummary {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub is_valid: bool,
+    pub verification_time_secs: f64,
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Verification {
+    pub id: String,
+    #[serde(default)]
+    pub proof_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub is_valid: bool,
+    pub verification_time_secs: f64,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub error_code: Option<ErrorCode>,
+    // Answered from the verification cache rather than by running zkEngine
+    #[serde(default)]
+    pub cached: bool,
+}
+
+// Body of POST /api/proofs/generate
+#[derive(Serialize, Clone, Debug)]
+pub struct GenerateProofRequest {
+    pub wasm_file: String,
+    pub function: String,
+    pub arguments: Vec<String>,
+    pub step_size: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    // Kept on the proof as its `custom_metadata`
+    #[serde(skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    // Unique on the server; the proof can then be looked up by it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl GenerateProofRequest {
+    pub fn new(wasm_file: impl Into<String>, arguments: impl IntoIterator<Item = impl ToString>) -> Self {
+        GenerateProofRequest {
+            wasm_file: wasm_file.into(),
+            function: "main".to_string(),
+            arguments: arguments.into_iter().map(|a| a.to_string()).collect(),
+            step_size: 50,
+            tags: Vec::new(),
+            expires_at: None,
+            metadata: serde_json::Map::new(),
+            name: None,
+        }
+    }
+
+    pub fn function(mut self, function: impl Into<String>) -> Self {
+        self.function = function.into();
+        self
+    }
+
+    pub fn step_size(mut self, step_size: u64) -> Self {
+        self.step_size = step_size;
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    // e.g. .metadata("order_id", "A-1042")
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
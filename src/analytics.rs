@@ -0,0 +1,203 @@
+use chrono::{Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    AppState, ProofRecord, ProofStatus,
+};
+
+// Aggregate proof and verification metrics, shared by the `stats` chat
+// command and GET /api/stats, plus per-day trends for GET /api/analytics
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Stats {
+    pub total_proofs: usize,
+    // status name (queued, proving, complete, ...) -> count
+    pub by_status: BTreeMap<String, usize>,
+    // Generation times of completed proofs, keyed by circuit (wasm file)
+    pub generation_times: BTreeMap<String, TimingStats>,
+    // Bytes used by everything under the proofs directory
+    pub storage_bytes: u64,
+    pub verifications: usize,
+    pub verifications_passed: usize,
+    // `None` until something has been verified
+    pub verification_pass_rate: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimingStats {
+    pub count: usize,
+    pub average_secs: f64,
+    pub p50_secs: f64,
+    pub p95_secs: f64,
+}
+
+pub fn circuit(proof: &ProofRecord) -> String {
+    proof.metadata.wasm_path.rsplit('/').next().unwrap_or_default().to_string()
+}
+
+// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn timing_stats(mut times: Vec<f64>) -> TimingStats {
+    times.sort_by(|a, b| a.total_cmp(b));
+    TimingStats {
+        count: times.len(),
+        average_secs: times.iter().sum::<f64>() / times.len() as f64,
+        p50_secs: percentile(&times, 50.0),
+        p95_secs: percentile(&times, 95.0),
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+pub async fn compute(state: &AppState) -> Stats {
+    let (total_proofs, by_status, generation_times) = {
+        let proofs = state.proof_store.snapshot().await;
+        let mut by_status: BTreeMap<String, usize> = ProofStatus::NAMES
+            .into_iter()
+            .map(|status| (status.to_string(), 0))
+            .collect();
+        let mut times: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+        for proof in &proofs {
+            *by_status.entry(proof.status.name().to_string()).or_default() += 1;
+            if matches!(proof.status, ProofStatus::Complete) {
+                times.entry(circuit(proof)).or_default().push(proof.metrics.generation_time_secs);
+            }
+        }
+        let generation_times = times.into_iter().map(|(circuit, t)| (circuit, timing_stats(t))).collect();
+        (proofs.len(), by_status, generation_times)
+    };
+
+    let (verifications, verifications_passed) = {
+        let verifications = state.verification_store.lock().await;
+        (verifications.len(), verifications.iter().filter(|v| v.is_valid).count())
+    };
+
+    let proofs_dir = state.proofs_dir.clone();
+    let storage_bytes = tokio::task::spawn_blocking(move || dir_size(Path::new(&proofs_dir)))
+        .await
+        .unwrap_or(0);
+
+    Stats {
+        total_proofs,
+        by_status,
+        generation_times,
+        storage_bytes,
+        verifications,
+        verifications_passed,
+        verification_pass_rate: (verifications > 0).then(|| verifications_passed as f64 / verifications as f64),
+    }
+}
+
+// One day of proofs for one circuit. Days without proofs are left out.
+#[derive(Serialize)]
+pub struct DailyPoint {
+    pub date: NaiveDate,
+    pub proofs: usize,
+    pub completed: usize,
+    pub failed: usize,
+    // Failed share of finished (completed or failed) proofs
+    pub failure_rate: Option<f64>,
+    // Generation time and size of completed proofs
+    pub p50_secs: Option<f64>,
+    pub p95_secs: Option<f64>,
+    pub average_size_mb: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct TrendParams {
+    // How far back to look (default 30 days)
+    pub days: Option<i64>,
+    // Function name (fibonacci, prove_kyc, ...) or wasm file
+    pub function: Option<String>,
+}
+
+// Daily series keyed by circuit (wasm file), oldest day first
+pub async fn trends(state: &AppState, params: &TrendParams) -> Result<BTreeMap<String, Vec<DailyPoint>>, AppError> {
+    let circuit_filter = params.function.as_deref()
+        .map(|name| catalog::wasm_file(name).unwrap_or_else(|| name.to_string()));
+    let days = params.days.unwrap_or(30);
+    if days <= 0 {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "days must be positive"));
+    }
+    let since = (Utc::now() - Duration::days(days - 1)).date_naive();
+
+    let mut buckets: BTreeMap<String, BTreeMap<NaiveDate, Vec<&ProofRecord>>> = BTreeMap::new();
+    let proofs = state.proof_store.snapshot().await;
+    for proof in &proofs {
+        let date = proof.timestamp.date_naive();
+        let circuit = circuit(proof);
+        if date < since || circuit_filter.as_deref().is_some_and(|c| c != circuit) {
+            continue;
+        }
+        buckets.entry(circuit).or_default().entry(date).or_default().push(proof);
+    }
+
+    Ok(buckets
+        .into_iter()
+        .map(|(circuit, days)| (circuit, days.into_iter().map(|(date, proofs)| daily_point(date, &proofs)).collect()))
+        .collect())
+}
+
+fn daily_point(date: NaiveDate, proofs: &[&ProofRecord]) -> DailyPoint {
+    let completed: Vec<&&ProofRecord> = proofs.iter().filter(|p| matches!(p.status, ProofStatus::Complete)).collect();
+    let failed = proofs.iter().filter(|p| matches!(p.status, ProofStatus::Failed(_))).count();
+    let finished = completed.len() + failed;
+    let timing = (!completed.is_empty())
+        .then(|| timing_stats(completed.iter().map(|p| p.metrics.generation_time_secs).collect()));
+    DailyPoint {
+        date,
+        proofs: proofs.len(),
+        completed: completed.len(),
+        failed,
+        failure_rate: (finished > 0).then(|| failed as f64 / finished as f64),
+        p50_secs: timing.as_ref().map(|t| t.p50_secs),
+        p95_secs: timing.as_ref().map(|t| t.p95_secs),
+        average_size_mb: (!completed.is_empty())
+            .then(|| completed.iter().map(|p| p.metrics.file_size_mb).sum::<f64>() / completed.len() as f64),
+    }
+}
+
+// One-line summary for chat
+pub fn describe(stats: &Stats) -> String {
+    let statuses = stats.by_status.iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut message = format!(
+        "{} proofs ({}), using {:.1} MB.",
+        stats.total_proofs, statuses, stats.storage_bytes as f64 / (1024.0 * 1024.0)
+    );
+    match stats.verification_pass_rate {
+        Some(rate) => message.push_str(&format!(
+            " {} of {} verifications passed ({:.0}%).",
+            stats.verifications_passed, stats.verifications, rate * 100.0
+        )),
+        None => message.push_str(" No verifications yet."),
+    }
+    for (circuit, timing) in &stats.generation_times {
+        message.push_str(&format!(
+            " {}: avg {:.1}s, p50 {:.1}s, p95 {:.1}s over {}.",
+            circuit, timing.average_secs, timing.p50_secs, timing.p95_secs, timing.count
+        ));
+    }
+    message
+}
@@ -0,0 +1,309 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::sync::{oneshot, Mutex};
+use tracing::{info, warn};
+
+use crate::{
+    auth::{Identity, Role},
+    catalog, cluster,
+    errors::{AppError, ErrorCode},
+    protocol::{WsEvent, WsMessage},
+    retention, run_proof_generation, short_id, update_proof_failed, AppState, ProofMetadata,
+    ProofRecord, ProofStatus,
+};
+
+// Multi-party approval for sensitive proof requests. Proofs of modules
+// named in APPROVAL_RULES are created as usual but wait in the
+// awaiting_approval state until enough designated approvers confirm them;
+// only then do they reach the prover. One rejection fails the proof.
+//
+//   APPROVAL_RULES   comma-separated module[=approvals], e.g.
+//                    "prove_kyc.wat=2,prove_location.wat" (one approval if
+//                    no number is given). A rule names the module's file,
+//                    and applies to every proof of a module with that file
+//                    name, whichever directory it was loaded from.
+//   APPROVERS        comma-separated subjects who may approve; unset means
+//                    any admin
+//
+//   GET  /api/approvals              proofs awaiting approval
+//   POST /api/proofs/:id/approve     {"comment": "..."}, optional
+//   POST /api/proofs/:id/reject      {"reason": "..."}
+//
+// In chat, "approve proof <id>" or "reject proof <id> because <reason>".
+// Each approver counts once, and whoever requested a proof (its origin's
+// subject) can't approve it; with auth off everyone is the same anonymous
+// admin, so that is only enforced when auth is on. Approvals are kept on the proof record, so a
+// proof still awaiting approval after a restart starts once it gets the
+// rest of them.
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Approval {
+    pub approver: String,
+    pub approved_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ApproveRequest {
+    #[serde(default)]
+    comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RejectRequest {
+    reason: String,
+}
+
+// Proof generations waiting for their approvals, by proof ID
+#[derive(Default)]
+pub struct ApprovalQueue {
+    waiting: Mutex<HashMap<String, oneshot::Sender<Result<(), AppError>>>>,
+}
+
+// Approvals a module needs, if it has a rule
+fn required_approvals(wasm_path: &str) -> Option<usize> {
+    let wasm_file = wasm_path.rsplit('/').next().unwrap_or(wasm_path);
+    std::env::var("APPROVAL_RULES").ok()?
+        .split(',')
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty())
+        .find_map(|rule| {
+            let (module, count) = rule.split_once('=').unwrap_or((rule, "1"));
+            (module.trim() == wasm_file).then(|| count.trim().parse().unwrap_or(1).max(1))
+        })
+}
+
+// Whether `identity` may approve or reject proofs
+fn check_approver(identity: &Identity) -> Result<(), AppError> {
+    let allowed = match std::env::var("APPROVERS") {
+        Ok(approvers) if !approvers.trim().is_empty() => approvers.split(',').any(|a| a.trim() == identity.subject),
+        _ => identity.role == Role::Admin,
+    };
+    if !allowed {
+        return Err(AppError::new(ErrorCode::Forbidden, format!("{} is not an approver", identity.subject)));
+    }
+    Ok(())
+}
+
+// Hold a proof generation until the proof is approved, when its module has
+// a rule; fails with the rejection otherwise
+pub async fn wait(state: &AppState, proof_id: &str, metadata: &ProofMetadata) -> Result<(), AppError> {
+    let Some(required) = required_approvals(&metadata.wasm_path) else {
+        return Ok(());
+    };
+    let (sender, receiver) = oneshot::channel();
+    state.approvals.waiting.lock().await.insert(proof_id.to_string(), sender);
+    let record = state.proof_store.update(proof_id, |proof| {
+        proof.status = ProofStatus::AwaitingApproval;
+        proof.clone()
+    }).await.ok_or_else(AppError::proof_not_found)?;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    state.publish(WsMessage::event(
+        format!("Proof {} needs {} approval(s) before it runs", short_id(proof_id), required),
+        WsEvent::progress(proof_id, &record.status),
+    ).for_session(record.session_id));
+    info!("Proof {} of {} is awaiting {} approval(s)", short_id(proof_id), metadata.function, required);
+    receiver.await.unwrap_or_else(|_| Err(AppError::new(ErrorCode::Internal, "The approval was abandoned")))
+}
+
+// Add an approval to a proof awaiting it, queueing the proof once it has
+// `required`; whether it has. The requester may approve their own proof
+// only if `self_approval` is allowed.
+fn add_approval(
+    proof: &mut ProofRecord,
+    identity: &Identity,
+    comment: Option<String>,
+    required: usize,
+    self_approval: bool,
+) -> Result<bool, AppError> {
+    if !matches!(proof.status, ProofStatus::AwaitingApproval) {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("Proof {} is not awaiting approval", short_id(&proof.id))));
+    }
+    let requester = proof.origin.as_ref().and_then(|origin| origin.subject.as_deref());
+    if !self_approval && requester == Some(identity.subject.as_str()) {
+        return Err(AppError::new(ErrorCode::Forbidden, format!("{} requested this proof and can't approve it", identity.subject)));
+    }
+    if proof.approvals.iter().any(|a| a.approver == identity.subject) {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("{} already approved this proof", identity.subject)));
+    }
+    proof.approvals.push(Approval { approver: identity.subject.clone(), approved_at: Utc::now(), comment });
+    let approved = proof.approvals.len() >= required;
+    if approved {
+        proof.status = ProofStatus::Queued;
+    }
+    Ok(approved)
+}
+
+// Record an approval; once there are enough, the proof goes to the prover
+pub async fn approve(state: &AppState, id: &str, identity: &Identity, comment: Option<String>) -> Result<ProofRecord, AppError> {
+    check_approver(identity)?;
+    let id = retention::resolve_proof_id(state, id).await?;
+    let self_approval = !state.auth.is_enabled();
+    let (record, approved) = state.proof_store.update(&id, |proof| {
+        let required = required_approvals(&proof.metadata.wasm_path).unwrap_or(1);
+        add_approval(proof, identity, comment, required, self_approval).map(|approved| (proof.clone(), approved))
+    }).await.ok_or_else(AppError::proof_not_found)??;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    info!(target: "audit", proof_id = %id, approver = %identity.subject, "Proof approved");
+
+    if approved {
+        match state.approvals.waiting.lock().await.remove(&id) {
+            Some(sender) => {
+                let _ = sender.send(Ok(()));
+            }
+            // Approved after a restart: nothing is waiting, so start it here
+            None => {
+                let wasm_file = record.metadata.wasm_path.rsplit('/').next().unwrap_or_default();
+                match catalog::encode_module_arguments(wasm_file, &record.metadata.arguments) {
+                    Ok(args) => {
+                        tokio::spawn(run_proof_generation(state.clone(), id.clone(), record.metadata.clone(), args));
+                    }
+                    Err(error) => update_proof_failed(state, &id, error).await,
+                }
+            }
+        }
+    }
+    Ok(record)
+}
+
+// Fail a proof awaiting approval
+pub async fn reject(state: &AppState, id: &str, identity: &Identity, reason: &str) -> Result<(), AppError> {
+    check_approver(identity)?;
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "A reason is needed to reject a proof"));
+    }
+    let id = retention::resolve_proof_id(state, id).await?;
+    let awaiting = state.proof_store.read(&id, |p| matches!(p.status, ProofStatus::AwaitingApproval)).await == Some(true);
+    if !awaiting {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("Proof {} is not awaiting approval", short_id(&id))));
+    }
+    warn!(target: "audit", proof_id = %id, approver = %identity.subject, reason = %reason, "Proof rejected");
+    let error = AppError::new(ErrorCode::ApprovalRejected, format!("Rejected by {}: {}", identity.subject, reason));
+    match state.approvals.waiting.lock().await.remove(&id) {
+        Some(sender) => {
+            let _ = sender.send(Err(error));
+        }
+        None => update_proof_failed(state, &id, error).await,
+    }
+    Ok(())
+}
+
+// Stop waiting for a cancelled proof's approvals; its generation ends
+// without starting
+pub async fn abandon(state: &AppState, proof_id: &str) {
+    state.approvals.waiting.lock().await.remove(proof_id);
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut awaiting: Vec<ProofRecord> = Vec::new();
+    for id in state.proof_store.ids(|p| matches!(p.status, ProofStatus::AwaitingApproval)).await {
+        awaiting.extend(state.proof_store.get(&id).await);
+    }
+    awaiting.sort_by_key(|p| p.timestamp);
+    let awaiting: Vec<serde_json::Value> = awaiting.into_iter()
+        .map(|p| json!({
+            "proof": p,
+            "required": required_approvals(&p.metadata.wasm_path).unwrap_or(1),
+        }))
+        .collect();
+    Json(json!({ "success": true, "count": awaiting.len(), "awaiting": awaiting }))
+}
+
+pub async fn approve_handler(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    Path(id): Path<String>,
+    request: Option<Json<ApproveRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match approve(&state, &id, &identity, request.comment).await {
+        Ok(proof) => Json(json!({ "success": true, "proof": proof })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn reject_handler(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    Path(id): Path<String>,
+    Json(request): Json<RejectRequest>,
+) -> Response {
+    match reject(&state, &id, &identity, &request.reason).await {
+        Ok(()) => Json(json!({ "success": true, "proof_id": id })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::origin::{Channel, Origin};
+
+    fn awaiting(requester: &str) -> ProofRecord {
+        let mut proof = crate::test_proof("3f2a9c1e-7b4d-4e8a-9c0f-1a2b3c4d5e6f");
+        proof.status = ProofStatus::AwaitingApproval;
+        proof.origin = Some(Origin::new(Channel::Rest).identity(&approver(requester)));
+        proof
+    }
+
+    fn approver(subject: &str) -> Identity {
+        Identity { subject: subject.to_string(), role: Role::Admin }
+    }
+
+    #[test]
+    fn queued_once_enough_approvers_agree() {
+        let mut proof = awaiting("requester");
+        assert_eq!(add_approval(&mut proof, &approver("alice"), None, 2, false), Ok(false));
+        assert!(matches!(proof.status, ProofStatus::AwaitingApproval));
+        assert_eq!(add_approval(&mut proof, &approver("bob"), Some("ok".to_string()), 2, false), Ok(true));
+        assert!(matches!(proof.status, ProofStatus::Queued));
+        assert_eq!(proof.approvals.len(), 2);
+    }
+
+    #[test]
+    fn requester_cannot_approve_their_own_proof() {
+        let mut proof = awaiting("alice");
+        let error = add_approval(&mut proof, &approver("alice"), None, 1, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Forbidden);
+        assert!(matches!(proof.status, ProofStatus::AwaitingApproval));
+        assert!(proof.approvals.is_empty());
+        // With auth off everyone is the same anonymous admin
+        assert_eq!(add_approval(&mut proof, &approver("alice"), None, 1, true), Ok(true));
+    }
+
+    #[test]
+    fn approvers_count_once() {
+        let mut proof = awaiting("requester");
+        add_approval(&mut proof, &approver("alice"), None, 2, false).unwrap();
+        let error = add_approval(&mut proof, &approver("alice"), None, 2, false).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidArguments);
+        assert_eq!(proof.approvals.len(), 1);
+    }
+
+    #[test]
+    fn only_proofs_awaiting_approval_can_be_approved() {
+        let mut proof = awaiting("requester");
+        proof.status = ProofStatus::Queued;
+        assert!(add_approval(&mut proof, &approver("alice"), None, 1, false).is_err());
+        assert!(proof.approvals.is_empty());
+    }
+
+    #[test]
+    fn only_approvers_may_approve() {
+        std::env::remove_var("APPROVERS");
+        assert!(check_approver(&approver("alice")).is_ok());
+        let prover = Identity { subject: "alice".to_string(), role: Role::Prover };
+        assert_eq!(check_approver(&prover).unwrap_err().code, ErrorCode::Forbidden);
+    }
+}
@@ -0,0 +1,227 @@
+use axum::{
+    extract::State,
+    http::{header, Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::{Arc, RwLock}};
+use tracing::warn;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    jwt::JwtVerifier,
+    secrets::{Secret, Secrets},
+    AppState,
+};
+
+// What a client may do; each role includes the ones before it
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    // List, inspect, compare and export proofs
+    #[default]
+    Viewer,
+    // Also generate and verify proofs
+    Prover,
+    // Also delete and archive proofs
+    Admin,
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Prover => "prover",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Role::Viewer, Role::Prover, Role::Admin]
+            .into_iter()
+            .find(|role| role.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+// Identity attached to an authenticated connection or request
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Identity {
+    pub subject: String,
+    #[serde(default)]
+    pub role: Role,
+}
+
+impl Identity {
+    // Everyone is an admin while auth is disabled
+    pub fn anonymous() -> Self {
+        Identity { subject: "anonymous".to_string(), role: Role::Admin }
+    }
+}
+
+// First-message handshake for clients that can't put the token in the URL
+#[derive(Deserialize)]
+pub struct AuthMessage {
+    pub token: String,
+}
+
+// Static API keys from the API_KEYS secret ("alice:key1,bob:key2:viewer"),
+// optionally with a role after the key. Keys without one are admin keys.
+#[derive(Clone, Default)]
+pub struct ApiKeys {
+    // The secret they were parsed from
+    raw: String,
+    keys: HashMap<String, Identity>,
+}
+
+impl ApiKeys {
+    pub fn parse(raw: &str) -> Self {
+        let keys = raw
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .enumerate()
+            .map(|(i, entry)| {
+                let (subject, key, role) = match entry.split(':').collect::<Vec<_>>()[..] {
+                    [subject, key, role] => (subject.to_string(), key, Some(role)),
+                    [subject, key] => (subject.to_string(), key, None),
+                    _ => (format!("key-{}", i + 1), entry, None),
+                };
+                let role = role.map_or(Some(Role::Admin), Role::parse).unwrap_or_else(|| {
+                    warn!("Unknown role for API key of {}; treating it as a viewer", subject);
+                    Role::Viewer
+                });
+                (key.to_string(), Identity { subject, role })
+            })
+            .collect();
+        ApiKeys { raw: raw.to_string(), keys }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<Identity> {
+        self.keys.get(token).cloned()
+    }
+}
+
+// Checks bearer tokens against the static API keys and, when JWT_ISSUER is
+// set, the identity provider. Auth is disabled when neither is configured.
+pub struct Authenticator {
+    api_keys_secret: Secret,
+    api_keys: RwLock<ApiKeys>,
+    jwt: Option<JwtVerifier>,
+}
+
+impl Authenticator {
+    pub fn from_env(secrets: &Arc<Secrets>) -> Self {
+        let api_keys_secret = secrets.secret("API_KEYS");
+        let api_keys = ApiKeys::parse(&api_keys_secret.value().unwrap_or_default());
+        Authenticator { api_keys_secret, api_keys: RwLock::new(api_keys), jwt: JwtVerifier::from_env(secrets) }
+    }
+
+    // Run `f` on the current API keys, reparsing them after API_KEYS is rotated
+    fn with_api_keys<T>(&self, f: impl FnOnce(&ApiKeys) -> T) -> T {
+        let raw = self.api_keys_secret.value().unwrap_or_default();
+        if self.api_keys.read().unwrap().raw != raw {
+            *self.api_keys.write().unwrap() = ApiKeys::parse(&raw);
+        }
+        f(&self.api_keys.read().unwrap())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.with_api_keys(ApiKeys::is_enabled) || self.jwt.is_some()
+    }
+
+    pub async fn authenticate(&self, token: &str) -> Option<Identity> {
+        if let Some(identity) = self.with_api_keys(|keys| keys.authenticate(token)) {
+            return Some(identity);
+        }
+        match self.jwt.as_ref()?.verify(token).await {
+            Ok(identity) => Some(identity),
+            Err(e) => {
+                warn!("Rejected JWT: {}", e);
+                None
+            }
+        }
+    }
+}
+
+// Bearer token from the `Authorization` header or a `token` query parameter
+pub fn request_token<B>(request: &Request<B>) -> Option<String> {
+    let bearer = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    bearer.or_else(|| {
+        request.uri().query()?
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+            .map(str::to_string)
+    })
+}
+
+// Least role allowed to make a REST call; `None` for endpoints anyone may
+// call. The WebSocket and MCP endpoints authenticate their own connections.
+fn required_role(method: &Method, path: &str) -> Option<Role> {
+    if !path.starts_with("/api/") || path == "/api/health" || path == "/api/langchain/health" {
+        return None;
+    }
+    Some(match *method {
+        _ if path.starts_with("/api/admin/") => Role::Admin,
+        // Webhook URLs can carry credentials
+        _ if path.starts_with("/api/webhooks") => Role::Admin,
+        Method::GET | Method::HEAD => Role::Viewer,
+        // Read-only; see graphql.rs
+        Method::POST if path == "/api/graphql" => Role::Viewer,
+        Method::POST if matches!(path, "/api/proofs/generate" | "/api/verify/upload" | "/api/kyc" | "/api/provenance" | "/api/templates" | "/api/pipelines") => Role::Prover,
+        Method::POST if path.starts_with("/api/proofs/")
+            && ["/federate", "/share", "/verify", "/cancel", "/rerun"].iter().any(|action| path.ends_with(action)) => Role::Prover,
+        // Approvers are checked by approvals.rs
+        Method::POST if path.starts_with("/api/proofs/") && (path.ends_with("/approve") || path.ends_with("/reject")) => Role::Viewer,
+        Method::POST if path.starts_with("/api/templates/") && path.ends_with("/run") => Role::Prover,
+        // Deleting, archiving and cleanup, and anything else that changes state
+        _ => Role::Admin,
+    })
+}
+
+// Role check for REST calls: 401 without a valid token, 403 when the
+// caller's role is too low. The caller's Identity is added to the request's
+// extensions for handlers that need to know who is asking.
+pub async fn middleware<B>(State(state): State<AppState>, mut request: Request<B>, next: Next<B>) -> Response {
+    let Some(required) = required_role(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    match authorize(&state.auth, &request, required).await {
+        Ok(identity) => {
+            request.extensions_mut().insert(identity);
+            next.run(request).await
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+// The caller, if the request's token has at least the `required` role;
+// always the anonymous admin with auth disabled
+pub async fn authorize<B>(auth: &Authenticator, request: &Request<B>, required: Role) -> Result<Identity, AppError> {
+    if !auth.is_enabled() {
+        return Ok(Identity::anonymous());
+    }
+    let identity = match request_token(request) {
+        Some(token) => auth.authenticate(&token).await,
+        None => None,
+    };
+    match identity {
+        Some(identity) if identity.role >= required => Ok(identity),
+        Some(identity) => {
+            warn!("{} ({}) may not {} {}", identity.subject, identity.role.name(), request.method(), request.uri().path());
+            Err(AppError::new(
+                ErrorCode::Forbidden,
+                format!("This needs the {} role; you are a {}", required.name(), identity.role.name()),
+            ))
+        }
+        None => Err(AppError::new(ErrorCode::Unauthorized, "Missing or invalid API token")),
+    }
+}
@@ -0,0 +1,306 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{
+    path::Path,
+    process::{Command, ExitStatus},
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    prove_command, prover_logs, sandbox::Sandbox, verifier, verify_command, AppState,
+};
+
+// zkEngine benchmark: proves and verifies a fixed matrix of catalog
+// functions and step sizes in a scratch directory and reports the prove and
+// verify time, peak memory and proof size of each. The matrix is the same
+// on every machine, so reports from different hardware, sandbox settings or
+// zkEngine builds (identified by the binary's sha256) can be compared line
+// by line. Run it with `POST /api/benchmark` (admins; optionally
+// `{"functions": [...], "step_sizes": [...]}` to run part of the matrix) or
+// `zkp-agentkit benchmark`. On the server it takes one prover slot, and only
+// one benchmark runs at a time.
+
+// A catalog function with its arguments
+type Workload = (&'static str, &'static [&'static str]);
+
+// Chosen to cover short and long executions
+const FUNCTIONS: &[Workload] = &[
+    ("add", &["3", "5"]),
+    ("fibonacci", &["20"]),
+    ("factorial", &["10"]),
+    ("prove_ai_content", &["12345", "1"]),
+];
+const STEP_SIZES: &[u64] = &[10, 50, 100];
+
+// Lines of zkEngine output quoted when a case fails
+const FAILURE_LOG_LINES: usize = 5;
+
+static RUNNING: Mutex<()> = Mutex::const_new(());
+
+#[derive(Deserialize, Default)]
+pub struct BenchmarkRequest {
+    // Catalog functions to run (default: the whole matrix)
+    #[serde(default)]
+    pub functions: Option<Vec<String>>,
+    #[serde(default)]
+    pub step_sizes: Option<Vec<u64>>,
+}
+
+#[derive(Serialize)]
+pub struct BenchmarkReport {
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub total_secs: f64,
+    pub host: Host,
+    pub zkengine_binary: String,
+    pub zkengine_sha256: Option<String>,
+    pub cases: Vec<BenchmarkCase>,
+}
+
+#[derive(Serialize)]
+pub struct Host {
+    pub os: &'static str,
+    pub arch: &'static str,
+    pub cpus: usize,
+    // PROVER_THREADS and PROVER_CPUS, which change the numbers
+    pub prover_threads: Option<String>,
+    pub prover_cpus: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BenchmarkCase {
+    pub function: String,
+    pub arguments: Vec<String>,
+    pub step_size: u64,
+    pub prove_secs: Option<f64>,
+    pub verify_secs: Option<f64>,
+    // Largest resident set of the prover (Unix only)
+    pub peak_memory_mb: Option<f64>,
+    pub proof_size_mb: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+// The cases to run, checked against the catalog before anything starts
+fn matrix(request: &BenchmarkRequest) -> Result<Vec<(Workload, u64)>, AppError> {
+    let functions: Vec<Workload> = match &request.functions {
+        None => FUNCTIONS.to_vec(),
+        Some(names) => names.iter()
+            .map(|name| {
+                FUNCTIONS.iter().find(|(function, _)| function == name).copied().ok_or_else(|| {
+                    let known: Vec<&str> = FUNCTIONS.iter().map(|(function, _)| *function).collect();
+                    AppError::new(ErrorCode::UnknownFunction, format!("{} is not in the benchmark; use one of {}", name, known.join(", ")))
+                })
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    let step_sizes = request.step_sizes.clone().unwrap_or_else(|| STEP_SIZES.to_vec());
+    for &step_size in &step_sizes {
+        catalog::check_step_size(step_size)?;
+    }
+    Ok(functions.iter()
+        .flat_map(|&workload| step_sizes.iter().map(move |&step_size| (workload, step_size)))
+        .collect())
+}
+
+pub async fn run(sandbox: &Sandbox, zkengine_binary: &str, wasm_dir: &str, proofs_dir: &str, request: &BenchmarkRequest) -> Result<BenchmarkReport, AppError> {
+    let cases = matrix(request)?;
+    let Ok(_running) = RUNNING.try_lock() else {
+        return Err(AppError::new(ErrorCode::ProofNotReady, "A benchmark is already running"));
+    };
+    let started_at = chrono::Utc::now();
+    let start = Instant::now();
+    let scratch = Path::new(proofs_dir).join(format!(".benchmark-{}", Uuid::new_v4()));
+    let total = cases.len();
+    let mut results = Vec::with_capacity(total);
+    for (index, ((function, arguments), step_size)) in cases.into_iter().enumerate() {
+        info!("Benchmark {}/{}: {}({}) at step size {}", index + 1, total, function, arguments.join(", "), step_size);
+        let case_dir = scratch.join(index.to_string());
+        results.push(run_case(sandbox, zkengine_binary, wasm_dir, &case_dir, function, arguments, step_size).await);
+    }
+    tokio::fs::remove_dir_all(&scratch).await.ok();
+    Ok(BenchmarkReport {
+        started_at,
+        total_secs: start.elapsed().as_secs_f64(),
+        host: Host {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            cpus: std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            prover_threads: std::env::var("PROVER_THREADS").ok(),
+            prover_cpus: std::env::var("PROVER_CPUS").ok(),
+        },
+        zkengine_binary: zkengine_binary.to_string(),
+        zkengine_sha256: verifier::sha256_file(Path::new(zkengine_binary)).await.ok(),
+        cases: results,
+    })
+}
+
+async fn run_case(
+    sandbox: &Sandbox,
+    zkengine_binary: &str,
+    wasm_dir: &str,
+    case_dir: &Path,
+    function: &str,
+    arguments: &[&str],
+    step_size: u64,
+) -> BenchmarkCase {
+    let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+    let mut case = BenchmarkCase {
+        function: function.to_string(),
+        arguments: arguments.clone(),
+        step_size,
+        prove_secs: None,
+        verify_secs: None,
+        peak_memory_mb: None,
+        proof_size_mb: None,
+        error: None,
+    };
+    if let Err(error) = measure(&mut case, sandbox, zkengine_binary, wasm_dir, case_dir, &arguments).await {
+        case.error = Some(error.message);
+    }
+    case
+}
+
+async fn measure(
+    case: &mut BenchmarkCase,
+    sandbox: &Sandbox,
+    zkengine_binary: &str,
+    wasm_dir: &str,
+    case_dir: &Path,
+    arguments: &[String],
+) -> Result<(), AppError> {
+    let spec = catalog::find(&case.function)
+        .ok_or_else(|| AppError::new(ErrorCode::UnknownFunction, format!("{} is not in the function catalog", case.function)))?;
+    let wasm_path = catalog::validate_request(wasm_dir, spec.wasm_file, arguments, case.step_size)?;
+    let zkengine_args = catalog::encode_module_arguments(spec.wasm_file, arguments)?;
+    tokio::fs::create_dir_all(case_dir).await.map_err(|e| {
+        AppError::new(ErrorCode::StorageError, format!("Failed to create {}: {}", case_dir.display(), e))
+    })?;
+
+    let prove = prove_command(sandbox, zkengine_binary, &wasm_path, case.step_size, case_dir, &zkengine_args);
+    let (elapsed, peak_memory_mb) = run_measured("prove", prove, &case_dir.join("prove.log")).await?;
+    case.prove_secs = Some(elapsed.as_secs_f64());
+    case.peak_memory_mb = peak_memory_mb;
+
+    let proof_file = case_dir.join("proof.bin");
+    let public_file = case_dir.join("public.json");
+    let size = tokio::fs::metadata(&proof_file).await
+        .map_err(|_| AppError::new(ErrorCode::ProofFileMissing, "zkEngine prove succeeded but wrote no proof.bin"))?
+        .len();
+    case.proof_size_mb = Some(size as f64 / 1_048_576.0);
+
+    let verify = verify_command(sandbox, zkengine_binary, case.step_size, &proof_file, &public_file);
+    let (elapsed, _) = run_measured("verify", verify, &case_dir.join("verify.log")).await?;
+    case.verify_secs = Some(elapsed.as_secs_f64());
+    Ok(())
+}
+
+// Run one zkEngine command with its output in `log_path`; returns how long
+// it took and its peak memory in MB
+async fn run_measured(name: &'static str, mut command: Command, log_path: &Path) -> Result<(Duration, Option<f64>), AppError> {
+    let log_path = log_path.to_path_buf();
+    let log = std::fs::File::create(&log_path)
+        .and_then(|log| Ok((log.try_clone()?, log)))
+        .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Failed to create {}: {}", log_path.display(), e)))?;
+    command.stdout(log.0).stderr(log.1);
+    let (status, elapsed, peak_memory_mb) = tokio::task::spawn_blocking(move || wait_measured(command))
+        .await
+        .map_err(|_| AppError::new(ErrorCode::Internal, "Internal error"))?
+        .map_err(|e| AppError::new(ErrorCode::ProverUnavailable, format!("Failed to run zkEngine {}: {}", name, e)))?;
+    if !status.success() {
+        let output = tokio::fs::read_to_string(&log_path).await.unwrap_or_default();
+        let code = match (name, status.code()) {
+            (_, None) => ErrorCode::ProverCrashed,
+            ("verify", _) => ErrorCode::VerifierMismatch,
+            _ => ErrorCode::ProverFailed,
+        };
+        return Err(AppError::new(code, format!("zkEngine {} failed ({}): {}", name, status, prover_logs::tail(&output, FAILURE_LOG_LINES).trim())));
+    }
+    Ok((elapsed, peak_memory_mb))
+}
+
+fn wait_measured(mut command: Command) -> std::io::Result<(ExitStatus, Duration, Option<f64>)> {
+    let start = Instant::now();
+    let child = command.spawn()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        // wait4 reports the child's own resource usage, unlike
+        // RUSAGE_CHILDREN, which covers every child the server has had
+        let mut status = 0;
+        // Safety: rusage is plain data, and the child is ours and reaped
+        // only here
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            if unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut usage) } >= 0 {
+                break;
+            }
+            let error = std::io::Error::last_os_error();
+            if error.kind() != std::io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+        // ru_maxrss is in kilobytes, except on macOS where it is in bytes
+        let kilobytes = if cfg!(target_os = "macos") { usage.ru_maxrss as f64 / 1024.0 } else { usage.ru_maxrss as f64 };
+        Ok((ExitStatus::from_raw(status), start.elapsed(), Some(kilobytes / 1024.0)))
+    }
+    #[cfg(not(unix))]
+    {
+        let mut child = child;
+        let status = child.wait()?;
+        Ok((status, start.elapsed(), None))
+    }
+}
+
+// Plain-text table of a report, for the CLI
+pub fn report(report: &BenchmarkReport) -> String {
+    let mut lines = vec![
+        format!(
+            "zkEngine {} (sha256 {}) on {}/{}, {} CPUs",
+            report.zkengine_binary,
+            report.zkengine_sha256.as_deref().map_or("unknown", |hash| &hash[..hash.len().min(12)]),
+            report.host.os,
+            report.host.arch,
+            report.host.cpus,
+        ),
+        format!("{:<18}  {:<10}  {:>5}  {:>9}  {:>9}  {:>9}  {:>9}", "FUNCTION", "ARGUMENTS", "STEP", "PROVE", "VERIFY", "MEMORY", "SIZE"),
+    ];
+    let secs = |secs: Option<f64>| secs.map_or("-".to_string(), |s| format!("{:.2}s", s));
+    let mb = |mb: Option<f64>| mb.map_or("-".to_string(), |mb| format!("{:.1}MB", mb));
+    for case in &report.cases {
+        lines.push(format!(
+            "{:<18}  {:<10}  {:>5}  {:>9}  {:>9}  {:>9}  {:>9}",
+            case.function,
+            case.arguments.join(","),
+            case.step_size,
+            secs(case.prove_secs),
+            secs(case.verify_secs),
+            mb(case.peak_memory_mb),
+            mb(case.proof_size_mb),
+        ));
+        if let Some(error) = &case.error {
+            lines.push(format!("  error: {}", error));
+        }
+    }
+    lines.push(format!("Total {:.1}s", report.total_secs));
+    lines.join("\n")
+}
+
+pub async fn handler(State(state): State<AppState>, request: Option<Json<BenchmarkRequest>>) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let _slot = state.prove_pool.slot().await;
+    match run(&state.sandbox, &state.zkengine_binary, &state.wasm_dir, &state.proofs_dir, &request).await {
+        Ok(report) => Json(json!({ "success": true, "benchmark": report })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::path::PathBuf;
+use zkp_agentkit_client::{types::Channel, Client, GenerateProofRequest, Proof, ProofStatus};
+
+// Client for a running zkp-agentkit server, so operators and CI jobs don't
+// have to script curl and WebSocket calls:
+//
+//   zkagent prove fib.wat 10 --wait --verify
+//   zkagent watch <proof-id>
+//   zkagent cancel <proof-id>
+//   zkagent rerun <proof-id> --wait
+//   zkagent verify <proof-id>
+//   zkagent download <proof-id> --format bundle -o proof.json
+//   zkagent events
+//
+// The exit status is 0 on success, 1 when a call fails and 2 when a proof
+// fails, is cancelled or doesn't verify. Built on the zkp-agentkit-client crate.
+
+#[derive(Parser)]
+#[command(name = "zkagent", version, about = "Client for the zkp-agentkit API")]
+struct Cli {
+    /// Server URL
+    #[arg(long, global = true, env = "ZKAGENT_SERVER", default_value = "http://localhost:8001")]
+    server: String,
+    /// API key or JWT
+    #[arg(long, global = true, env = "ZKAGENT_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Print JSON instead of summaries
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start a proof
+    Prove(ProveArgs),
+    /// Show a proof
+    Status { proof_id: String },
+    /// Follow a proof until it completes, fails or is cancelled
+    Watch { proof_id: String },
+    /// Stop a proof that hasn't finished
+    Cancel { proof_id: String },
+    /// Run a finished proof again
+    Rerun {
+        proof_id: String,
+        /// Follow the new proof until it finishes
+        #[arg(long)]
+        wait: bool,
+    },
+    /// Verify a completed proof
+    Verify { proof_id: String },
+    /// List proofs, newest first
+    List(ListArgs),
+    /// Download a proof export
+    Download(DownloadArgs),
+    /// Print proof and verification events as they happen, one JSON object per line
+    Events,
+}
+
+#[derive(Args)]
+struct ProveArgs {
+    /// Module in the server's wasm directory, e.g. fib.wat
+    wasm_file: String,
+    /// Arguments for the module
+    #[arg(allow_negative_numbers = true)]
+    arguments: Vec<String>,
+    /// Function name stored with the proof
+    #[arg(long, default_value = "main")]
+    function: String,
+    /// zkEngine step size
+    #[arg(long, default_value_t = 50)]
+    step: u64,
+    /// Tag the proof; may be repeated
+    #[arg(long = "tag")]
+    tags: Vec<String>,
+    /// Name the proof, to refer to it by instead of its ID
+    #[arg(long)]
+    name: Option<String>,
+    /// Attach metadata to the proof as key=value, e.g. order_id=A-1042; may
+    /// be repeated
+    #[arg(long = "meta")]
+    metadata: Vec<String>,
+    /// Follow the proof until it completes or fails
+    #[arg(long)]
+    wait: bool,
+    /// Verify the proof once it completes; implies --wait
+    #[arg(long)]
+    verify: bool,
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Only proofs with this status
+    #[arg(long)]
+    status: Option<String>,
+    /// Only proofs with this tag
+    #[arg(long)]
+    tag: Option<String>,
+    /// At most this many proofs (0 for all)
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+}
+
+#[derive(Args)]
+struct DownloadArgs {
+    proof_id: String,
+    /// bundle or json
+    #[arg(long, default_value = "bundle")]
+    format: String,
+    /// File to write (default: proof-<id>.<format>.json)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+// A proof that failed or didn't verify, as opposed to a failed call
+struct Rejected(String);
+
+fn short(id: &str) -> &str {
+    &id[..id.len().min(8)]
+}
+
+fn describe(proof: &Proof) -> String {
+    let mut line = format!(
+        "{}  {}  {}({})",
+        short(&proof.id),
+        proof.status.describe(),
+        proof.metadata.function,
+        proof.metadata.arguments.join(", "),
+    );
+    if matches!(proof.status, ProofStatus::Complete) {
+        line.push_str(&format!(
+            "  {:.2}s  {:.2}MB",
+            proof.metrics.generation_time_secs, proof.metrics.file_size_mb
+        ));
+    }
+    if let Some(name) = &proof.name {
+        line.push_str(&format!("  \"{}\"", name));
+    }
+    if let (Some(parent_id), Some(rerun)) = (&proof.parent_id, &proof.rerun) {
+        line.push_str(&format!("  ({} of {})", rerun, short(parent_id)));
+    }
+    if proof.revoked {
+        line.push_str("  (revoked)");
+    } else if proof.expired {
+        line.push_str("  (expired)");
+    }
+    line
+}
+
+fn print<T: Serialize>(json: bool, value: &T, summary: impl FnOnce() -> String) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value).unwrap_or_default());
+    } else {
+        println!("{}", summary());
+    }
+}
+
+// Follow a proof, reporting each status change on stderr
+async fn watch(client: &Client, proof_id: &str) -> anyhow::Result<Proof> {
+    Ok(client.wait_for_proof(proof_id, |proof| eprintln!("{}", describe(proof))).await?)
+}
+
+fn finished(proof: &Proof) -> Result<(), Rejected> {
+    match &proof.status {
+        ProofStatus::Complete => Ok(()),
+        ProofStatus::Cancelled => Err(Rejected(format!("proof {} was cancelled", short(&proof.id)))),
+        _ => Err(Rejected(format!("proof {} failed", short(&proof.id)))),
+    }
+}
+
+async fn verify(client: &Client, json: bool, proof_id: &str) -> anyhow::Result<Result<(), Rejected>> {
+    let verification = client.verify(proof_id).await?;
+    print(json, &verification, || match verification.is_valid {
+        true => format!("{}  valid ({:.3}s)", short(proof_id), verification.verification_time_secs),
+        false => format!("{}  INVALID: {}", short(proof_id), verification.error.as_deref().unwrap_or_default()),
+    });
+    Ok(match verification.is_valid {
+        true => Ok(()),
+        false => Err(Rejected(format!("proof {} did not verify", short(proof_id)))),
+    })
+}
+
+async fn run(client: &Client, json: bool, command: Command) -> anyhow::Result<Result<(), Rejected>> {
+    match command {
+        Command::Prove(args) => {
+            let mut request = GenerateProofRequest::new(args.wasm_file, args.arguments)
+                .function(args.function)
+                .step_size(args.step);
+            request.tags = args.tags;
+            request.name = args.name;
+            for entry in &args.metadata {
+                let (key, value) = entry.split_once('=')
+                    .ok_or_else(|| anyhow::anyhow!("--meta takes key=value, not '{}'", entry))?;
+                request = request.metadata(key, value);
+            }
+            let proof_id = client.generate_proof(&request).await?;
+            if !(args.wait || args.verify) {
+                print(json, &proof_id, || proof_id.clone());
+                return Ok(Ok(()));
+            }
+            let proof = watch(client, &proof_id).await?;
+            print(json, &proof, || describe(&proof));
+            if let Err(rejected) = finished(&proof) {
+                return Ok(Err(rejected));
+            }
+            if args.verify {
+                return verify(client, json, &proof_id).await;
+            }
+            Ok(Ok(()))
+        }
+        Command::Status { proof_id } => {
+            let proof = client.proof(&proof_id).await?;
+            print(json, &proof, || describe(&proof));
+            Ok(Ok(()))
+        }
+        Command::Watch { proof_id } => {
+            let proof = watch(client, &proof_id).await?;
+            print(json, &proof, || describe(&proof));
+            Ok(finished(&proof))
+        }
+        Command::Cancel { proof_id } => {
+            let proof = client.cancel(&proof_id).await?;
+            print(json, &proof, || describe(&proof));
+            Ok(Ok(()))
+        }
+        Command::Rerun { proof_id, wait } => {
+            let rerun_id = client.rerun(&proof_id, None).await?;
+            if !wait {
+                print(json, &rerun_id, || rerun_id.clone());
+                return Ok(Ok(()));
+            }
+            let proof = watch(client, &rerun_id).await?;
+            print(json, &proof, || describe(&proof));
+            Ok(finished(&proof))
+        }
+        Command::Verify { proof_id } => verify(client, json, &proof_id).await,
+        Command::List(args) => {
+            let tags: Vec<&str> = args.tag.as_deref().into_iter().collect();
+            let mut proofs: Vec<Proof> = client.list_proofs(&tags).await?
+                .into_iter()
+                .filter(|p| args.status.as_deref().is_none_or(|status| p.status.name().eq_ignore_ascii_case(status)))
+                .collect();
+            proofs.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+            if args.limit > 0 {
+                proofs.truncate(args.limit);
+            }
+            print(json, &proofs, || match proofs.is_empty() {
+                true => "No proofs".to_string(),
+                false => proofs.iter().map(describe).collect::<Vec<_>>().join("\n"),
+            });
+            Ok(Ok(()))
+        }
+        Command::Download(args) => {
+            let export = client.export(&args.proof_id, &args.format).await?;
+            let output = args.output.unwrap_or_else(|| {
+                PathBuf::from(format!("proof-{}.{}.json", short(&args.proof_id), args.format))
+            });
+            tokio::fs::write(&output, serde_json::to_string_pretty(&export)?)
+                .await
+                .with_context(|| format!("writing {}", output.display()))?;
+            eprintln!("Wrote {}", output.display());
+            Ok(Ok(()))
+        }
+        Command::Events => {
+            let mut events = client.events().await?;
+            while let Some(envelope) = events.next().await {
+                let envelope = envelope?;
+                // Chat is for people in the web UI
+                if envelope.channel == Channel::Event {
+                    println!("{}", envelope.raw);
+                }
+            }
+            Ok(Ok(()))
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let mut client = Client::new(cli.server);
+    if let Some(token) = cli.token {
+        client = client.with_token(token);
+    }
+    std::process::exit(match run(&client, cli.json, cli.command).await {
+        Ok(Ok(())) => 0,
+        Ok(Err(Rejected(message))) => {
+            eprintln!("{}", message);
+            2
+        }
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            1
+        }
+    });
+}
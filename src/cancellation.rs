@@ -0,0 +1,75 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+    Extension,
+};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::{
+    approvals,
+    auth::Identity,
+    cluster,
+    errors::{AppError, ErrorCode},
+    protocol::{WsEvent, WsMessage},
+    retention, short_id, AppState, ProofRecord, ProofStatus,
+};
+
+// Cancelling proofs that haven't finished.
+//
+//   POST /api/proofs/:id/cancel
+//
+// A queued proof or one awaiting approval is marked cancelled and never
+// reaches the prover. A proof being proven is marked cancelled and the
+// instance running zkEngine for it, which in a cluster may be another one,
+// kills the run when it next checks the proof (see watch_prover in
+// main.rs). A proof that is already finalizing is left to finish.
+
+pub async fn cancel(state: &AppState, id: &str, identity: &Identity) -> Result<ProofRecord, AppError> {
+    let id = retention::resolve_proof_id(state, id).await?;
+    let record = state.proof_store.update(&id, |proof| {
+        if !matches!(proof.status, ProofStatus::Queued | ProofStatus::AwaitingApproval | ProofStatus::Proving { .. }) {
+            return Err(AppError::new(
+                ErrorCode::InvalidArguments,
+                format!("Proof {} is {} and can't be cancelled", short_id(&proof.id), proof.status.describe()),
+            ));
+        }
+        proof.status = ProofStatus::Cancelled;
+        Ok(proof.clone())
+    }).await.ok_or_else(AppError::proof_not_found)??;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    approvals::abandon(state, &id).await;
+    info!(target: "audit", proof_id = %id, subject = %identity.subject, "Proof cancelled");
+    state.publish(WsMessage::event(
+        format!("Proof {} was cancelled", short_id(&id)),
+        WsEvent::ProofCancelled { proof_id: id.clone() },
+    ).for_session(record.session_id.clone()));
+    Ok(record)
+}
+
+// Stop a zkEngine run whose proof was cancelled
+pub fn kill(proof_id: &str, pid: u32) {
+    #[cfg(unix)]
+    {
+        // The pid is still ours: the child isn't reaped until the run is over
+        if unsafe { libc::kill(pid as libc::pid_t, libc::SIGKILL) } == 0 {
+            info!("Stopped zkEngine (pid {}) for cancelled proof {}", pid, short_id(proof_id));
+        } else {
+            warn!("Failed to stop zkEngine (pid {}) for cancelled proof {}: {}", pid, short_id(proof_id), std::io::Error::last_os_error());
+        }
+    }
+    #[cfg(not(unix))]
+    warn!("Can't stop zkEngine (pid {}) for cancelled proof {} on this platform; it will run to the end", pid, short_id(proof_id));
+}
+
+pub async fn handler(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    Path(id): Path<String>,
+) -> Response {
+    match cancel(&state, &id, &identity).await {
+        Ok(proof) => Json(json!({ "success": true, "proof": proof })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
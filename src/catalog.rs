@@ -0,0 +1,372 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use zkp_agentkit_client::types::PublicInput;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    locations,
+};
+
+// Catalog of the proof functions the agent knows how to run. The help
+// command, welcome message, tool definitions and intent fallbacks are all
+// generated from it, so registering a function here is enough for users to
+// see how to invoke it.
+
+// What zkEngine accepts for an argument
+#[derive(Clone, Copy)]
+pub enum ArgKind {
+    // Any 64-bit integer
+    Integer,
+    // 0 or 1
+    Flag,
+    // A place from the location catalog (see locations.rs)
+    City,
+}
+
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+}
+
+const fn int(name: &'static str) -> Arg {
+    Arg { name, kind: ArgKind::Integer }
+}
+
+pub struct FunctionSpec {
+    pub name: &'static str,
+    pub wasm_file: &'static str,
+    pub arguments: &'static [Arg],
+    pub description: &'static str,
+    pub example: &'static str,
+}
+
+pub const FUNCTIONS: [FunctionSpec; 11] = [
+    FunctionSpec {
+        name: "prove_location",
+        wasm_file: "prove_location.wat",
+        arguments: &[Arg { name: "city", kind: ArgKind::City }, int("device_id")],
+        description: "Prove a device is in a known place",
+        example: "prove location sf device 42",
+    },
+    FunctionSpec {
+        name: "prove_kyc",
+        wasm_file: "prove_kyc.wat",
+        arguments: &[int("wallet_hash"), Arg { name: "kyc_approved", kind: ArgKind::Flag }],
+        description: "Prove a wallet passed KYC without revealing it",
+        example: "prove kyc",
+    },
+    FunctionSpec {
+        name: "prove_ai_content",
+        wasm_file: "prove_ai_content.wat",
+        arguments: &[int("content_hash"), int("provider_id")],
+        description: "Prove content came from an AI provider",
+        example: "prove ai content",
+    },
+    FunctionSpec {
+        name: "fibonacci",
+        wasm_file: "fib.wat",
+        arguments: &[int("n")],
+        description: "Compute the nth Fibonacci number",
+        example: "prove fibonacci of 10",
+    },
+    FunctionSpec {
+        name: "add",
+        wasm_file: "add.wat",
+        arguments: &[int("a"), int("b")],
+        description: "Add two numbers",
+        example: "add 3 and 5",
+    },
+    FunctionSpec {
+        name: "multiply",
+        wasm_file: "multiply.wat",
+        arguments: &[int("a"), int("b")],
+        description: "Multiply two numbers",
+        example: "multiply 6 by 7",
+    },
+    FunctionSpec {
+        name: "factorial",
+        wasm_file: "factorial_i32.wat",
+        arguments: &[int("n")],
+        description: "Compute n!",
+        example: "prove factorial of 5",
+    },
+    FunctionSpec {
+        name: "is_even",
+        wasm_file: "is_even.wat",
+        arguments: &[int("n")],
+        description: "Check whether a number is even",
+        example: "is 8 even",
+    },
+    FunctionSpec {
+        name: "square",
+        wasm_file: "square.wat",
+        arguments: &[int("n")],
+        description: "Square a number",
+        example: "square 9",
+    },
+    FunctionSpec {
+        name: "max",
+        wasm_file: "max.wat",
+        arguments: &[int("a"), int("b")],
+        description: "Pick the larger of two numbers",
+        example: "max of 4 and 11",
+    },
+    FunctionSpec {
+        name: "count_until",
+        wasm_file: "count_until.wat",
+        arguments: &[int("n")],
+        description: "Count up to n",
+        example: "count until 20",
+    },
+];
+
+// Non-proof chat commands, shown after the functions in help
+const COMMANDS: [(&str, &str); 12] = [
+    ("list proofs / list verifications", "Show proof or verification history"),
+    ("verify [proof-id | my last location proof]", "Verify a proof (the latest one by default)"),
+    ("compare proof A and proof B", "Diff two proofs"),
+    ("stats", "Proof and verification metrics"),
+    ("export proof <id> as bundle/json", "Get a download link to hand a proof to someone else"),
+    ("delete proof <id>", "Delete a proof (asks first)"),
+    ("archive proofs older than <N> days", "Move old proofs out of the history (asks first)"),
+    ("prove custom <wasm-file>", "Prove any module in the wasm directory"),
+    ("run the <name> template", "Prove a saved template"),
+    ("approve/reject proof <id>", "Review a proof awaiting approval"),
+    ("cancel proof <id>", "Stop a proof that hasn't finished"),
+    ("rerun proof <id>", "Run a finished proof again"),
+];
+
+// Custom modules have no declared signature; cap how much gets passed through
+const MAX_CUSTOM_ARGUMENTS: usize = 16;
+
+impl ArgKind {
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            ArgKind::Integer => value.parse::<i64>().is_ok(),
+            ArgKind::Flag => matches!(value, "0" | "1"),
+            ArgKind::City => locations::resolve(value).is_ok(),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            ArgKind::Integer => "an integer",
+            ArgKind::Flag => "0 or 1",
+            ArgKind::City => "a known place",
+        }
+    }
+}
+
+impl FunctionSpec {
+    pub fn argument_names(&self) -> Vec<&'static str> {
+        self.arguments.iter().map(|a| a.name).collect()
+    }
+
+    // Type-check arguments against the signature
+    pub fn check_arguments(&self, arguments: &[String]) -> Result<(), AppError> {
+        if arguments.len() != self.arguments.len() {
+            return Err(AppError::new(
+                ErrorCode::InvalidArguments,
+                format!("{} takes {} argument(s) ({}), got {}", self.name, self.arguments.len(), self.argument_names().join(", "), arguments.len()),
+            ));
+        }
+        for (arg, value) in self.arguments.iter().zip(arguments) {
+            if let ArgKind::City = arg.kind {
+                locations::resolve(value).map_err(|e| AppError::new(e.code, format!("{} of {}: {}", arg.name, self.name, e.message)))?;
+            } else if !arg.kind.accepts(value) {
+                return Err(AppError::new(
+                    ErrorCode::InvalidArguments,
+                    format!("{} of {} must be {}, got '{}'", arg.name, self.name, arg.kind.describe(), value),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Arguments as zkEngine takes them: places become their location codes
+    pub fn encode_arguments(&self, arguments: &[String]) -> Result<Vec<String>, AppError> {
+        self.arguments
+            .iter()
+            .zip(arguments)
+            .map(|(arg, value)| match arg.kind {
+                ArgKind::City => locations::resolve(value).map(|code| code.to_string()),
+                _ => Ok(value.clone()),
+            })
+            .collect()
+    }
+}
+
+// Catalog function a module belongs to
+fn module_spec(wasm_file: &str) -> Option<&'static FunctionSpec> {
+    FUNCTIONS.iter().find(|f| f.wasm_file == wasm_file)
+}
+
+// Arguments for a module as zkEngine takes them; custom modules' are passed
+// through
+pub fn encode_module_arguments(wasm_file: &str, arguments: &[String]) -> Result<Vec<String>, AppError> {
+    match module_spec(wasm_file) {
+        Some(spec) => spec.encode_arguments(arguments),
+        None => Ok(arguments.to_vec()),
+    }
+}
+
+// The arguments a module was proven with, as zkEngine received them and
+// named after the catalog function's parameters (arg1, arg2, ... for
+// custom modules)
+pub fn public_inputs(wasm_file: &str, arguments: &[String]) -> Vec<PublicInput> {
+    let values = encode_module_arguments(wasm_file, arguments).unwrap_or_else(|_| arguments.to_vec());
+    let spec = module_spec(wasm_file);
+    values.into_iter()
+        .enumerate()
+        .map(|(i, value)| PublicInput {
+            name: spec.and_then(|spec| spec.arguments.get(i)).map_or_else(|| format!("arg{}", i + 1), |arg| arg.name.to_string()),
+            value,
+        })
+        .collect()
+}
+
+// Check arguments for a module: against its signature when it belongs to a
+// catalog function, otherwise each must be an integer
+fn check_module_arguments(wasm_file: &str, arguments: &[String]) -> Result<(), AppError> {
+    if let Some(spec) = module_spec(wasm_file) {
+        return spec.check_arguments(arguments);
+    }
+    if arguments.len() > MAX_CUSTOM_ARGUMENTS {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("At most {} arguments are allowed", MAX_CUSTOM_ARGUMENTS)));
+    }
+    match arguments.iter().find(|value| !ArgKind::Integer.accepts(value)) {
+        Some(value) => Err(AppError::new(ErrorCode::InvalidArguments, format!("Arguments must be integers, got '{}'", value))),
+        None => Ok(()),
+    }
+}
+
+// Path of a module clients asked for by name. Only bare file names of
+// catalog modules or modules in the wasm directory are accepted, so
+// requests can't reach files elsewhere.
+fn wasm_path(wasm_dir: &str, wasm_file: &str) -> Result<PathBuf, AppError> {
+    let invalid = |reason: &str| AppError::new(ErrorCode::InvalidArguments, format!("Invalid wasm_file '{}': {}", wasm_file, reason));
+    if wasm_file.contains(['/', '\\', '\0']) || wasm_file.starts_with('.') {
+        return Err(invalid("must be a file name in the wasm directory"));
+    }
+    if !matches!(Path::new(wasm_file).extension().and_then(|e| e.to_str()), Some("wat" | "wasm")) {
+        return Err(invalid("must be a .wat or .wasm module"));
+    }
+    let registered = FUNCTIONS.iter().any(|f| f.wasm_file == wasm_file) || modules(wasm_dir).iter().any(|m| m == wasm_file);
+    let path = Path::new(wasm_dir).join(wasm_file);
+    if !registered || !path.is_file() {
+        return Err(AppError::new(ErrorCode::WasmNotFound, format!("WASM file not found: {}", wasm_file)));
+    }
+    Ok(path)
+}
+
+pub fn check_step_size(step_size: u64) -> Result<(), AppError> {
+    if step_size == 0 {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "step_size must be at least 1"));
+    }
+    Ok(())
+}
+
+// Validate a raw proof request for a module before anything is spawned;
+// returns the module's path
+pub fn validate_request(wasm_dir: &str, wasm_file: &str, arguments: &[String], step_size: u64) -> Result<PathBuf, AppError> {
+    let path = wasm_path(wasm_dir, wasm_file)?;
+    check_module_arguments(wasm_file, arguments)?;
+    check_step_size(step_size)?;
+    Ok(path)
+}
+
+pub fn find(name: &str) -> Option<&'static FunctionSpec> {
+    FUNCTIONS.iter().find(|f| f.name == name)
+}
+
+// Modules that prove a catalog function instead of its own (WASM_FUNCTION_MAP),
+// kept up to date by the live configuration
+static WASM_OVERRIDES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+pub fn set_wasm_overrides(overrides: BTreeMap<String, String>) {
+    *WASM_OVERRIDES.write().unwrap() = overrides;
+}
+
+// Module that proves a catalog function
+pub fn wasm_file(function: &str) -> Option<String> {
+    let spec = find(function)?;
+    let overridden = WASM_OVERRIDES.read().unwrap().get(function).cloned();
+    Some(overridden.unwrap_or_else(|| spec.wasm_file.to_string()))
+}
+
+// Whether a module proves one of the catalog's functions
+pub fn is_catalog_module(wasm_file: &str) -> bool {
+    FUNCTIONS.iter().any(|f| self::wasm_file(f.name).as_deref() == Some(wasm_file))
+}
+
+// Function names, comma separated
+pub fn names() -> String {
+    FUNCTIONS.iter().map(|f| f.name).collect::<Vec<_>>().join(", ")
+}
+
+// Short list of examples, for fallbacks when an input wasn't understood
+pub fn examples() -> String {
+    let examples: Vec<&str> = FUNCTIONS.iter().map(|f| f.example).collect();
+    format!("I can handle: {}, verify, list proofs, stats. Type 'help' for details.", examples.join(", "))
+}
+
+// Module files in the wasm directory
+fn modules(wasm_dir: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(wasm_dir) else {
+        return Vec::new();
+    };
+    let mut modules: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            let ext = Path::new(name).extension().and_then(|e| e.to_str());
+            matches!(ext, Some("wat" | "wasm"))
+        })
+        .collect();
+    modules.sort();
+    modules
+}
+
+// Modules in the wasm directory that aren't in the catalog; they can still
+// be proven with "prove custom <file>"
+fn unregistered_modules(wasm_dir: &str) -> Vec<String> {
+    modules(wasm_dir)
+        .into_iter()
+        .filter(|name| !FUNCTIONS.iter().any(|f| f.wasm_file == name))
+        .collect()
+}
+
+pub fn help_text(wasm_dir: &str) -> String {
+    let mut lines = vec!["Proofs I can generate:".to_string()];
+    for function in &FUNCTIONS {
+        lines.push(format!(
+            "• {}({}): {}. Try \"{}\"",
+            function.name,
+            function.argument_names().join(", "),
+            function.description,
+            function.example
+        ));
+    }
+    lines.push("Add \"step size N\" to any proof to change the step size.".to_string());
+
+    let modules = unregistered_modules(wasm_dir);
+    if !modules.is_empty() {
+        let shown = modules.iter().take(10).cloned().collect::<Vec<_>>().join(", ");
+        let more = match modules.len() {
+            n if n > 10 => format!(" and {} more", n - 10),
+            _ => String::new(),
+        };
+        lines.push(format!("Other modules (use \"prove custom <file>\"): {}{}", shown, more));
+    }
+
+    lines.push("Commands:".to_string());
+    for (command, description) in COMMANDS {
+        lines.push(format!("• {}: {}", command, description));
+    }
+    lines.push(crate::slash::USAGE.to_string());
+    lines.join("\n")
+}
@@ -0,0 +1,498 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use futures_util::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{collections::HashMap, time::Duration};
+use tokio_stream::{wrappers::BroadcastStream, StreamMap};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{info, warn};
+
+use crate::{
+    auth::Role,
+    events::Topic,
+    history,
+    origin::{self, Channel, Origin},
+    outbound::{self, Integration},
+    process_nl_command,
+    protocol::{WsEvent, WsMessage},
+    AppState, ChatSession,
+};
+
+// Slack and Discord bots driving the same command processor as the web
+// chat. Each channel (or DM) is one chat session, so "prove fib of 10",
+// slash commands like "/zk verify last", proposals and their yes/no
+// answers work as they do in the browser, and the session's proof
+// progress, results and verification outcomes are posted back into the
+// channel.
+//
+//   SLACK_BOT_TOKEN          bot token (xoxb-...), for chat.postMessage
+//   SLACK_SIGNING_SECRET     checks requests really come from Slack
+//   DISCORD_BOT_TOKEN        bot token; also connects to the gateway to read
+//                            messages that mention the bot, and DMs
+//   DISCORD_PUBLIC_KEY       application public key (hex), checks interactions
+//   CHAT_BRIDGE_ROLE         role bridge users act with (default prover)
+//
+// Point Slack's slash command at /bridge/slack/commands and its Events API
+// (app_mention and message.im) at /bridge/slack/events; point Discord's
+// interactions endpoint at /bridge/discord/interactions and register a
+// slash command with one string option, e.g. /zk text:<request>. Anyone who
+// can talk to the bot acts with CHAT_BRIDGE_ROLE, so keep it to channels
+// whose members should have that role.
+
+const SLACK_API: &str = "https://slack.com/api/chat.postMessage";
+const DISCORD_API: &str = "https://discord.com/api/v10";
+const DISCORD_GATEWAY: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+// GUILD_MESSAGES, DIRECT_MESSAGES and MESSAGE_CONTENT
+const DISCORD_INTENTS: u64 = (1 << 9) | (1 << 12) | (1 << 15);
+// Slack requests older than this are replays
+const SLACK_MAX_AGE_SECS: i64 = 300;
+const DISCORD_MAX_LEN: usize = 2000;
+const SLACK_MAX_LEN: usize = 3900;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Platform {
+    Slack,
+    Discord,
+}
+
+impl Platform {
+    fn name(&self) -> &'static str {
+        match self {
+            Platform::Slack => "slack",
+            Platform::Discord => "discord",
+        }
+    }
+}
+
+pub struct ChatBridge {
+    slack_token: Option<String>,
+    slack_secret: Option<String>,
+    discord_token: Option<String>,
+    discord_key: Option<VerifyingKey>,
+    role: Role,
+    // Channel each bridged session posts to
+    channels: std::sync::Mutex<HashMap<String, (Platform, String)>>,
+    client: reqwest::Client,
+}
+
+impl ChatBridge {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let discord_key = var("DISCORD_PUBLIC_KEY").and_then(|hex| {
+            let key = decode_hex(&hex).and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+            let key = key.and_then(|key| VerifyingKey::from_bytes(&key).ok());
+            if key.is_none() {
+                warn!("DISCORD_PUBLIC_KEY is not a hex Ed25519 public key; Discord interactions are off");
+            }
+            key
+        });
+        ChatBridge {
+            slack_token: var("SLACK_BOT_TOKEN"),
+            slack_secret: var("SLACK_SIGNING_SECRET"),
+            discord_token: var("DISCORD_BOT_TOKEN"),
+            discord_key,
+            role: var("CHAT_BRIDGE_ROLE").and_then(|name| Role::parse(&name)).unwrap_or(Role::Prover),
+            channels: Default::default(),
+            client: outbound::client(Integration::ChatBridge),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.slack_token.is_some() || self.discord_token.is_some()
+    }
+
+    // Post to a channel, split to the platform's message size
+    async fn post(&self, platform: Platform, channel: &str, text: &str) {
+        let limit = match platform {
+            Platform::Slack => SLACK_MAX_LEN,
+            Platform::Discord => DISCORD_MAX_LEN,
+        };
+        for chunk in chunks(text, limit) {
+            let result = match (platform, &self.slack_token, &self.discord_token) {
+                (Platform::Slack, Some(token), _) => self.client.post(SLACK_API)
+                    .bearer_auth(token)
+                    .json(&json!({ "channel": channel, "text": chunk }))
+                    .send()
+                    .await,
+                (Platform::Discord, _, Some(token)) => self.client
+                    .post(format!("{}/channels/{}/messages", DISCORD_API, channel))
+                    .header("Authorization", format!("Bot {}", token))
+                    .json(&json!({ "content": chunk }))
+                    .send()
+                    .await,
+                _ => return,
+            };
+            match result {
+                // Slack answers 200 with {"ok": false} on errors
+                Ok(response) if response.status().is_success() => {
+                    if platform == Platform::Slack {
+                        let body: Value = response.json().await.unwrap_or_default();
+                        if body["ok"] == json!(false) {
+                            warn!("Slack refused a message for {}: {}", channel, body["error"]);
+                        }
+                    }
+                }
+                Ok(response) => warn!("{} refused a message for {}: {}", platform.name(), channel, response.status()),
+                Err(e) => warn!("Failed to post to {} channel {}: {}", platform.name(), channel, e),
+            }
+        }
+    }
+}
+
+// Split text at line breaks where possible, never inside a character
+fn chunks(text: &str, limit: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = rest[..end].rfind('\n').filter(|&at| at > 0).unwrap_or(end);
+        chunks.push(&rest[..end]);
+        rest = rest[end..].trim_start_matches('\n');
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if hex.len() % 2 == 1 {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+// Drop mentions of the bot (and anyone else) from a message
+fn strip_mentions(text: &str) -> String {
+    text.split_whitespace()
+        .filter(|word| !(word.starts_with("<@") && word.ends_with('>')))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Bridged channels' sessions; WebSocket clients can't choose these IDs
+fn session_id(platform: Platform, channel: &str) -> String {
+    format!("{}-{}", platform.name(), channel)
+}
+
+pub fn is_bridge_session(session_id: &str) -> bool {
+    [Platform::Slack, Platform::Discord].iter().any(|platform| session_id.starts_with(&format!("{}-", platform.name())))
+}
+
+// Run a message from a channel through the command processor, as the web
+// chat does; replies and events go back through the forwarder
+async fn handle_message(state: AppState, platform: Platform, channel: String, user: String, text: String) {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return;
+    }
+    let session_id = session_id(platform, &channel);
+    let subject = format!("{}:{}", platform.name(), user);
+    state.chat_bridge.channels.lock().unwrap().insert(session_id.clone(), (platform, channel));
+    // The channel's session belongs to the platform; each message is sent
+    // on behalf of its speaker through the origin below
+    state.session_store.lock().await.entry(session_id.clone()).or_insert_with(|| ChatSession {
+        llm_session_id: history::conversation_id(platform.name(), &session_id),
+        subject: platform.name().to_string(),
+        role: state.chat_bridge.role,
+        // Never closed, so never cleaned up
+        connections: 1,
+        language: Default::default(),
+    });
+    info!("[{}] session {}: {}", subject, session_id, text);
+    let conversation_id = state.conversation_id(&session_id).await;
+    state.history.begin_turn(&conversation_id, &text).await;
+    let origin = Origin {
+        subject: Some(subject),
+        role: Some(state.chat_bridge.role),
+        ..Origin::new(match platform {
+            Platform::Slack => Channel::Slack,
+            Platform::Discord => Channel::Discord,
+        })
+    };
+    let response = origin::scope(origin, process_nl_command(&state, &session_id, &text)).await;
+    if !response.message.is_empty() {
+        state.history.record_response(&conversation_id, &response.message).await;
+    }
+    if !response.message.is_empty() || response.data.is_some() {
+        state.publish(WsMessage::new(response.message, response.data).for_session(Some(session_id)));
+    }
+}
+
+// Whether a session message is worth posting to a channel
+fn postable(msg: &WsMessage) -> bool {
+    !msg.content.trim().is_empty()
+        && !matches!(
+            msg.data,
+            Some(WsEvent::ChatDelta { .. } | WsEvent::Subscription { .. } | WsEvent::Lagged { .. } | WsEvent::Welcome { .. })
+        )
+}
+
+// Post bridged sessions' messages to their channels, and read Discord
+// messages from the gateway
+pub fn spawn(state: &AppState) {
+    let bridge = &state.chat_bridge;
+    if !bridge.enabled() {
+        return;
+    }
+    let platforms: Vec<&str> = [bridge.slack_token.as_ref().map(|_| "Slack"), bridge.discord_token.as_ref().map(|_| "Discord")]
+        .into_iter()
+        .flatten()
+        .collect();
+    info!("Chat bridge on for {} (role {})", platforms.join(" and "), bridge.role.name());
+
+    let forward_state = state.clone();
+    tokio::spawn(async move {
+        let mut streams = StreamMap::new();
+        for topic in Topic::ALL {
+            streams.insert(topic, BroadcastStream::new(forward_state.events.subscribe(topic)));
+        }
+        while let Some((_, msg)) = streams.next().await {
+            // A lagged channel only loses messages; carry on
+            let Ok(msg) = msg else {
+                continue;
+            };
+            let Some(session_id) = &msg.session_id else {
+                continue;
+            };
+            let target = forward_state.chat_bridge.channels.lock().unwrap().get(session_id).cloned();
+            if let Some((platform, channel)) = target.filter(|_| postable(&msg)) {
+                let state = forward_state.clone();
+                tokio::spawn(async move { state.chat_bridge.post(platform, &channel, &msg.content).await });
+            }
+        }
+    });
+
+    if let Some(token) = bridge.discord_token.clone() {
+        let state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = discord_gateway(&state, &token).await {
+                    warn!("Discord gateway connection lost: {}", e);
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+}
+
+// First tick after one period, not at once
+fn heartbeat_every(period: Duration) -> tokio::time::Interval {
+    tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+}
+
+// One gateway connection: identify, heartbeat and hand on messages that
+// mention the bot or are sent to it directly
+async fn discord_gateway(state: &AppState, token: &str) -> anyhow::Result<()> {
+    let (socket, _) = connect_async(DISCORD_GATEWAY).await?;
+    let (mut write, mut read) = socket.split();
+    // Rescheduled at the gateway's pace once it says hello
+    let mut heartbeat = heartbeat_every(Duration::from_secs(41));
+    let mut seq: Option<u64> = None;
+    let mut bot_id = String::new();
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                write.send(Message::Text(json!({ "op": 1, "d": seq }).to_string())).await?;
+            }
+            message = read.next() => {
+                let message = message.ok_or_else(|| anyhow::anyhow!("gateway closed the connection"))??;
+                let Message::Text(text) = message else {
+                    continue;
+                };
+                let payload: Value = serde_json::from_str(&text)?;
+                if let Some(s) = payload["s"].as_u64() {
+                    seq = Some(s);
+                }
+                match payload["op"].as_u64() {
+                    // Hello: start heartbeating at the gateway's pace and identify
+                    Some(10) => {
+                        let interval = payload["d"]["heartbeat_interval"].as_u64().unwrap_or(41_250);
+                        heartbeat = heartbeat_every(Duration::from_millis(interval));
+                        let identify = json!({
+                            "op": 2,
+                            "d": {
+                                "token": token,
+                                "intents": DISCORD_INTENTS,
+                                "properties": { "os": std::env::consts::OS, "browser": "zkp-agentkit", "device": "zkp-agentkit" },
+                            },
+                        });
+                        write.send(Message::Text(identify.to_string())).await?;
+                    }
+                    Some(1) => write.send(Message::Text(json!({ "op": 1, "d": seq }).to_string())).await?,
+                    // Reconnect or invalid session
+                    Some(7) | Some(9) => anyhow::bail!("gateway asked for a reconnect"),
+                    Some(0) => match payload["t"].as_str() {
+                        Some("READY") => {
+                            bot_id = payload["d"]["user"]["id"].as_str().unwrap_or_default().to_string();
+                            info!("Connected to the Discord gateway as {}", payload["d"]["user"]["username"]);
+                        }
+                        Some("MESSAGE_CREATE") => on_discord_message(state, &bot_id, &payload["d"]),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn on_discord_message(state: &AppState, bot_id: &str, message: &Value) {
+    if message["author"]["bot"] == json!(true) {
+        return;
+    }
+    let direct = message["guild_id"].is_null();
+    let mentioned = message["mentions"].as_array().is_some_and(|m| m.iter().any(|user| user["id"] == json!(bot_id)));
+    if !(direct || mentioned) {
+        return;
+    }
+    let (Some(channel), Some(user), Some(text)) = (
+        message["channel_id"].as_str(),
+        message["author"]["id"].as_str(),
+        message["content"].as_str(),
+    ) else {
+        return;
+    };
+    tokio::spawn(handle_message(state.clone(), Platform::Discord, channel.to_string(), user.to_string(), strip_mentions(text)));
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, "invalid request signature").into_response()
+}
+
+// Slack signs "v0:<timestamp>:<body>" with the signing secret
+fn slack_signed(state: &AppState, headers: &HeaderMap, body: &[u8]) -> bool {
+    let Some(secret) = &state.chat_bridge.slack_secret else {
+        return false;
+    };
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (header("x-slack-request-timestamp"), header("x-slack-signature")) else {
+        return false;
+    };
+    let fresh = timestamp.parse::<i64>().is_ok_and(|at| (chrono::Utc::now().timestamp() - at).abs() <= SLACK_MAX_AGE_SECS);
+    let Some(signature) = signature.strip_prefix("v0=").and_then(decode_hex) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    fresh && mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Deserialize)]
+struct SlashCommand {
+    channel_id: String,
+    user_id: String,
+    #[serde(default)]
+    text: String,
+}
+
+pub async fn slack_command_handler(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    if !slack_signed(&state, &headers, &body) {
+        return unauthorized();
+    }
+    let Ok(command) = serde_urlencoded::from_bytes::<SlashCommand>(&body) else {
+        return (StatusCode::BAD_REQUEST, "malformed slash command").into_response();
+    };
+    tokio::spawn(handle_message(state, Platform::Slack, command.channel_id, command.user_id, command.text));
+    // Slack wants an answer within three seconds; this one shows the
+    // command in the channel, and the results follow as messages
+    Json(json!({ "response_type": "in_channel" })).into_response()
+}
+
+pub async fn slack_events_handler(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    if !slack_signed(&state, &headers, &body) {
+        return unauthorized();
+    }
+    let Ok(payload) = serde_json::from_slice::<Value>(&body) else {
+        return (StatusCode::BAD_REQUEST, "malformed event").into_response();
+    };
+    if payload["type"] == "url_verification" {
+        return Json(json!({ "challenge": payload["challenge"] })).into_response();
+    }
+    // Slack retries events it thinks were missed; the first copy was handled
+    if headers.contains_key("x-slack-retry-num") {
+        return StatusCode::OK.into_response();
+    }
+    let event = &payload["event"];
+    let wanted = match event["type"].as_str() {
+        Some("app_mention") => true,
+        Some("message") => event["channel_type"] == "im",
+        _ => false,
+    };
+    // Skip the bot's own messages and edits
+    if wanted && event["bot_id"].is_null() && event["subtype"].is_null() {
+        if let (Some(channel), Some(user), Some(text)) = (event["channel"].as_str(), event["user"].as_str(), event["text"].as_str()) {
+            tokio::spawn(handle_message(state, Platform::Slack, channel.to_string(), user.to_string(), strip_mentions(text)));
+        }
+    }
+    StatusCode::OK.into_response()
+}
+
+// Discord signs "<timestamp><body>" with the application's Ed25519 key
+fn discord_signed(state: &AppState, headers: &HeaderMap, body: &[u8]) -> bool {
+    let Some(key) = &state.chat_bridge.discord_key else {
+        return false;
+    };
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (header("x-signature-timestamp"), header("x-signature-ed25519")) else {
+        return false;
+    };
+    let Some(signature) = decode_hex(signature).and_then(|bytes| <[u8; 64]>::try_from(bytes).ok()) else {
+        return false;
+    };
+    let mut message = timestamp.as_bytes().to_vec();
+    message.extend_from_slice(body);
+    key.verify(&message, &Signature::from_bytes(&signature)).is_ok()
+}
+
+pub async fn discord_interactions_handler(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    if !discord_signed(&state, &headers, &body) {
+        return unauthorized();
+    }
+    let Ok(interaction) = serde_json::from_slice::<Value>(&body) else {
+        return (StatusCode::BAD_REQUEST, "malformed interaction").into_response();
+    };
+    match interaction["type"].as_u64() {
+        // Ping
+        Some(1) => Json(json!({ "type": 1 })).into_response(),
+        // Slash command: its first option is the request
+        Some(2) => {
+            let text = interaction["data"]["options"][0]["value"].as_str().unwrap_or_default().to_string();
+            let user = interaction["member"]["user"]["id"].as_str().or(interaction["user"]["id"].as_str()).unwrap_or_default();
+            let Some(channel) = interaction["channel_id"].as_str() else {
+                return (StatusCode::BAD_REQUEST, "interaction without a channel").into_response();
+            };
+            tokio::spawn(handle_message(state, Platform::Discord, channel.to_string(), user.to_string(), text.clone()));
+            // Echo the request; the results follow as channel messages
+            let echo: String = format!("> {}", text).chars().take(DISCORD_MAX_LEN).collect();
+            Json(json!({ "type": 4, "data": { "content": echo } })).into_response()
+        }
+        _ => (StatusCode::BAD_REQUEST, "unsupported interaction").into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bridge_sessions_are_recognised() {
+        assert!(is_bridge_session(&session_id(Platform::Slack, "C0123ABCD")));
+        assert!(is_bridge_session(&session_id(Platform::Discord, "112233445566")));
+        assert!(!is_bridge_session("0f8e2c1a-5b6d-4e7f-8a9b-0c1d2e3f4a5b"));
+        assert!(!is_bridge_session("my-slack-session"));
+    }
+}
@@ -0,0 +1,431 @@
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+use uuid::Uuid;
+
+use crate::{
+    benchmark, catalog, config, doctor,
+    errors::{AppError, ErrorCode},
+    listen, locations, origin, proof_db, prover_logs, sandbox, secrets, short_id, signing, ui, verifier,
+    ProofMetadata, ProofMetrics, ProofRecord, ProofStatus, DEFAULT_STEP_SIZE,
+};
+
+// Command line. `serve` (the default) runs the HTTP and WebSocket service,
+// `verifier` a server that only verifies proofs (see verifier.rs);
+// `prove`, `verify`, `list` and `benchmark` work on the local proofs
+// directory and proofs_db without it, using the same validation, sandbox,
+// signing key and proof store as the server. Every option falls back to the environment
+// variable the server has always read. A running server doesn't see proofs
+// made with `prove` and leaves them out when it rewrites the index, but
+// keeps their records; they show up after a restart (see proof_db.rs).
+
+#[derive(Parser)]
+#[command(name = "zkp-agentkit", version, about = "zkEngine proof agent: HTTP/WebSocket service and local prover")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+    #[command(flatten)]
+    pub paths: Paths,
+    // Serve options given without the `serve` subcommand
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Args)]
+pub struct Paths {
+    /// zkEngine binary
+    #[arg(long, global = true, env = "ZKENGINE_BINARY", default_value = "/home/hshadab/zkengine/zkEngine_dev/wasm_file")]
+    pub zkengine_binary: String,
+    /// Directory of WASM modules that may be proven
+    #[arg(long, global = true, env = "WASM_DIR", default_value = "/home/hshadab/agentkit/zkengine/example_wasms")]
+    pub wasm_dir: String,
+    /// Directory proofs are written to
+    #[arg(long, global = true, env = "PROOFS_DIR", default_value = "./proofs")]
+    pub proofs_dir: String,
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Run the HTTP and WebSocket service (the default)
+    Serve(ServeArgs),
+    /// Generate a proof locally
+    Prove(ProveArgs),
+    /// Verify a proof directory
+    Verify(VerifyArgs),
+    /// List stored proofs, newest first
+    List(ListArgs),
+    /// Check the zkEngine binary, wasm and proofs directories and LangChain service
+    Doctor(DoctorArgs),
+    /// Time proving and verifying a standard set of functions and step sizes
+    Benchmark(BenchmarkArgs),
+    /// Run a server that verifies proofs made elsewhere and never generates any
+    Verifier(VerifierArgs),
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, env = "PORT", default_value_t = 8001)]
+    pub port: u16,
+    /// Listeners as [all|public|admin@]address, e.g. public@0.0.0.0:8001,admin@127.0.0.1:9001,unix:/run/zkp.sock (default: 0.0.0.0 on --port)
+    #[arg(long, env = "LISTEN", value_delimiter = ',')]
+    pub listen: Vec<listen::Listener>,
+    /// LangChain service for the langchain intent provider
+    #[arg(long, env = "LANGCHAIN_SERVICE_URL", default_value = "http://localhost:8002")]
+    pub langchain_url: String,
+    /// Speak MCP over stdin and stdout instead of serving HTTP
+    #[arg(long)]
+    pub mcp_stdio: bool,
+    /// Web UI: files, embedded or headless (default: embedded in builds with the embed-ui feature, else files)
+    #[arg(long, env = "UI_MODE", value_enum)]
+    pub ui: Option<ui::UiMode>,
+    /// Directory the UI is served from in files mode
+    #[arg(long, env = "STATIC_DIR", default_value = "static")]
+    pub static_dir: String,
+    /// Page served at / in files mode (default: index.html in the static directory)
+    #[arg(long, env = "UI_INDEX")]
+    pub ui_index: Option<String>,
+}
+
+#[derive(Args)]
+pub struct VerifierArgs {
+    /// Port to listen on
+    #[arg(long, env = "PORT", default_value_t = 8001)]
+    pub port: u16,
+    /// Listeners, as for serve
+    #[arg(long, env = "LISTEN", value_delimiter = ',')]
+    pub listen: Vec<listen::Listener>,
+    /// Directory that proofs verified by path must be in (default: the proofs directory)
+    #[arg(long, env = "VERIFIER_ROOT")]
+    pub root: Option<String>,
+    /// IPFS gateway proof bundles are fetched from by CID
+    #[arg(long, env = "IPFS_GATEWAY", default_value = "https://ipfs.io/ipfs")]
+    pub ipfs_gateway: String,
+}
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// LangChain service to check
+    #[arg(long, env = "LANGCHAIN_SERVICE_URL", default_value = "http://localhost:8002")]
+    langchain_url: String,
+    /// Print the checks as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct BenchmarkArgs {
+    /// Only these functions of the benchmark, comma separated
+    #[arg(long, value_delimiter = ',')]
+    functions: Vec<String>,
+    /// Step sizes to run (default 10, 50 and 100)
+    #[arg(long = "steps", value_delimiter = ',')]
+    step_sizes: Vec<u64>,
+    /// Print the report as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct ProveArgs {
+    /// Module in the wasm directory (e.g. fib.wat), or a path to one
+    #[arg(long)]
+    wasm: String,
+    /// Arguments for the module, comma separated or repeated
+    #[arg(long = "args", value_delimiter = ',', num_args = 1.., allow_negative_numbers = true)]
+    args: Vec<String>,
+    /// zkEngine step size
+    #[arg(long, default_value_t = 50)]
+    step: u64,
+    /// Function name stored with the proof (default: the catalog name of the module, or main)
+    #[arg(long)]
+    function: Option<String>,
+    /// Print the proof record as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Directory holding proof.bin and public.json, or the ID of a stored proof
+    proof: String,
+    /// zkEngine step size the proof was made with (default: the stored proof's, or 50)
+    #[arg(long)]
+    step: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only proofs with this status: queued, awaiting_approval, proving,
+    /// finalizing, complete, failed or cancelled
+    #[arg(long)]
+    status: Option<String>,
+    /// Show at most this many proofs (0 for all)
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+    /// Print the proof records as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+// Run a local command; returns the process exit code
+pub async fn run(paths: &Paths, command: CliCommand) -> i32 {
+    let result = match command {
+        CliCommand::Serve(_) | CliCommand::Verifier(_) => unreachable!("servers are started by main"),
+        CliCommand::Prove(args) => prove(paths, args).await,
+        CliCommand::Verify(args) => verify(paths, args).await,
+        CliCommand::List(args) => list(args).await,
+        CliCommand::Doctor(args) => doctor(paths, args).await,
+        CliCommand::Benchmark(args) => run_benchmark(paths, args).await,
+    };
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("error: {}", error.message);
+            1
+        }
+    }
+}
+
+// Module to prove: a path as given, or a module name checked against the
+// wasm directory and function catalog like server requests are
+fn module_path(paths: &Paths, args: &ProveArgs) -> Result<PathBuf, AppError> {
+    if !args.wasm.contains('/') {
+        return catalog::validate_request(&paths.wasm_dir, &args.wasm, &args.args, args.step);
+    }
+    catalog::check_step_size(args.step)?;
+    let path = PathBuf::from(&args.wasm);
+    if !path.is_file() {
+        return Err(AppError::new(ErrorCode::WasmNotFound, format!("WASM file not found: {}", args.wasm)));
+    }
+    Ok(path)
+}
+
+async fn prove(paths: &Paths, args: ProveArgs) -> Result<(), AppError> {
+    locations::load();
+    let wasm_path = module_path(paths, &args)?;
+    let module = wasm_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let zkengine_args = catalog::encode_module_arguments(module, &args.args)?;
+    let function = args.function.clone()
+        .or_else(|| catalog::FUNCTIONS.iter().find(|f| f.wasm_file == module).map(|f| f.name.to_string()))
+        .unwrap_or_else(|| "main".to_string());
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function,
+        arguments: args.args.clone(),
+        step_size: args.step,
+        statement: None,
+    };
+
+    let proof_id = Uuid::new_v4().to_string();
+    let proof_dir = PathBuf::from(&paths.proofs_dir).join(&proof_id);
+    std::fs::create_dir_all(&proof_dir).map_err(|e| {
+        AppError::new(ErrorCode::StorageError, format!("Failed to create {}: {}", proof_dir.display(), e))
+    })?;
+    let secrets = secrets::Secrets::from_env().await
+        .map_err(|e| AppError::new(ErrorCode::Internal, format!("Invalid secrets configuration: {}", e)))?;
+    let signer = signing::ProofSigner::from_env(&secrets);
+
+    eprintln!("Proving {} ({}) as {}", metadata.wasm_path, args.args.join(", "), short_id(&proof_id));
+    let start_time = Instant::now();
+    let log_path = prover_logs::log_path(&paths.proofs_dir, &proof_id);
+    let run = crate::run_prover(
+        Arc::new(sandbox::Sandbox::from_env()),
+        paths.zkengine_binary.clone(),
+        &metadata,
+        zkengine_args,
+        proof_dir.clone(),
+        log_path.clone(),
+        |_| {},
+    );
+    let outcome = match run.await {
+        Ok(Ok(status)) if status.success() => {
+            crate::collect_proof(&signer, &proof_id, &proof_dir, &log_path, &metadata, start_time.elapsed())
+                .await
+                .ok_or_else(|| AppError::new(ErrorCode::ProofFileMissing, "Proof file not found after generation"))
+        }
+        Ok(Ok(status)) => Err(crate::prover_error(status, &log_path).await),
+        Ok(Err(e)) => Err(AppError::new(ErrorCode::ProverUnavailable, format!("Execution error: {}", e))),
+        Err(_) => Err(AppError::new(ErrorCode::Internal, "Internal error")),
+    };
+
+    let mut record = ProofRecord {
+        id: proof_id.clone(),
+        timestamp: Utc::now(),
+        metadata,
+        metrics: ProofMetrics {
+            generation_time_secs: start_time.elapsed().as_secs_f64(),
+            file_size_mb: 0.0,
+            file_hash: String::new(),
+            peak_memory_mb: None,
+            circuit: None,
+        },
+        status: ProofStatus::Queued,
+        file_path: None,
+        session_id: None,
+        request_id: None,
+        signature: None,
+        verifications: Vec::new(),
+        name: None,
+        tags: Vec::new(),
+        custom_metadata: Default::default(),
+        validity: Default::default(),
+        approvals: Vec::new(),
+        // Whoever ran the command on this host
+        origin: Some(origin::Origin { subject: std::env::var("USER").ok(), ..origin::Origin::new(origin::Channel::Cli) }),
+        parent_id: None,
+        rerun: None,
+    };
+    match &outcome {
+        Ok(output) => {
+            record.status = ProofStatus::Complete;
+            record.file_path = Some(output.file_path.to_string_lossy().to_string());
+            record.metrics = output.metrics.clone();
+            record.signature = Some(output.signature.clone());
+            record.metadata.statement = Some(output.statement.clone());
+        }
+        Err(error) => record.status = ProofStatus::Failed(error.clone()),
+    }
+    store(record.clone()).await?;
+
+    let output = outcome?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&record).unwrap_or_default());
+    } else {
+        println!("Proof {} complete in {:.2}s", proof_id, output.metrics.generation_time_secs);
+        println!("  file: {} ({:.2} MB)", output.file_path.display(), output.metrics.file_size_mb);
+        println!("  sha256: {}", output.metrics.file_hash);
+        if let Some(circuit) = &output.metrics.circuit {
+            println!("  circuit: {}", circuit.describe());
+        }
+        println!("  signed by: {}", output.signature.key_id);
+    }
+    Ok(())
+}
+
+// Add a proof to proofs_db
+async fn store(record: ProofRecord) -> Result<(), AppError> {
+    let storage_error = |e: anyhow::Error| AppError::new(ErrorCode::StorageError, format!("Proof store: {}", e));
+    let mut index = proof_db::load_index(|_| {}).await.map_err(storage_error)?;
+    proof_db::save(&record).await.map_err(storage_error)?;
+    index.push(proof_db::IndexEntry::of(&record));
+    proof_db::save_index(&index).await.map_err(storage_error)
+}
+
+async fn verify(paths: &Paths, args: VerifyArgs) -> Result<(), AppError> {
+    let (proof_dir, record) = match Path::new(&args.proof) {
+        dir if dir.is_dir() => (dir.to_path_buf(), None),
+        _ => {
+            // Anything with a separator is a path, not an ID
+            let record = if args.proof.contains(['/', '\\']) {
+                None
+            } else {
+                proof_db::load(&args.proof).await
+                    .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Proof store: {}", e)))?
+            };
+            (PathBuf::from(&paths.proofs_dir).join(&args.proof), record)
+        }
+    };
+    let step = args.step.or(record.map(|record| record.metadata.step_size)).unwrap_or(DEFAULT_STEP_SIZE);
+    let proof_file = proof_dir.join("proof.bin");
+    let public_file = proof_dir.join("public.json");
+    if !proof_file.is_file() {
+        return Err(AppError::new(ErrorCode::ProofFileMissing, format!("No proof.bin in {}", proof_dir.display())));
+    }
+
+    let start_time = Instant::now();
+    let output = verifier::run(&sandbox::Sandbox::from_env(), &paths.zkengine_binary, step, &proof_file, &public_file).await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppError::new(
+            ErrorCode::VerifierMismatch,
+            format!("Proof in {} is INVALID ({}): {}", proof_dir.display(), output.status, stderr.trim()),
+        ));
+    }
+    println!("Proof in {} is valid (verified in {:.3}s)", proof_dir.display(), start_time.elapsed().as_secs_f64());
+    Ok(())
+}
+
+async fn list(args: ListArgs) -> Result<(), AppError> {
+    let proofs = proof_db::load_all().await
+        .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Proof store: {}", e)))?;
+    let mut proofs: Vec<ProofRecord> = proofs.into_values()
+        .filter(|p| args.status.as_deref().is_none_or(|status| p.status.is_named(&status.to_ascii_lowercase())))
+        .collect();
+    proofs.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+    if args.limit > 0 {
+        proofs.truncate(args.limit);
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&proofs).unwrap_or_default());
+        return Ok(());
+    }
+    println!("{:<8}  {:<8}  {:<16}  {:<16}  {:<19}  {:>8}  {:>8}", "ID", "STATUS", "FUNCTION", "ARGUMENTS", "CREATED", "TIME", "SIZE");
+    for proof in &proofs {
+        let (time, size) = match proof.status {
+            ProofStatus::Complete => (
+                format!("{:.2}s", proof.metrics.generation_time_secs),
+                format!("{:.2}MB", proof.metrics.file_size_mb),
+            ),
+            _ => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:<8}  {:<8}  {:<16}  {:<16}  {:<19}  {:>8}  {:>8}",
+            short_id(&proof.id),
+            proof.status.name(),
+            proof.metadata.function,
+            proof.metadata.arguments.join(","),
+            proof.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            time,
+            size,
+        );
+    }
+    if proofs.is_empty() {
+        println!("No proofs");
+    }
+    Ok(())
+}
+
+async fn doctor(paths: &Paths, args: DoctorArgs) -> Result<(), AppError> {
+    // Applies WASM_FUNCTION_MAP, as the server does
+    config::LiveConfig::new(args.langchain_url.clone());
+    let env = doctor::Environment {
+        zkengine_binary: &paths.zkengine_binary,
+        wasm_dir: &paths.wasm_dir,
+        proofs_dir: &paths.proofs_dir,
+        langchain_url: &args.langchain_url,
+    };
+    let checks = doctor::run(&env).await;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&checks).unwrap_or_default());
+    } else {
+        println!("{}", doctor::report(&checks));
+    }
+    match doctor::failed(&checks) {
+        0 => Ok(()),
+        failed => Err(AppError::new(ErrorCode::Internal, format!("{} critical checks failed", failed))),
+    }
+}
+
+async fn run_benchmark(paths: &Paths, args: BenchmarkArgs) -> Result<(), AppError> {
+    let request = benchmark::BenchmarkRequest {
+        functions: (!args.functions.is_empty()).then_some(args.functions),
+        step_sizes: (!args.step_sizes.is_empty()).then_some(args.step_sizes),
+    };
+    let sandbox = sandbox::Sandbox::from_env();
+    let report = benchmark::run(&sandbox, &paths.zkengine_binary, &paths.wasm_dir, &paths.proofs_dir, &request).await?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+    } else {
+        println!("{}", benchmark::report(&report));
+    }
+    match report.cases.iter().filter(|case| case.error.is_some()).count() {
+        0 => Ok(()),
+        failed => Err(AppError::new(ErrorCode::ProverFailed, format!("{} of {} cases failed", failed, report.cases.len()))),
+    }
+}
@@ -0,0 +1,495 @@
+// Grammar for the chat commands handled without the LLM.
+//
+//   command  := filler* verb args filler*
+//   filler   := "please" | "can you" | "could you" | "would you" | "kindly" | "now"
+//   list     := ("list" | "show") ["me"] ["all" | "my" | "the"]* ("proofs" | "verifications" | "stats")
+//   stats    := "stats" | "statistics"
+//   help     := "help" ["me"] | "commands"
+//   export   := "export" ["proof"] proof-id ["as" ("bundle" | "json")]
+//   compare  := "compare" ["proof"] proof-id ("and" | "with" | "to" | "vs") ["proof"] proof-id
+//   verify   := "verify" ["my" | "the"] (["proof"] [proof-id | "last" | "latest" | "it"] | selector)
+//   selector := ("last" | "latest") function "proof"     e.g. "verify my last location proof"
+//   custom   := "prove" "custom" [wasm-file]
+//   delete   := ("delete" | "remove") ["proof"] proof-id
+//   archive  := "archive" ["all" | "the"] ["proofs"] "older" "than" N ["day" | "days"]
+//   run      := "run" ["the"] (name "template" | "template" name)
+//   review   := ("approve" | "reject") ["proof"] proof-id ["because" reason]
+//   cancel   := ("cancel" | "stop") ["proof"] proof-id
+//   rerun    := ("rerun" | "retry") ["proof"] proof-id
+//
+// A proof request may end in a verify step ("prove fibonacci of 20 and
+// verify it"); `strip_verify_suffix` splits that off before parsing.
+//
+// Anything that doesn't match the grammar exactly (questions such as "can
+// you explain how verification works") falls through to the LLM.
+
+use serde::Deserialize;
+
+use crate::{auth::Role, export::ExportFormat, proof_db::IndexEntry, proof_names, proof_store::ProofStore, wasm_file_for, ProofRecord};
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    ListProofs { filter: ProofFilter },
+    ListVerifications,
+    Stats,
+    Help,
+    Export { proof_id: String, format: ExportFormat },
+    Compare { a: String, b: String },
+    Verify { target: ProofSelector },
+    ProveCustom { wasm_file: Option<String> },
+    // Full ID, unique prefix or name; asks for confirmation before deleting
+    DeleteProof { proof_id: String },
+    ArchiveProofs { older_than_days: i64 },
+    // A saved template (see templates.rs)
+    RunTemplate { name: String },
+    // A proof awaiting approval (see approvals.rs); approvers are checked there
+    ReviewProof { proof_id: String, approve: bool, reason: Option<String> },
+    // A proof that hasn't finished (see cancellation.rs)
+    CancelProof { proof_id: String },
+    // A finished proof, run again (see lineage.rs)
+    RerunProof { proof_id: String },
+}
+
+impl Command {
+    // Least role allowed to run the command
+    pub fn required_role(&self) -> Role {
+        match self {
+            Command::DeleteProof { .. } | Command::ArchiveProofs { .. } => Role::Admin,
+            Command::Verify { .. } | Command::ProveCustom { .. } | Command::RunTemplate { .. } | Command::CancelProof { .. }
+            | Command::RerunProof { .. } => Role::Prover,
+            _ => Role::Viewer,
+        }
+    }
+}
+
+// Narrows a proof listing; shared with the list_proofs tool
+#[derive(Debug, PartialEq, Default, Deserialize)]
+pub struct ProofFilter {
+    pub status: Option<String>,
+    pub function: Option<String>,
+    pub tag: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl ProofFilter {
+    pub fn matches(&self, proof: &ProofRecord) -> bool {
+        self.status.as_deref().is_none_or(|s| proof.status.is_named(s))
+            && self.function.as_deref().is_none_or(|f| {
+                wasm_file_for(f).is_some_and(|wasm| proof.metadata.wasm_path.ends_with(&wasm))
+            })
+            && self.tag.as_deref().is_none_or(|t| proof.tags.iter().any(|tag| tag.eq_ignore_ascii_case(t)))
+    }
+
+    // Matching proofs, newest first, up to the limit
+    pub async fn select(&self, store: &ProofStore) -> Vec<ProofRecord> {
+        self.page(store, self.limit).await.1
+    }
+
+    // How many proofs match, and the newest `limit` of them. Status is
+    // answered from the index, so records are read for the page alone, or
+    // with a function or tag filter, for every proof of that status.
+    pub async fn page(&self, store: &ProofStore, limit: Option<usize>) -> (usize, Vec<ProofRecord>) {
+        let status = |entry: &IndexEntry| self.status.as_deref().is_none_or(|s| entry.status.is_named(s));
+        if self.function.is_none() && self.tag.is_none() {
+            return (store.count_indexed(status).await, store.newest(status, limit).await);
+        }
+        let mut matching = store.filter_map_where(status, |p| self.matches(p).then(|| p.clone())).await;
+        matching.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+        let count = matching.len();
+        if let Some(limit) = limit {
+            matching.truncate(limit);
+        }
+        (count, matching)
+    }
+}
+
+// Which proof a command refers to
+#[derive(Debug, PartialEq)]
+pub enum ProofSelector {
+    // The most recent completed proof
+    Latest,
+    // The most recent completed proof of a function (prove_location, fibonacci, ...)
+    LatestOf(String),
+    // Full ID, unique prefix, the short ID shown in chat or proof name
+    Id(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Parse {
+    Command(Command),
+    // Looks like a command but could mean several things; the message asks which
+    Ambiguous(String),
+    NoMatch,
+}
+
+const FILLER: [&str; 3] = ["please", "kindly", "now"];
+const POLITE_PREFIXES: [[&str; 2]; 3] = [["can", "you"], ["could", "you"], ["would", "you"]];
+
+// A word with its original spelling (IDs and file names are case-sensitive)
+struct Token<'a> {
+    text: &'a str,
+    lower: String,
+}
+
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    input
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '?' | '!' | ',' | ';' | ':' | '"' | '\'')))
+        .map(|word| word.trim_end_matches('.'))
+        .filter(|word| !word.is_empty())
+        .map(|text| Token { text, lower: text.to_lowercase() })
+        .collect()
+}
+
+// Drop politeness words around the command
+fn strip_filler<'a, 'b>(mut tokens: &'b [Token<'a>]) -> &'b [Token<'a>] {
+    loop {
+        if let Some(prefix) = POLITE_PREFIXES.iter().find(|prefix| {
+            tokens.len() >= 2 && tokens[0].lower == prefix[0] && tokens[1].lower == prefix[1]
+        }) {
+            tokens = &tokens[prefix.len()..];
+        } else if tokens.first().is_some_and(|t| FILLER.contains(&t.lower.as_str())) {
+            tokens = &tokens[1..];
+        } else if tokens.last().is_some_and(|t| FILLER.contains(&t.lower.as_str())) {
+            tokens = &tokens[..tokens.len() - 1];
+        } else {
+            return tokens;
+        }
+    }
+}
+
+// Full UUIDs, the short hex prefixes shown in chat or proof names
+fn looks_like_proof_id(word: &str) -> bool {
+    (word.len() >= 4 && word.chars().all(|c| c.is_ascii_hexdigit() || c == '-')) || proof_names::looks_like_name(word)
+}
+
+const VERIFY_SUFFIXES: [&str; 3] = ["verify it", "verify the proof", "verify"];
+const VERIFY_CONJUNCTIONS: [&str; 3] = ["and then", "and", "then"];
+
+// The proof request before a trailing "and verify it" / "then verify it",
+// or `None` when the input doesn't ask for verification afterwards
+pub fn strip_verify_suffix(input: &str) -> Option<&str> {
+    let trimmed = input.trim_end().trim_end_matches(['.', '!']);
+    // ASCII lowercasing keeps byte offsets aligned with `input`
+    let lower = trimmed.to_ascii_lowercase();
+    let suffix = VERIFY_SUFFIXES.iter().find(|suffix| lower.ends_with(*suffix))?;
+    let before = lower[..lower.len() - suffix.len()].trim_end().trim_end_matches(',').trim_end();
+    let conjunction = VERIFY_CONJUNCTIONS.iter().find(|c| {
+        before.ends_with(*c) && before[..before.len() - c.len()].ends_with([' ', ','])
+    })?;
+    let request = trimmed[..before.len() - conjunction.len()].trim_end().trim_end_matches(',').trim_end();
+    (!request.is_empty()).then_some(request)
+}
+
+pub fn parse(input: &str) -> Parse {
+    let tokens = tokenize(input);
+    let tokens = strip_filler(&tokens);
+    let Some((verb, rest)) = tokens.split_first() else {
+        return Parse::NoMatch;
+    };
+    let words: Vec<&str> = rest.iter().map(|t| t.lower.as_str()).collect();
+
+    match verb.lower.as_str() {
+        "list" | "show" => parse_list(&words),
+        "verify" => parse_verify(rest),
+        "prove" if words.first() == Some(&"custom") => match rest {
+            [_] => Parse::Command(Command::ProveCustom { wasm_file: None }),
+            [_, file] => Parse::Command(Command::ProveCustom { wasm_file: Some(file.text.to_string()) }),
+            _ => Parse::NoMatch,
+        },
+        "help" if words.is_empty() || words == ["me"] => Parse::Command(Command::Help),
+        "commands" if rest.is_empty() => Parse::Command(Command::Help),
+        "stats" | "statistics" if rest.is_empty() => Parse::Command(Command::Stats),
+        "compare" => parse_compare(rest),
+        "export" => parse_export(rest),
+        "delete" | "remove" => parse_delete(rest),
+        "archive" => parse_archive(&words),
+        "run" => parse_run(rest),
+        "approve" | "reject" => parse_review(rest, verb.lower == "approve"),
+        "cancel" | "stop" => parse_cancel(rest),
+        "rerun" | "retry" => parse_rerun(rest),
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_list(words: &[&str]) -> Parse {
+    let words: Vec<&str> = words.iter()
+        .copied()
+        .skip_while(|w| *w == "me")
+        .collect();
+    let (qualifiers, nouns): (Vec<&str>, Vec<&str>) = words.iter()
+        .partition(|w| matches!(**w, "all" | "my" | "the"));
+    let nouns: Vec<&str> = nouns.into_iter().filter(|w| !matches!(*w, "and" | "or")).collect();
+
+    match nouns.as_slice() {
+        ["proofs" | "proof"] => Parse::Command(Command::ListProofs { filter: ProofFilter::default() }),
+        ["verifications" | "verification"] => Parse::Command(Command::ListVerifications),
+        ["stats" | "statistics"] => Parse::Command(Command::Stats),
+        // "list all" has always meant proofs
+        [] if qualifiers.contains(&"all") => Parse::Command(Command::ListProofs { filter: ProofFilter::default() }),
+        [] => Parse::Ambiguous("List what? Try 'list proofs' or 'list verifications'.".to_string()),
+        [a, b] if [*a, *b].iter().all(|w| w.starts_with("proof") || w.starts_with("verification")) && a != b => {
+            Parse::Ambiguous("I can list one at a time: 'list proofs' or 'list verifications'.".to_string())
+        }
+        _ => Parse::NoMatch,
+    }
+}
+
+// Function names for the words people use in selectors
+fn selector_function(words: &[&str]) -> Option<String> {
+    match words {
+        ["location"] => Some("prove_location".to_string()),
+        ["kyc"] => Some("prove_kyc".to_string()),
+        ["ai"] | ["ai", "content"] => Some("prove_ai_content".to_string()),
+        ["fib"] => Some("fibonacci".to_string()),
+        [function] if wasm_file_for(function).is_some() => Some(function.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_verify(rest: &[Token]) -> Parse {
+    let rest = match rest.first() {
+        Some(t) if matches!(t.lower.as_str(), "my" | "the") => &rest[1..],
+        _ => rest,
+    };
+    let rest = match rest.first() {
+        Some(t) if t.lower == "proof" => &rest[1..],
+        _ => rest,
+    };
+    let verify = |target| Parse::Command(Command::Verify { target });
+    match rest {
+        [] => verify(ProofSelector::Latest),
+        [t] if matches!(t.lower.as_str(), "last" | "latest" | "it") => verify(ProofSelector::Latest),
+        [t, p] if matches!(t.lower.as_str(), "last" | "latest") && p.lower == "proof" => verify(ProofSelector::Latest),
+        [t] if looks_like_proof_id(t.text) => verify(ProofSelector::Id(t.text.to_string())),
+        [a, b] if looks_like_proof_id(a.text) && looks_like_proof_id(b.text) => {
+            Parse::Ambiguous("Which proof? I can verify one proof at a time.".to_string())
+        }
+        [first, middle @ .., last] if matches!(first.lower.as_str(), "last" | "latest") && last.lower == "proof" => {
+            let words: Vec<&str> = middle.iter().map(|t| t.lower.as_str()).collect();
+            match selector_function(&words) {
+                Some(function) => verify(ProofSelector::LatestOf(function)),
+                None => Parse::Ambiguous(format!("I don't know a '{}' proof. Try 'verify my last location proof'.", words.join(" "))),
+            }
+        }
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_compare(rest: &[Token]) -> Parse {
+    let ids: Vec<&Token> = rest.iter()
+        .filter(|t| !matches!(t.lower.as_str(), "proof" | "proofs" | "and" | "with" | "to" | "vs" | "versus"))
+        .collect();
+    match ids.as_slice() {
+        [a, b] if looks_like_proof_id(a.text) && looks_like_proof_id(b.text) => {
+            Parse::Command(Command::Compare { a: a.text.to_string(), b: b.text.to_string() })
+        }
+        [a] if looks_like_proof_id(a.text) => Parse::Ambiguous("Compare it with which proof? Try 'compare proof A and proof B'.".to_string()),
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_export(rest: &[Token]) -> Parse {
+    let rest = match rest.first() {
+        Some(t) if t.lower == "proof" => &rest[1..],
+        _ => rest,
+    };
+    let export = |id: &Token, format| Parse::Command(Command::Export { proof_id: id.text.to_string(), format });
+    match rest {
+        [id] if looks_like_proof_id(id.text) => export(id, ExportFormat::Bundle),
+        [id, as_, format] if looks_like_proof_id(id.text) && as_.lower == "as" => match ExportFormat::parse(&format.lower) {
+            Some(format) => export(id, format),
+            None => Parse::Ambiguous("I can export as a bundle or as json.".to_string()),
+        },
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_delete(rest: &[Token]) -> Parse {
+    let rest = match rest.first() {
+        Some(t) if t.lower == "proof" => &rest[1..],
+        _ => rest,
+    };
+    match rest {
+        [t] if looks_like_proof_id(t.text) => Parse::Command(Command::DeleteProof { proof_id: t.text.to_string() }),
+        [a, b, ..] if looks_like_proof_id(a.text) && looks_like_proof_id(b.text) => {
+            Parse::Ambiguous("Which proof? I can delete one proof at a time.".to_string())
+        }
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_cancel(rest: &[Token]) -> Parse {
+    match one_proof_id(rest) {
+        Some(proof_id) => Parse::Command(Command::CancelProof { proof_id }),
+        None => Parse::NoMatch,
+    }
+}
+
+fn parse_rerun(rest: &[Token]) -> Parse {
+    match one_proof_id(rest) {
+        Some(proof_id) => Parse::Command(Command::RerunProof { proof_id }),
+        None => Parse::NoMatch,
+    }
+}
+
+// ["proof"] proof-id
+fn one_proof_id(rest: &[Token]) -> Option<String> {
+    let rest = match rest.first() {
+        Some(t) if t.lower == "proof" => &rest[1..],
+        _ => rest,
+    };
+    match rest {
+        [t] if looks_like_proof_id(t.text) => Some(t.text.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_archive(words: &[&str]) -> Parse {
+    let words: Vec<&str> = words.iter()
+        .copied()
+        .skip_while(|w| matches!(*w, "all" | "the"))
+        .collect();
+    let words = match words.first() {
+        Some(&"proofs" | &"proof") => &words[1..],
+        _ => &words[..],
+    };
+    match words {
+        ["older", "than", n] | ["older", "than", n, "day" | "days"] => match n.parse::<i64>() {
+            Ok(days) if days >= 0 => Parse::Command(Command::ArchiveProofs { older_than_days: days }),
+            _ => Parse::Ambiguous("Archive proofs older than how many days? Try 'archive proofs older than 30 days'.".to_string()),
+        },
+        [] => Parse::Ambiguous("Archive which proofs? Try 'archive proofs older than 30 days'.".to_string()),
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_run(rest: &[Token]) -> Parse {
+    let rest = match rest.first() {
+        Some(t) if t.lower == "the" => &rest[1..],
+        _ => rest,
+    };
+    let run = |name: &Token| Parse::Command(Command::RunTemplate { name: name.lower.clone() });
+    match rest {
+        [name, t] | [t, name] if t.lower == "template" && name.lower != "template" => run(name),
+        [t] if t.lower == "template" => Parse::Ambiguous("Run which template? Try 'run the nightly-kyc template'.".to_string()),
+        _ => Parse::NoMatch,
+    }
+}
+
+fn parse_review(rest: &[Token], approve: bool) -> Parse {
+    let rest = match rest.first() {
+        Some(t) if t.lower == "proof" => &rest[1..],
+        _ => rest,
+    };
+    let reason = match rest {
+        [_, because, reason @ ..] if because.lower == "because" && !reason.is_empty() => {
+            Some(reason.iter().map(|t| t.text).collect::<Vec<_>>().join(" "))
+        }
+        [_] => None,
+        _ => return Parse::NoMatch,
+    };
+    match rest.first() {
+        Some(id) if looks_like_proof_id(id.text) => {
+            Parse::Command(Command::ReviewProof { proof_id: id.text.to_string(), approve, reason })
+        }
+        _ => Parse::NoMatch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(input: &str) -> Command {
+        match parse(input) {
+            Parse::Command(command) => command,
+            other => panic!("{:?} parsed as {:?}", input, other),
+        }
+    }
+
+    fn verify(target: ProofSelector) -> Command {
+        Command::Verify { target }
+    }
+
+    #[test]
+    fn questions_fall_through_to_the_llm() {
+        for input in [
+            "can you explain how verification works",
+            "how do I verify a proof?",
+            "what does list mean",
+            "show me how proofs work",
+            "help me understand zero knowledge proofs",
+            "prove that you are not a robot",
+            "",
+        ] {
+            assert_eq!(parse(input), Parse::NoMatch, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn verify_targets() {
+        assert_eq!(command("verify"), verify(ProofSelector::Latest));
+        assert_eq!(command("Please verify it."), verify(ProofSelector::Latest));
+        assert_eq!(command("verify my last proof"), verify(ProofSelector::Latest));
+        assert_eq!(command("can you verify my last location proof?"), verify(ProofSelector::LatestOf("prove_location".to_string())));
+        assert_eq!(command("verify the latest fib proof"), verify(ProofSelector::LatestOf("fibonacci".to_string())));
+    }
+
+    #[test]
+    fn proof_ids_keep_their_spelling() {
+        let id = "3f2a9c1e-7b4d-4e8a-9c0f-1a2b3c4d5e6f";
+        assert_eq!(command(&format!("verify {}", id)), verify(ProofSelector::Id(id.to_string())));
+        assert_eq!(command("verify proof 3F2A9C1E"), verify(ProofSelector::Id("3F2A9C1E".to_string())));
+        assert_eq!(command("verify nightly-kyc-2024-06-01"), verify(ProofSelector::Id("nightly-kyc-2024-06-01".to_string())));
+        assert_eq!(command("delete proof 3f2a"), Command::DeleteProof { proof_id: "3f2a".to_string() });
+        assert_eq!(command("export 3f2a9c1e as json"), Command::Export { proof_id: "3f2a9c1e".to_string(), format: ExportFormat::Json });
+        assert_eq!(command("compare proof 3f2a with proof 9c1e"), Command::Compare { a: "3f2a".to_string(), b: "9c1e".to_string() });
+        assert_eq!(command("stop proof 3f2a9c1e"), Command::CancelProof { proof_id: "3f2a9c1e".to_string() });
+    }
+
+    #[test]
+    fn other_arguments() {
+        assert_eq!(command("list all my proofs"), Command::ListProofs { filter: ProofFilter::default() });
+        assert_eq!(command("show me the verifications"), Command::ListVerifications);
+        assert_eq!(command("archive proofs older than 30 days"), Command::ArchiveProofs { older_than_days: 30 });
+        assert_eq!(command("run the Nightly-KYC template"), Command::RunTemplate { name: "nightly-kyc".to_string() });
+        assert_eq!(command("prove custom my_module.wat"), Command::ProveCustom { wasm_file: Some("my_module.wat".to_string()) });
+        assert_eq!(
+            command("reject proof 3f2a9c1e because Wrong region"),
+            Command::ReviewProof { proof_id: "3f2a9c1e".to_string(), approve: false, reason: Some("Wrong region".to_string()) }
+        );
+        // A bare word is no proof ID
+        assert_eq!(parse("verify everything"), Parse::NoMatch);
+        assert_eq!(parse("delete proof"), Parse::NoMatch);
+    }
+
+    #[test]
+    fn ambiguous_commands_ask_which() {
+        for input in [
+            "list",
+            "list proofs and verifications",
+            "verify 3f2a9c1e 9c1e7b4d",
+            "verify my last banana proof",
+            "delete 3f2a9c1e 9c1e7b4d",
+            "compare 3f2a9c1e",
+            "export 3f2a9c1e as pdf",
+            "archive proofs older than soon",
+            "run template",
+        ] {
+            assert!(matches!(parse(input), Parse::Ambiguous(_)), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn verify_suffix_is_split_off() {
+        assert_eq!(strip_verify_suffix("prove fibonacci of 20 and verify it"), Some("prove fibonacci of 20"));
+        assert_eq!(strip_verify_suffix("Prove my location, then verify the proof."), Some("Prove my location"));
+        assert_eq!(strip_verify_suffix("prove kyc and then verify"), Some("prove kyc"));
+        assert_eq!(strip_verify_suffix("prove fibonacci of 20"), None);
+        // "verify" alone is a command, not a suffix
+        assert_eq!(strip_verify_suffix("verify"), None);
+        assert_eq!(strip_verify_suffix("and verify it"), None);
+        assert_eq!(strip_verify_suffix("prove I can reverify"), None);
+    }
+}
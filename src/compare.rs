@@ -0,0 +1,69 @@
+use serde_json::{json, Map, Value};
+
+use crate::{errors::AppError, read_public_outputs, retention, short_id, AppState, ProofRecord, ProofStatus};
+
+// Field-by-field comparison of two proofs, shared by the `compare` chat
+// command and GET /api/proofs/compare. Handy for working out why a re-run
+// produced a different proof.
+
+async fn compared_fields(state: &AppState, proof: &ProofRecord) -> Vec<(&'static str, Value)> {
+    vec![
+        ("wasm_file", json!(proof.metadata.wasm_path.rsplit('/').next())),
+        ("function", json!(proof.metadata.function)),
+        ("arguments", json!(proof.metadata.arguments)),
+        ("step_size", json!(proof.metadata.step_size)),
+        ("status", json!(proof.status.name())),
+        ("error", json!(match &proof.status { ProofStatus::Failed(e) => Some(&e.message), _ => None })),
+        ("file_size_mb", json!(proof.metrics.file_size_mb)),
+        ("file_hash", json!(proof.metrics.file_hash)),
+        ("generation_time_secs", json!(proof.metrics.generation_time_secs)),
+        ("circuit", json!(proof.metrics.circuit)),
+        ("public_outputs", read_public_outputs(state, &proof.id).await.unwrap_or(Value::Null)),
+    ]
+}
+
+// `a` and `b` may be full IDs or unique prefixes
+pub async fn compare(state: &AppState, a: &str, b: &str) -> Result<Value, AppError> {
+    let a = retention::resolve_proof_id(state, a).await?;
+    let b = retention::resolve_proof_id(state, b).await?;
+    let (proof_a, proof_b) = match (state.proof_store.get(&a).await, state.proof_store.get(&b).await) {
+        (Some(pa), Some(pb)) => (pa, pb),
+        _ => return Err(AppError::proof_not_found()),
+    };
+
+    let fields_a = compared_fields(state, &proof_a).await;
+    let fields_b = compared_fields(state, &proof_b).await;
+    let mut fields = Map::new();
+    let mut differences = Vec::new();
+    for ((name, value_a), (_, value_b)) in fields_a.into_iter().zip(fields_b) {
+        let equal = value_a == value_b;
+        // Timings always vary a little; they are reported but not counted as differences
+        if !equal && name != "generation_time_secs" {
+            differences.push(name);
+        }
+        fields.insert(name.to_string(), json!({ "a": value_a, "b": value_b, "equal": equal }));
+    }
+
+    Ok(json!({
+        "a": a,
+        "b": b,
+        "identical": differences.is_empty(),
+        "differences": differences,
+        "fields": fields,
+    }))
+}
+
+// One-line summary for chat
+pub fn describe(comparison: &Value) -> String {
+    let a = short_id(comparison["a"].as_str().unwrap_or_default());
+    let b = short_id(comparison["b"].as_str().unwrap_or_default());
+    let differences: Vec<&str> = comparison["differences"]
+        .as_array()
+        .map(|d| d.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if differences.is_empty() {
+        format!("Proofs {} and {} match on everything compared", a, b)
+    } else {
+        format!("Proofs {} and {} differ in: {}", a, b, differences.join(", "))
+    }
+}
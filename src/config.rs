@@ -0,0 +1,246 @@
+use axum::{extract::State, response::IntoResponse, Json};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+    sync::{Mutex, OnceLock, RwLock},
+    time::{Duration, SystemTime},
+};
+use tracing::{info, warn};
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    locations, AppState,
+};
+
+// Settings that can change while the server runs, without dropping WebSocket
+// sessions or proofs in progress. They come from the environment and the
+// config file (CONFIG_FILE, default: the .env file), which is re-read by
+// POST /api/admin/reload and, with CONFIG_WATCH_SECS set, whenever it
+// changes. Variables the process was started with take precedence over the
+// file, as they do at startup.
+//
+//   LANGCHAIN_SERVICE_URL   LangChain service
+//   WASM_FUNCTION_MAP       modules that prove catalog functions instead of
+//                           their own, e.g. "fibonacci=fib_fast.wat"
+//   RETENTION_DAYS          age at which /api/cleanup deletes proofs (default 7)
+//   RETENTION_PROTECTED_TAGS  see tags.rs
+//   TRASH_RETENTION_HOURS   see trash.rs
+//   RATE_LIMIT_*            see rate_limit.rs
+//   FEATURE_FLAGS           see flags.rs
+//   LOCATIONS_FILE          see locations.rs; the file itself is re-read too
+//   SHARE_TTL_HOURS         see share.rs
+//   APPROVAL_RULES, APPROVERS  see approvals.rs
+//   WEBHOOK_MAX_ATTEMPTS    see webhooks.rs
+//
+// Secrets read from the environment (API_KEYS, OPENAI_API_KEY, ...) are
+// looked up when used, so they take effect too. Anything else, such as the
+// port, directories or LLM provider, needs a restart.
+
+const RELOADABLE: &[&str] = &[
+    "LANGCHAIN_SERVICE_URL",
+    "WASM_FUNCTION_MAP",
+    "RETENTION_DAYS",
+    "RETENTION_PROTECTED_TAGS",
+    "TRASH_RETENTION_HOURS",
+    "RATE_LIMIT_READS_PER_MIN",
+    "RATE_LIMIT_READ_BURST",
+    "RATE_LIMIT_GENERATE_PER_MIN",
+    "RATE_LIMIT_GENERATE_BURST",
+    "RATE_LIMIT_TRUST_PROXY",
+    "FEATURE_FLAGS",
+    "LOCATIONS_FILE",
+    "SHARE_TTL_HOURS",
+    "APPROVAL_RULES",
+    "APPROVERS",
+    "WEBHOOK_MAX_ATTEMPTS",
+    "API_KEYS",
+    "JWT_HS256_SECRET",
+    "OPENAI_API_KEY",
+    "ANTHROPIC_API_KEY",
+    "PROOF_SIGNING_KEY",
+];
+
+// Variables set before the config file was loaded
+static PROCESS_ENV: OnceLock<HashSet<String>> = OnceLock::new();
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+// Load the config file into the environment at startup
+pub fn load_env_file() {
+    PROCESS_ENV.get_or_init(|| std::env::vars_os().filter_map(|(name, _)| name.into_string().ok()).collect());
+    let path = match std::env::var("CONFIG_FILE") {
+        Ok(path) => dotenvy::from_path(&path).map(|_| PathBuf::from(path)),
+        Err(_) => dotenvy::dotenv(),
+    };
+    CONFIG_FILE.get_or_init(|| path.unwrap_or_else(|_| PathBuf::from(".env")));
+}
+
+fn config_file() -> PathBuf {
+    CONFIG_FILE.get().cloned().unwrap_or_else(|| PathBuf::from(".env"))
+}
+
+// Variables in the config file that the process environment doesn't override
+fn file_vars() -> Result<BTreeMap<String, String>, String> {
+    let path = config_file();
+    let process_env = PROCESS_ENV.get();
+    let vars = dotenvy::from_path_iter(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    vars.map(|var| var.map_err(|e| format!("{}: {}", path.display(), e)))
+        .filter(|var| var.as_ref().map_or(true, |(name, _)| !process_env.is_some_and(|env| env.contains(name))))
+        .collect()
+}
+
+#[derive(Serialize, Clone)]
+pub struct Settings {
+    pub langchain_url: String,
+    pub wasm_function_map: BTreeMap<String, String>,
+    pub retention_days: i64,
+}
+
+pub struct LiveConfig {
+    settings: RwLock<Settings>,
+    // Variables last loaded from the config file, to notice removals
+    loaded: Mutex<BTreeMap<String, String>>,
+    watch: Option<Duration>,
+}
+
+impl LiveConfig {
+    // `langchain_url` as given on the command line or by the environment
+    pub fn new(langchain_url: String) -> Self {
+        let settings = Settings {
+            langchain_url,
+            wasm_function_map: function_map(),
+            retention_days: retention_days(),
+        };
+        catalog::set_wasm_overrides(settings.wasm_function_map.clone());
+        locations::load();
+        let watch = std::env::var("CONFIG_WATCH_SECS").ok().and_then(|v| v.parse().ok()).filter(|secs| *secs > 0);
+        LiveConfig {
+            settings: RwLock::new(settings),
+            loaded: Mutex::new(file_vars().unwrap_or_default()),
+            watch: watch.map(Duration::from_secs),
+        }
+    }
+
+    pub fn settings(&self) -> Settings {
+        self.settings.read().unwrap().clone()
+    }
+
+    pub fn langchain_url(&self) -> String {
+        self.settings.read().unwrap().langchain_url.clone()
+    }
+
+    pub fn retention_days(&self) -> i64 {
+        self.settings.read().unwrap().retention_days
+    }
+
+    // Re-read the config file into the environment and rebuild the
+    // settings; returns the names of the variables that changed
+    fn reload(&self) -> Result<Vec<String>, String> {
+        let vars = file_vars()?;
+        let mut loaded = self.loaded.lock().unwrap();
+        let mut changed = Vec::new();
+        for (name, value) in &vars {
+            if std::env::var(name).ok().as_ref() != Some(value) {
+                std::env::set_var(name, value);
+                changed.push(name.clone());
+            }
+        }
+        for name in loaded.keys().filter(|name| !vars.contains_key(*name)) {
+            std::env::remove_var(name);
+            changed.push(name.clone());
+        }
+        *loaded = vars;
+
+        let mut settings = self.settings.write().unwrap();
+        // Keep a URL given on the command line unless the file changes it
+        if changed.iter().any(|name| name == "LANGCHAIN_SERVICE_URL") {
+            if let Ok(url) = std::env::var("LANGCHAIN_SERVICE_URL") {
+                settings.langchain_url = url;
+            }
+        }
+        settings.wasm_function_map = function_map();
+        settings.retention_days = retention_days();
+        catalog::set_wasm_overrides(settings.wasm_function_map.clone());
+        locations::load();
+        Ok(changed)
+    }
+}
+
+// WASM_FUNCTION_MAP, ignoring entries for functions not in the catalog
+fn function_map() -> BTreeMap<String, String> {
+    std::env::var("WASM_FUNCTION_MAP")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(function, wasm_file)| (function.trim().to_string(), wasm_file.trim().to_string()))
+        .filter(|(function, wasm_file)| {
+            let known = catalog::find(function).is_some();
+            if !known {
+                warn!("WASM_FUNCTION_MAP names unknown function {}; ignoring it", function);
+            }
+            known && !wasm_file.is_empty()
+        })
+        .collect()
+}
+
+fn retention_days() -> i64 {
+    std::env::var("RETENTION_DAYS").ok().and_then(|v| v.parse().ok()).filter(|days| *days >= 0).unwrap_or(7)
+}
+
+// Apply the config file to everything that can change while running
+async fn reload(state: &AppState) -> Result<serde_json::Value, AppError> {
+    let changed = state.config.reload()
+        .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Failed to read the config file: {}", e)))?;
+    state.rate_limiter.reload();
+    state.flags.reload();
+
+    let restart_required: Vec<&String> = changed.iter().filter(|name| !RELOADABLE.contains(&name.as_str())).collect();
+    if changed.is_empty() {
+        info!("Configuration reloaded; nothing changed");
+    } else {
+        info!("Configuration reloaded; changed {}", changed.join(", "));
+    }
+    if !restart_required.is_empty() {
+        let names: Vec<&str> = restart_required.iter().map(|name| name.as_str()).collect();
+        warn!("Restart to apply {}", names.join(", "));
+    }
+    Ok(json!({
+        "success": true,
+        "changed": changed,
+        "restart_required": restart_required,
+        "settings": state.config.settings(),
+    }))
+}
+
+pub async fn reload_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match reload(&state).await {
+        Ok(result) => Json(result).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+// Reload whenever the config file's modification time changes
+pub fn spawn_watch(state: AppState) {
+    let Some(interval) = state.config.watch else {
+        return;
+    };
+    let path = config_file();
+    info!("Watching {} for configuration changes every {:?}", path.display(), interval);
+    tokio::spawn(async move {
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut last: Option<SystemTime> = modified(&path);
+        loop {
+            tokio::time::sleep(interval).await;
+            let current = modified(&path);
+            if current == last {
+                continue;
+            }
+            last = current;
+            if let Err(error) = reload(&state).await {
+                warn!("{}", error.message);
+            }
+        }
+    });
+}
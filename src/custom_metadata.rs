@@ -0,0 +1,65 @@
+use serde_json::{Map, Value};
+
+use crate::errors::{AppError, ErrorCode};
+
+// Integrators' own data on proofs, e.g. an order ID or the agent run that
+// asked for the proof, so they can find their proofs again without a
+// database of their own. Given as a `metadata` object when a proof is
+// started (REST, JSON-RPC, gRPC as a JSON string), kept on the record as
+// `custom_metadata` and returned wherever the proof is.
+//
+//   {"wasm_file": "fib.wat", "arguments": ["10"],
+//    "metadata": {"order_id": "A-1042", "agent_run": "run_7f3e"}}
+//
+// Keys are up to 64 letters, digits, '_', '-' or '.', starting with a
+// letter; values are any JSON. A proof has at most MAX_KEYS keys, in at
+// most MAX_BYTES of JSON.
+
+pub type CustomMetadata = Map<String, Value>;
+
+const MAX_KEYS: usize = 32;
+const MAX_BYTES: usize = 4096;
+
+fn check_key(key: &str) -> Result<(), AppError> {
+    let valid = key.len() <= 64
+        && key.starts_with(|c: char| c.is_ascii_alphabetic())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("Invalid metadata key '{}'", key)));
+    }
+    Ok(())
+}
+
+// A request's `metadata`; nothing when it is absent or null
+pub fn parse(value: &Value) -> Result<CustomMetadata, AppError> {
+    let metadata = match value {
+        Value::Null => return Ok(CustomMetadata::new()),
+        Value::Object(metadata) => metadata,
+        _ => return Err(AppError::new(ErrorCode::InvalidArguments, "metadata must be a JSON object")),
+    };
+    if metadata.len() > MAX_KEYS {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("metadata has at most {} keys", MAX_KEYS)));
+    }
+    for key in metadata.keys() {
+        check_key(key)?;
+    }
+    let size = serde_json::to_vec(metadata).map_or(usize::MAX, |json| json.len());
+    if size > MAX_BYTES {
+        return Err(AppError::new(
+            ErrorCode::InvalidArguments,
+            format!("metadata is {} bytes of JSON; the limit is {}", size, MAX_BYTES),
+        ));
+    }
+    Ok(metadata.clone())
+}
+
+// gRPC carries it as a JSON string, empty for none
+#[cfg(feature = "grpc")]
+pub fn parse_json(json: &str) -> Result<CustomMetadata, AppError> {
+    if json.trim().is_empty() {
+        return Ok(CustomMetadata::new());
+    }
+    let value = serde_json::from_str(json)
+        .map_err(|e| AppError::new(ErrorCode::InvalidArguments, format!("metadata is not valid JSON: {}", e)))?;
+    parse(&value)
+}
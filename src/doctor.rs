@@ -0,0 +1,235 @@
+use serde::Serialize;
+use std::{path::Path, process::Stdio, time::Duration};
+use tracing::{error, info, warn};
+
+use crate::{
+    catalog,
+    outbound::{self, Integration},
+};
+
+// Environment checks, run at startup and by `zkp-agentkit doctor`: the
+// zkEngine binary runs, the catalog's modules are in the wasm directory, the
+// proofs directory is writable with room for proofs, and the LangChain
+// service answers. Each problem comes with what to do about it. Failures of
+// critical checks are logged and the server starts anyway, unless
+// DOCTOR_STRICT is set, in which case it refuses to.
+//
+//   DOCTOR_STRICT        refuse to serve when a critical check fails (default false)
+//   DOCTOR_MIN_FREE_MB   free space the proofs directory needs (default 500)
+
+const BINARY_TIMEOUT: Duration = Duration::from_secs(10);
+const LANGCHAIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Warning,
+    // A critical check failed
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+fn ok(name: &'static str, detail: impl Into<String>) -> Check {
+    Check { name, status: Status::Ok, detail: detail.into(), fix: None }
+}
+
+fn problem(name: &'static str, status: Status, detail: impl Into<String>, fix: impl Into<String>) -> Check {
+    Check { name, status, detail: detail.into(), fix: Some(fix.into()) }
+}
+
+pub struct Environment<'a> {
+    pub zkengine_binary: &'a str,
+    pub wasm_dir: &'a str,
+    pub proofs_dir: &'a str,
+    pub langchain_url: &'a str,
+}
+
+pub async fn run(env: &Environment<'_>) -> Vec<Check> {
+    vec![
+        zkengine(env.zkengine_binary).await,
+        wasm_modules(env.wasm_dir),
+        proofs_dir(env.proofs_dir),
+        langchain(env.langchain_url).await,
+    ]
+}
+
+// Present, executable and able to start; `--help` is enough to show missing
+// libraries or a binary built for another platform
+async fn zkengine(binary: &str) -> Check {
+    const NAME: &str = "zkEngine binary";
+    let fix = "install zkEngine and point ZKENGINE_BINARY (or --zkengine-binary) at its wasm_file binary";
+    if !Path::new(binary).is_file() {
+        return problem(NAME, Status::Failed, format!("{} not found", binary), fix);
+    }
+    let run = tokio::process::Command::new(binary)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(BINARY_TIMEOUT, run).await {
+        Err(_) => problem(NAME, Status::Failed, format!("{} --help did not finish in {:?}", binary, BINARY_TIMEOUT), fix),
+        Ok(Err(e)) => problem(
+            NAME,
+            Status::Failed,
+            format!("{} can't be run: {}", binary, e),
+            "make it executable (chmod +x) and check it was built for this platform",
+        ),
+        // Killed, or 126/127, which the loader and shell use for binaries that can't start
+        Ok(Ok(output)) if matches!(output.status.code(), None | Some(126 | 127)) => problem(
+            NAME,
+            Status::Failed,
+            format!("{} failed to start ({}): {}", binary, output.status, String::from_utf8_lossy(&output.stderr).trim()),
+            "check its shared libraries with ldd, or rebuild it for this machine",
+        ),
+        Ok(Ok(_)) => ok(NAME, format!("{} runs", binary)),
+    }
+}
+
+// Every catalog function's module, after WASM_FUNCTION_MAP
+fn wasm_modules(wasm_dir: &str) -> Check {
+    const NAME: &str = "WASM modules";
+    if !Path::new(wasm_dir).is_dir() {
+        let fix = "point WASM_DIR (or --wasm-dir) at the directory of example WASM modules";
+        return problem(NAME, Status::Failed, format!("{} is not a directory", wasm_dir), fix);
+    }
+    let missing: Vec<String> = catalog::FUNCTIONS.iter()
+        .filter_map(|f| catalog::wasm_file(f.name))
+        .filter(|file| !Path::new(wasm_dir).join(file).is_file())
+        .collect();
+    if missing.is_empty() {
+        return ok(NAME, format!("all {} catalog modules in {}", catalog::FUNCTIONS.len(), wasm_dir));
+    }
+    problem(
+        NAME,
+        Status::Warning,
+        format!("{} of {} catalog modules missing from {}: {}", missing.len(), catalog::FUNCTIONS.len(), wasm_dir, missing.join(", ")),
+        "copy them into the wasm directory or map their functions to other modules with WASM_FUNCTION_MAP; proofs of those functions fail until then",
+    )
+}
+
+fn proofs_dir(proofs_dir: &str) -> Check {
+    const NAME: &str = "Proofs directory";
+    let probe = Path::new(proofs_dir).join(".doctor");
+    let writable = std::fs::create_dir_all(proofs_dir).and_then(|_| std::fs::write(&probe, b""));
+    std::fs::remove_file(&probe).ok();
+    if let Err(e) = writable {
+        let fix = "fix its permissions or point PROOFS_DIR (or --proofs-dir) somewhere writable";
+        return problem(NAME, Status::Failed, format!("{} is not writable: {}", proofs_dir, e), fix);
+    }
+    let min_free_mb = std::env::var("DOCTOR_MIN_FREE_MB").ok().and_then(|v| v.parse::<u64>().ok()).unwrap_or(500);
+    match free_mb(proofs_dir) {
+        Some(free) if free < min_free_mb => problem(
+            NAME,
+            Status::Failed,
+            format!("only {} MB free in {} (DOCTOR_MIN_FREE_MB is {})", free, proofs_dir, min_free_mb),
+            "free some space, e.g. with POST /api/cleanup, or move PROOFS_DIR to a larger disk",
+        ),
+        Some(free) => ok(NAME, format!("{} is writable, {} MB free", proofs_dir, free)),
+        None => ok(NAME, format!("{} is writable", proofs_dir)),
+    }
+}
+
+#[cfg(unix)]
+fn free_mb(path: &str) -> Option<u64> {
+    let path = std::ffi::CString::new(path).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid statvfs to fill in
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64 / (1024 * 1024))
+}
+
+#[cfg(not(unix))]
+fn free_mb(_path: &str) -> Option<u64> {
+    None
+}
+
+// Only needed by the langchain intent provider, and only critical when the
+// local parser may not stand in for it
+async fn langchain(url: &str) -> Check {
+    const NAME: &str = "LangChain service";
+    let provider = std::env::var("LLM_PROVIDER").unwrap_or_else(|_| "langchain".to_string()).to_lowercase();
+    if provider != "langchain" {
+        return ok(NAME, format!("not used (LLM_PROVIDER={})", provider));
+    }
+    let fallback = std::env::var("LLM_LOCAL_FALLBACK").map(|v| v != "false" && v != "0").unwrap_or(true);
+    let (status, fix) = if fallback {
+        (Status::Warning, "start it with `python langchain_service.py` or set LANGCHAIN_SERVICE_URL; chat uses the local parser until then")
+    } else {
+        (Status::Failed, "start it with `python langchain_service.py` or set LANGCHAIN_SERVICE_URL; chat fails until then, as LLM_LOCAL_FALLBACK is off")
+    };
+    let response = outbound::client(Integration::LangChain).get(format!("{}/health", url)).timeout(LANGCHAIN_TIMEOUT).send().await;
+    match response {
+        Ok(response) if response.status().is_success() => ok(NAME, format!("{} is reachable", url)),
+        Ok(response) => problem(NAME, status, format!("{} returned {}", url, response.status()), fix),
+        Err(e) => problem(NAME, status, format!("{} is unreachable: {}", url, e), fix),
+    }
+}
+
+pub fn failed(checks: &[Check]) -> usize {
+    checks.iter().filter(|c| c.status == Status::Failed).count()
+}
+
+// Startup: log the report, and in strict mode refuse to go on if a critical
+// check failed
+pub async fn startup(env: &Environment<'_>) -> Result<(), String> {
+    let checks = run(env).await;
+    for check in &checks {
+        let fix = check.fix.as_deref().map(|fix| format!(" - {}", fix)).unwrap_or_default();
+        match check.status {
+            Status::Ok => info!("Doctor: {}: {}", check.name, check.detail),
+            Status::Warning => warn!("Doctor: {}: {}{}", check.name, check.detail, fix),
+            Status::Failed => error!("Doctor: {}: {}{}", check.name, check.detail, fix),
+        }
+    }
+    let failed = failed(&checks);
+    if failed == 0 {
+        return Ok(());
+    }
+    let strict = std::env::var("DOCTOR_STRICT").is_ok_and(|v| v == "true" || v == "1");
+    if strict {
+        return Err(format!("{} critical startup checks failed", failed));
+    }
+    warn!("{} critical startup checks failed; starting anyway (set DOCTOR_STRICT=true to refuse)", failed);
+    Ok(())
+}
+
+// The report `zkp-agentkit doctor` prints
+pub fn report(checks: &[Check]) -> String {
+    let mut lines = Vec::new();
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warning => "WARN",
+            Status::Failed => "FAIL",
+        };
+        lines.push(format!("{:<4}  {:<18}  {}", status, check.name, check.detail));
+        if let Some(fix) = &check.fix {
+            lines.push(format!("{:<4}  {:<18}  fix: {}", "", "", fix));
+        }
+    }
+    let warnings = checks.iter().filter(|c| c.status == Status::Warning).count();
+    lines.push(match (failed(checks), warnings) {
+        (0, 0) => "All checks passed".to_string(),
+        (failed, warnings) => format!(
+            "{} critical {}, {} {}",
+            failed,
+            if failed == 1 { "failure" } else { "failures" },
+            warnings,
+            if warnings == 1 { "warning" } else { "warnings" },
+        ),
+    });
+    lines.join("\n")
+}
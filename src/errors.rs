@@ -0,0 +1,61 @@
+use axum::response::{IntoResponse, Json, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+
+// Every failure reported to clients carries a stable snake_case `code`
+// next to its human-readable message; the codes are part of the wire
+// types shared with the client crate.
+pub use zkp_agentkit_client::types::ErrorCode;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "StoredError")]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+// Stored records hold either a typed error or, from older versions, a bare message
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredError {
+    Typed { code: ErrorCode, message: String },
+    Legacy(String),
+}
+
+impl From<StoredError> for AppError {
+    fn from(stored: StoredError) -> Self {
+        match stored {
+            StoredError::Typed { code, message } => AppError { code, message },
+            StoredError::Legacy(message) => AppError { code: ErrorCode::classify(&message), message },
+        }
+    }
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        AppError { code, message: message.into() }
+    }
+
+    pub fn proof_not_found() -> Self {
+        AppError::new(ErrorCode::ProofNotFound, "Proof not found")
+    }
+}
+
+// Every REST error is answered with the status its code maps to
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = Json(json!({
+            "success": false,
+            "error": self.message,
+            "code": self.code
+        }));
+        (self.code.http_status(), body).into_response()
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    protocol::{WsEvent, WsMessage},
+    short_id,
+};
+
+// Proof and verification lifecycle events published to a message bus, for
+// downstream analytics and agents that react to proofs. Off unless
+// EVENT_BUS_URL is set; each backend is a cargo feature:
+//
+//   EVENT_BUS_URL     nats://host:4222 (--features nats) or
+//                     kafka://broker1:9092,broker2:9092 (--features kafka)
+//   EVENT_BUS_TOPIC   Kafka topic, or NATS subject prefix (default "agentkit.events")
+//
+// On NATS each event goes to "<prefix>.<event>", e.g.
+// "agentkit.events.proof_complete", so subscribers can pick events with
+// wildcards; on Kafka every event goes to the one topic, keyed by proof ID
+// so a proof's events stay in order. Messages are JSON:
+//
+//   {"schema": "agentkit.event", "schema_version": 1, "id": "...", "event": "proof_complete",
+//    "emitted_at": "...", "proof_id": "...", "data": {...}}
+//
+// where `data` is the WebSocket event. schema_version is bumped whenever a
+// field is removed or changes meaning. Publishing never holds up the
+// server: events are queued and dropped (with a warning) if the bus falls
+// more than QUEUE_SIZE behind. Only events raised on this instance are
+// published, so a cluster publishes each one once.
+
+const SCHEMA: &str = "agentkit.event";
+const SCHEMA_VERSION: u32 = 1;
+const DEFAULT_TOPIC: &str = "agentkit.events";
+const QUEUE_SIZE: usize = 1024;
+
+// Read by the backends, which are all optional
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(dead_code))]
+struct Message {
+    event: &'static str,
+    proof_id: Option<String>,
+    payload: Vec<u8>,
+}
+
+pub struct EventBus {
+    queue: Option<mpsc::Sender<Message>>,
+}
+
+// Lifecycle events and their names; everything else stays off the bus
+fn event_name(event: &WsEvent) -> Option<&'static str> {
+    Some(match event {
+        WsEvent::ProofStart { .. } => "proof_start",
+        WsEvent::ProofProgress { .. } => "proof_progress",
+        WsEvent::ProofComplete { .. } => "proof_complete",
+        WsEvent::ProofFailed { .. } => "proof_failed",
+        WsEvent::ProofCancelled { .. } => "proof_cancelled",
+        WsEvent::VerificationStart { .. } => "verification_start",
+        WsEvent::VerificationComplete { .. } => "verification_complete",
+        _ => return None,
+    })
+}
+
+impl EventBus {
+    // Connects in the background when EVENT_BUS_URL is set
+    pub fn from_env() -> Self {
+        let Some(url) = std::env::var("EVENT_BUS_URL").ok().filter(|v| !v.is_empty()) else {
+            return EventBus { queue: None };
+        };
+        let topic = std::env::var("EVENT_BUS_TOPIC").ok().filter(|v| !v.is_empty()).unwrap_or_else(|| DEFAULT_TOPIC.to_string());
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(async move {
+            if let Err(e) = run(&url, &topic, receiver).await {
+                error!("Event bus publisher stopped: {}", e);
+            }
+        });
+        EventBus { queue: Some(sender) }
+    }
+
+    // Queue a published message, if it is a lifecycle event
+    pub fn publish(&self, msg: &WsMessage) {
+        let Some(queue) = &self.queue else {
+            return;
+        };
+        let Some(data) = &msg.data else {
+            return;
+        };
+        let Some(event) = event_name(data) else {
+            return;
+        };
+        let proof_id = data.proof_id().map(str::to_string);
+        let envelope: Value = json!({
+            "schema": SCHEMA,
+            "schema_version": SCHEMA_VERSION,
+            "id": Uuid::new_v4().to_string(),
+            "event": event,
+            "emitted_at": Utc::now(),
+            "proof_id": proof_id,
+            "data": data,
+        });
+        let message = Message { event, proof_id, payload: envelope.to_string().into_bytes() };
+        // A closed queue means the publisher stopped, which it has logged
+        if let Err(mpsc::error::TrySendError::Full(message)) = queue.try_send(message) {
+            warn!(
+                "Event bus is behind; dropped a {} event for proof {}",
+                message.event,
+                message.proof_id.as_deref().map(short_id).unwrap_or("-")
+            );
+        }
+    }
+}
+
+#[cfg_attr(not(any(feature = "nats", feature = "kafka")), allow(unused_variables))]
+async fn run(url: &str, topic: &str, receiver: mpsc::Receiver<Message>) -> anyhow::Result<()> {
+    match url.split_once("://") {
+        #[cfg(feature = "nats")]
+        Some(("nats", _)) => nats::run(url, topic, receiver).await,
+        #[cfg(feature = "kafka")]
+        Some(("kafka", brokers)) => kafka::run(brokers, topic, receiver).await,
+        Some((scheme, _)) if matches!(scheme, "nats" | "kafka") => {
+            anyhow::bail!("EVENT_BUS_URL is a {} URL, but this build lacks --features {}", scheme, scheme)
+        }
+        _ => anyhow::bail!("EVENT_BUS_URL must be a nats:// or kafka:// URL"),
+    }
+}
+
+#[cfg(feature = "nats")]
+mod nats {
+    use super::*;
+    use tracing::info;
+
+    pub async fn run(url: &str, prefix: &str, mut receiver: mpsc::Receiver<Message>) -> anyhow::Result<()> {
+        // Reconnects on its own after the first connection
+        let client = async_nats::connect(url).await?;
+        info!("Publishing events to NATS subjects {}.*", prefix);
+        while let Some(message) = receiver.recv().await {
+            let subject = format!("{}.{}", prefix, message.event);
+            if let Err(e) = client.publish(subject, message.payload.into()).await {
+                warn!("Failed to publish a {} event to NATS: {}", message.event, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kafka")]
+mod kafka {
+    use super::*;
+    use rdkafka::{
+        config::ClientConfig,
+        producer::{FutureProducer, FutureRecord},
+    };
+    use std::time::Duration;
+    use tracing::info;
+
+    pub async fn run(brokers: &str, topic: &str, mut receiver: mpsc::Receiver<Message>) -> anyhow::Result<()> {
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("message.timeout.ms", "30000")
+            .create()?;
+        info!("Publishing events to Kafka topic {}", topic);
+        while let Some(message) = receiver.recv().await {
+            let key = message.proof_id.as_deref().unwrap_or_default();
+            let record = FutureRecord::to(topic).key(key).payload(&message.payload);
+            if let Err((e, _)) = producer.send(record, Duration::from_secs(5)).await {
+                warn!("Failed to publish a {} event to Kafka: {}", message.event, e);
+            }
+        }
+        Ok(())
+    }
+}
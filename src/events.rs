@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+use tokio::sync::broadcast;
+
+use crate::protocol::{WsEvent, WsMessage};
+
+// Event categories clients can subscribe to; each has its own channel
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Topic {
+    Proofs,
+    Verifications,
+    Chat,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 3] = [Topic::Proofs, Topic::Verifications, Topic::Chat];
+
+    // Classify a message by its event payload
+    pub fn of(msg: &WsMessage) -> Topic {
+        match &msg.data {
+            Some(
+                WsEvent::ProofStart { .. }
+                | WsEvent::ProofProposal { .. }
+                | WsEvent::ActionProposal { .. }
+                | WsEvent::WorkflowUpdate { .. }
+                | WsEvent::ProofProgress { .. }
+                | WsEvent::ProofComplete { .. }
+                | WsEvent::ProofFailed { .. }
+                | WsEvent::ProofCancelled { .. }
+                | WsEvent::ProofList { .. },
+            ) => Topic::Proofs,
+            Some(
+                WsEvent::VerificationStart { .. }
+                | WsEvent::VerificationComplete { .. }
+                | WsEvent::VerificationList { .. },
+            ) => Topic::Verifications,
+            _ => Topic::Chat,
+        }
+    }
+}
+
+// Per-topic broadcast channels plus a ring buffer of recent events
+// (sized by WS_REPLAY_BUFFER) for replay. A plain mutex is enough: it is
+// only held to stamp and copy a message.
+pub struct EventHub {
+    channels: HashMap<Topic, broadcast::Sender<WsMessage>>,
+    log: Mutex<EventLog>,
+    capacity: usize,
+}
+
+struct EventLog {
+    next_seq: u64,
+    events: VecDeque<WsMessage>,
+}
+
+impl EventHub {
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("WS_REPLAY_BUFFER")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(500);
+        let channels = Topic::ALL.iter()
+            .map(|topic| (*topic, broadcast::channel::<WsMessage>(1000).0))
+            .collect();
+        EventHub {
+            channels,
+            log: Mutex::new(EventLog { next_seq: 1, events: VecDeque::with_capacity(capacity) }),
+            capacity,
+        }
+    }
+
+    pub fn subscribe(&self, topic: Topic) -> broadcast::Receiver<WsMessage> {
+        self.channels[&topic].subscribe()
+    }
+
+    // Stamp the next sequence number, buffer the event (unless ephemeral) and
+    // send it on its topic channel. The lock spans the send so subscribers observe events
+    // in seq order.
+    pub fn publish(&self, mut msg: WsMessage) {
+        let mut log = self.log.lock().unwrap();
+        msg.seq = Some(log.next_seq);
+        log.next_seq += 1;
+        let ephemeral = msg.data.as_ref().is_some_and(WsEvent::is_ephemeral);
+        if self.capacity > 0 && !ephemeral {
+            if log.events.len() == self.capacity {
+                log.events.pop_front();
+            }
+            log.events.push_back(msg.clone());
+        }
+        let _ = self.channels[&Topic::of(&msg)].send(msg);
+    }
+
+    // Sequence number of the oldest event still buffered
+    pub fn oldest_seq(&self) -> Option<u64> {
+        self.log.lock().unwrap().events.front().and_then(|msg| msg.seq)
+    }
+
+    // Whether any buffered event is addressed to the session
+    pub fn has_session(&self, session_id: &str) -> bool {
+        let log = self.log.lock().unwrap();
+        log.events.iter().any(|msg| msg.session_id.as_deref() == Some(session_id))
+    }
+
+    // Buffered events with a sequence number greater than `since`
+    pub fn since(&self, since: u64) -> Vec<WsMessage> {
+        let log = self.log.lock().unwrap();
+        log.events.iter()
+            .filter(|msg| msg.seq.is_some_and(|seq| seq > since))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_names() {
+        assert_eq!(serde_json::to_value(Topic::ALL).unwrap(), serde_json::json!(["proofs", "verifications", "chat"]));
+        let topics: Vec<Topic> = serde_json::from_str(r#"["verifications", "proofs"]"#).unwrap();
+        assert_eq!(topics, [Topic::Verifications, Topic::Proofs]);
+    }
+
+    #[test]
+    fn messages_are_classified_by_their_event() {
+        let proof = WsMessage::event("", WsEvent::ProofCancelled { proof_id: "p".into() });
+        let verification = WsMessage::event("", WsEvent::VerificationStart { proof_id: "p".into() });
+        assert_eq!(Topic::of(&proof), Topic::Proofs);
+        assert_eq!(Topic::of(&verification), Topic::Verifications);
+        assert_eq!(Topic::of(&WsMessage::new("Hello", None)), Topic::Chat);
+    }
+}
@@ -0,0 +1,229 @@
+use axum::{
+    body::{self, Body, Bytes, StreamBody},
+    extract::{Path, Query, State},
+    http::{header, HeaderValue, Request},
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use chrono::Utc;
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    read_public_outputs, retention, short_id, verifier, AppState, ProofStatus,
+};
+
+// Proof exports an auditor can be handed without access to the server.
+// `json` is the record, public outputs and verification history; `bundle`
+// adds the proof file itself (base64) so it can be verified offline.
+//
+// GET /api/proofs/:id/download is proof.bin on its own. It answers Range
+// requests, so a client on a flaky link can fetch a large proof in parts
+// and resume an interrupted download (e.g. `curl -C -`). The ETag is the
+// file's sha256; a Range request whose If-Range doesn't match it gets the
+// whole file.
+
+const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    Bundle,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bundle" | "zip" => Some(ExportFormat::Bundle),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Bundle => "bundle",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+// Download path for an export; the chat command sends this to the client
+pub fn download_url(proof_id: &str, format: ExportFormat) -> String {
+    format!("/api/proofs/{}/export?format={}", proof_id, format.name())
+}
+
+// The export without proof.bin's contents, and for a bundle, the file they
+// come from. `proof_id` may be a full ID or unique prefix.
+async fn prepare(state: &AppState, proof_id: &str, format: ExportFormat) -> Result<(Value, Option<String>), AppError> {
+    let proof_id = retention::resolve_proof_id(state, proof_id).await?;
+    let proof = state.proof_store.get(&proof_id).await.ok_or_else(AppError::proof_not_found)?;
+    let verifications: Vec<_> = state.verification_store.lock().await
+        .iter()
+        .filter(|v| v.proof_id == proof_id)
+        .cloned()
+        .collect();
+
+    let mut export = json!({
+        "format": format.name(),
+        "version": BUNDLE_FORMAT_VERSION,
+        "exported_at": Utc::now(),
+        "proof": proof,
+        "public_outputs": read_public_outputs(state, &proof_id).await,
+        "verifications": verifications,
+    });
+
+    if format != ExportFormat::Bundle {
+        return Ok((export, None));
+    }
+    if !matches!(proof.status, ProofStatus::Complete) {
+        return Err(AppError::new(ErrorCode::ProofNotReady, "Only completed proofs can be exported as a bundle"));
+    }
+    let file_path = proof.file_path.ok_or_else(|| AppError::new(ErrorCode::ProofFileMissing, "Proof file not found"))?;
+    tokio::fs::metadata(&file_path).await.map_err(missing_file)?;
+    export["proof_file"] = json!({
+        "name": "proof.bin",
+        "sha256": proof.metrics.file_hash,
+    });
+    Ok((export, Some(file_path)))
+}
+
+fn missing_file(e: std::io::Error) -> AppError {
+    AppError::new(ErrorCode::ProofFileMissing, format!("Failed to read proof file: {}", e))
+}
+
+// Whether the export can be made, without reading proof.bin
+pub async fn check(state: &AppState, proof_id: &str, format: ExportFormat) -> Result<(), AppError> {
+    prepare(state, proof_id, format).await.map(|_| ())
+}
+
+pub async fn build(state: &AppState, proof_id: &str, format: ExportFormat) -> Result<Value, AppError> {
+    let (mut export, file_path) = prepare(state, proof_id, format).await?;
+    if let Some(file_path) = file_path {
+        let bytes = tokio::fs::read(&file_path).await.map_err(missing_file)?;
+        export["proof_file"]["base64"] = json!(base64::engine::general_purpose::STANDARD.encode(bytes));
+    }
+    Ok(export)
+}
+
+// The export as a download. proof.bin is base64-encoded into the body a
+// chunk at a time as it is sent, so a large proof is never held in memory.
+async fn body(mut export: Value, file_path: Option<String>) -> Result<Response, AppError> {
+    let Some(file_path) = file_path else {
+        return Ok(serde_json::to_string_pretty(&export).unwrap().into_response());
+    };
+    let file = tokio::fs::File::open(&file_path).await.map_err(missing_file)?;
+    // Stands in for the base64 while the rest is serialized
+    let placeholder = format!("base64-{}", Uuid::new_v4());
+    export["proof_file"]["base64"] = json!(placeholder);
+    let text = serde_json::to_string_pretty(&export).unwrap();
+    let (head, tail) = text.split_once(&placeholder).unwrap();
+    let (head, tail) = (Bytes::from(head.to_string()), Bytes::from(tail.to_string()));
+
+    let chunks = stream::unfold(Some((file, vec![0; verifier::CHUNK_SIZE])), |open| async move {
+        let (mut file, mut buffer) = open?;
+        match verifier::read_chunk(&mut file, &mut buffer).await {
+            Ok(0) => None,
+            Ok(n) => {
+                let encoded = Bytes::from(base64::engine::general_purpose::STANDARD.encode(&buffer[..n]));
+                Some((Ok(encoded), Some((file, buffer))))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    let body = stream::once(async { Ok::<_, std::io::Error>(head) })
+        .chain(chunks)
+        .chain(stream::once(async { Ok(tail) }));
+    Ok(StreamBody::new(body).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    format: Option<String>,
+}
+
+pub async fn export_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Response {
+    let format = match params.format.as_deref() {
+        None => ExportFormat::Bundle,
+        Some(name) => match ExportFormat::parse(name) {
+            Some(format) => format,
+            None => {
+                return AppError::new(ErrorCode::InvalidArguments, "format must be bundle or json").into_response();
+            }
+        },
+    };
+    let id = match retention::resolve_proof_id(&state, &id).await {
+        Ok(id) => id,
+        Err(error) => return error.into_response(),
+    };
+    let download = match prepare(&state, &id, format).await {
+        Ok((export, file_path)) => body(export, file_path).await,
+        Err(error) => Err(error),
+    };
+    match download {
+        Ok(body) => {
+            let file_name = format!("proof-{}.{}.json", short_id(&id), format.name());
+            (
+                [
+                    (header::CONTENT_TYPE, "application/json".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+                ],
+                body,
+            ).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn download_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    mut request: Request<Body>,
+) -> Response {
+    let proof = match retention::resolve_proof_id(&state, &id).await {
+        Ok(id) => state.proof_store.get(&id).await,
+        Err(error) => return error.into_response(),
+    };
+    let Some(proof) = proof else {
+        return AppError::proof_not_found().into_response();
+    };
+    if !matches!(proof.status, ProofStatus::Complete) {
+        return AppError::new(ErrorCode::ProofNotReady, "Proof not complete").into_response();
+    }
+    let Some(file_path) = proof.file_path else {
+        return AppError::new(ErrorCode::ProofFileMissing, "Proof file not found").into_response();
+    };
+    if let Err(e) = tokio::fs::metadata(&file_path).await {
+        return missing_file(e).into_response();
+    }
+
+    let etag = format!("\"{}\"", proof.metrics.file_hash);
+    // The file changed since the part the client has
+    let changed = request.headers().get(header::IF_RANGE).is_some_and(|v| v.as_bytes() != etag.as_bytes());
+    if changed {
+        request.headers_mut().remove(header::RANGE);
+    }
+    let mut response = match ServeFile::new(&file_path).oneshot(request).await {
+        Ok(response) => response.map(body::boxed),
+        Err(never) => match never {},
+    };
+    let file_name = format!("attachment; filename=\"proof-{}.bin\"", short_id(&proof.id));
+    let headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Ok(file_name) = HeaderValue::from_str(&file_name) {
+        headers.insert(header::CONTENT_DISPOSITION, file_name);
+    }
+    response
+}
+
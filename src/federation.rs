@@ -0,0 +1,269 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    export,
+    outbound::{self, Integration},
+    retention,
+    secrets::{Secret, Secrets},
+    short_id, signing, store_verification, AppState, ProofStatus, VerificationRecord,
+};
+
+// Federated verification: a proof is sent to trusted peer verifier nodes
+// (`zkp-agentkit verifier`, see verifier.rs) which check it independently
+// and sign their results. The combined result, with every peer's answer and
+// signature, is stored as one verification of the proof.
+//
+//   VERIFIER_PEERS               name=url pairs, comma separated
+//   VERIFIER_PEER_KEYS           name=public key pairs pinning a peer's base64
+//                                Ed25519 key; other peers' keys are fetched from
+//                                their /.well-known/proof-signing-key
+//   VERIFIER_PEER_TOKEN          bearer token sent to peers that require API keys
+//   VERIFIER_PEER_QUORUM         confirmations needed (default: every peer asked)
+//   VERIFIER_PEER_TIMEOUT_SECS   per-peer timeout (default 120)
+//
+// POST /api/proofs/:id/federate sends the proof to every peer, or the first
+// `peers` of them with {"peers": N}. A peer confirms the proof when it says
+// valid, verified the same proof.bin and its signature checks out. The
+// proof is valid when at least the quorum confirms and no peer rejects it.
+
+#[derive(Clone)]
+struct Peer {
+    name: String,
+    url: String,
+}
+
+// One peer's answer, as stored with the combined verification
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PeerVerification {
+    pub peer: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_valid: Option<bool>,
+    // The peer's own ID for the verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<signing::VerificationSignature>,
+    pub signature_valid: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl PeerVerification {
+    fn failed(peer: &Peer, error: String) -> Self {
+        PeerVerification {
+            peer: peer.name.clone(),
+            url: peer.url.clone(),
+            is_valid: None,
+            verification_id: None,
+            signature: None,
+            signature_valid: false,
+            error: Some(error),
+        }
+    }
+
+    fn confirms(&self) -> bool {
+        self.is_valid == Some(true) && self.signature_valid && self.error.is_none()
+    }
+
+    fn rejects(&self) -> bool {
+        self.is_valid == Some(false) && self.signature_valid
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct FederateRequest {
+    // Ask only the first N peers
+    #[serde(default)]
+    peers: Option<usize>,
+}
+
+pub struct Federation {
+    peers: Vec<Peer>,
+    // Peer name -> pinned base64 public key
+    keys: HashMap<String, String>,
+    token: Secret,
+    quorum: Option<usize>,
+    timeout: Duration,
+    client: reqwest::Client,
+}
+
+// name=value pairs from a comma-separated variable
+fn pairs(name: &str) -> Vec<(String, String)> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, value)| !key.is_empty() && !value.is_empty())
+        .collect()
+}
+
+impl Federation {
+    pub fn from_env(secrets: &Arc<Secrets>) -> Self {
+        let peers: Vec<Peer> = pairs("VERIFIER_PEERS")
+            .into_iter()
+            .map(|(name, url)| Peer { name, url: url.trim_end_matches('/').to_string() })
+            .collect();
+        if !peers.is_empty() {
+            info!("Federated verification with {} peer(s): {}", peers.len(), peers.iter().map(|p| p.name.as_str()).collect::<Vec<_>>().join(", "));
+        }
+        let number = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Federation {
+            peers,
+            keys: pairs("VERIFIER_PEER_KEYS").into_iter().collect(),
+            token: secrets.secret("VERIFIER_PEER_TOKEN"),
+            quorum: number("VERIFIER_PEER_QUORUM").map(|n| n as usize),
+            timeout: Duration::from_secs(number("VERIFIER_PEER_TIMEOUT_SECS").unwrap_or(120)),
+            client: outbound::client(Integration::Federation),
+        }
+    }
+
+    // The peer's public key for `key_id`, pinned or from its well-known
+    // endpoint. A pinned key is never replaced.
+    async fn public_key(&self, peer: &Peer, key_id: &str) -> Result<String, String> {
+        if let Some(key) = self.keys.get(&peer.name) {
+            return Ok(key.clone());
+        }
+        let url = format!("{}/.well-known/proof-signing-key", peer.url);
+        let published: Value = self.client.get(&url)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+        let current = std::iter::once(&published);
+        let previous = published["previous_keys"].as_array().into_iter().flatten();
+        current
+            .chain(previous)
+            .find(|key| key["key_id"] == key_id)
+            .and_then(|key| key["public_key"].as_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("{} doesn't publish key {}", peer.name, key_id))
+    }
+
+    async fn ask(&self, peer: &Peer, bundle: &Value, proof_hash: &str) -> PeerVerification {
+        let url = format!("{}/api/verify", peer.url);
+        let mut request = self.client.post(&url).json(&json!({ "bundle": bundle })).timeout(self.timeout);
+        if let Some(token) = self.token.value() {
+            request = request.bearer_auth(token);
+        }
+        let response: Value = match request.send().await {
+            Ok(response) => match response.json().await {
+                Ok(body) => body,
+                Err(e) => return PeerVerification::failed(peer, format!("Unreadable answer: {}", e)),
+            },
+            Err(e) => return PeerVerification::failed(peer, format!("Failed to reach {}: {}", url, e)),
+        };
+        if response["success"] != true {
+            let error = response["error"].as_str().unwrap_or("the peer refused the proof");
+            return PeerVerification::failed(peer, error.to_string());
+        }
+
+        let verification = &response["verification"];
+        let verification_id = verification["id"].as_str().unwrap_or_default().to_string();
+        let is_valid = verification["is_valid"].as_bool();
+        let signature: Option<signing::VerificationSignature> = serde_json::from_value(verification["signature"].clone()).ok();
+        let mut error: Option<String> = None;
+        let signature_valid = match (&signature, is_valid) {
+            (Some(signature), Some(is_valid)) => match self.public_key(peer, &signature.key_id).await {
+                Ok(key) => {
+                    let message = signing::verification_message(&verification_id, proof_hash, is_valid, &signature.signed_at);
+                    signing::check_signature(&key, &message, &signature.signature)
+                }
+                Err(e) => {
+                    error = Some(e);
+                    false
+                }
+            },
+            _ => false,
+        };
+        if verification["proof_hash"] != proof_hash {
+            error = Some(format!("{} verified a different proof.bin", peer.name));
+        } else if !signature_valid && error.is_none() {
+            error = Some(format!("{}'s signature doesn't check out", peer.name));
+        }
+        PeerVerification {
+            peer: peer.name.clone(),
+            url: peer.url.clone(),
+            is_valid,
+            verification_id: Some(verification_id),
+            signature,
+            signature_valid,
+            error,
+        }
+    }
+
+    // Send a proof to the peers and store the combined result
+    pub async fn verify(&self, state: &AppState, proof_id: &str, peer_count: Option<usize>) -> Result<VerificationRecord, AppError> {
+        if self.peers.is_empty() {
+            return Err(AppError::new(ErrorCode::FeatureDisabled, "No verifier peers are configured (VERIFIER_PEERS)"));
+        }
+        let proof_id = retention::resolve_proof_id(state, proof_id).await?;
+        let proof = state.proof_store.get(&proof_id).await.ok_or_else(AppError::proof_not_found)?;
+        if !matches!(proof.status, ProofStatus::Complete) {
+            return Err(AppError::new(ErrorCode::ProofNotReady, "Proof not complete"));
+        }
+        let bundle = export::build(state, &proof_id, export::ExportFormat::Bundle).await?;
+        let peers = &self.peers[..peer_count.unwrap_or(self.peers.len()).clamp(1, self.peers.len())];
+        let quorum = self.quorum.unwrap_or(peers.len()).min(peers.len());
+
+        let start_time = Instant::now();
+        let answers: Vec<PeerVerification> = join_all(peers.iter().map(|peer| self.ask(peer, &bundle, &proof.metrics.file_hash))).await;
+        let confirmed = answers.iter().filter(|answer| answer.confirms()).count();
+        let rejected = answers.iter().filter(|answer| answer.rejects()).count();
+        let is_valid = confirmed >= quorum && rejected == 0;
+        let error = (!is_valid).then(|| {
+            format!("{} of {} peers confirmed the proof ({} needed), {} rejected it", confirmed, peers.len(), quorum, rejected)
+        });
+        for answer in answers.iter().filter(|answer| answer.error.is_some()) {
+            warn!("Peer {} on proof {}: {}", answer.peer, short_id(&proof_id), answer.error.as_deref().unwrap_or_default());
+        }
+        info!("Federated verification of proof {}: {} of {} peers confirmed", short_id(&proof_id), confirmed, peers.len());
+
+        let verification = VerificationRecord {
+            id: Uuid::new_v4().to_string(),
+            proof_id: proof_id.clone(),
+            timestamp: Utc::now(),
+            is_valid,
+            verification_time_secs: start_time.elapsed().as_secs_f64(),
+            error,
+            error_code: (!is_valid).then_some(ErrorCode::VerifierMismatch),
+            cached: false,
+            upload: None,
+            peers: answers,
+            receipt: None,
+        };
+        Ok(store_verification(state, verification).await)
+    }
+}
+
+pub async fn federate_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<FederateRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match state.federation.verify(&state, &id, request.peers).await {
+        Ok(verification) => Json(json!({ "success": true, "verification": verification })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
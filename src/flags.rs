@@ -0,0 +1,224 @@
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Map, Value};
+use std::{collections::BTreeMap, sync::RwLock};
+use tracing::{info, warn};
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    AppState,
+};
+
+// Subsystems that can be turned off per deployment without a rebuild. Every
+// flag is on unless FEATURE_FLAGS turns it off, e.g. "custom_wasm=off,ui=off";
+// it is re-read by POST /api/admin/reload. Admins can also flip flags with
+// POST /api/admin/flags, e.g. {"custom_wasm": false}, with null dropping the
+// override again. Overrides win over FEATURE_FLAGS and are kept in
+// ./feature_flags.json across restarts. GET /api/admin/flags lists the flags.
+//
+//   llm_routing   chat the grammar doesn't understand goes to the intent
+//                 provider; off, only the local parser is used
+//   custom_wasm   modules outside the catalog can be proved, over REST,
+//                 JSON-RPC and "prove custom"
+//   ui            the web UI at / and /static; off, they are 404s
+
+const FLAGS_DB_FILE: &str = "./feature_flags.json";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    LlmRouting,
+    CustomWasm,
+    Ui,
+}
+
+impl Flag {
+    const ALL: [Flag; 3] = [Flag::LlmRouting, Flag::CustomWasm, Flag::Ui];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Flag::LlmRouting => "llm_routing",
+            Flag::CustomWasm => "custom_wasm",
+            Flag::Ui => "ui",
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Flag::LlmRouting => "Send chat messages the grammar doesn't understand to the intent provider",
+            Flag::CustomWasm => "Prove modules outside the catalog",
+            Flag::Ui => "Serve the web UI",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Flag::ALL.into_iter().find(|flag| flag.name() == name)
+    }
+}
+
+fn parse_value(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "on" | "true" | "1" | "yes" => Some(true),
+        "off" | "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+// FEATURE_FLAGS, ignoring entries that don't parse
+fn configured() -> BTreeMap<String, bool> {
+    std::env::var("FEATURE_FLAGS")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let parsed = entry.split_once('=').and_then(|(name, value)| {
+                let flag = Flag::from_name(name.trim())?;
+                Some((flag.name().to_string(), parse_value(value)?))
+            });
+            if parsed.is_none() {
+                warn!("FEATURE_FLAGS entry {:?} is not a known flag set to on or off; ignoring it", entry.trim());
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn load_overrides() -> BTreeMap<String, bool> {
+    let Ok(json) = std::fs::read_to_string(FLAGS_DB_FILE) else {
+        return BTreeMap::new();
+    };
+    match serde_json::from_str::<BTreeMap<String, bool>>(&json) {
+        Ok(overrides) => overrides.into_iter().filter(|(name, _)| Flag::from_name(name).is_some()).collect(),
+        Err(e) => {
+            warn!("Failed to load feature flag overrides from {}: {}", FLAGS_DB_FILE, e);
+            BTreeMap::new()
+        }
+    }
+}
+
+pub struct FeatureFlags {
+    configured: RwLock<BTreeMap<String, bool>>,
+    // Set through the admin API
+    overrides: RwLock<BTreeMap<String, bool>>,
+}
+
+impl FeatureFlags {
+    pub fn from_env() -> Self {
+        let flags = FeatureFlags {
+            configured: RwLock::new(configured()),
+            overrides: RwLock::new(load_overrides()),
+        };
+        let off: Vec<&str> = Flag::ALL.into_iter().filter(|flag| !flags.enabled(*flag)).map(Flag::name).collect();
+        if !off.is_empty() {
+            info!("Feature flags turned off: {}", off.join(", "));
+        }
+        flags
+    }
+
+    // Re-read FEATURE_FLAGS; overrides stay
+    pub fn reload(&self) {
+        *self.configured.write().unwrap() = configured();
+    }
+
+    pub fn enabled(&self, flag: Flag) -> bool {
+        self.value(flag).0
+    }
+
+    // The flag's value and where it comes from
+    fn value(&self, flag: Flag) -> (bool, &'static str) {
+        if let Some(enabled) = self.overrides.read().unwrap().get(flag.name()) {
+            return (*enabled, "override");
+        }
+        match self.configured.read().unwrap().get(flag.name()) {
+            Some(enabled) => (*enabled, "FEATURE_FLAGS"),
+            None => (true, "default"),
+        }
+    }
+
+    pub fn require(&self, flag: Flag) -> Result<(), AppError> {
+        if self.enabled(flag) {
+            return Ok(());
+        }
+        Err(AppError::new(ErrorCode::FeatureDisabled, format!("{} is turned off on this server", flag.name())))
+    }
+
+    // Modules outside the catalog need custom_wasm
+    pub fn check_module(&self, wasm_file: &str) -> Result<(), AppError> {
+        if catalog::is_catalog_module(wasm_file) {
+            return Ok(());
+        }
+        self.require(Flag::CustomWasm)
+            .map_err(|e| AppError::new(e.code, format!("Only catalog modules can be proved here ({}); {} isn't one", catalog::names(), wasm_file)))
+    }
+
+    fn list(&self) -> Vec<Value> {
+        Flag::ALL
+            .into_iter()
+            .map(|flag| {
+                let (enabled, source) = self.value(flag);
+                json!({
+                    "name": flag.name(),
+                    "enabled": enabled,
+                    "source": source,
+                    "description": flag.description(),
+                })
+            })
+            .collect()
+    }
+
+    // Apply {"flag": true | false | null} and save the overrides
+    fn update(&self, changes: Map<String, Value>) -> Result<(), AppError> {
+        let mut parsed = Vec::new();
+        for (name, value) in changes {
+            let Some(flag) = Flag::from_name(&name) else {
+                let known: Vec<&str> = Flag::ALL.into_iter().map(Flag::name).collect();
+                return Err(AppError::new(ErrorCode::InvalidArguments, format!("Unknown flag {} (expected one of {})", name, known.join(", "))));
+            };
+            match value {
+                Value::Bool(enabled) => parsed.push((flag, Some(enabled))),
+                Value::Null => parsed.push((flag, None)),
+                _ => return Err(AppError::new(ErrorCode::InvalidArguments, format!("{} must be true, false or null", name))),
+            }
+        }
+        let mut overrides = self.overrides.write().unwrap();
+        for (flag, enabled) in parsed {
+            match enabled {
+                Some(enabled) => {
+                    info!("Feature flag {} turned {}", flag.name(), if enabled { "on" } else { "off" });
+                    overrides.insert(flag.name().to_string(), enabled);
+                }
+                None => {
+                    info!("Feature flag {} override dropped", flag.name());
+                    overrides.remove(flag.name());
+                }
+            }
+        }
+        let json = serde_json::to_string_pretty(&*overrides).map_err(|e| AppError::new(ErrorCode::Internal, e.to_string()))?;
+        std::fs::write(FLAGS_DB_FILE, json)
+            .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Failed to save feature flags: {}", e)))
+    }
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({ "success": true, "flags": state.flags.list() }))
+}
+
+pub async fn update_handler(State(state): State<AppState>, Json(changes): Json<Map<String, Value>>) -> impl IntoResponse {
+    match state.flags.update(changes) {
+        Ok(()) => Json(json!({ "success": true, "flags": state.flags.list() })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+// Hides the UI routes while the ui flag is off
+pub async fn ui_middleware<B>(State(state): State<AppState>, request: Request<B>, next: Next<B>) -> Response {
+    if !state.flags.enabled(Flag::Ui) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,130 @@
+use std::{sync::Mutex, time::Duration};
+use tokio::sync::Notify;
+use tracing::{error, info};
+
+use crate::{proof_db, AppState};
+
+// Writes changes to the proof store to proofs_db (see proof_db.rs) in the
+// background. Code that changes a proof marks the store dirty and moves on;
+// the flusher takes the records that changed since the last write and
+// writes them and the index with no lock held, so proof operations don't
+// wait on the disk.
+//
+// Writes are batched: the first change after a write starts a
+// PERSIST_INTERVAL_MS wait (default 1000, 0 writes at once), and every
+// change made during it goes into the same write. A proof going from
+// queued to proving to complete within the interval is written once, and
+// so is the index, however many proofs changed. Changes made while a write
+// is in progress are picked up by the next one. A failed write is retried
+// and reported by /readyz until a write succeeds.
+//
+// On shutdown `flush` writes whatever is still waiting, after any write in
+// progress has finished.
+
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(1000);
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+pub struct ProofFlusher {
+    dirty: Notify,
+    interval: Duration,
+    // Held for the length of a write, so a flush can't overtake one
+    writing: tokio::sync::Mutex<()>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl ProofFlusher {
+    pub fn from_env() -> Self {
+        ProofFlusher {
+            dirty: Notify::new(),
+            interval: std::env::var("PERSIST_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(DEFAULT_INTERVAL),
+            writing: tokio::sync::Mutex::new(()),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    // Schedule a write of the proof store
+    pub fn mark_dirty(&self) {
+        self.dirty.notify_one();
+    }
+
+    // The last write's error, if it failed
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().unwrap().clone()
+    }
+
+    #[tracing::instrument(name = "persist.proofs", skip_all)]
+    async fn write(&self, state: &AppState) -> bool {
+        let store = &state.proof_store;
+        // In cluster mode the records are kept in the shared database
+        if !store.is_backed() {
+            return true;
+        }
+        let _writing = self.writing.lock().await;
+        let changes = store.take_changes().await;
+        // A notification that arrived during the last write, with nothing
+        // left for this one
+        if changes.is_empty() && self.last_error().is_none() {
+            return true;
+        }
+        let mut failed = Vec::new();
+        let mut result = Ok(());
+        for (id, record) in changes {
+            let written = match &record {
+                Some(record) => proof_db::save(record).await,
+                None => proof_db::delete(&id).await,
+            };
+            if let Err(e) = written {
+                result = Err(format!("proof {}: {}", id, e));
+                failed.push(id);
+            }
+        }
+        store.restore_changes(failed);
+        if result.is_ok() {
+            result = proof_db::save_index(&store.index().await).await.map_err(|e| format!("index: {}", e));
+        }
+        store.evict().await;
+        let ok = result.is_ok();
+        if let Err(e) = &result {
+            error!("Failed to save proofs to disk: {}", e);
+        }
+        *self.last_error.lock().unwrap() = result.err();
+        ok
+    }
+
+    // Write now, e.g. on shutdown, so no change is left unsaved
+    pub async fn flush(&self, state: &AppState) {
+        if self.write(state).await {
+            info!("Proof store saved");
+        }
+    }
+
+    // Write now, for changes that must be on disk before going on
+    pub async fn persist(&self, state: &AppState) -> Result<(), String> {
+        if self.write(state).await {
+            Ok(())
+        } else {
+            Err(self.last_error().unwrap_or_default())
+        }
+    }
+}
+
+pub fn spawn(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let flusher = state.proof_flusher.clone();
+        loop {
+            flusher.dirty.notified().await;
+            // Let the rest of the burst arrive; its notifications are
+            // covered by this write
+            tokio::time::sleep(flusher.interval).await;
+            if !flusher.write(&state).await {
+                tokio::time::sleep(RETRY_DELAY).await;
+                flusher.mark_dirty();
+            }
+        }
+    });
+}
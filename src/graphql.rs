@@ -0,0 +1,488 @@
+use async_graphql::{
+    futures_util::{Stream, StreamExt},
+    http::{GraphiQLSource, ALL_WEBSOCKET_PROTOCOLS},
+    Context, Data, EmptyMutation, Json, Object, Schema, SimpleObject, Subscription,
+};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
+use axum::{
+    extract::{State, WebSocketUpgrade},
+    response::{Html, IntoResponse, Response},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::{collections::BTreeMap, sync::OnceLock};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+
+use crate::{
+    analytics,
+    auth::Identity,
+    commands::ProofFilter,
+    events::Topic,
+    history::ConversationTurn,
+    read_public_outputs, receipts, retention, AppState, ProofRecord, ProofStatus, VerificationRecord,
+};
+
+// Read-only GraphQL over proofs, verifications, devices and chat sessions,
+// for dashboards that want nested data in one request, e.g.
+//
+//   { proofs(status: "complete", limit: 5) { id function
+//       verifications { isValid receipt { verifier signature } } } }
+//
+//   POST /api/graphql      queries
+//   GET  /api/graphql      GraphiQL
+//   GET  /api/graphql/ws   subscriptions (graphql-ws or graphql-transport-ws):
+//                          proofEvents and verificationEvents, optionally
+//                          for one proof
+//
+// Devices are the device IDs of prove_location proofs. Queries need the
+// viewer role, like the REST reads, and are limited to QUERY_DEPTH levels.
+
+const QUERY_DEPTH: usize = 8;
+
+pub type AgentSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+fn schema() -> &'static AgentSchema {
+    static SCHEMA: OnceLock<AgentSchema> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot).limit_depth(QUERY_DEPTH).finish()
+    })
+}
+
+fn state<'a>(ctx: &Context<'a>) -> &'a AppState {
+    ctx.data_unchecked::<AppState>()
+}
+
+pub struct Proof(ProofRecord);
+
+#[Object]
+impl Proof {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    // See proof_names.rs
+    async fn name(&self) -> Option<&str> {
+        self.0.name.as_deref()
+    }
+
+    async fn status(&self) -> &str {
+        self.0.status.name()
+    }
+
+    // zkEngine's progress while proving, once it reports it
+    async fn step(&self) -> Option<u64> {
+        match self.0.status {
+            ProofStatus::Proving { step, .. } => step,
+            _ => None,
+        }
+    }
+
+    async fn total_steps(&self) -> Option<u64> {
+        match self.0.status {
+            ProofStatus::Proving { total, .. } => total,
+            _ => None,
+        }
+    }
+
+    async fn function(&self) -> &str {
+        &self.0.metadata.function
+    }
+
+    async fn wasm_file(&self) -> String {
+        analytics::circuit(&self.0)
+    }
+
+    async fn arguments(&self) -> &[String] {
+        &self.0.metadata.arguments
+    }
+
+    async fn step_size(&self) -> u64 {
+        self.0.metadata.step_size
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    async fn generation_time_secs(&self) -> f64 {
+        self.0.metrics.generation_time_secs
+    }
+
+    async fn file_size_mb(&self) -> f64 {
+        self.0.metrics.file_size_mb
+    }
+
+    async fn file_hash(&self) -> &str {
+        &self.0.metrics.file_hash
+    }
+
+    // Circuit statistics, when zkEngine reported them
+    async fn execution_steps(&self) -> Option<u64> {
+        self.0.metrics.circuit.as_ref()?.steps
+    }
+
+    async fn constraints(&self) -> Option<u64> {
+        self.0.metrics.circuit.as_ref()?.constraints
+    }
+
+    async fn folding_rounds(&self) -> Option<u64> {
+        self.0.metrics.circuit.as_ref()?.folding_rounds
+    }
+
+    async fn error(&self) -> Option<&str> {
+        match &self.0.status {
+            ProofStatus::Failed(error) => Some(&error.message),
+            _ => None,
+        }
+    }
+
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+
+    async fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.0.validity.expires_at
+    }
+
+    async fn expired(&self) -> bool {
+        self.0.validity.is_expired()
+    }
+
+    async fn revoked(&self) -> bool {
+        self.0.validity.is_revoked()
+    }
+
+    // The proof this one was re-run from, if it is still stored, and how:
+    // retry, replay or template; see lineage.rs
+    async fn parent(&self, ctx: &Context<'_>) -> Option<Proof> {
+        state(ctx).proof_store.get(self.0.parent_id.as_deref()?).await.map(Proof)
+    }
+
+    async fn rerun(&self) -> Option<&str> {
+        self.0.rerun.map(|rerun| rerun.name())
+    }
+
+    // Proofs re-run from this one, oldest first
+    async fn reruns(&self, ctx: &Context<'_>) -> Vec<Proof> {
+        let state = state(ctx);
+        let ids = state.proof_store.ids(|entry| entry.parent_id.as_deref() == Some(self.0.id.as_str())).await;
+        let mut proofs = Vec::new();
+        for id in ids {
+            proofs.extend(state.proof_store.get(&id).await);
+        }
+        proofs.sort_by_key(|proof| proof.timestamp);
+        proofs.into_iter().map(Proof).collect()
+    }
+
+    // The integrator's own data it was started with; see custom_metadata.rs
+    async fn metadata(&self) -> Option<Json<Value>> {
+        (!self.0.custom_metadata.is_empty()).then(|| Json(Value::Object(self.0.custom_metadata.clone())))
+    }
+
+    // Public inputs and claimed output, once the proof is complete
+    async fn statement(&self) -> Option<Json<Value>> {
+        self.0.metadata.statement.as_ref().and_then(|statement| serde_json::to_value(statement).ok()).map(Json)
+    }
+
+    async fn public_outputs(&self, ctx: &Context<'_>) -> Option<Json<Value>> {
+        read_public_outputs(state(ctx), &self.0.id).await.map(Json)
+    }
+
+    // Oldest first
+    async fn verifications(&self, ctx: &Context<'_>) -> Vec<Verification> {
+        state(ctx).verification_store.lock().await
+            .iter()
+            .filter(|v| v.proof_id == self.0.id)
+            .cloned()
+            .map(Verification)
+            .collect()
+    }
+}
+
+pub struct Verification(VerificationRecord);
+
+#[Object]
+impl Verification {
+    async fn id(&self) -> &str {
+        &self.0.id
+    }
+
+    async fn proof_id(&self) -> Option<&str> {
+        (!self.0.proof_id.is_empty()).then_some(self.0.proof_id.as_str())
+    }
+
+    async fn verified_at(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+
+    async fn is_valid(&self) -> bool {
+        self.0.is_valid
+    }
+
+    async fn verification_time_secs(&self) -> f64 {
+        self.0.verification_time_secs
+    }
+
+    async fn error(&self) -> Option<&str> {
+        self.0.error.as_deref()
+    }
+
+    async fn cached(&self) -> bool {
+        self.0.cached
+    }
+
+    async fn receipt(&self) -> Option<Receipt> {
+        self.0.receipt.clone().map(Receipt::from)
+    }
+
+    // The proof it verified; none for uploaded proofs
+    async fn proof(&self, ctx: &Context<'_>) -> Option<Proof> {
+        state(ctx).proof_store.get(&self.0.proof_id).await.map(Proof)
+    }
+}
+
+// See receipts.rs
+#[derive(SimpleObject)]
+pub struct Receipt {
+    format: String,
+    verification_id: String,
+    proof_id: String,
+    proof_hash: String,
+    wasm_hash: String,
+    step_size: u64,
+    result: String,
+    verifier: String,
+    verified_at: String,
+    algorithm: String,
+    signature: String,
+}
+
+impl From<receipts::Receipt> for Receipt {
+    fn from(receipt: receipts::Receipt) -> Self {
+        Receipt {
+            format: receipt.format,
+            verification_id: receipt.verification_id,
+            proof_id: receipt.proof_id,
+            proof_hash: receipt.proof_hash,
+            wasm_hash: receipt.wasm_hash,
+            step_size: receipt.step_size,
+            result: receipt.result,
+            verifier: receipt.verifier,
+            verified_at: receipt.verified_at,
+            algorithm: receipt.algorithm,
+            signature: receipt.signature,
+        }
+    }
+}
+
+pub struct Device {
+    id: String,
+    proofs: Vec<ProofRecord>,
+}
+
+#[Object]
+impl Device {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    // Newest first
+    async fn proofs(&self) -> Vec<Proof> {
+        self.proofs.iter().cloned().map(Proof).collect()
+    }
+
+    // City code of the newest completed proof
+    async fn last_location(&self) -> Option<&str> {
+        self.proofs.iter()
+            .find(|p| matches!(p.status, ProofStatus::Complete))
+            .and_then(|p| p.metadata.arguments.first())
+            .map(String::as_str)
+    }
+}
+
+pub struct Session {
+    id: String,
+    turns: Vec<ConversationTurn>,
+}
+
+#[derive(SimpleObject)]
+pub struct Turn {
+    timestamp: DateTime<Utc>,
+    user_message: String,
+    response: Option<String>,
+    proof_ids: Vec<String>,
+}
+
+#[Object]
+impl Session {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn turns(&self) -> Vec<Turn> {
+        self.turns.iter()
+            .map(|turn| Turn {
+                timestamp: turn.timestamp,
+                user_message: turn.user_message.clone(),
+                response: turn.response.clone(),
+                proof_ids: turn.proof_ids.clone(),
+            })
+            .collect()
+    }
+
+    // Proofs the session started, in order
+    async fn proofs(&self, ctx: &Context<'_>) -> Vec<Proof> {
+        let mut proofs = Vec::new();
+        for id in self.turns.iter().flat_map(|turn| &turn.proof_ids) {
+            if let Some(proof) = state(ctx).proof_store.get(id).await {
+                proofs.push(Proof(proof));
+            }
+        }
+        proofs
+    }
+}
+
+// prove_location proofs by device ID, newest first
+async fn devices(state: &AppState) -> BTreeMap<String, Vec<ProofRecord>> {
+    let proofs = state.proof_store.filter_map(|p| (analytics::circuit(p) == "prove_location.wat").then(|| p.clone())).await;
+    let mut devices: BTreeMap<String, Vec<ProofRecord>> = BTreeMap::new();
+    for proof in proofs {
+        if let Some(device_id) = proof.metadata.arguments.get(1).cloned() {
+            devices.entry(device_id).or_default().push(proof);
+        }
+    }
+    for proofs in devices.values_mut() {
+        proofs.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+    }
+    devices
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // Newest first
+    async fn proofs(
+        &self,
+        ctx: &Context<'_>,
+        status: Option<String>,
+        function: Option<String>,
+        tag: Option<String>,
+        limit: Option<usize>,
+    ) -> Vec<Proof> {
+        let filter = ProofFilter { status, function, tag, limit };
+        filter.select(&state(ctx).proof_store).await.into_iter().map(Proof).collect()
+    }
+
+    // A full ID, unique prefix or name
+    async fn proof(&self, ctx: &Context<'_>, id: String) -> Option<Proof> {
+        let id = retention::resolve_proof_id(state(ctx), &id).await.ok()?;
+        state(ctx).proof_store.get(&id).await.map(Proof)
+    }
+
+    // Newest first
+    async fn verifications(&self, ctx: &Context<'_>, limit: Option<usize>) -> Vec<Verification> {
+        state(ctx).verification_store.lock().await
+            .iter()
+            .rev()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .map(Verification)
+            .collect()
+    }
+
+    async fn verification(&self, ctx: &Context<'_>, id: String) -> Option<Verification> {
+        state(ctx).verification_store.lock().await.iter().find(|v| v.id == id).cloned().map(Verification)
+    }
+
+    async fn devices(&self, ctx: &Context<'_>) -> Vec<Device> {
+        devices(state(ctx)).await.into_iter().map(|(id, proofs)| Device { id, proofs }).collect()
+    }
+
+    async fn device(&self, ctx: &Context<'_>, id: String) -> Option<Device> {
+        devices(state(ctx)).await.remove(&id).map(|proofs| Device { id, proofs })
+    }
+
+    // A chat session's history, by session ID; only the caller's own
+    // unless they are an admin, as over REST
+    async fn session(&self, ctx: &Context<'_>, id: String) -> Option<Session> {
+        let identity = ctx.data_unchecked::<Identity>();
+        state(ctx).history.get(identity, &id).await.map(|turns| Session { id, turns })
+    }
+}
+
+// A lifecycle event as sent to WebSocket clients
+#[derive(SimpleObject)]
+pub struct Event {
+    seq: Option<u64>,
+    // The event's type, e.g. proof_complete
+    kind: Option<String>,
+    proof_id: Option<String>,
+    content: String,
+    data: Json<Value>,
+}
+
+fn events(state: &AppState, topic: Topic, proof_id: Option<String>) -> impl Stream<Item = Event> {
+    BroadcastStream::new(state.events.subscribe(topic)).filter_map(move |msg| {
+        let event = match msg {
+            Ok(msg) => {
+                let data = serde_json::to_value(&msg.data).unwrap_or_default();
+                let event_proof_id = data["proof_id"].as_str().map(str::to_string);
+                let wanted = proof_id.as_ref().is_none_or(|id| event_proof_id.as_ref() == Some(id));
+                wanted.then(|| Event {
+                    seq: msg.seq,
+                    kind: data["type"].as_str().map(str::to_string),
+                    proof_id: event_proof_id,
+                    content: msg.content,
+                    data: Json(data),
+                })
+            }
+            Err(BroadcastStreamRecvError::Lagged(_)) => None,
+        };
+        async move { event }
+    })
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    // Proof starts, progress, completions and failures
+    async fn proof_events(&self, ctx: &Context<'_>, proof_id: Option<String>) -> impl Stream<Item = Event> {
+        events(state(ctx), Topic::Proofs, proof_id)
+    }
+
+    async fn verification_events(&self, ctx: &Context<'_>, proof_id: Option<String>) -> impl Stream<Item = Event> {
+        events(state(ctx), Topic::Verifications, proof_id)
+    }
+}
+
+pub async fn graphiql_handler() -> Html<String> {
+    Html(GraphiQLSource::build().endpoint("/api/graphql").subscription_endpoint("/api/graphql/ws").finish())
+}
+
+pub async fn query_handler(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    schema().execute(request.into_inner().data(state).data(identity)).await.into()
+}
+
+pub async fn ws_handler(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            let mut data = Data::default();
+            data.insert(state);
+            data.insert(identity);
+            GraphQLWebSocket::new(stream, schema().clone(), protocol).with_data(data).serve()
+        })
+        .into_response()
+}
@@ -0,0 +1,271 @@
+use std::{net::SocketAddr, pin::Pin};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::{error, info};
+
+use crate::{
+    auth::{Identity, Role},
+    catalog, custom_metadata,
+    errors::{AppError, ErrorCode},
+    events::Topic,
+    origin::{self, Channel, Origin},
+    proof_names, retention, start_proof, tags, verify_proof_async, AppState, ProofMetadata, ProofOptions, ProofRecord,
+    ProofStatus,
+    DEFAULT_STEP_SIZE,
+};
+
+// gRPC API (proto/agentkit.proto) for backend services that would rather
+// not speak the WebSocket chat protocol: proof generation, status
+// streaming, verification and listing. Built with `--features grpc` and
+// served when GRPC_LISTEN is set (e.g. "0.0.0.0:50051"). Calls carry the
+// same tokens and need the same roles as their REST counterparts, in an
+// "authorization: Bearer <token>" header; errors map the REST error codes
+// onto gRPC status codes, with the code in the message.
+
+pub mod pb {
+    tonic::include_proto!("agentkit.v1");
+}
+
+use pb::proof_service_server::{ProofService, ProofServiceServer};
+
+struct Service {
+    state: AppState,
+}
+
+// The snake_case name REST responses use
+fn code_name(code: ErrorCode) -> String {
+    serde_json::to_value(code).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn status(error: AppError) -> Status {
+    let message = format!("{}: {}", code_name(error.code), error.message);
+    match error.code {
+        ErrorCode::ProofNotFound | ErrorCode::NotFound | ErrorCode::WasmNotFound => Status::not_found(message),
+        ErrorCode::UnknownFunction | ErrorCode::InvalidArguments | ErrorCode::AmbiguousProofId => Status::invalid_argument(message),
+        ErrorCode::ProofNotReady | ErrorCode::ArtifactTampered => Status::failed_precondition(message),
+        ErrorCode::NameTaken => Status::already_exists(message),
+        ErrorCode::Unauthorized => Status::unauthenticated(message),
+        ErrorCode::Forbidden
+        | ErrorCode::SessionForbidden
+        | ErrorCode::FeatureDisabled
+        | ErrorCode::ApprovalRejected => Status::permission_denied(message),
+        ErrorCode::RateLimited => Status::resource_exhausted(message),
+        ErrorCode::Timeout => Status::deadline_exceeded(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn proof_message(proof: &ProofRecord) -> pb::Proof {
+    let (error, error_code) = match &proof.status {
+        ProofStatus::Failed(error) => (error.message.clone(), code_name(error.code)),
+        _ => Default::default(),
+    };
+    let (step, total_steps) = match proof.status {
+        ProofStatus::Proving { step, total } => (step.unwrap_or(0), total.unwrap_or(0)),
+        _ => (0, 0),
+    };
+    let circuit = proof.metrics.circuit.clone().unwrap_or_default();
+    pb::Proof {
+        proof_id: proof.id.clone(),
+        status: proof.status.name().to_string(),
+        function: proof.metadata.function.clone(),
+        wasm_file: proof.metadata.wasm_path.rsplit('/').next().unwrap_or_default().to_string(),
+        arguments: proof.metadata.arguments.clone(),
+        step_size: proof.metadata.step_size,
+        created_at: proof.timestamp.to_rfc3339(),
+        generation_time_secs: proof.metrics.generation_time_secs,
+        file_size_mb: proof.metrics.file_size_mb,
+        file_hash: proof.metrics.file_hash.clone(),
+        error,
+        error_code,
+        tags: proof.tags.clone(),
+        expired: proof.validity.is_expired(),
+        revoked: proof.validity.is_revoked(),
+        step,
+        total_steps,
+        execution_steps: circuit.steps.unwrap_or(0),
+        constraints: circuit.constraints.unwrap_or(0),
+        folding_rounds: circuit.folding_rounds.unwrap_or(0),
+        name: proof.name.clone().unwrap_or_default(),
+        parent_id: proof.parent_id.clone().unwrap_or_default(),
+        rerun: proof.rerun.map(|rerun| rerun.name().to_string()).unwrap_or_default(),
+        metadata: if proof.custom_metadata.is_empty() {
+            String::new()
+        } else {
+            serde_json::to_string(&proof.custom_metadata).unwrap_or_default()
+        },
+    }
+}
+
+impl Service {
+    // The same role check as auth::middleware does for REST
+    async fn authorize<T>(&self, request: &Request<T>, required: Role) -> Result<Identity, Status> {
+        let auth = &self.state.auth;
+        if !auth.is_enabled() {
+            return Ok(Identity::anonymous());
+        }
+        let token = request.metadata()
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let identity = match token {
+            Some(token) => auth.authenticate(token).await,
+            None => None,
+        };
+        match identity {
+            Some(identity) if identity.role >= required => Ok(identity),
+            Some(identity) => Err(Status::permission_denied(format!(
+                "This needs the {} role; you are a {}",
+                required.name(),
+                identity.role.name()
+            ))),
+            None => Err(Status::unauthenticated("Missing or invalid API token")),
+        }
+    }
+
+    async fn proof(&self, id: &str) -> Result<ProofRecord, Status> {
+        let id = retention::resolve_proof_id(&self.state, id).await.map_err(status)?;
+        self.state.proof_store.get(&id).await.ok_or_else(|| status(AppError::proof_not_found()))
+    }
+}
+
+#[tonic::async_trait]
+impl ProofService for Service {
+    async fn generate_proof(&self, request: Request<pb::GenerateProofRequest>) -> Result<Response<pb::Proof>, Status> {
+        let identity = self.authorize(&request, Role::Prover).await?;
+        let mut origin = Origin::new(Channel::Grpc).identity(&identity);
+        origin.client_ip = request.remote_addr().map(|addr| addr.ip().to_string());
+        origin.user_agent = request.metadata()
+            .get("user-agent")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let request = request.into_inner();
+        let state = &self.state;
+        let function = if request.function.is_empty() { "main".to_string() } else { request.function };
+        let step_size = if request.step_size == 0 { DEFAULT_STEP_SIZE } else { request.step_size };
+        state.flags.check_module(&request.wasm_file).map_err(status)?;
+        let wasm_path = catalog::validate_request(&state.wasm_dir, &request.wasm_file, &request.arguments, step_size)
+            .map_err(status)?;
+        let arguments = catalog::encode_module_arguments(&request.wasm_file, &request.arguments).map_err(status)?;
+        let tags = tags::normalize(&request.tags).map_err(status)?;
+        let custom_metadata = custom_metadata::parse_json(&request.metadata).map_err(status)?;
+        let name = match request.name.as_str() {
+            "" => None,
+            name => Some(proof_names::normalize(name).map_err(status)?),
+        };
+        let metadata = ProofMetadata {
+            wasm_path: wasm_path.display().to_string(),
+            function,
+            arguments: request.arguments,
+            step_size,
+            statement: None,
+        };
+        let options = ProofOptions { tags, custom_metadata, name, ..Default::default() };
+        let proof_id = origin::scope(origin, start_proof(state, metadata, arguments, None, options)).await.map_err(status)?.id;
+        info!("gRPC client started proof {}", proof_id);
+        Ok(Response::new(proof_message(&self.proof(&proof_id).await?)))
+    }
+
+    async fn get_proof(&self, request: Request<pb::ProofId>) -> Result<Response<pb::Proof>, Status> {
+        self.authorize(&request, Role::Viewer).await?;
+        let proof = self.proof(&request.into_inner().proof_id).await?;
+        Ok(Response::new(proof_message(&proof)))
+    }
+
+    type WatchProofStream = Pin<Box<dyn Stream<Item = Result<pb::Proof, Status>> + Send>>;
+
+    async fn watch_proof(&self, request: Request<pb::ProofId>) -> Result<Response<Self::WatchProofStream>, Status> {
+        self.authorize(&request, Role::Viewer).await?;
+        let id = self.proof(&request.into_inner().proof_id).await?.id;
+        let state = self.state.clone();
+        // Subscribe before the first read so no change is missed
+        let mut events = state.events.subscribe(Topic::Proofs);
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut last = None;
+            loop {
+                let proof = state.proof_store.read(&id, proof_message).await;
+                let Some(proof) = proof else {
+                    let _ = tx.send(Err(status(AppError::proof_not_found()))).await;
+                    return;
+                };
+                let done = matches!(proof.status.as_str(), "complete" | "failed" | "cancelled");
+                let progress = (proof.status.clone(), proof.step);
+                if last.as_ref() != Some(&progress) {
+                    last = Some(progress);
+                    if tx.send(Ok(proof)).await.is_err() {
+                        return;
+                    }
+                }
+                if done {
+                    return;
+                }
+                match events.recv().await {
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn verify_proof(&self, request: Request<pb::ProofId>) -> Result<Response<pb::Verification>, Status> {
+        self.authorize(&request, Role::Prover).await?;
+        let proof = self.proof(&request.into_inner().proof_id).await?;
+        if !matches!(proof.status, ProofStatus::Complete) {
+            return Err(status(AppError::new(ErrorCode::ProofNotReady, "Proof not complete")));
+        }
+        let verification = verify_proof_async(self.state.clone(), proof.id.clone(), None).await.map_err(status)?;
+        Ok(Response::new(pb::Verification {
+            verification_id: verification.id,
+            proof_id: verification.proof_id,
+            is_valid: verification.is_valid,
+            verification_time_secs: verification.verification_time_secs,
+            error: verification.error.unwrap_or_default(),
+            error_code: verification.error_code.map(code_name).unwrap_or_default(),
+        }))
+    }
+
+    async fn list_proofs(&self, request: Request<pb::ListProofsRequest>) -> Result<Response<pb::ListProofsResponse>, Status> {
+        self.authorize(&request, Role::Viewer).await?;
+        let request = request.into_inner();
+        let wanted = tags::normalize(&request.tags).map_err(status)?;
+        let mut matching: Vec<ProofRecord> = self.state.proof_store
+            .filter_map_where(
+                |entry| request.status.is_empty() || entry.status.is_named(&request.status),
+                |p| wanted.iter().all(|tag| p.tags.contains(tag)).then(|| p.clone()),
+            )
+            .await;
+        matching.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+        let count = matching.len() as u32;
+        if request.limit > 0 {
+            matching.truncate(request.limit as usize);
+        }
+        Ok(Response::new(pb::ListProofsResponse {
+            proofs: matching.into_iter().map(proof_message).collect(),
+            count,
+        }))
+    }
+}
+
+// Serve the gRPC API on GRPC_LISTEN, if set
+pub fn spawn(state: &AppState) {
+    let Ok(listen) = std::env::var("GRPC_LISTEN") else {
+        return;
+    };
+    let addr: SocketAddr = match listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("Invalid GRPC_LISTEN {}: {}", listen, e);
+            return;
+        }
+    };
+    let service = ProofServiceServer::new(Service { state: state.clone() });
+    tokio::spawn(async move {
+        info!("gRPC API listening on {}", addr);
+        if let Err(e) = Server::builder().add_service(service).serve(addr).await {
+            error!("gRPC server failed: {}", e);
+        }
+    });
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+use tokio::sync::Mutex;
+use tracing::{error, warn};
+
+use crate::auth::{Identity, Role};
+
+const HISTORY_DB_FILE: &str = "./sessions_db.json";
+
+// Conversations are kept under `subject:session_id`, the chat session's
+// LLM session ID, so a client that later takes over a session ID neither
+// sees nor adds to its earlier owner's history
+pub fn conversation_id(subject: &str, session_id: &str) -> String {
+    format!("{}:{}", subject, session_id)
+}
+
+// One chat exchange: what the user said, what was answered and which proofs it started
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConversationTurn {
+    pub timestamp: DateTime<Utc>,
+    pub user_message: String,
+    pub response: Option<String>,
+    #[serde(default)]
+    pub proof_ids: Vec<String>,
+}
+
+// Conversation turns per conversation ID, persisted like the proof store.
+// Each session keeps at most HISTORY_MAX_TURNS turns (default 100).
+pub struct HistoryStore {
+    sessions: Mutex<HashMap<String, Vec<ConversationTurn>>>,
+    max_turns: usize,
+}
+
+impl HistoryStore {
+    pub async fn load() -> Self {
+        let max_turns = std::env::var("HISTORY_MAX_TURNS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(100);
+        let sessions = load_history_from_disk().await.unwrap_or_else(|e| {
+            warn!("Failed to load conversation history from disk: {}", e);
+            HashMap::new()
+        });
+        HistoryStore { sessions: Mutex::new(sessions), max_turns }
+    }
+
+    // Start a new turn for a user message
+    pub async fn begin_turn(&self, session_id: &str, user_message: &str) {
+        let mut sessions = self.sessions.lock().await;
+        let turns = sessions.entry(session_id.to_string()).or_default();
+        turns.push(ConversationTurn {
+            timestamp: Utc::now(),
+            user_message: user_message.to_string(),
+            response: None,
+            proof_ids: Vec::new(),
+        });
+        if turns.len() > self.max_turns {
+            let excess = turns.len() - self.max_turns;
+            turns.drain(..excess);
+        }
+        save_history_to_disk(&sessions).await;
+    }
+
+    // Append reply text to the session's current turn
+    pub async fn record_response(&self, session_id: &str, text: &str) {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(turn) = sessions.get_mut(session_id).and_then(|turns| turns.last_mut()) {
+            match &mut turn.response {
+                Some(response) => {
+                    response.push('\n');
+                    response.push_str(text);
+                }
+                None => turn.response = Some(text.to_string()),
+            }
+            save_history_to_disk(&sessions).await;
+        }
+    }
+
+    // Link a proof started by the session's current turn
+    pub async fn record_proof(&self, session_id: &str, proof_id: &str) {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(turn) = sessions.get_mut(session_id).and_then(|turns| turns.last_mut()) {
+            turn.proof_ids.push(proof_id.to_string());
+            save_history_to_disk(&sessions).await;
+        }
+    }
+
+    // A session's history as `identity` may see it: their own conversation
+    // in the session. Admins may also read anyone's, by session ID (the most
+    // recent conversation in it) or conversation ID.
+    pub async fn get(&self, identity: &Identity, session_id: &str) -> Option<Vec<ConversationTurn>> {
+        let sessions = self.sessions.lock().await;
+        if let Some(turns) = sessions.get(&conversation_id(&identity.subject, session_id)) {
+            return Some(turns.clone());
+        }
+        if identity.role < Role::Admin {
+            return None;
+        }
+        if let Some(turns) = sessions.get(session_id) {
+            return Some(turns.clone());
+        }
+        let suffix = format!(":{}", session_id);
+        sessions.iter()
+            .filter(|(id, _)| id.ends_with(&suffix))
+            .map(|(_, turns)| turns)
+            .max_by_key(|turns| turns.last().map(|turn| turn.timestamp))
+            .cloned()
+    }
+}
+
+async fn save_history_to_disk(sessions: &HashMap<String, Vec<ConversationTurn>>) {
+    let result = match serde_json::to_string_pretty(sessions) {
+        Ok(json) => tokio::fs::write(HISTORY_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save conversation history to disk: {}", e);
+    }
+}
+
+async fn load_history_from_disk() -> Result<HashMap<String, Vec<ConversationTurn>>, Box<dyn std::error::Error>> {
+    if Path::new(HISTORY_DB_FILE).exists() {
+        let json = tokio::fs::read_to_string(HISTORY_DB_FILE).await?;
+        Ok(serde_json::from_str(&json)?)
+    } else {
+        Ok(HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(message: &str) -> ConversationTurn {
+        ConversationTurn { timestamp: Utc::now(), user_message: message.to_string(), response: None, proof_ids: Vec::new() }
+    }
+
+    fn store() -> HistoryStore {
+        let sessions = HashMap::from([
+            (conversation_id("alice", "session-1"), vec![turn("alice's question")]),
+            (conversation_id("bob", "session-2"), vec![turn("bob's question")]),
+        ]);
+        HistoryStore { sessions: Mutex::new(sessions), max_turns: 100 }
+    }
+
+    fn identity(subject: &str, role: Role) -> Identity {
+        Identity { subject: subject.to_string(), role }
+    }
+
+    #[tokio::test]
+    async fn clients_only_see_their_own_conversations() {
+        let store = store();
+        let alice = identity("alice", Role::Viewer);
+        assert_eq!(store.get(&alice, "session-1").await.unwrap()[0].user_message, "alice's question");
+        assert!(store.get(&alice, "session-2").await.is_none());
+        assert!(store.get(&alice, "bob:session-2").await.is_none());
+        assert!(store.get(&identity("bob", Role::Prover), "session-1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn admins_see_any_conversation() {
+        let store = store();
+        let admin = identity("root", Role::Admin);
+        assert_eq!(store.get(&admin, "session-2").await.unwrap()[0].user_message, "bob's question");
+        assert_eq!(store.get(&admin, "alice:session-1").await.unwrap()[0].user_message, "alice's question");
+        assert!(store.get(&admin, "session-3").await.is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::short_id;
+
+// Translations for the messages the server itself writes (proof and
+// verification updates). Each chat session has a language, taken from the
+// `lang` query parameter or Accept-Language header when it connects and
+// changeable per message; it is also passed to the LLM so its replies match.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Es,
+    Ja,
+}
+
+impl Language {
+    // Accepts language tags ("es", "es-MX", "ja_JP") and English names
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_lowercase();
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary {
+            "en" | "english" => Some(Language::En),
+            "es" | "spanish" | "español" => Some(Language::Es),
+            "ja" | "japanese" | "日本語" => Some(Language::Ja),
+            _ => None,
+        }
+    }
+
+    // First supported language in an Accept-Language header, in order of
+    // preference (q-values are ignored; browsers already list by preference)
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        header
+            .split(',')
+            .filter_map(|part| part.split(';').next())
+            .find_map(Language::parse)
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Es => "es",
+            Language::Ja => "ja",
+        }
+    }
+
+    // English name, for the LLM prompt
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Es => "Spanish",
+            Language::Ja => "Japanese",
+        }
+    }
+}
+
+pub enum Text<'a> {
+    ProofStarted { function: &'a str, arguments: &'a [String] },
+    ProofComplete { proof_id: &'a str, secs: f64, size_mb: f64 },
+    ProofReused { proof_id: &'a str, secs: f64 },
+    ProofFailed { error: &'a str },
+    VerificationValid { proof_id: &'a str, secs: f64 },
+    VerificationInvalid { proof_id: &'a str, error: &'a str },
+    VerificationTampered { proof_id: &'a str },
+    VerificationNotFound { proof_id: &'a str },
+    VerificationNotComplete { proof_id: &'a str },
+    VerificationNoFile { proof_id: &'a str },
+    VerificationError { error: &'a str },
+}
+
+pub fn text(language: Language, text: Text) -> String {
+    match (language, text) {
+        (Language::En, Text::ProofStarted { function, arguments }) => {
+            format!("Starting proof generation for {} with arguments {:?}", function, arguments)
+        }
+        (Language::Es, Text::ProofStarted { function, arguments }) => {
+            format!("Iniciando la generación de la prueba {} con argumentos {:?}", function, arguments)
+        }
+        (Language::Ja, Text::ProofStarted { function, arguments }) => {
+            format!("{} の証明生成を開始します（引数 {:?}）", function, arguments)
+        }
+
+        (Language::En, Text::ProofComplete { proof_id, secs, size_mb }) => format!(
+            "Proof generated successfully! ID: {} Time: {:.1}s Size: {:.1}MB",
+            short_id(proof_id), secs, size_mb
+        ),
+        (Language::Es, Text::ProofComplete { proof_id, secs, size_mb }) => format!(
+            "¡Prueba generada con éxito! ID: {} Tiempo: {:.1}s Tamaño: {:.1}MB",
+            short_id(proof_id), secs, size_mb
+        ),
+        (Language::Ja, Text::ProofComplete { proof_id, secs, size_mb }) => format!(
+            "証明の生成に成功しました！ID: {} 時間: {:.1}秒 サイズ: {:.1}MB",
+            short_id(proof_id), secs, size_mb
+        ),
+
+        (Language::En, Text::ProofReused { proof_id, secs }) => format!(
+            "This exact proof was already generated, so it was reused instead of proving again ({:.1}s saved). ID: {}",
+            secs, short_id(proof_id)
+        ),
+        (Language::Es, Text::ProofReused { proof_id, secs }) => format!(
+            "Esta misma prueba ya se había generado, así que se reutilizó en lugar de generarla de nuevo ({:.1}s ahorrados). ID: {}",
+            secs, short_id(proof_id)
+        ),
+        (Language::Ja, Text::ProofReused { proof_id, secs }) => format!(
+            "同じ証明が既に生成されていたため、再生成せずに再利用しました（{:.1}秒の節約）。ID: {}",
+            secs, short_id(proof_id)
+        ),
+
+        (Language::En, Text::ProofFailed { error }) => format!("Proof generation failed: {}", error),
+        (Language::Es, Text::ProofFailed { error }) => format!("La generación de la prueba falló: {}", error),
+        (Language::Ja, Text::ProofFailed { error }) => format!("証明の生成に失敗しました: {}", error),
+
+        (Language::En, Text::VerificationValid { proof_id, secs }) => {
+            format!("✅ Proof {} is VALID! Verified in {:.3}s", short_id(proof_id), secs)
+        }
+        (Language::Es, Text::VerificationValid { proof_id, secs }) => {
+            format!("✅ ¡La prueba {} es VÁLIDA! Verificada en {:.3}s", short_id(proof_id), secs)
+        }
+        (Language::Ja, Text::VerificationValid { proof_id, secs }) => {
+            format!("✅ 証明 {} は有効です！検証時間 {:.3}秒", short_id(proof_id), secs)
+        }
+
+        (Language::En, Text::VerificationInvalid { proof_id, error }) => {
+            format!("❌ Proof {} is INVALID. Error: {}", short_id(proof_id), error)
+        }
+        (Language::Es, Text::VerificationInvalid { proof_id, error }) => {
+            format!("❌ La prueba {} NO es válida. Error: {}", short_id(proof_id), error)
+        }
+        (Language::Ja, Text::VerificationInvalid { proof_id, error }) => {
+            format!("❌ 証明 {} は無効です。エラー: {}", short_id(proof_id), error)
+        }
+
+        (Language::En, Text::VerificationTampered { proof_id }) => {
+            format!("⚠️ Proof {} has been modified since it was generated and was not verified", short_id(proof_id))
+        }
+        (Language::Es, Text::VerificationTampered { proof_id }) => {
+            format!("⚠️ La prueba {} fue modificada después de generarse y no se verificó", short_id(proof_id))
+        }
+        (Language::Ja, Text::VerificationTampered { proof_id }) => {
+            format!("⚠️ 証明 {} は生成後に変更されているため、検証しませんでした", short_id(proof_id))
+        }
+
+        (Language::En, Text::VerificationNotFound { proof_id }) => format!("Proof {} not found", short_id(proof_id)),
+        (Language::Es, Text::VerificationNotFound { proof_id }) => format!("No se encontró la prueba {}", short_id(proof_id)),
+        (Language::Ja, Text::VerificationNotFound { proof_id }) => format!("証明 {} が見つかりません", short_id(proof_id)),
+
+        (Language::En, Text::VerificationNotComplete { proof_id }) => {
+            format!("Proof {} is not complete yet", short_id(proof_id))
+        }
+        (Language::Es, Text::VerificationNotComplete { proof_id }) => {
+            format!("La prueba {} todavía no está completa", short_id(proof_id))
+        }
+        (Language::Ja, Text::VerificationNotComplete { proof_id }) => {
+            format!("証明 {} はまだ完了していません", short_id(proof_id))
+        }
+
+        (Language::En, Text::VerificationNoFile { proof_id }) => format!("Proof file not found for {}", short_id(proof_id)),
+        (Language::Es, Text::VerificationNoFile { proof_id }) => {
+            format!("No se encontró el archivo de la prueba {}", short_id(proof_id))
+        }
+        (Language::Ja, Text::VerificationNoFile { proof_id }) => {
+            format!("証明 {} のファイルが見つかりません", short_id(proof_id))
+        }
+
+        (Language::En, Text::VerificationError { error }) => format!("Verification failed: {}", error),
+        (Language::Es, Text::VerificationError { error }) => format!("La verificación falló: {}", error),
+        (Language::Ja, Text::VerificationError { error }) => format!("検証に失敗しました: {}", error),
+    }
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+
+use crate::{
+    catalog, locations,
+    llm::{IntentProvider, IntentRequest, IntentResponse, ProofIntent},
+};
+
+// Rule-based intent parser that keeps the core proof commands working when
+// no LLM is reachable. Handles phrases like "prove fibonacci of 10",
+// "prove location sf device 42" and "add 3 and 5 with step size 100".
+
+// Keyword -> (function, number of arguments)
+const FUNCTIONS: [(&str, &str, usize); 16] = [
+    ("fibonacci", "fibonacci", 1),
+    ("fib", "fibonacci", 1),
+    ("factorial", "factorial", 1),
+    ("square", "square", 1),
+    ("even", "is_even", 1),
+    ("count", "count_until", 1),
+    ("add", "add", 2),
+    ("sum", "add", 2),
+    ("plus", "add", 2),
+    ("multiply", "multiply", 2),
+    ("times", "multiply", 2),
+    ("product", "multiply", 2),
+    ("max", "max", 2),
+    ("maximum", "max", 2),
+    ("larger", "max", 2),
+    ("bigger", "max", 2),
+];
+
+struct Tokens {
+    words: Vec<String>,
+    numbers: Vec<String>,
+    step_size: Option<u64>,
+}
+
+fn tokenize(input: &str) -> Tokens {
+    let cleaned: String = input
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { ' ' })
+        .collect();
+    let words: Vec<String> = cleaned.split_whitespace().map(str::to_string).collect();
+
+    // "step size 100" / "step 100" sets the step size and isn't an argument
+    let mut numbers = Vec::new();
+    let mut step_size = None;
+    let mut i = 0;
+    while i < words.len() {
+        if words[i] == "step" || words[i] == "steps" {
+            let j = if words.get(i + 1).is_some_and(|w| w == "size") { i + 2 } else { i + 1 };
+            if let Some(n) = words.get(j).and_then(|w| w.parse::<u64>().ok()) {
+                step_size = Some(n);
+                i = j + 1;
+                continue;
+            }
+        }
+        if words[i].parse::<i64>().is_ok() {
+            numbers.push(words[i].clone());
+        }
+        i += 1;
+    }
+
+    Tokens { words, numbers, step_size }
+}
+
+impl Tokens {
+    fn has(&self, word: &str) -> bool {
+        self.words.iter().any(|w| w == word)
+    }
+
+    // Match a (possibly multi-word) phrase on word boundaries
+    fn has_phrase(&self, phrase: &str) -> bool {
+        let parts: Vec<&str> = phrase.split(' ').collect();
+        self.words.windows(parts.len()).any(|window| window.iter().zip(&parts).all(|(w, p)| w == p))
+    }
+
+    // Number that follows `word`, e.g. the 42 in "device 42" or "device id 42"
+    fn number_after(&self, word: &str) -> Option<String> {
+        let pos = self.words.iter().position(|w| w == word)?;
+        self.words[pos + 1..]
+            .iter()
+            .take(2)
+            .find(|w| w.parse::<i64>().is_ok())
+            .cloned()
+    }
+
+    fn numbers_or(&self, defaults: &[&str]) -> Vec<String> {
+        defaults
+            .iter()
+            .enumerate()
+            .map(|(i, default)| self.numbers.get(i).cloned().unwrap_or_else(|| default.to_string()))
+            .collect()
+    }
+}
+
+pub fn parse(input: &str) -> Option<ProofIntent> {
+    let tokens = tokenize(input);
+
+    let place = locations::phrases().into_iter().find(|(phrase, _)| tokens.has_phrase(phrase));
+    let (function, arguments) = if let Some((_, city)) = place {
+        let device_id = tokens
+            .number_after("device")
+            .or_else(|| tokens.numbers.first().cloned())
+            .unwrap_or_else(|| "1".to_string());
+        ("prove_location", vec![city, device_id])
+    } else if tokens.has("kyc") || tokens.has("compliance") {
+        ("prove_kyc", tokens.numbers_or(&["12345", "1"]))
+    } else if tokens.has_phrase("ai content") || tokens.has("authenticity") {
+        ("prove_ai_content", tokens.numbers_or(&["42", "1"]))
+    } else {
+        let (_, function, arity) = FUNCTIONS.iter().find(|(keyword, _, _)| tokens.has(keyword))?;
+        if tokens.numbers.len() < *arity {
+            return None;
+        }
+        (*function, tokens.numbers[..*arity].to_vec())
+    };
+
+    Some(ProofIntent {
+        function: function.to_string(),
+        explanation: format!("Generating proof for {}({})", function, arguments.join(", ")),
+        arguments,
+        step_size: tokens.step_size.unwrap_or(50),
+        complexity_reasoning: None,
+    })
+}
+
+// Provider backed by the rule-based parser (LLM_PROVIDER=local)
+pub struct LocalProvider;
+
+#[async_trait]
+impl IntentProvider for LocalProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let intent = parse(&request.message);
+        Ok(IntentResponse {
+            response: intent.as_ref().map_or_else(catalog::examples, |intent| intent.explanation.clone()),
+            requires_proof: intent.is_some(),
+            intent,
+            session_id: request.session_id.clone().unwrap_or_else(|| "default".to_string()),
+            tool_calls: Vec::new(),
+        })
+    }
+}
@@ -0,0 +1,376 @@
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use redis::{aio::ConnectionManager, Value};
+use serde::Serialize;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use uuid::Uuid;
+
+use crate::cluster::Database;
+
+// Where proof jobs wait for a worker in cluster mode (CLUSTER_QUEUE):
+//
+//   postgres   the zkp_jobs table in the cluster database (default)
+//   redis      the Redis stream zkp:jobs at CLUSTER_REDIS_URL
+//              (redis://host:6379/0), read by the consumer group "provers"
+//
+// Delivery is at least once. A job stays leased to the worker running it
+// until it finishes, and goes back to the queue when the worker stops
+// renewing the lease. A job delivered CLUSTER_MAX_ATTEMPTS times without
+// finishing is poisoned, usually because it kills its workers; it moves to
+// the dead-letter queue (the zkp_dead_jobs table or zkp:jobs:dead stream,
+// listed at GET /api/admin/dead-jobs) and its proof fails.
+
+pub struct Job {
+    // The queue's handle for this delivery
+    pub delivery: String,
+    pub proof_id: String,
+    pub args: Vec<String>,
+    // Deliveries so far, this one included
+    pub attempts: u64,
+}
+
+#[derive(Serialize)]
+pub struct DeadJob {
+    pub proof_id: String,
+    pub args: Vec<String>,
+    pub attempts: u64,
+    pub reason: String,
+    pub failed_at: String,
+}
+
+pub struct QueueStatus {
+    pub queued: u64,
+    pub running: u64,
+    pub dead: u64,
+}
+
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn enqueue(&self, proof_id: &str, args: &[String]) -> anyhow::Result<()>;
+    // Lease the next job nobody holds to `worker`
+    async fn claim(&self, worker: &str) -> anyhow::Result<Option<Job>>;
+    // Extend `worker`'s lease; false if the job went to another worker
+    async fn renew(&self, job: &Job, worker: &str) -> anyhow::Result<bool>;
+    // Remove a job whose proof is finished
+    async fn complete(&self, job: &Job, worker: &str) -> anyhow::Result<()>;
+    async fn dead_letter(&self, job: &Job, worker: &str, reason: &str) -> anyhow::Result<()>;
+    // Most recent dead-lettered jobs, newest first
+    async fn dead_jobs(&self, limit: usize) -> anyhow::Result<Vec<DeadJob>>;
+    async fn status(&self) -> anyhow::Result<QueueStatus>;
+}
+
+pub async fn from_env(db: Arc<Database>, lease: Duration) -> anyhow::Result<Arc<dyn JobQueue>> {
+    match std::env::var("CLUSTER_QUEUE").unwrap_or_else(|_| "postgres".to_string()).as_str() {
+        "postgres" => Ok(Arc::new(PostgresQueue::new(db, lease).await?)),
+        "redis" => {
+            let url = std::env::var("CLUSTER_REDIS_URL").map_err(|_| anyhow::anyhow!("CLUSTER_QUEUE=redis needs CLUSTER_REDIS_URL"))?;
+            Ok(Arc::new(RedisQueue::new(&url, lease).await?))
+        }
+        other => anyhow::bail!("unknown CLUSTER_QUEUE {:?} (expected postgres or redis)", other),
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Postgres
+
+const POSTGRES_SCHEMA: &str = "
+    BEGIN;
+    SELECT pg_advisory_xact_lock(7300202);
+    CREATE TABLE IF NOT EXISTS zkp_jobs (
+        proof_id TEXT PRIMARY KEY,
+        args JSONB NOT NULL,
+        created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+        lease_owner TEXT,
+        lease_expires TIMESTAMPTZ,
+        attempts INT NOT NULL DEFAULT 0
+    );
+    CREATE TABLE IF NOT EXISTS zkp_dead_jobs (
+        proof_id TEXT NOT NULL,
+        args JSONB NOT NULL,
+        attempts INT NOT NULL,
+        reason TEXT NOT NULL,
+        failed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    );
+    COMMIT;
+";
+const CLAIM_JOB: &str = "
+    UPDATE zkp_jobs
+    SET lease_owner = $1, lease_expires = now() + make_interval(secs => $2), attempts = attempts + 1
+    WHERE proof_id = (
+        SELECT proof_id FROM zkp_jobs
+        WHERE lease_expires IS NULL OR lease_expires < now()
+        ORDER BY created_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING proof_id, args, attempts";
+const RENEW_LEASE: &str = "
+    UPDATE zkp_jobs SET lease_expires = now() + make_interval(secs => $3)
+    WHERE proof_id = $1 AND lease_owner = $2";
+const DEAD_LETTER: &str = "
+    WITH job AS (DELETE FROM zkp_jobs WHERE proof_id = $1 AND lease_owner = $2 RETURNING proof_id, args, attempts)
+    INSERT INTO zkp_dead_jobs (proof_id, args, attempts, reason) SELECT proof_id, args, attempts, $3 FROM job";
+
+pub struct PostgresQueue {
+    db: Arc<Database>,
+    lease: Duration,
+}
+
+impl PostgresQueue {
+    async fn new(db: Arc<Database>, lease: Duration) -> anyhow::Result<Self> {
+        db.client().await?.batch_execute(POSTGRES_SCHEMA).await.map_err(|e| anyhow::anyhow!("job queue schema: {}", e))?;
+        Ok(PostgresQueue { db, lease })
+    }
+}
+
+#[async_trait]
+impl JobQueue for PostgresQueue {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn enqueue(&self, proof_id: &str, args: &[String]) -> anyhow::Result<()> {
+        let args = serde_json::to_value(args)?;
+        self.db.client().await?
+            .execute("INSERT INTO zkp_jobs (proof_id, args) VALUES ($1, $2) ON CONFLICT DO NOTHING", &[&proof_id, &args])
+            .await?;
+        Ok(())
+    }
+
+    // The delivery is a lease token unique to the claim, so a worker that
+    // lost a job and claimed it again can't act on the old lease
+    async fn claim(&self, worker: &str) -> anyhow::Result<Option<Job>> {
+        let lease = format!("{}/{}", worker, Uuid::new_v4());
+        let row = self.db.client().await?.query_opt(CLAIM_JOB, &[&lease, &self.lease.as_secs_f64()]).await?;
+        Ok(row.map(|row| Job {
+            delivery: lease,
+            proof_id: row.get(0),
+            args: serde_json::from_value(row.get(1)).unwrap_or_default(),
+            attempts: row.get::<_, i32>(2) as u64,
+        }))
+    }
+
+    async fn renew(&self, job: &Job, _worker: &str) -> anyhow::Result<bool> {
+        let rows = self.db.client().await?
+            .execute(RENEW_LEASE, &[&job.proof_id, &job.delivery, &self.lease.as_secs_f64()])
+            .await?;
+        Ok(rows > 0)
+    }
+
+    async fn complete(&self, job: &Job, _worker: &str) -> anyhow::Result<()> {
+        self.db.client().await?
+            .execute("DELETE FROM zkp_jobs WHERE proof_id = $1 AND lease_owner = $2", &[&job.proof_id, &job.delivery])
+            .await?;
+        Ok(())
+    }
+
+    async fn dead_letter(&self, job: &Job, _worker: &str, reason: &str) -> anyhow::Result<()> {
+        self.db.client().await?.execute(DEAD_LETTER, &[&job.proof_id, &job.delivery, &reason]).await?;
+        Ok(())
+    }
+
+    async fn dead_jobs(&self, limit: usize) -> anyhow::Result<Vec<DeadJob>> {
+        let rows = self.db.client().await?
+            .query(
+                "SELECT proof_id, args, attempts, reason, to_char(failed_at AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS\"Z\"')
+                 FROM zkp_dead_jobs ORDER BY failed_at DESC LIMIT $1",
+                &[&(limit as i64)],
+            )
+            .await?;
+        Ok(rows.into_iter()
+            .map(|row| DeadJob {
+                proof_id: row.get(0),
+                args: serde_json::from_value(row.get(1)).unwrap_or_default(),
+                attempts: row.get::<_, i32>(2) as u64,
+                reason: row.get(3),
+                failed_at: row.get(4),
+            })
+            .collect())
+    }
+
+    async fn status(&self) -> anyhow::Result<QueueStatus> {
+        let row = self.db.client().await?
+            .query_one(
+                "SELECT
+                    (SELECT count(*) FROM zkp_jobs WHERE lease_expires IS NULL OR lease_expires < now()),
+                    (SELECT count(*) FROM zkp_jobs WHERE lease_expires >= now()),
+                    (SELECT count(*) FROM zkp_dead_jobs)",
+                &[],
+            )
+            .await?;
+        Ok(QueueStatus {
+            queued: row.get::<_, i64>(0) as u64,
+            running: row.get::<_, i64>(1) as u64,
+            dead: row.get::<_, i64>(2) as u64,
+        })
+    }
+}
+
+// Redis streams. Finished jobs are acknowledged and deleted, so the stream
+// only holds jobs that are waiting or running; a worker's leases are its
+// pending entries, renewed by claiming them again.
+
+const STREAM: &str = "zkp:jobs";
+const DEAD_STREAM: &str = "zkp:jobs:dead";
+const GROUP: &str = "provers";
+
+pub struct RedisQueue {
+    connection: ConnectionManager,
+    lease: Duration,
+}
+
+type StreamEntry = (String, HashMap<String, String>);
+
+fn job_from_entry((delivery, fields): StreamEntry, attempts: u64) -> Job {
+    Job {
+        delivery,
+        proof_id: fields.get("proof_id").cloned().unwrap_or_default(),
+        args: fields.get("args").and_then(|args| serde_json::from_str(args).ok()).unwrap_or_default(),
+        attempts,
+    }
+}
+
+impl RedisQueue {
+    async fn new(url: &str, lease: Duration) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let mut connection = ConnectionManager::new(client).await.map_err(|e| anyhow::anyhow!("Redis job queue: {}", e))?;
+        let created: redis::RedisResult<()> = redis::cmd("XGROUP")
+            .arg("CREATE").arg(STREAM).arg(GROUP).arg("0").arg("MKSTREAM")
+            .query_async(&mut connection)
+            .await;
+        match created {
+            Err(e) if e.code() != Some("BUSYGROUP") => return Err(anyhow::anyhow!("Redis job queue: {}", e)),
+            _ => {}
+        }
+        Ok(RedisQueue { connection, lease })
+    }
+
+    // Worker holding a pending entry and how often it was delivered
+    async fn pending(&self, delivery: &str) -> anyhow::Result<Option<(String, u64)>> {
+        let entries: Vec<(String, String, u64, u64)> = redis::cmd("XPENDING")
+            .arg(STREAM).arg(GROUP).arg(delivery).arg(delivery).arg(1)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(entries.into_iter().next().map(|(_, worker, _, deliveries)| (worker, deliveries)))
+    }
+
+    // Take over an entry whose worker stopped renewing its lease
+    async fn claim_abandoned(&self, worker: &str) -> anyhow::Result<Option<Job>> {
+        let mut cursor = "0-0".to_string();
+        loop {
+            let reply: Vec<Value> = redis::cmd("XAUTOCLAIM")
+                .arg(STREAM).arg(GROUP).arg(worker).arg(self.lease.as_millis() as u64).arg(&cursor).arg("COUNT").arg(1)
+                .query_async(&mut self.connection.clone())
+                .await?;
+            let mut reply = reply.into_iter();
+            cursor = reply.next().map(|v| redis::from_redis_value(&v)).transpose()?.unwrap_or_default();
+            let entries: Vec<StreamEntry> = reply.next().map(|v| redis::from_redis_value(&v)).transpose()?.unwrap_or_default();
+            if let Some(entry) = entries.into_iter().next() {
+                let attempts = self.pending(&entry.0).await?.map_or(1, |(_, deliveries)| deliveries);
+                return Ok(Some(job_from_entry(entry, attempts)));
+            }
+            if cursor == "0-0" || cursor.is_empty() {
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn remove(&self, job: &Job) -> anyhow::Result<()> {
+        redis::pipe()
+            .cmd("XACK").arg(STREAM).arg(GROUP).arg(&job.delivery).ignore()
+            .cmd("XDEL").arg(STREAM).arg(&job.delivery).ignore()
+            .query_async::<()>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobQueue for RedisQueue {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn enqueue(&self, proof_id: &str, args: &[String]) -> anyhow::Result<()> {
+        redis::cmd("XADD")
+            .arg(STREAM).arg("*").arg("proof_id").arg(proof_id).arg("args").arg(serde_json::to_string(args)?)
+            .query_async::<String>(&mut self.connection.clone())
+            .await?;
+        Ok(())
+    }
+
+    async fn claim(&self, worker: &str) -> anyhow::Result<Option<Job>> {
+        if let Some(job) = self.claim_abandoned(worker).await? {
+            return Ok(Some(job));
+        }
+        let reply: Option<Vec<(String, Vec<StreamEntry>)>> = redis::cmd("XREADGROUP")
+            .arg("GROUP").arg(GROUP).arg(worker).arg("COUNT").arg(1).arg("STREAMS").arg(STREAM).arg(">")
+            .query_async(&mut self.connection.clone())
+            .await?;
+        let entry = reply.into_iter().flatten().flat_map(|(_, entries)| entries).next();
+        Ok(entry.map(|entry| job_from_entry(entry, 1)))
+    }
+
+    async fn renew(&self, job: &Job, worker: &str) -> anyhow::Result<bool> {
+        if self.pending(&job.delivery).await?.is_none_or(|(holder, _)| holder != worker) {
+            return Ok(false);
+        }
+        // Claiming our own entry resets its idle time without counting a delivery
+        redis::cmd("XCLAIM")
+            .arg(STREAM).arg(GROUP).arg(worker).arg(0).arg(&job.delivery).arg("JUSTID")
+            .query_async::<Vec<String>>(&mut self.connection.clone())
+            .await?;
+        Ok(true)
+    }
+
+    async fn complete(&self, job: &Job, _worker: &str) -> anyhow::Result<()> {
+        self.remove(job).await
+    }
+
+    async fn dead_letter(&self, job: &Job, _worker: &str, reason: &str) -> anyhow::Result<()> {
+        redis::cmd("XADD")
+            .arg(DEAD_STREAM).arg("*")
+            .arg("proof_id").arg(&job.proof_id)
+            .arg("args").arg(serde_json::to_string(&job.args)?)
+            .arg("attempts").arg(job.attempts)
+            .arg("reason").arg(reason)
+            .arg("failed_at").arg(now())
+            .query_async::<String>(&mut self.connection.clone())
+            .await?;
+        self.remove(job).await
+    }
+
+    async fn dead_jobs(&self, limit: usize) -> anyhow::Result<Vec<DeadJob>> {
+        let entries: Vec<StreamEntry> = redis::cmd("XREVRANGE")
+            .arg(DEAD_STREAM).arg("+").arg("-").arg("COUNT").arg(limit)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        Ok(entries.into_iter()
+            .map(|(_, mut fields)| {
+                let mut field = |name: &str| fields.remove(name).unwrap_or_default();
+                DeadJob {
+                    proof_id: field("proof_id"),
+                    args: serde_json::from_str(&field("args")).unwrap_or_default(),
+                    attempts: field("attempts").parse().unwrap_or_default(),
+                    reason: field("reason"),
+                    failed_at: field("failed_at"),
+                }
+            })
+            .collect())
+    }
+
+    async fn status(&self) -> anyhow::Result<QueueStatus> {
+        let (length, pending, dead): (u64, Vec<Value>, u64) = redis::pipe()
+            .cmd("XLEN").arg(STREAM)
+            .cmd("XPENDING").arg(STREAM).arg(GROUP)
+            .cmd("XLEN").arg(DEAD_STREAM)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        let running: u64 = pending.first().map(redis::from_redis_value).transpose()?.unwrap_or_default();
+        Ok(QueueStatus { queued: length.saturating_sub(running), running, dead })
+    }
+}
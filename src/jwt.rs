@@ -0,0 +1,282 @@
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::{
+    auth::{Identity, Role},
+    outbound::{self, Integration},
+    secrets::{Secret, Secrets},
+};
+
+// Bearer tokens issued by an OIDC identity provider, so deployments can use
+// their existing logins instead of sharing static API keys. Configured from
+// the environment:
+//
+//   JWT_ISSUER          expected `iss`; enables JWT auth. Signing keys are
+//                       discovered from <issuer>/.well-known/openid-configuration
+//   JWT_JWKS_URL        signing keys URL, skipping discovery
+//   JWT_HS256_SECRET    accept HS256 tokens signed with this shared secret instead
+//                       (read from the secrets provider)
+//   JWT_AUDIENCE        expected `aud` (not checked when unset)
+//   JWT_SUBJECT_CLAIM   claim naming the user (default: sub)
+//   JWT_ROLES_CLAIM     claim holding the user's roles or groups, dotted for
+//                       nested claims such as realm_access.roles (default: roles)
+//   JWT_ROLE_MAP        claim values to roles, e.g. "zk-admins=admin,zk-users=prover";
+//                       values named after a role (viewer, prover, admin) map to it
+//   JWT_DEFAULT_ROLE    role for valid tokens without a mapped role (default:
+//                       viewer); "none" rejects them
+
+// Refetch the key set for an unknown `kid` at most this often, so garbage
+// tokens can't hammer the identity provider
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct KeyCache {
+    jwks_url: Option<String>,
+    keys: JwkSet,
+    fetched_at: Option<Instant>,
+}
+
+pub struct JwtVerifier {
+    issuer: String,
+    audience: Option<String>,
+    hs256_secret: Secret,
+    subject_claim: String,
+    roles_claim: String,
+    role_map: HashMap<String, Role>,
+    default_role: Option<Role>,
+    cache: Mutex<KeyCache>,
+    client: reqwest::Client,
+}
+
+impl JwtVerifier {
+    // `None` when JWT_ISSUER is not set
+    pub fn from_env(secrets: &Arc<Secrets>) -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let issuer = var("JWT_ISSUER")?;
+        let role_map = var("JWT_ROLE_MAP")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| entry.split_once('='))
+            .filter_map(|(value, role)| Some((value.trim().to_string(), Role::parse(role)?)))
+            .collect();
+        let default_role = match var("JWT_DEFAULT_ROLE") {
+            Some(name) => Role::parse(&name),
+            None => Some(Role::Viewer),
+        };
+        info!("JWT authentication enabled for issuer {}", issuer);
+        Some(JwtVerifier {
+            audience: var("JWT_AUDIENCE"),
+            hs256_secret: secrets.secret("JWT_HS256_SECRET"),
+            subject_claim: var("JWT_SUBJECT_CLAIM").unwrap_or_else(|| "sub".to_string()),
+            roles_claim: var("JWT_ROLES_CLAIM").unwrap_or_else(|| "roles".to_string()),
+            role_map,
+            default_role,
+            cache: Mutex::new(KeyCache { jwks_url: var("JWT_JWKS_URL"), keys: JwkSet { keys: Vec::new() }, fetched_at: None }),
+            client: outbound::client(Integration::Jwks),
+            issuer,
+        })
+    }
+
+    // Check the signature, issuer, audience and expiry, and map the claims
+    // to an identity
+    pub async fn verify(&self, token: &str) -> Result<Identity, String> {
+        let header = decode_header(token).map_err(|e| format!("malformed token: {}", e))?;
+        let key = match header.alg {
+            Algorithm::HS256 => self.hs256_secret.value()
+                .map(|secret| DecodingKey::from_secret(secret.as_bytes()))
+                .ok_or("HS256 tokens are not accepted")?,
+            Algorithm::HS384 | Algorithm::HS512 => return Err(format!("{:?} tokens are not accepted", header.alg)),
+            _ => self.signing_key(header.kid.as_deref(), header.alg).await?,
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        let claims = decode::<Value>(token, &key, &validation)
+            .map_err(|e| format!("invalid token: {}", e))?
+            .claims;
+
+        let subject = claims.get(&self.subject_claim)
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("token has no {} claim", self.subject_claim))?;
+        let role = self.role(&claims)
+            .or(self.default_role)
+            .ok_or_else(|| format!("{} has no role that grants access", subject))?;
+        Ok(Identity { subject: subject.to_string(), role })
+    }
+
+    // Highest role granted by the roles claim, which may be a list or a
+    // space- or comma-separated string
+    fn role(&self, claims: &Value) -> Option<Role> {
+        let claim = self.roles_claim.split('.').try_fold(claims, |value, key| value.get(key))?;
+        let values: Vec<&str> = match claim {
+            Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+            Value::String(s) => s.split([' ', ',']).filter(|v| !v.is_empty()).collect(),
+            _ => Vec::new(),
+        };
+        values.into_iter()
+            .filter_map(|value| self.role_map.get(value).copied().or_else(|| Role::parse(value)))
+            .max()
+    }
+
+    // Key from the provider's key set, refreshing it when `kid` is unknown
+    async fn signing_key(&self, kid: Option<&str>, alg: Algorithm) -> Result<DecodingKey, String> {
+        let mut cache = self.cache.lock().await;
+        let stale = cache.fetched_at.is_none_or(|at| at.elapsed() >= JWKS_REFRESH_INTERVAL);
+        if find_key(&cache.keys, kid).is_none() && stale {
+            self.refresh(&mut cache).await?;
+        }
+        let jwk = find_key(&cache.keys, kid).ok_or("token signed with an unknown key")?;
+        if jwk.common.key_algorithm.is_some_and(|key_alg| key_alg.to_string() != format!("{:?}", alg)) {
+            return Err(format!("key {} is not for {:?}", kid.unwrap_or_default(), alg));
+        }
+        DecodingKey::from_jwk(jwk).map_err(|e| format!("unusable signing key: {}", e))
+    }
+
+    async fn refresh(&self, cache: &mut KeyCache) -> Result<(), String> {
+        cache.fetched_at = Some(Instant::now());
+        let jwks_url = match &cache.jwks_url {
+            Some(url) => url.clone(),
+            None => {
+                let discovery = format!("{}/.well-known/openid-configuration", self.issuer.trim_end_matches('/'));
+                let config: Value = self.fetch(&discovery).await?;
+                let url = config["jwks_uri"].as_str()
+                    .ok_or_else(|| format!("{} has no jwks_uri", discovery))?
+                    .to_string();
+                cache.jwks_url = Some(url.clone());
+                url
+            }
+        };
+        cache.keys = self.fetch(&jwks_url).await?;
+        info!("Loaded {} JWT signing keys from {}", cache.keys.keys.len(), jwks_url);
+        Ok(())
+    }
+
+    async fn fetch<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let response = self.client.get(url)
+            .timeout(HTTP_TIMEOUT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("failed to fetch {}: {}", url, e))?;
+        response.json().await.map_err(|e| format!("invalid response from {}: {}", url, e))
+    }
+}
+
+// Key with the given ID, or the only key when the token names none
+fn find_key<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const ISSUER: &str = "https://login.example.com";
+    const SECRET: &str = "a-shared-secret-for-tests";
+
+    fn verifier(audience: Option<&str>, default_role: Option<Role>) -> JwtVerifier {
+        JwtVerifier {
+            issuer: ISSUER.to_string(),
+            audience: audience.map(str::to_string),
+            hs256_secret: Secrets::with_values(&[("JWT_HS256_SECRET", SECRET)]).secret("JWT_HS256_SECRET"),
+            subject_claim: "sub".to_string(),
+            roles_claim: "realm_access.roles".to_string(),
+            role_map: HashMap::from([("zk-admins".to_string(), Role::Admin), ("zk-users".to_string(), Role::Prover)]),
+            default_role,
+            cache: Mutex::new(KeyCache { jwks_url: None, keys: JwkSet { keys: Vec::new() }, fetched_at: None }),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn claims(extra: Value) -> Value {
+        let mut claims = json!({
+            "iss": ISSUER,
+            "sub": "alice",
+            "aud": "zk-agentkit",
+            "exp": chrono::Utc::now().timestamp() + 600,
+        });
+        claims.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        claims
+    }
+
+    fn token(alg: Algorithm, secret: &str, claims: &Value) -> String {
+        encode(&Header::new(alg), claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    fn hs256(claims: &Value) -> String {
+        token(Algorithm::HS256, SECRET, claims)
+    }
+
+    #[tokio::test]
+    async fn roles_come_from_the_mapped_claim() {
+        let verifier = verifier(None, Some(Role::Viewer));
+        let role = |roles: Value| {
+            let token = hs256(&claims(json!({ "realm_access": { "roles": roles } })));
+            let verifier = &verifier;
+            async move { verifier.verify(&token).await.map(|identity| identity.role) }
+        };
+        assert_eq!(role(json!(["zk-users"])).await, Ok(Role::Prover));
+        assert_eq!(role(json!(["zk-users", "zk-admins"])).await, Ok(Role::Admin), "the highest role wins");
+        assert_eq!(role(json!("other, prover")).await, Ok(Role::Prover), "role names map to themselves");
+        assert_eq!(role(json!(["unmapped"])).await, Ok(Role::Viewer), "the default role");
+
+        let identity = verifier.verify(&hs256(&claims(json!({})))).await.unwrap();
+        assert_eq!(identity.subject, "alice");
+        assert_eq!(identity.role, Role::Viewer);
+    }
+
+    #[tokio::test]
+    async fn tokens_without_a_role_are_refused_when_there_is_no_default() {
+        let verifier = verifier(None, None);
+        assert!(verifier.verify(&hs256(&claims(json!({ "realm_access": { "roles": ["unmapped"] } })))).await.is_err());
+        let admin = hs256(&claims(json!({ "realm_access": { "roles": ["zk-admins"] } })));
+        assert_eq!(verifier.verify(&admin).await.map(|identity| identity.role), Ok(Role::Admin));
+    }
+
+    #[tokio::test]
+    async fn the_audience_is_checked_when_configured() {
+        let checked = verifier(Some("zk-agentkit"), Some(Role::Viewer));
+        assert!(checked.verify(&hs256(&claims(json!({})))).await.is_ok());
+        assert!(checked.verify(&hs256(&claims(json!({ "aud": "another-app" })))).await.is_err());
+
+        let unchecked = verifier(None, Some(Role::Viewer));
+        assert!(unchecked.verify(&hs256(&claims(json!({ "aud": "another-app" })))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn forged_expired_and_foreign_tokens_are_refused() {
+        let verifier = verifier(None, Some(Role::Viewer));
+        let refused = [
+            token(Algorithm::HS256, "another-secret", &claims(json!({}))),
+            token(Algorithm::HS512, SECRET, &claims(json!({}))),
+            hs256(&claims(json!({ "iss": "https://evil.example.com" }))),
+            hs256(&claims(json!({ "exp": chrono::Utc::now().timestamp() - 600 }))),
+            hs256(&claims(json!({ "sub": null }))),
+            "not-a-token".to_string(),
+        ];
+        for token in refused {
+            assert!(verifier.verify(&token).await.is_err(), "accepted {}", token);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    catalog, create_proof_record,
+    errors::{AppError, ErrorCode},
+    request_id, short_id, spawn_proof_generation, verify_proof_async, AppState, ProofMetadata, ProofOptions, ProofStatus,
+    DEFAULT_STEP_SIZE,
+};
+
+// KYC attestations. A KYC provider that has checked someone's documents
+// posts a commitment to them, never the documents themselves:
+//
+//   POST /api/kyc  {"subject": "0xabc...", "commitment": "<sha256 hex>"}
+//
+// or {"subject": ..., "attributes": {"country": "DE", ...}}, which is
+// committed to here (sha256 of the sorted key=value lines) and not stored.
+// The prove_kyc circuit proves the subject passed KYC; its wallet_hash
+// argument is derived from the subject and the commitment, so the proof is
+// bound to both. Once the proof is generated and verifies, the attestation
+// is signed with the proof signing key and is valid for KYC_VALIDITY_DAYS
+// (default 365, or "valid_days" in the request).
+//
+//   GET  /api/kyc?subject=...   attestations, optionally for one subject
+//   GET  /api/kyc/:id           one attestation and its status
+//   POST /api/kyc/:id/revoke    {"reason": "..."}; admins only
+//
+// The signed message is these lines joined with '\n':
+//
+//   zkengine-agentkit-kyc-attestation-v1
+//   <attestation id>
+//   <subject>
+//   <commitment>    sha256, hex
+//   <proof id>
+//   <proof_hash>    sha256 of the proof file, hex
+//   <issued_at>     RFC 3339
+//   <expires_at>    RFC 3339
+//   <issuer>        ID of the key that signed it
+//
+// Revocation isn't part of the signature; relying parties check the status.
+
+const KYC_DB_FILE: &str = "./kyc_db.json";
+const ATTESTATION_PREFIX: &str = "zkengine-agentkit-kyc-attestation-v1";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum KycStatus {
+    Proving,
+    Verifying,
+    Active,
+    Expired,
+    Revoked,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Attestation {
+    pub format: String,
+    pub proof_hash: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub issuer: String,
+    pub algorithm: String,
+    // Base64
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KycRecord {
+    pub id: String,
+    pub subject: String,
+    pub commitment: String,
+    pub proof_id: String,
+    pub status: KycStatus,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_reason: Option<String>,
+}
+
+impl KycRecord {
+    fn message(&self, attestation: &Attestation) -> String {
+        [
+            ATTESTATION_PREFIX,
+            &self.id,
+            &self.subject,
+            &self.commitment,
+            &self.proof_id,
+            &attestation.proof_hash,
+            &attestation.issued_at,
+            &attestation.expires_at,
+            &attestation.issuer,
+        ]
+        .join("\n")
+    }
+
+    // The record as clients see it, expired once past its expiry
+    fn current(&self) -> KycRecord {
+        let mut record = self.clone();
+        if record.status == KycStatus::Active && record.expires_at <= Utc::now() {
+            record.status = KycStatus::Expired;
+        }
+        record
+    }
+}
+
+#[derive(Deserialize)]
+pub struct KycRequest {
+    subject: String,
+    #[serde(default)]
+    commitment: Option<String>,
+    #[serde(default)]
+    attributes: Option<Map<String, Value>>,
+    #[serde(default)]
+    valid_days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    subject: Option<String>,
+}
+
+// Attestations, persisted like the proof store
+pub struct KycStore {
+    records: Mutex<HashMap<String, KycRecord>>,
+    validity_days: i64,
+}
+
+impl KycStore {
+    pub async fn load() -> Self {
+        let validity_days = std::env::var("KYC_VALIDITY_DAYS").ok().and_then(|v| v.parse().ok()).filter(|d| *d > 0).unwrap_or(365);
+        let records = match tokio::fs::read_to_string(KYC_DB_FILE).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Failed to load KYC attestations from {}: {}", KYC_DB_FILE, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        KycStore { records: Mutex::new(records), validity_days }
+    }
+
+    // Change a record and persist the store; returns the updated record
+    async fn update(&self, id: &str, apply: impl FnOnce(&mut KycRecord)) -> Option<KycRecord> {
+        let mut records = self.records.lock().await;
+        let record = records.get_mut(id).map(|record| {
+            apply(record);
+            record.clone()
+        })?;
+        save(&records).await;
+        Some(record)
+    }
+}
+
+async fn save(records: &HashMap<String, KycRecord>) {
+    let result = match serde_json::to_string_pretty(records) {
+        Ok(json) => tokio::fs::write(KYC_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save KYC attestations to disk: {}", e);
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Commitment to the attributes: sha256 of their sorted key=value lines
+fn commit(attributes: &Map<String, Value>) -> Result<String, AppError> {
+    let mut lines = Vec::new();
+    for (key, value) in attributes {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return Err(AppError::new(ErrorCode::InvalidArguments, format!("Attribute {} must be a string, number or boolean", key))),
+        };
+        lines.push(format!("{}={}", key, value));
+    }
+    lines.sort();
+    Ok(sha256_hex(lines.join("\n").as_bytes()))
+}
+
+fn commitment(request: &KycRequest) -> Result<String, AppError> {
+    match (&request.commitment, &request.attributes) {
+        (Some(commitment), None) => {
+            let commitment = commitment.trim().to_lowercase();
+            if commitment.len() != 64 || !commitment.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(AppError::new(ErrorCode::InvalidArguments, "commitment must be a sha256 hash in hex"));
+            }
+            Ok(commitment)
+        }
+        (None, Some(attributes)) if !attributes.is_empty() => commit(attributes),
+        _ => Err(AppError::new(ErrorCode::InvalidArguments, "Give either a commitment or the attributes to commit to")),
+    }
+}
+
+// prove_kyc's wallet_hash: the first 63 bits of sha256(subject, commitment)
+fn wallet_hash(subject: &str, commitment: &str) -> String {
+    let digest = Sha256::digest(format!("{}\n{}", subject, commitment).as_bytes());
+    let bytes: [u8; 8] = digest[..8].try_into().expect("sha256 is 32 bytes");
+    (u64::from_be_bytes(bytes) >> 1).to_string()
+}
+
+// Start proving for an attestation; it is issued once the proof verifies
+async fn start(state: &AppState, request: KycRequest) -> Result<KycRecord, AppError> {
+    let subject = request.subject.trim().to_string();
+    if subject.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "subject is required"));
+    }
+    let commitment = commitment(&request)?;
+    let valid_days = request.valid_days.unwrap_or(state.kyc.validity_days);
+    if valid_days <= 0 {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "valid_days must be at least 1"));
+    }
+
+    let wasm_file = catalog::wasm_file("prove_kyc").unwrap_or_else(|| "prove_kyc.wat".to_string());
+    let arguments = vec![wallet_hash(&subject, &commitment), "1".to_string()];
+    let wasm_path = catalog::validate_request(&state.wasm_dir, &wasm_file, &arguments, DEFAULT_STEP_SIZE)?;
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function: "prove_kyc".to_string(),
+        arguments: arguments.clone(),
+        step_size: DEFAULT_STEP_SIZE,
+        statement: None,
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), None, ProofOptions { tags: vec!["kyc".to_string()], ..Default::default() }).await;
+    let generation = spawn_proof_generation(state, &proof_id, metadata, arguments);
+
+    let now = Utc::now();
+    let record = KycRecord {
+        id: Uuid::new_v4().to_string(),
+        subject,
+        commitment,
+        proof_id: proof_id.clone(),
+        status: KycStatus::Proving,
+        created_at: now,
+        expires_at: now + Duration::days(valid_days),
+        attestation: None,
+        error: None,
+        error_code: None,
+        revoked_at: None,
+        revocation_reason: None,
+    };
+    {
+        let mut records = state.kyc.records.lock().await;
+        records.insert(record.id.clone(), record.clone());
+        save(&records).await;
+    }
+    info!("KYC attestation {}: proving {} for {}", short_id(&record.id), short_id(&proof_id), record.subject);
+
+    let state = state.clone();
+    let id = record.id.clone();
+    tokio::spawn(request_id::inherit(async move {
+        let _ = generation.await;
+        finish(&state, &id, &proof_id).await;
+    }.in_current_span()));
+    Ok(record)
+}
+
+// Verify the generated proof and sign the attestation
+async fn finish(state: &AppState, id: &str, proof_id: &str) {
+    let fail = |error: AppError| {
+        warn!("KYC attestation {} failed: {}", short_id(id), error.message);
+        move |record: &mut KycRecord| {
+            if record.status != KycStatus::Revoked {
+                record.status = KycStatus::Failed;
+            }
+            record.error = Some(error.message);
+            record.error_code = Some(error.code);
+        }
+    };
+    let proof = state.proof_store.get(proof_id).await;
+    let proof = match proof {
+        Some(proof) if matches!(proof.status, ProofStatus::Complete) => proof,
+        Some(proof) => {
+            let error = match proof.status {
+                ProofStatus::Failed(error) => error,
+                _ => AppError::new(ErrorCode::ProofNotReady, "Proof did not complete"),
+            };
+            state.kyc.update(id, fail(error)).await;
+            return;
+        }
+        None => {
+            state.kyc.update(id, fail(AppError::proof_not_found())).await;
+            return;
+        }
+    };
+
+    state.kyc.update(id, |record| record.status = KycStatus::Verifying).await;
+    match verify_proof_async(state.clone(), proof_id.to_string(), None).await {
+        Ok(verification) if verification.is_valid => {}
+        Ok(verification) => {
+            let error = AppError::new(
+                verification.error_code.unwrap_or(ErrorCode::VerifierMismatch),
+                verification.error.unwrap_or_else(|| "The proof did not verify".to_string()),
+            );
+            state.kyc.update(id, fail(error)).await;
+            return;
+        }
+        Err(error) => {
+            state.kyc.update(id, fail(error)).await;
+            return;
+        }
+    }
+
+    let signed = state.kyc.update(id, |record| {
+        let mut attestation = Attestation {
+            format: ATTESTATION_PREFIX.to_string(),
+            proof_hash: proof.metrics.file_hash.clone(),
+            issued_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            expires_at: record.expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            issuer: String::new(),
+            algorithm: "ed25519".to_string(),
+            signature: String::new(),
+        };
+        let (_, signature) = state.signer.sign_message(|key_id| {
+            attestation.issuer = key_id.to_string();
+            record.message(&attestation)
+        });
+        attestation.signature = signature;
+        record.attestation = Some(attestation);
+        // Revoked while proving stays revoked
+        if record.status != KycStatus::Revoked {
+            record.status = KycStatus::Active;
+        }
+    }).await;
+    if let Some(record) = signed {
+        info!("KYC attestation {} issued for {}, valid until {}", short_id(id), record.subject, record.expires_at);
+    }
+}
+
+pub async fn issue_handler(State(state): State<AppState>, Json(request): Json<KycRequest>) -> Response {
+    match start(&state, request).await {
+        Ok(record) => Json(json!({ "success": true, "attestation": record })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn list_handler(State(state): State<AppState>, Query(query): Query<ListQuery>) -> Json<Value> {
+    let mut records: Vec<KycRecord> = state.kyc.records.lock().await
+        .values()
+        .filter(|record| query.subject.as_deref().is_none_or(|subject| record.subject == subject.trim()))
+        .map(KycRecord::current)
+        .collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+    Json(json!({ "success": true, "attestations": records }))
+}
+
+pub async fn get_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.kyc.records.lock().await.get(&id).map(KycRecord::current) {
+        Some(record) => Json(json!({ "success": true, "attestation": record })).into_response(),
+        None => AppError::new(ErrorCode::NotFound, format!("No KYC attestation {}", id)).into_response(),
+    }
+}
+
+pub async fn revoke_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<RevokeRequest>>,
+) -> Response {
+    let reason = request.and_then(|Json(request)| request.reason);
+    let revoked = state.kyc.update(&id, |record| {
+        if record.revoked_at.is_none() {
+            record.status = KycStatus::Revoked;
+            record.revoked_at = Some(Utc::now());
+            record.revocation_reason = reason;
+        }
+    }).await;
+    match revoked {
+        Some(record) => {
+            warn!(target: "audit", "KYC attestation {} for {} revoked", short_id(&id), record.subject);
+            Json(json!({ "success": true, "attestation": record })).into_response()
+        }
+        None => AppError::new(ErrorCode::NotFound, format!("No KYC attestation {}", id)).into_response(),
+    }
+}
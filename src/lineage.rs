@@ -0,0 +1,190 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use tracing::info;
+
+use crate::{
+    catalog, create_proof_record,
+    errors::{AppError, ErrorCode},
+    protocol::{WsEvent, WsMessage},
+    retention, short_id, spawn_proof_generation, AppState, ProofMetadata, ProofOptions, ProofStatus,
+};
+
+// Proofs made again from an earlier one keep its ID as `parent_id`, with
+// `rerun` saying how, so an attestation can be traced across attempts:
+//
+//   retry     the parent failed or was cancelled and was run again
+//   replay    the parent was complete and was run again
+//   template  the previous proof from the same template (see templates.rs)
+//
+//   POST /api/proofs/:id/rerun     {"arguments": [...], "step_size": N}, both optional
+//   GET  /api/proofs/:id/lineage   every proof descended from the same root
+//
+// In chat, "rerun proof <id>" or /rerun <id>. A re-run keeps its parent's
+// module, function, tags and metadata, but not its name or expiry, and is
+// never answered from the proof cache. The lineage of a proof whose parent
+// was deleted starts at the proof.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rerun {
+    Retry,
+    Replay,
+    Template,
+}
+
+impl Rerun {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rerun::Retry => "retry",
+            Rerun::Replay => "replay",
+            Rerun::Template => "template",
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct RerunRequest {
+    #[serde(default)]
+    arguments: Option<Vec<String>>,
+    #[serde(default)]
+    step_size: Option<u64>,
+}
+
+// Start a proof again from its record, with the arguments or step size
+// replaced when given; returns the new proof's ID
+pub async fn rerun(
+    state: &AppState,
+    id: &str,
+    request: RerunRequest,
+    session_id: Option<&str>,
+) -> Result<String, AppError> {
+    let id = retention::resolve_proof_id(state, id).await?;
+    let parent = state.proof_store.get(&id).await.ok_or_else(AppError::proof_not_found)?;
+    let kind = match parent.status {
+        ProofStatus::Complete => Rerun::Replay,
+        ProofStatus::Failed(_) | ProofStatus::Cancelled => Rerun::Retry,
+        ref status => {
+            return Err(AppError::new(
+                ErrorCode::InvalidArguments,
+                format!("Proof {} is {}; only finished proofs can be re-run", short_id(&id), status.describe()),
+            ))
+        }
+    };
+    let wasm_file = parent.metadata.wasm_path.rsplit('/').next().unwrap_or_default().to_string();
+    let arguments = request.arguments.unwrap_or(parent.metadata.arguments);
+    let step_size = request.step_size.unwrap_or(parent.metadata.step_size);
+    state.flags.check_module(&wasm_file)?;
+    let wasm_path = catalog::validate_request(&state.wasm_dir, &wasm_file, &arguments, step_size)?;
+    let encoded = catalog::encode_module_arguments(&wasm_file, &arguments)?;
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function: parent.metadata.function.clone(),
+        arguments: arguments.clone(),
+        step_size,
+        statement: None,
+    };
+
+    let options = ProofOptions {
+        tags: parent.tags,
+        custom_metadata: parent.custom_metadata,
+        parent_id: Some(id.clone()),
+        rerun: Some(kind),
+        ..Default::default()
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), session_id.map(str::to_string), options).await;
+    if let Some(session_id) = session_id {
+        let start_msg = WsMessage::event(
+            format!("Re-running proof {}: {}({})", short_id(&id), metadata.function, arguments.join(", ")),
+            WsEvent::ProofStart {
+                proof_id: proof_id.clone(),
+                function: metadata.function.clone(),
+                arguments,
+                wasm_file,
+                step_size,
+            },
+        ).for_session(Some(session_id.to_string()));
+        state.publish(start_msg);
+        state.history.record_proof(&state.conversation_id(session_id).await, &proof_id).await;
+    }
+    spawn_proof_generation(state, &proof_id, metadata, encoded);
+    info!(target: "audit", proof_id = %proof_id, parent_id = %id, rerun = kind.name(), "Proof re-run");
+    Ok(proof_id)
+}
+
+// A proof's lineage: its oldest ancestor still in the store, and every
+// proof descended from that one, oldest first
+#[derive(Serialize)]
+pub struct Lineage {
+    pub proof_id: String,
+    pub root: String,
+    pub proofs: Vec<Value>,
+}
+
+pub async fn lineage(state: &AppState, id: &str) -> Result<Lineage, AppError> {
+    let id = retention::resolve_proof_id(state, id).await?;
+    let mut root = id.clone();
+    let mut seen = HashSet::from([id.clone()]);
+    while let Some(parent_id) = state.proof_store.read(&root, |proof| proof.parent_id.clone()).await.flatten() {
+        let deleted = state.proof_store.read(&parent_id, |_| ()).await.is_none();
+        if deleted || !seen.insert(parent_id.clone()) {
+            break;
+        }
+        root = parent_id;
+    }
+
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    for (parent_id, child_id) in state.proof_store.filter_map_indexed(|entry| Some((entry.parent_id.clone()?, entry.id.clone()))).await {
+        children.entry(parent_id).or_default().push(child_id);
+    }
+    let mut members = vec![root.clone()];
+    let mut queue = VecDeque::from([root.clone()]);
+    let mut seen = HashSet::from([root.clone()]);
+    while let Some(next) = queue.pop_front() {
+        for child in children.remove(&next).unwrap_or_default() {
+            if seen.insert(child.clone()) {
+                members.push(child.clone());
+                queue.push_back(child);
+            }
+        }
+    }
+
+    let mut proofs = Vec::new();
+    for member in &members {
+        let node = state.proof_store.read(member, |proof| (proof.timestamp, json!({
+            "id": proof.id,
+            "name": proof.name,
+            "parent_id": proof.parent_id,
+            "rerun": proof.rerun,
+            "status": proof.status,
+            "function": proof.metadata.function,
+            "timestamp": proof.timestamp,
+        }))).await;
+        proofs.extend(node);
+    }
+    proofs.sort_by_key(|(timestamp, _)| *timestamp);
+    Ok(Lineage { proof_id: id, root, proofs: proofs.into_iter().map(|(_, node)| node).collect() })
+}
+
+pub async fn rerun_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<RerunRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match rerun(&state, &id, request, None).await {
+        Ok(proof_id) => Json(json!({ "success": true, "proof_id": proof_id })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn lineage_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match lineage(&state, &id).await {
+        Ok(lineage) => Json(json!({ "success": true, "lineage": lineage })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
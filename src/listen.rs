@@ -0,0 +1,164 @@
+use axum::{
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::IntoResponse,
+    Router,
+};
+use futures_util::future::try_join_all;
+use std::{
+    fmt,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tokio::sync::watch;
+use tracing::info;
+
+// Where the server listens. `--listen` (LISTEN) takes a comma-separated list
+// of listeners, each `[routes@]address`:
+//
+//   0.0.0.0:8001                    every route, on all interfaces
+//   public@0.0.0.0:8001             everything but /api/admin/*
+//   admin@127.0.0.1:9001            /api/admin/* and the probes only
+//   unix:/run/zkp-agentkit.sock     a Unix domain socket, every route
+//
+// Without it the server listens on 0.0.0.0:PORT for every route. Requests on
+// a Unix socket have no client address, so unless they carry a token or come
+// through a trusted proxy (RATE_LIMIT_TRUST_PROXY) they share a rate limit.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Routes {
+    All,
+    Public,
+    Admin,
+}
+
+impl Routes {
+    fn allows(self, path: &str) -> bool {
+        let admin = path.starts_with("/api/admin/");
+        match self {
+            Routes::All => true,
+            Routes::Public => !admin,
+            Routes::Admin => admin || path == "/healthz" || path == "/readyz",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Address {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+pub struct Listener {
+    routes: Routes,
+    address: Address,
+}
+
+impl Listener {
+    // Every route on all interfaces
+    pub fn all(port: u16) -> Self {
+        Listener { routes: Routes::All, address: Address::Tcp(SocketAddr::from(([0, 0, 0, 0], port))) }
+    }
+}
+
+impl FromStr for Listener {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let (routes, address) = match value.split_once('@') {
+            Some(("all", address)) => (Routes::All, address),
+            Some(("public", address)) => (Routes::Public, address),
+            Some(("admin", address)) => (Routes::Admin, address),
+            Some((routes, _)) => return Err(format!("unknown routes {:?} (expected all, public or admin)", routes)),
+            None => (Routes::All, value),
+        };
+        let address = match address.strip_prefix("unix:") {
+            Some("") => return Err("unix: needs a socket path".to_string()),
+            Some(path) => Address::Unix(PathBuf::from(path)),
+            None => Address::Tcp(address.parse().map_err(|_| format!("{:?} is not an address like 0.0.0.0:8001", address))?),
+        };
+        Ok(Listener { routes, address })
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.address {
+            Address::Tcp(addr) => write!(f, "http://{}", addr)?,
+            Address::Unix(path) => write!(f, "unix:{}", path.display())?,
+        }
+        match self.routes {
+            Routes::All => Ok(()),
+            Routes::Public => write!(f, " (public routes)"),
+            Routes::Admin => write!(f, " (admin routes)"),
+        }
+    }
+}
+
+// Serve `app` on every listener until Ctrl-C
+pub async fn serve(app: Router, listeners: Vec<Listener>) -> anyhow::Result<()> {
+    let (stop, stopped) = watch::channel(false);
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c().await.ok();
+        let _ = stop.send(true);
+    });
+    let servers = listeners.into_iter().map(|listener| {
+        let mut stopped = stopped.clone();
+        let shutdown = async move {
+            let _ = stopped.changed().await;
+        };
+        let routes = listener.routes;
+        let app = app.clone().layer(middleware::from_fn(move |request: Request<_>, next: Next<_>| async move {
+            if routes.allows(request.uri().path()) {
+                next.run(request).await
+            } else {
+                StatusCode::NOT_FOUND.into_response()
+            }
+        }));
+        serve_one(app, listener, shutdown)
+    });
+    try_join_all(servers).await?;
+    Ok(())
+}
+
+async fn serve_one(app: Router, listener: Listener, shutdown: impl std::future::Future<Output = ()>) -> anyhow::Result<()> {
+    match &listener.address {
+        Address::Tcp(addr) => {
+            let server = axum::Server::try_bind(addr).map_err(|e| anyhow::anyhow!("{}: {}", addr, e))?;
+            info!("🚀 zkEngine Agent Kit running on {}", listener);
+            server
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown)
+                .await?;
+        }
+        Address::Unix(path) => serve_unix(app, &listener, path, shutdown).await?,
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_unix(app: Router, listener: &Listener, path: &Path, shutdown: impl std::future::Future<Output = ()>) -> anyhow::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // A socket left behind by an earlier run; anything else there is kept
+    if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    let socket = tokio::net::UnixListener::bind(path).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+    info!("🚀 zkEngine Agent Kit running on {}", listener);
+    let incoming = hyper::server::accept::from_stream(tokio_stream::wrappers::UnixListenerStream::new(socket));
+    let result = axum::Server::builder(incoming)
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await;
+    std::fs::remove_file(path).ok();
+    Ok(result?)
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(_app: Router, _listener: &Listener, path: &Path, _shutdown: impl std::future::Future<Output = ()>) -> anyhow::Result<()> {
+    anyhow::bail!("unix:{}: Unix sockets are not supported on this platform", path.display())
+}
@@ -0,0 +1,506 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+use crate::{
+    config::LiveConfig,
+    i18n::Language,
+    outbound::{self, Integration},
+    secrets::{Secret, Secrets},
+};
+
+// Timeout for a single request to any provider
+const LLM_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Instructions for providers called directly (the LangChain service has its own prompt)
+const SYSTEM_PROMPT: &str = r#"You are the assistant for zkEngine, a zero-knowledge proof system.
+Reply in plain text without markdown. When the user asks for a proof, pick one of these functions:
+- prove_location(city, device_id): city is a place name such as "san francisco", "new york" or "london"
+- prove_kyc(wallet_hash, kyc_status): kyc_status 1 means approved
+- prove_ai_content(content_hash, auth_type)
+- fibonacci(n), factorial(n), is_even(n), square(n), count_until(n)
+- add(a, b), multiply(a, b), max(a, b)
+Answer ONLY with a JSON object of this shape:
+{"response": "<message for the user>", "requires_proof": true|false,
+ "intent": null | {"function": "<name>", "arguments": ["<arg>", ...], "step_size": 50, "explanation": "<what will be proved>"}}
+All arguments are strings. Use step_size 50 unless the user asks for another value."#;
+
+// Added to the prompt of direct providers when tools are offered
+const TOOLS_PROMPT: &str = r#"You can also call tools to look things up or act before answering.
+To call tools answer with {"response": "", "requires_proof": false, "intent": null,
+ "tool_calls": [{"name": "<tool>", "arguments": {...}}]}
+The results are sent back to you; then give your final answer. Available tools:"#;
+
+// LangChain service wire format
+#[derive(Debug, Serialize, Deserialize)]
+struct LangChainRequest {
+    message: String,
+    session_id: Option<String>,
+    context: Option<Value>,
+    // Language code for the reply, e.g. "es"
+    language: String,
+}
+
+// A chat message to interpret, with the tool calls already made for it
+pub struct IntentRequest {
+    pub message: String,
+    pub session_id: Option<String>,
+    // Extra context for the model; `tools` lists the callable tools
+    pub context: Option<Value>,
+    pub tool_results: Vec<ToolResult>,
+    // Language the reply should be written in
+    pub language: Language,
+}
+
+impl IntentRequest {
+    // Context including the tool results so far
+    fn full_context(&self) -> Option<Value> {
+        if self.tool_results.is_empty() {
+            return self.context.clone();
+        }
+        let mut context = self.context.clone().unwrap_or_else(|| json!({}));
+        context["tool_results"] = json!(self.tool_results);
+        Some(context)
+    }
+
+    fn system_prompt(&self) -> String {
+        let mut prompt = SYSTEM_PROMPT.to_string();
+        let context = self.context.as_ref();
+        if let Some(tools) = context.and_then(|context| context.get("tools")) {
+            prompt.push_str(&format!("\n\n{}\n{}", TOOLS_PROMPT, tools));
+        }
+        if let Some(proofs) = context.and_then(|context| context.get("recent_proofs")) {
+            prompt.push_str(&format!("\n\nThe user's most recent proofs, newest first:\n{}", proofs));
+        }
+        if self.language != Language::En {
+            prompt.push_str(&format!(
+                "\n\nWrite the response and explanation in {}. Keep JSON keys and function names in English.",
+                self.language.name()
+            ));
+        }
+        prompt
+    }
+
+    // User message followed by earlier tool calls and their results
+    fn conversation(&self) -> Vec<Value> {
+        let mut messages = vec![json!({ "role": "user", "content": self.message })];
+        if !self.tool_results.is_empty() {
+            let calls: Vec<&ToolCall> = self.tool_results.iter().map(|r| &r.call).collect();
+            messages.push(json!({ "role": "assistant", "content": json!({ "tool_calls": calls }).to_string() }));
+            messages.push(json!({ "role": "user", "content": format!("Tool results: {}", json!(self.tool_results)) }));
+        }
+        messages
+    }
+
+    // Conversation with the system prompt as the first message
+    fn chat_messages(&self) -> Vec<Value> {
+        let mut messages = vec![json!({ "role": "system", "content": self.system_prompt() })];
+        messages.extend(self.conversation());
+        messages
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ToolResult {
+    pub call: ToolCall,
+    pub result: Value,
+}
+
+// Interpretation of a chat message, shared by all providers
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntentResponse {
+    pub intent: Option<ProofIntent>,
+    pub response: String,
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub requires_proof: bool,
+    // Tools the model wants run before it answers
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProofIntent {
+    pub function: String,
+    pub arguments: Vec<String>,
+    #[serde(default = "default_step_size")]
+    pub step_size: u64,
+    #[serde(default)]
+    pub explanation: String,
+    pub complexity_reasoning: Option<String>,
+}
+
+fn default_step_size() -> u64 {
+    50
+}
+
+// Turns natural language into a reply and an optional proof intent
+#[async_trait]
+pub trait IntentProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse>;
+
+    // Like `process`, but sends the reply text to `deltas` while it is being
+    // generated. Providers that can't stream send nothing.
+    async fn process_streaming(&self, request: &IntentRequest, deltas: DeltaSender) -> anyhow::Result<IntentResponse> {
+        drop(deltas);
+        self.process(request).await
+    }
+}
+
+pub type DeltaSender = mpsc::UnboundedSender<String>;
+
+// Build the provider selected by LLM_PROVIDER (langchain, openai, anthropic, ollama or local)
+pub fn provider_from_env(config: &Arc<LiveConfig>, secrets: &Arc<Secrets>) -> anyhow::Result<Box<dyn IntentProvider>> {
+    let env = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+    let provider = env("LLM_PROVIDER", "langchain").to_lowercase();
+    let client = outbound::client(match provider.as_str() {
+        "langchain" => Integration::LangChain,
+        _ => Integration::Llm,
+    });
+
+    Ok(match provider.as_str() {
+        "langchain" => Box::new(LangChainProvider { client, config: config.clone() }),
+        "openai" => Box::new(OpenAiProvider {
+            client,
+            api_key: required_secret(secrets, "OPENAI_API_KEY", "openai")?,
+            base_url: env("OPENAI_BASE_URL", "https://api.openai.com/v1"),
+            model: env("OPENAI_MODEL", "gpt-4o-mini"),
+        }),
+        "anthropic" => Box::new(AnthropicProvider {
+            client,
+            api_key: required_secret(secrets, "ANTHROPIC_API_KEY", "anthropic")?,
+            model: env("ANTHROPIC_MODEL", "claude-3-5-haiku-latest"),
+        }),
+        "ollama" => Box::new(OllamaProvider {
+            client,
+            url: env("OLLAMA_URL", "http://localhost:11434"),
+            model: env("OLLAMA_MODEL", "llama3.1"),
+        }),
+        "local" => Box::new(crate::intent_parser::LocalProvider),
+        other => return Err(anyhow::anyhow!("Unknown LLM_PROVIDER: {}", other)),
+    })
+}
+
+// API key secret, which must be set at startup but may be rotated later
+fn required_secret(secrets: &Arc<Secrets>, name: &'static str, provider: &str) -> anyhow::Result<Secret> {
+    let secret = secrets.secret(name);
+    match secret.value() {
+        Some(_) => Ok(secret),
+        None => Err(anyhow::anyhow!("{} is required for LLM_PROVIDER={}", name, provider)),
+    }
+}
+
+// Parse the JSON object a model was asked to produce, tolerating code fences
+fn parse_model_output(text: &str, session_id: Option<String>) -> anyhow::Result<IntentResponse> {
+    let start = text.find('{').ok_or_else(|| anyhow::anyhow!("Model reply contained no JSON"))?;
+    let end = text.rfind('}').ok_or_else(|| anyhow::anyhow!("Model reply contained no JSON"))?;
+    let mut response: IntentResponse = serde_json::from_str(&text[start..=end])?;
+    response.session_id = session_id.unwrap_or_else(|| "default".to_string());
+    Ok(response)
+}
+
+// Decode the `"response"` string of a JSON object that is still being
+// generated, stopping at the first incomplete escape sequence
+fn partial_response_text(json: &str) -> Option<String> {
+    let rest = &json[json.find("\"response\"")? + "\"response\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+
+    let mut text = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some('r') => text.push('\r'),
+                Some('b') => text.push('\u{8}'),
+                Some('f') => text.push('\u{c}'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    match u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4) {
+                        Some(code) => text.push(char::from_u32(code).unwrap_or('\u{fffd}')),
+                        None => break,
+                    }
+                }
+                Some(other) => text.push(other),
+                None => break,
+            },
+            c => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+// Accumulates a streamed model reply and forwards the newly decoded part of
+// its `response` field as deltas
+struct DeltaExtractor {
+    raw: String,
+    sent: usize,
+    deltas: DeltaSender,
+}
+
+impl DeltaExtractor {
+    fn new(deltas: DeltaSender) -> Self {
+        DeltaExtractor { raw: String::new(), sent: 0, deltas }
+    }
+
+    fn push(&mut self, chunk: &str) {
+        self.raw.push_str(chunk);
+        if let Some(text) = partial_response_text(&self.raw) {
+            if let Some(delta) = text.get(self.sent..).filter(|delta| !delta.is_empty()) {
+                let _ = self.deltas.send(delta.to_string());
+                self.sent = text.len();
+            }
+        }
+    }
+
+    fn finish(self, session_id: Option<String>) -> anyhow::Result<IntentResponse> {
+        parse_model_output(&self.raw, session_id)
+    }
+}
+
+// Call `f` for every complete line of a streamed (SSE or NDJSON) body
+async fn for_each_line(response: reqwest::Response, mut f: impl FnMut(&str)) -> anyhow::Result<()> {
+    let mut stream = response.bytes_stream();
+    let mut buf = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        buf.extend_from_slice(&chunk?);
+        while let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buf.drain(..=pos).collect();
+            f(String::from_utf8_lossy(&line).trim());
+        }
+    }
+    if !buf.is_empty() {
+        f(String::from_utf8_lossy(&buf).trim());
+    }
+    Ok(())
+}
+
+// Parse the payload of an SSE `data:` line
+fn sse_data(line: &str) -> Option<serde_json::Value> {
+    serde_json::from_str(line.strip_prefix("data:")?.trim()).ok()
+}
+
+async fn error_for_status(response: reqwest::Response, provider: &str) -> anyhow::Result<reqwest::Response> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        let error_text = response.text().await?;
+        Err(anyhow::anyhow!("{} error: {}", provider, error_text))
+    }
+}
+
+pub struct LangChainProvider {
+    client: reqwest::Client,
+    // The service URL can be reloaded
+    config: Arc<LiveConfig>,
+}
+
+#[async_trait]
+impl IntentProvider for LangChainProvider {
+    fn name(&self) -> &'static str {
+        "langchain"
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let body = LangChainRequest {
+            message: request.message.clone(),
+            session_id: request.session_id.clone(),
+            context: request.full_context(),
+            language: request.language.code().to_string(),
+        };
+
+        let request = self.client
+            .post(format!("{}/chat", self.config.langchain_url()))
+            .json(&body)
+            .timeout(LLM_TIMEOUT);
+        let response = crate::telemetry::propagate(request)
+            .send()
+            .await?;
+        let response = error_for_status(response, "LangChain service").await?;
+        Ok(response.json().await?)
+    }
+}
+
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    api_key: Secret,
+    base_url: String,
+    model: String,
+}
+
+#[async_trait]
+impl IntentProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(self.api_key.value().unwrap_or_default())
+            .json(&json!({
+                "model": self.model,
+                "response_format": { "type": "json_object" },
+                "messages": request.chat_messages()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let body: serde_json::Value = error_for_status(response, "OpenAI").await?.json().await?;
+        let text = body["choices"][0]["message"]["content"].as_str().unwrap_or_default();
+        parse_model_output(text, request.session_id.clone())
+    }
+
+    async fn process_streaming(&self, request: &IntentRequest, deltas: DeltaSender) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post(format!("{}/chat/completions", self.base_url))
+            .bearer_auth(self.api_key.value().unwrap_or_default())
+            .json(&json!({
+                "model": self.model,
+                "stream": true,
+                "response_format": { "type": "json_object" },
+                "messages": request.chat_messages()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let mut extractor = DeltaExtractor::new(deltas);
+        for_each_line(error_for_status(response, "OpenAI").await?, |line| {
+            if let Some(event) = sse_data(line) {
+                if let Some(text) = event["choices"][0]["delta"]["content"].as_str() {
+                    extractor.push(text);
+                }
+            }
+        }).await?;
+        extractor.finish(request.session_id.clone())
+    }
+}
+
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    api_key: Secret,
+    model: String,
+}
+
+#[async_trait]
+impl IntentProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", self.api_key.value().unwrap_or_default())
+            .header("anthropic-version", "2023-06-01")
+            .json(&json!({
+                "model": self.model,
+                "max_tokens": 1024,
+                "system": request.system_prompt(),
+                "messages": request.conversation()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let body: serde_json::Value = error_for_status(response, "Anthropic").await?.json().await?;
+        let text = body["content"][0]["text"].as_str().unwrap_or_default();
+        parse_model_output(text, request.session_id.clone())
+    }
+
+    async fn process_streaming(&self, request: &IntentRequest, deltas: DeltaSender) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post("https://api.anthropic.com/v1/messages")
+            .header("x-api-key", self.api_key.value().unwrap_or_default())
+            .header("anthropic-version", "2023-06-01")
+            .json(&json!({
+                "model": self.model,
+                "max_tokens": 1024,
+                "stream": true,
+                "system": request.system_prompt(),
+                "messages": request.conversation()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let mut extractor = DeltaExtractor::new(deltas);
+        for_each_line(error_for_status(response, "Anthropic").await?, |line| {
+            if let Some(event) = sse_data(line).filter(|event| event["type"] == "content_block_delta") {
+                if let Some(text) = event["delta"]["text"].as_str() {
+                    extractor.push(text);
+                }
+            }
+        }).await?;
+        extractor.finish(request.session_id.clone())
+    }
+}
+
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    url: String,
+    model: String,
+}
+
+#[async_trait]
+impl IntentProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post(format!("{}/api/chat", self.url))
+            .json(&json!({
+                "model": self.model,
+                "stream": false,
+                "format": "json",
+                "messages": request.chat_messages()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let body: serde_json::Value = error_for_status(response, "Ollama").await?.json().await?;
+        let text = body["message"]["content"].as_str().unwrap_or_default();
+        parse_model_output(text, request.session_id.clone())
+    }
+
+    async fn process_streaming(&self, request: &IntentRequest, deltas: DeltaSender) -> anyhow::Result<IntentResponse> {
+        let response = self.client
+            .post(format!("{}/api/chat", self.url))
+            .json(&json!({
+                "model": self.model,
+                "stream": true,
+                "format": "json",
+                "messages": request.chat_messages()
+            }))
+            .timeout(LLM_TIMEOUT)
+            .send()
+            .await?;
+        let mut extractor = DeltaExtractor::new(deltas);
+        // Ollama streams newline-delimited JSON objects
+        for_each_line(error_for_status(response, "Ollama").await?, |line| {
+            if let Ok(event) = serde_json::from_str::<serde_json::Value>(line) {
+                if let Some(text) = event["message"]["content"].as_str() {
+                    extractor.push(text);
+                }
+            }
+        }).await?;
+        extractor.finish(request.session_id.clone())
+    }
+}
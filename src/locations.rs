@@ -0,0 +1,251 @@
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::errors::{AppError, ErrorCode};
+
+// Places the location circuit knows, by the numeric code zkEngine takes for
+// them. A place can be given by name or alias (case-insensitive), by its
+// code, or as "lat,lon" inside its geofence; a near miss of a name, such as
+// "londn", is matched to the closest place when only one is close. Anything
+// else is refused instead of reaching zkEngine as a string.
+//
+// The catalog is kept in LOCATIONS_FILE (default ./locations.json), a JSON
+// array of
+//
+//   {"name": "london", "code": 3, "aliases": ["ldn"],
+//    "geofence": [[51.28, -0.51], [51.28, 0.33], [51.69, 0.33], [51.69, -0.51]]}
+//
+// where the geofence is a polygon of [lat, lon] points. Without the file the
+// built-in san francisco, new york and london are used. It is re-read by
+// POST /api/admin/reload. Admins can list places with GET
+// /api/admin/locations, add or replace one with POST /api/admin/locations
+// and remove one with DELETE /api/admin/locations/:name; changes are written
+// back to the file.
+
+const DEFAULT_LOCATIONS_FILE: &str = "./locations.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Location {
+    pub name: String,
+    pub code: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    // [lat, lon] corners
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geofence: Option<Vec<[f64; 2]>>,
+}
+
+impl Location {
+    fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    fn contains(&self, lat: f64, lon: f64) -> bool {
+        let Some(polygon) = self.geofence.as_deref().filter(|polygon| polygon.len() >= 3) else {
+            return false;
+        };
+        // Ray casting
+        let mut inside = false;
+        let mut previous = polygon[polygon.len() - 1];
+        for &point in polygon {
+            let ([lat1, lon1], [lat2, lon2]) = (point, previous);
+            if (lon1 > lon) != (lon2 > lon) && lat < (lat2 - lat1) * (lon - lon1) / (lon2 - lon1) + lat1 {
+                inside = !inside;
+            }
+            previous = point;
+        }
+        inside
+    }
+}
+
+fn place(name: &str, code: u32, aliases: &[&str], geofence: [[f64; 2]; 4]) -> Location {
+    Location {
+        name: name.to_string(),
+        code,
+        aliases: aliases.iter().map(|alias| alias.to_string()).collect(),
+        geofence: Some(geofence.to_vec()),
+    }
+}
+
+fn defaults() -> Vec<Location> {
+    vec![
+        place("san francisco", 1, &["sf"], [[37.70, -122.52], [37.70, -122.35], [37.83, -122.35], [37.83, -122.52]]),
+        place("new york", 2, &["nyc"], [[40.49, -74.26], [40.49, -73.70], [40.92, -73.70], [40.92, -74.26]]),
+        place("london", 3, &[], [[51.28, -0.51], [51.28, 0.33], [51.69, 0.33], [51.69, -0.51]]),
+    ]
+}
+
+// The catalog, kept up to date by the live configuration; None until loaded
+static LOCATIONS: RwLock<Option<Vec<Location>>> = RwLock::new(None);
+
+fn locations_file() -> String {
+    std::env::var("LOCATIONS_FILE").unwrap_or_else(|_| DEFAULT_LOCATIONS_FILE.to_string())
+}
+
+// (Re)load the catalog from LOCATIONS_FILE, keeping the current one when the
+// file is unreadable
+pub fn load() {
+    let path = locations_file();
+    let loaded = match std::fs::read_to_string(&path) {
+        Ok(content) => match serde_json::from_str::<Vec<Location>>(&content).map_err(|e| e.to_string()).and_then(check) {
+            Ok(locations) => locations,
+            Err(e) => {
+                warn!("Failed to load {}: {}; keeping the current locations", path, e);
+                return;
+            }
+        },
+        Err(_) => defaults(),
+    };
+    info!("{} location(s) in the catalog", loaded.len());
+    *LOCATIONS.write().unwrap() = Some(loaded);
+}
+
+fn snapshot() -> Vec<Location> {
+    LOCATIONS.read().unwrap().clone().unwrap_or_else(defaults)
+}
+
+// Names and codes must each belong to one place
+fn check(mut locations: Vec<Location>) -> Result<Vec<Location>, String> {
+    for location in &mut locations {
+        location.name = location.name.trim().to_lowercase();
+        location.aliases = location.aliases.iter().map(|alias| alias.trim().to_lowercase()).filter(|a| !a.is_empty()).collect();
+        if location.name.is_empty() {
+            return Err("a location has no name".to_string());
+        }
+    }
+    for (i, location) in locations.iter().enumerate() {
+        for other in &locations[i + 1..] {
+            if location.code == other.code {
+                return Err(format!("{} and {} share code {}", location.name, other.name, location.code));
+            }
+            if let Some(name) = location.names().find(|name| other.names().any(|n| n == *name)) {
+                return Err(format!("'{}' names both {} and {}", name, location.name, other.name));
+            }
+        }
+    }
+    Ok(locations)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// The place a name is closest to, when it's a near miss and no other place
+// is as close
+fn nearest<'a>(locations: &'a [Location], value: &str) -> Option<&'a Location> {
+    let allowed = (value.chars().count() / 4).max(1);
+    let mut scored: Vec<(usize, &Location)> = locations
+        .iter()
+        .filter_map(|location| location.names().map(|name| edit_distance(value, name)).min().map(|d| (d, location)))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    scored.sort_by_key(|(distance, _)| *distance);
+    match scored.as_slice() {
+        [(best, location), rest @ ..] if rest.first().is_none_or(|(next, _)| next > best) => Some(location),
+        _ => None,
+    }
+}
+
+fn coordinates(value: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = value.split_once(',')?;
+    let (lat, lon) = (lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?);
+    ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon)).then_some((lat, lon))
+}
+
+// Code zkEngine takes for a place
+pub fn resolve(value: &str) -> Result<u32, AppError> {
+    let locations = snapshot();
+    let value = value.trim().to_lowercase();
+    if let Some(location) = locations.iter().find(|location| location.names().any(|name| name == value)) {
+        return Ok(location.code);
+    }
+    if let Some(location) = value.parse::<u32>().ok().and_then(|code| locations.iter().find(|l| l.code == code)) {
+        return Ok(location.code);
+    }
+    if let Some((lat, lon)) = coordinates(&value) {
+        return match locations.iter().find(|location| location.contains(lat, lon)) {
+            Some(location) => Ok(location.code),
+            None => Err(AppError::new(ErrorCode::InvalidArguments, format!("{} is outside every known place's geofence", value))),
+        };
+    }
+    if let Some(location) = nearest(&locations, &value) {
+        info!("Matched location '{}' to {}", value, location.name);
+        return Ok(location.code);
+    }
+    let known: Vec<&str> = locations.iter().map(|location| location.name.as_str()).collect();
+    Err(AppError::new(ErrorCode::InvalidArguments, format!("Unknown location '{}'. Known places: {}", value, known.join(", "))))
+}
+
+// Place names and aliases, longest first, for spotting them in chat
+pub fn phrases() -> Vec<(String, String)> {
+    let mut phrases: Vec<(String, String)> = snapshot()
+        .iter()
+        .flat_map(|location| location.names().map(|name| (name.to_string(), location.name.clone())).collect::<Vec<_>>())
+        .collect();
+    phrases.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.len()));
+    phrases
+}
+
+fn save(locations: Vec<Location>) -> Result<(), AppError> {
+    let locations = check(locations).map_err(|e| AppError::new(ErrorCode::InvalidArguments, e))?;
+    let path = locations_file();
+    let json = serde_json::to_string_pretty(&locations).unwrap_or_default();
+    std::fs::write(&path, json).map_err(|e| AppError::new(ErrorCode::StorageError, format!("Failed to write {}: {}", path, e)))?;
+    *LOCATIONS.write().unwrap() = Some(locations);
+    Ok(())
+}
+
+pub async fn list_handler() -> Json<serde_json::Value> {
+    Json(json!({ "success": true, "locations": snapshot() }))
+}
+
+// Add a place, replacing one of the same name
+pub async fn upsert_handler(Json(location): Json<Location>) -> Response {
+    let mut locations = snapshot();
+    let name = location.name.trim().to_lowercase();
+    locations.retain(|existing| existing.name != name);
+    locations.push(location);
+    match save(locations) {
+        Ok(()) => {
+            info!("Location {} saved", name);
+            Json(json!({ "success": true, "locations": snapshot() })).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn delete_handler(Path(name): Path<String>) -> Response {
+    let mut locations = snapshot();
+    let name = name.trim().to_lowercase();
+    let before = locations.len();
+    locations.retain(|existing| existing.name != name);
+    let result = if locations.len() == before {
+        Err(AppError::new(ErrorCode::NotFound, format!("No location {}", name)))
+    } else {
+        save(locations)
+    };
+    match result {
+        Ok(()) => {
+            info!("Location {} removed", name);
+            Json(json!({ "success": true, "locations": snapshot() })).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
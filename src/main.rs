@@ -1,293 +1,810 @@
 use axum::{
-    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, State},
-    response::{Html, IntoResponse, Json},
-    routing::{get, post},
-    Router,
+    extract::{DefaultBodyLimit, State},
+    middleware,
+    response::{IntoResponse, Json},
+    routing::{delete, get, patch, post, put},
+    Extension, Router,
 };
 use chrono::{DateTime, Utc};
+use clap::Parser;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     fs,
-    path::Path,
-    process::{Command, Stdio},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
-use tokio::sync::{Mutex, broadcast};
-use tower_http::{cors::CorsLayer, services::ServeDir};
-use tracing::{error, info, warn};
+use tokio::sync::{oneshot, Mutex};
+use tower_http::{
+    cors::CorsLayer,
+    trace::{DefaultMakeSpan, TraceLayer},
+};
+use tracing::{error, info, warn, Instrument, Level};
 use uuid::Uuid;
-use futures_util::{StreamExt, SinkExt};
+
+mod analytics;
+mod approvals;
+mod auth;
+mod benchmark;
+mod cancellation;
+mod catalog;
+mod chat_bridge;
+mod cli;
+mod cluster;
+mod commands;
+mod compare;
+mod config;
+mod custom_metadata;
+mod doctor;
+mod errors;
+mod event_bus;
+mod events;
+mod export;
+mod federation;
+mod flags;
+mod flusher;
+mod graphql;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod i18n;
+mod intent_parser;
+mod job_queue;
+mod jwt;
+mod kyc;
+mod lineage;
+mod listen;
+mod llm;
+mod locations;
+mod mcp;
+mod notifications;
+mod origin;
+mod outbound;
+mod pipelines;
+mod probes;
+mod proof_cache;
+mod proof_db;
+mod proof_names;
+mod proof_store;
+mod proposals;
+mod protocol;
+mod provenance;
+mod prover_logs;
+mod rate_limit;
+mod receipts;
+mod request_id;
+mod resilience;
+mod retention;
+mod reverify;
+mod revocation;
+mod rpc;
+mod sandbox;
+mod schema;
+mod secrets;
+mod selftest;
+mod share;
+mod siem;
+mod signing;
+mod slash;
+mod tags;
+mod telemetry;
+mod templates;
+mod tools;
+mod trash;
+mod ui;
+mod upload;
+mod verify_cache;
+mod webhooks;
+mod workers;
+mod verifier;
+mod workflows;
+mod ws;
+
+use errors::{AppError, ErrorCode};
+use protocol::{WsEvent, WsMessage};
+use zkp_agentkit_client::types::{ProofMetadata, ProofMetrics, Statement};
 
 // Constants for persistence
 const PROOFS_DB_FILE: &str = "./proofs_db.json";
 const VERIFICATIONS_DB_FILE: &str = "./verifications_db.json";
+// Lines of prover output quoted in a failed proof's error message
+const FAILURE_LOG_LINES: usize = 20;
+// How often a running proof's log is checked for steps and the proof for
+// cancellation
+const PROVER_WATCH_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Clone)]
 struct AppState {
     zkengine_binary: String,
     wasm_dir: String,
     proofs_dir: String,
-    proof_store: Arc<Mutex<HashMap<String, ProofRecord>>>,
+    proof_store: Arc<proof_store::ProofStore>,
+    // Saves proof_store to disk off the request path
+    proof_flusher: Arc<flusher::ProofFlusher>,
     verification_store: Arc<Mutex<Vec<VerificationRecord>>>,
-    tx: broadcast::Sender<WsMessage>,
-    langchain_url: String,
-    session_store: Arc<Mutex<HashMap<String, String>>>,
+    // Settings that can be reloaded while running
+    config: Arc<config::LiveConfig>,
+    // WebSocket session ID -> live chat session
+    session_store: Arc<Mutex<HashMap<String, ChatSession>>>,
+    auth: Arc<auth::Authenticator>,
+    ws_config: ws::WsConfig,
+    events: Arc<events::EventHub>,
+    // Turns chat messages into proof intents (LLM_PROVIDER)
+    intent_provider: Arc<dyn llm::IntentProvider>,
+    llm_breaker: Arc<resilience::CircuitBreaker>,
+    history: Arc<history::HistoryStore>,
+    mcp_sessions: mcp::McpSessions,
+    proposals: Arc<proposals::ProposalStore>,
+    workflows: Arc<workflows::WorkflowStore>,
+    kyc: Arc<kyc::KycStore>,
+    provenance: Arc<provenance::ProvenanceStore>,
+    templates: Arc<templates::TemplateStore>,
+    pipelines: Arc<pipelines::PipelineStore>,
+    // Deleted proofs, until they are purged
+    trash: Arc<trash::Trash>,
+    // Proofs held until they are approved (APPROVAL_RULES)
+    approvals: Arc<approvals::ApprovalQueue>,
+    // Outbound proof and verification webhooks (/api/webhooks)
+    webhooks: Arc<webhooks::WebhookStore>,
+    // Lifecycle events for Kafka or NATS (EVENT_BUS_URL)
+    event_bus: Arc<event_bus::EventBus>,
+    // Slack and Discord bots (SLACK_BOT_TOKEN, DISCORD_BOT_TOKEN)
+    chat_bridge: Arc<chat_bridge::ChatBridge>,
+    self_test: Arc<selftest::SelfTest>,
+    // Problems loading the proof and verification databases at startup
+    storage_errors: Arc<Vec<String>>,
+    // Use the rule-based parser when the intent provider fails (LLM_LOCAL_FALLBACK)
+    llm_local_fallback: bool,
+    notifier: Arc<notifications::Notifier>,
+    rate_limiter: Arc<rate_limit::RateLimiter>,
+    signer: Arc<signing::ProofSigner>,
+    // Resource and filesystem limits for zkEngine children
+    sandbox: Arc<sandbox::Sandbox>,
+    // Shared proof store and job queue (CLUSTER_DATABASE_URL)
+    cluster: Option<Arc<cluster::Cluster>>,
+    // Subsystems turned on or off for this deployment (FEATURE_FLAGS)
+    flags: Arc<flags::FeatureFlags>,
+    // Scheduled re-verification of stored proofs (REVERIFY_INTERVAL_SECS)
+    reverifier: Arc<reverify::Reverifier>,
+    // zkEngine verify results by artifact hash (VERIFY_CACHE_SIZE)
+    verify_cache: Arc<verify_cache::VerifyCache>,
+    // Completed proofs reused for identical requests (PROOF_CACHE)
+    proof_cache: Arc<proof_cache::ProofCache>,
+    // Hands out proof names one at a time
+    proof_names: Arc<proof_names::ProofNames>,
+    // Trusted peer verifier nodes (VERIFIER_PEERS)
+    federation: Arc<federation::Federation>,
+    // Separate zkEngine slots for proving and verifying (PROVE_WORKERS, VERIFY_WORKERS)
+    prove_pool: Arc<workers::WorkerPool>,
+    verify_pool: Arc<workers::WorkerPool>,
+}
+
+impl AppState {
+    // Language of a chat session's server messages; English for REST and
+    // unknown sessions
+    async fn language(&self, session_id: Option<&str>) -> i18n::Language {
+        match session_id {
+            Some(id) => self.session_store.lock().await.get(id).map(|s| s.language).unwrap_or_default(),
+            None => i18n::Language::default(),
+        }
+    }
+
+    // Key of a chat session's history: its conversation (see history.rs),
+    // or the session ID itself for sessions that aren't open
+    async fn conversation_id(&self, session_id: &str) -> String {
+        let session = self.session_store.lock().await.get(session_id).map(|s| s.llm_session_id.clone());
+        session.unwrap_or_else(|| session_id.to_string())
+    }
+
+    // Role of the client behind a chat or MCP session
+    async fn role(&self, session_id: &str) -> auth::Role {
+        if let Some(session) = self.session_store.lock().await.get(session_id) {
+            return session.role;
+        }
+        self.mcp_sessions.lock().unwrap().get(session_id).map(|s| s.role).unwrap_or_default()
+    }
+
+    // Client behind a chat session, for approvals and cancellations: whoever
+    // sent the message being handled (in a bridged channel, the speaker),
+    // or else the session's owner
+    async fn identity(&self, session_id: &str) -> auth::Identity {
+        let speaker = origin::current().and_then(|origin| Some((origin.subject?, origin.role?)));
+        if let Some((subject, role)) = speaker {
+            return auth::Identity { subject, role };
+        }
+        let subject = self.session_store.lock().await.get(session_id).map(|s| s.subject.clone());
+        auth::Identity { subject: subject.unwrap_or_else(|| "anonymous".to_string()), role: self.role(session_id).await }
+    }
+
+    // Fails with `forbidden` unless the session's client has at least `required`
+    async fn require_role(&self, session_id: &str, required: auth::Role) -> Result<(), AppError> {
+        let role = self.role(session_id).await;
+        if role >= required {
+            return Ok(());
+        }
+        Err(AppError::new(
+            ErrorCode::Forbidden,
+            format!("This needs the {} role; you are a {}", required.name(), role.name()),
+        ))
+    }
+
+    // Send an event to WebSocket clients, recording it for replay
+    fn publish(&self, mut msg: WsMessage) {
+        msg.trace_id = msg.trace_id.or_else(telemetry::current_trace_id);
+        msg.request_id = msg.request_id.or_else(request_id::current);
+        if let Some(cluster) = &self.cluster {
+            cluster.relay(&msg);
+        }
+        self.webhooks.dispatch(&msg);
+        self.event_bus.publish(&msg);
+        self.events.publish(msg);
+    }
+}
+
+// A chat session, shared by every connection that resumes it
+#[derive(Clone)]
+struct ChatSession {
+    // Conversation ID the LLM keys its memory by
+    llm_session_id: String,
+    // Identity that opened the session; only it may resume the session
+    subject: String,
+    // Role of the most recent connection
+    role: auth::Role,
+    connections: usize,
+    language: i18n::Language,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct ProofRecord {
     id: String,
+    // See proof_names.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
     timestamp: DateTime<Utc>,
     metadata: ProofMetadata,
     metrics: ProofMetrics,
     status: ProofStatus,
     file_path: Option<String>,
+    // WebSocket session that requested the proof; `None` for REST requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    // Request that created the proof, for matching client reports to logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    // Server signature, set when the proof completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<signing::ProofSignature>,
+    // Every verification of this proof, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    verifications: Vec<VerificationSummary>,
+    // See tags.rs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    // See custom_metadata.rs
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    custom_metadata: custom_metadata::CustomMetadata,
+    // Expiry and revocation, with their flags; see revocation.rs
+    #[serde(flatten)]
+    validity: revocation::Validity,
+    // See approvals.rs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    approvals: Vec<approvals::Approval>,
+    // Who asked for the proof and how; see origin.rs. Missing on records
+    // from older versions and on proofs started by the server itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<origin::Origin>,
+    // The proof this one was re-run from, and how; see lineage.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rerun: Option<lineage::Rerun>,
+}
+
+// A queued proof of fibonacci(10), for tests
+#[cfg(test)]
+fn test_proof(id: &str) -> ProofRecord {
+    serde_json::from_value(json!({
+        "id": id,
+        "timestamp": Utc::now(),
+        "metadata": { "wasm_path": "/wasm/fib.wat", "function": "fibonacci", "arguments": ["10"], "step_size": 50 },
+        "metrics": { "generation_time_secs": 0.0, "file_size_mb": 0.0, "file_hash": "", "peak_memory_mb": null },
+        "status": "queued",
+    })).unwrap()
 }
 
+// A verification as recorded on its proof; the full record, with zkEngine's
+// output, is in the verification store
 #[derive(Serialize, Deserialize, Clone)]
-struct VerificationRecord {
+struct VerificationSummary {
     id: String,
-    proof_id: String,
     timestamp: DateTime<Utc>,
     is_valid: bool,
     verification_time_secs: f64,
-    error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct ProofMetadata {
-    wasm_path: String,
-    function: String,
-    arguments: Vec<String>,
-    step_size: u64,
+impl From<&VerificationRecord> for VerificationSummary {
+    fn from(verification: &VerificationRecord) -> Self {
+        VerificationSummary {
+            id: verification.id.clone(),
+            timestamp: verification.timestamp,
+            is_valid: verification.is_valid,
+            verification_time_secs: verification.verification_time_secs,
+            error_code: verification.error_code,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-struct ProofMetrics {
-    generation_time_secs: f64,
-    file_size_mb: f64,
-    file_hash: String,
-    peak_memory_mb: Option<f64>,
+struct VerificationRecord {
+    id: String,
+    // Empty for uploaded proofs, which have no local record
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    proof_id: String,
+    timestamp: DateTime<Utc>,
+    is_valid: bool,
+    verification_time_secs: f64,
+    error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
+    // Answered from the verification cache rather than by running zkEngine
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    cached: bool,
+    // What was checked, for proofs uploaded to /api/verify/upload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upload: Option<upload::UploadedProof>,
+    // Each peer's answer, for federated verifications
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    peers: Vec<federation::PeerVerification>,
+    // Signed when stored; missing on records from older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    receipt: Option<receipts::Receipt>,
 }
 
+// Where a proof is in its life. Proving carries zkEngine's step count once
+// its log reports one; records from before these stages had "pending" and
+// "running", which schema.rs migrates to queued and proving.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "lowercase")]
 enum ProofStatus {
-    Pending,
-    Running,
+    // Waiting for a prover slot
+    Queued,
+    // Held for approvers; see approvals.rs
+    #[serde(rename = "awaiting_approval")]
+    AwaitingApproval,
+    Proving {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+    },
+    // zkEngine is done; the proof is being signed and recorded
+    Finalizing,
     Complete,
-    Failed(String),
+    Failed(AppError),
+    // Stopped by POST /api/proofs/:id/cancel; see cancellation.rs
+    Cancelled,
 }
 
-#[derive(Serialize, Clone)]
-struct WsMessage {
-    #[serde(rename = "type")]
-    msg_type: String,
-    content: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<serde_json::Value>,
-}
+impl ProofStatus {
+    const NAMES: [&'static str; 7] = ["queued", "awaiting_approval", "proving", "finalizing", "complete", "failed", "cancelled"];
 
-#[derive(Deserialize)]
-struct ChatMessage {
-    message: String,
-}
+    fn name(&self) -> &'static str {
+        match self {
+            ProofStatus::Queued => "queued",
+            ProofStatus::AwaitingApproval => "awaiting_approval",
+            ProofStatus::Proving { .. } => "proving",
+            ProofStatus::Finalizing => "finalizing",
+            ProofStatus::Complete => "complete",
+            ProofStatus::Failed(_) => "failed",
+            ProofStatus::Cancelled => "cancelled",
+        }
+    }
 
-// LangChain service integration
-#[derive(Debug, Serialize, Deserialize)]
-struct LangChainRequest {
-    message: String,
-    session_id: Option<String>,
-    context: Option<serde_json::Value>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct LangChainResponse {
-    intent: Option<LangChainIntent>,
-    response: String,
-    session_id: String,
-    requires_proof: bool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct LangChainIntent {
-    function: String,
-    arguments: Vec<String>,
-    step_size: u64,
-    explanation: String,
-    complexity_reasoning: Option<String>,
-}
-
-// Convert city names to numeric codes for zkEngine
-fn convert_location_args(args: &[String]) -> Vec<String> {
-    args.iter().enumerate().map(|(i, arg)| {
-        if i == 0 {  // First argument is city name
-            match arg.to_lowercase().as_str() {
-                "san francisco" | "sf" => "1".to_string(),
-                "new york" | "nyc" => "2".to_string(),
-                "london" => "3".to_string(),
-                _ => arg.clone()
-            }
-        } else {
-            arg.clone()  // Keep device IDs and other args as-is
+    // Whether a status filter matches; the old names still select the
+    // stages they became
+    fn is_named(&self, name: &str) -> bool {
+        match name {
+            "pending" => matches!(self, ProofStatus::Queued),
+            "running" => matches!(self, ProofStatus::Proving { .. } | ProofStatus::Finalizing),
+            name => name == self.name(),
         }
-    }).collect()
-}
+    }
 
-// Persistence functions
-async fn save_proofs_to_disk(proofs: &HashMap<String, ProofRecord>) -> Result<(), Box<dyn std::error::Error>> {
-    let json = serde_json::to_string_pretty(proofs)?;
-    tokio::fs::write(PROOFS_DB_FILE, json).await?;
-    Ok(())
-}
+    // Queued for or holding a prover slot
+    fn in_progress(&self) -> bool {
+        matches!(self, ProofStatus::Queued | ProofStatus::Proving { .. } | ProofStatus::Finalizing)
+    }
 
-async fn load_proofs_from_disk() -> Result<HashMap<String, ProofRecord>, Box<dyn std::error::Error>> {
-    if Path::new(PROOFS_DB_FILE).exists() {
-        let json = tokio::fs::read_to_string(PROOFS_DB_FILE).await?;
-        let proofs: HashMap<String, ProofRecord> = serde_json::from_str(&json)?;
-        Ok(proofs)
-    } else {
-        Ok(HashMap::new())
+    // The stage for people, e.g. "proving (step 3 of 10)"
+    fn describe(&self) -> String {
+        match self {
+            ProofStatus::Proving { step: Some(step), total: Some(total) } => format!("proving (step {} of {})", step, total),
+            ProofStatus::Proving { step: Some(step), total: None } => format!("proving (step {})", step),
+            ProofStatus::Failed(error) => format!("failed: {}", error.message),
+            status => status.name().replace('_', " "),
+        }
     }
 }
 
+#[derive(Deserialize)]
+struct ChatMessage {
+    message: String,
+    // Switch the session's language (e.g. "es", "ja")
+    #[serde(default)]
+    language: Option<String>,
+}
+
+// Persistence functions (proofs are in proof_db.rs)
+#[tracing::instrument(name = "persist.verifications", skip_all, fields(count = verifications.len()))]
 async fn save_verifications_to_disk(verifications: &Vec<VerificationRecord>) -> Result<(), Box<dyn std::error::Error>> {
-    let json = serde_json::to_string_pretty(verifications)?;
+    let json = schema::VERIFICATION.encode(verifications)?;
     tokio::fs::write(VERIFICATIONS_DB_FILE, json).await?;
     Ok(())
 }
 
-async fn load_verifications_from_disk() -> Result<Vec<VerificationRecord>, Box<dyn std::error::Error>> {
-    if Path::new(VERIFICATIONS_DB_FILE).exists() {
-        let json = tokio::fs::read_to_string(VERIFICATIONS_DB_FILE).await?;
-        let verifications: Vec<VerificationRecord> = serde_json::from_str(&json)?;
-        Ok(verifications)
-    } else {
-        Ok(Vec::new())
-    }
+async fn load_verifications_from_disk() -> anyhow::Result<Vec<VerificationRecord>> {
+    Ok(schema::VERIFICATION.load(schema::Layout::List, VERIFICATIONS_DB_FILE).await?.unwrap_or_default())
 }
 
 #[tokio::main]
 async fn main() {
-    dotenv::dotenv().ok();
-    tracing_subscriber::fmt::init();
-
-    let zkengine_binary = std::env::var("ZKENGINE_BINARY")
-        .unwrap_or_else(|_| "/home/hshadab/zkengine/zkEngine_dev/wasm_file".to_string());
-    let wasm_dir = std::env::var("WASM_DIR")
-        .unwrap_or_else(|_| "/home/hshadab/agentkit/zkengine/example_wasms".to_string());
-    let proofs_dir = std::env::var("PROOFS_DIR")
-        .unwrap_or_else(|_| "./proofs".to_string());
-    let port = std::env::var("PORT")
-        .unwrap_or_else(|_| "8001".to_string())
-        .parse::<u16>()
-        .unwrap_or(8001);
-    let langchain_url = std::env::var("LANGCHAIN_SERVICE_URL")
-        .unwrap_or_else(|_| "http://localhost:8002".to_string());
+    config::load_env_file();
+    let cli = cli::Cli::parse();
+    match cli.command {
+        None => serve(cli.paths, cli.serve).await,
+        Some(cli::CliCommand::Serve(args)) => serve(cli.paths, args).await,
+        Some(cli::CliCommand::Verifier(args)) => verifier::serve(cli.paths, args).await,
+        Some(command) => {
+            telemetry::init_cli();
+            std::process::exit(cli::run(&cli.paths, command).await);
+        }
+    }
+}
+
+async fn serve(paths: cli::Paths, args: cli::ServeArgs) {
+    // In MCP stdio mode stdout carries the protocol, so logs go to stderr
+    let mcp_stdio = args.mcp_stdio;
+    let telemetry = telemetry::init(mcp_stdio);
+
+    let cli::Paths { zkengine_binary, wasm_dir, proofs_dir } = paths;
+    let listeners = match args.listen {
+        listeners if listeners.is_empty() => vec![listen::Listener::all(args.port)],
+        listeners => listeners,
+    };
+    let langchain_url = args.langchain_url;
+    let ui_mode = args.ui.unwrap_or_else(ui::UiMode::build_default);
 
     // Create directories
     fs::create_dir_all(&proofs_dir).ok();
 
-    // Create broadcast channel for WebSocket messages
-    let (tx, _rx) = broadcast::channel::<WsMessage>(1000);
-
-    // Load existing proofs and verifications
-    let stored_proofs = load_proofs_from_disk().await.unwrap_or_else(|e| {
-        warn!("Failed to load proofs from disk: {}", e);
-        HashMap::new()
-    });
-    
+    // Load existing verifications and the proof index
+    let mut storage_errors = Vec::new();
     let stored_verifications = load_verifications_from_disk().await.unwrap_or_else(|e| {
         warn!("Failed to load verifications from disk: {}", e);
+        storage_errors.push(format!("Failed to load verifications: {}", e));
+        Vec::new()
+    });
+
+    // Proofs saved before verifications were recorded on them are only in
+    // a proofs_db.json that is being migrated
+    let link_verifications = |proofs: &mut HashMap<String, ProofRecord>| {
+        for verification in &stored_verifications {
+            if let Some(proof) = proofs.get_mut(&verification.proof_id) {
+                if !proof.verifications.iter().any(|v| v.id == verification.id) {
+                    proof.verifications.push(verification.into());
+                }
+            }
+        }
+    };
+    let proof_index = proof_db::load_index(link_verifications).await.unwrap_or_else(|e| {
+        warn!("Failed to load proofs from disk: {}", e);
+        storage_errors.push(format!("Failed to load proofs: {}", e));
         Vec::new()
     });
 
     info!("Loaded {} proofs and {} verifications from disk", 
-          stored_proofs.len(), stored_verifications.len());
+          proof_index.len(), stored_verifications.len());
+
+    let cluster = cluster::Cluster::from_env().await.unwrap_or_else(|e| {
+        error!("Invalid cluster configuration: {}", e);
+        std::process::exit(1);
+    });
+    let proof_store = match &cluster {
+        Some(cluster) => {
+            let local = proof_db::load_all().await.unwrap_or_else(|e| {
+                warn!("Failed to load proofs from disk: {}", e);
+                HashMap::new()
+            });
+            let shared = cluster.load_proofs(local).await.unwrap_or_else(|e| {
+                error!("Failed to load proofs from the cluster database: {}", e);
+                std::process::exit(1);
+            });
+            proof_store::ProofStore::new(shared)
+        }
+        None => proof_store::ProofStore::indexed(proof_index),
+    };
+
+    let secrets = secrets::Secrets::from_env().await.unwrap_or_else(|e| {
+        error!("Invalid secrets configuration: {}", e);
+        std::process::exit(1);
+    });
+    secrets.spawn_refresh();
+
+    let live_config = Arc::new(config::LiveConfig::new(langchain_url));
+    let doctor_env = doctor::Environment {
+        zkengine_binary: &zkengine_binary,
+        wasm_dir: &wasm_dir,
+        proofs_dir: &proofs_dir,
+        langchain_url: &live_config.langchain_url(),
+    };
+    if let Err(e) = doctor::startup(&doctor_env).await {
+        error!("Refusing to start: {} (DOCTOR_STRICT is set)", e);
+        std::process::exit(1);
+    }
+    let intent_provider = llm::provider_from_env(&live_config, &secrets).unwrap_or_else(|e| {
+        error!("Invalid LLM provider configuration: {}", e);
+        std::process::exit(1);
+    });
+    info!("Using {} intent provider", intent_provider.name());
+    let llm_breaker = Arc::new(resilience::CircuitBreaker::from_env());
+    let intent_provider = resilience::ResilientProvider::from_env(intent_provider, llm_breaker.clone());
+
+    let authenticator = auth::Authenticator::from_env(&secrets);
+    if !authenticator.is_enabled() {
+        warn!("Neither API_KEYS nor JWT_ISSUER set - WebSocket authentication is disabled");
+    }
 
     let state = AppState {
         zkengine_binary,
         wasm_dir,
         proofs_dir,
-        proof_store: Arc::new(Mutex::new(stored_proofs)),
+        proof_store: Arc::new(proof_store),
+        proof_flusher: Arc::new(flusher::ProofFlusher::from_env()),
         verification_store: Arc::new(Mutex::new(stored_verifications)),
-        tx: tx.clone(),
-        langchain_url,
+        config: live_config,
         session_store: Arc::new(Mutex::new(HashMap::new())),
+        auth: Arc::new(authenticator),
+        ws_config: ws::WsConfig::from_env(),
+        events: Arc::new(events::EventHub::from_env()),
+        intent_provider: Arc::new(intent_provider),
+        llm_breaker,
+        history: Arc::new(history::HistoryStore::load().await),
+        mcp_sessions: Default::default(),
+        proposals: Arc::new(proposals::ProposalStore::from_env()),
+        workflows: Default::default(),
+        kyc: Arc::new(kyc::KycStore::load().await),
+        provenance: Arc::new(provenance::ProvenanceStore::load().await),
+        templates: Arc::new(templates::TemplateStore::load().await),
+        pipelines: Arc::new(pipelines::PipelineStore::load().await),
+        trash: Arc::new(trash::Trash::load().await),
+        approvals: Default::default(),
+        webhooks: Arc::new(webhooks::WebhookStore::load().await),
+        event_bus: Arc::new(event_bus::EventBus::from_env()),
+        chat_bridge: Arc::new(chat_bridge::ChatBridge::from_env()),
+        self_test: Arc::new(selftest::SelfTest::from_env()),
+        storage_errors: Arc::new(storage_errors),
+        llm_local_fallback: std::env::var("LLM_LOCAL_FALLBACK").map(|v| v != "false" && v != "0").unwrap_or(true),
+        notifier: Arc::new(notifications::Notifier::from_env()),
+        rate_limiter: Arc::new(rate_limit::RateLimiter::from_env()),
+        signer: Arc::new(signing::ProofSigner::from_env(&secrets)),
+        sandbox: Arc::new(sandbox::Sandbox::from_env()),
+        cluster,
+        flags: Arc::new(flags::FeatureFlags::from_env()),
+        reverifier: Arc::new(reverify::Reverifier::from_env()),
+        verify_cache: Arc::new(verify_cache::VerifyCache::from_env()),
+        proof_cache: Arc::new(proof_cache::ProofCache::from_env()),
+        proof_names: Default::default(),
+        federation: Arc::new(federation::Federation::from_env(&secrets)),
+        prove_pool: Arc::new(workers::WorkerPool::provers()),
+        verify_pool: Arc::new(workers::WorkerPool::verifiers()),
     };
+    flusher::spawn(&state);
+    cluster::spawn(&state);
+    reverify::spawn(&state);
+    trash::spawn(&state);
+    chat_bridge::spawn(&state);
+    #[cfg(feature = "grpc")]
+    grpc::spawn(&state);
 
+    if mcp_stdio {
+        mcp::serve_stdio(state.clone()).await;
+        state.proof_flusher.flush(&state).await;
+        telemetry.shutdown();
+        return;
+    }
+
+    fail_interrupted_proofs(&state).await;
+    config::spawn_watch(state.clone());
+
+    let ui = ui::router(ui_mode, &args.static_dir, args.ui_index.as_deref()).unwrap_or_else(|e| {
+        error!("Invalid UI configuration: {}", e);
+        std::process::exit(1);
+    });
+    let ui = ui.route_layer(middleware::from_fn_with_state(state.clone(), flags::ui_middleware));
     let app = Router::new()
-        .route("/", get(serve_index))
-        .route("/ws", get(websocket_handler))
+        .route("/ws", get(ws::websocket_handler))
+        .route("/healthz", get(probes::healthz))
+        .route("/readyz", get(probes::readyz))
+        .route("/.well-known/proof-signing-key", get(signing::public_key_handler))
+        .route("/share/:token", get(share::view_handler))
+        .route("/bridge/slack/commands", post(chat_bridge::slack_command_handler))
+        .route("/bridge/slack/events", post(chat_bridge::slack_events_handler))
+        .route("/bridge/discord/interactions", post(chat_bridge::discord_interactions_handler))
         .route("/api/health", get(health_check))
         .route("/api/langchain/health", get(langchain_health))
         .route("/api/proofs", get(list_proofs))
-        .route("/api/proofs/:id", get(get_proof))
+        .route("/api/proofs/:id", get(get_proof).delete(delete_proof))
         .route("/api/proofs/generate", post(generate_proof))
+        .route("/api/proofs/archive", post(archive_proofs))
+        .route("/api/proofs/compare", get(compare_proofs))
+        .route("/api/proofs/:id/export", get(export::export_handler))
+        .route("/api/proofs/:id/download", get(export::download_handler))
+        .route("/api/proofs/:id/logs", get(prover_logs::logs_handler))
+        .route("/api/proofs/:id/verifications", get(get_proof_verifications))
+        .route("/api/proofs/:id/verify", post(verify_proof))
+        .route("/api/proofs/:id/tags", patch(tags::update_handler))
+        .route("/api/proofs/:id/revoke", post(revocation::revoke_handler))
+        .route("/api/proofs/:id/share", post(share::create_handler))
+        .route("/api/proofs/:id/approve", post(approvals::approve_handler))
+        .route("/api/proofs/:id/reject", post(approvals::reject_handler))
+        .route("/api/proofs/:id/cancel", post(cancellation::handler))
+        .route("/api/proofs/:id/name", put(proof_names::rename_handler))
+        .route("/api/proofs/:id/rerun", post(lineage::rerun_handler))
+        .route("/api/proofs/:id/lineage", get(lineage::lineage_handler))
+        .route("/api/proofs/:id/restore", post(trash::restore_handler))
+        .route("/api/trash", get(trash::list_handler))
+        .route("/api/trash/:id", delete(trash::purge_handler))
+        .route("/api/names", get(proof_names::list_handler))
+        .route("/api/names/:name", get(proof_names::get_handler))
+        .route("/api/approvals", get(approvals::list_handler))
+        .route("/api/tools", get(tools::manifest_handler))
+        .route("/api/webhooks", get(webhooks::list_handler).post(webhooks::register_handler))
+        .route("/api/webhooks/:id", delete(webhooks::delete_handler))
+        .route("/api/webhooks/:id/deliveries", get(webhooks::deliveries_handler))
+        .route("/api/graphql", get(graphql::graphiql_handler).post(graphql::query_handler))
+        .route("/api/graphql/ws", get(graphql::ws_handler))
+        .route("/api/proofs/:id/federate", post(federation::federate_handler))
+        .route("/api/verifications/:id/receipt", get(receipts::receipt_handler))
+        .route("/api/kyc", get(kyc::list_handler).post(kyc::issue_handler))
+        .route("/api/kyc/:id", get(kyc::get_handler))
+        .route("/api/kyc/:id/revoke", post(kyc::revoke_handler))
+        .route("/api/pipelines", get(pipelines::list_handler).post(pipelines::start_handler))
+        .route("/api/pipelines/:id", get(pipelines::get_handler))
+        .route("/api/provenance", get(provenance::list_handler).post(provenance::ingest_handler))
+        .route("/api/provenance/:id", get(provenance::get_handler))
+        .route("/api/provenance/:id/manifest", get(provenance::manifest_handler))
+        .route("/api/templates", get(templates::list_handler).post(templates::save_handler))
+        .route("/api/templates/:name", get(templates::get_handler).delete(templates::delete_handler))
+        .route("/api/templates/:name/run", post(templates::run_handler))
+        .route("/api/verify/upload", post(upload::upload_handler).layer(DefaultBodyLimit::max(upload::max_upload_bytes())))
+        .route("/api/sessions/:id/history", get(get_session_history))
+        .route("/api/stats", get(get_stats))
+        .route("/api/analytics", get(get_analytics))
+        .route("/api/workflows/:id", get(get_workflow))
         .route("/api/cleanup", post(cleanup_old_proofs))
-        .nest_service("/static", ServeDir::new("static"))
+        .route("/api/admin/reload", post(config::reload_handler))
+        .route("/api/admin/dead-jobs", get(cluster::dead_jobs_handler))
+        .route("/api/admin/flags", get(flags::list_handler).post(flags::update_handler))
+        .route("/api/admin/reverify", get(reverify::status_handler).post(reverify::run_handler))
+        .route("/api/benchmark", post(benchmark::handler))
+        .route("/api/admin/locations", get(locations::list_handler).post(locations::upsert_handler))
+        .route("/api/admin/locations/:name", delete(locations::delete_handler))
+        .route("/mcp/sse", get(mcp::sse_handler))
+        .route("/mcp/messages", post(mcp::message_handler))
+        .merge(ui)
+        .layer(middleware::from_fn_with_state(state.clone(), origin::middleware))
+        .layer(middleware::from_fn_with_state(state.clone(), auth::middleware))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit::middleware))
         .layer(CorsLayer::permissive())
-        .with_state(state);
+        .layer(middleware::from_fn(request_id::middleware))
+        .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
+        .with_state(state.clone());
 
-    let addr = format!("0.0.0.0:{}", port);
-    info!("🚀 zkEngine Agent Kit running on http://{}", addr);
-    
-    axum::Server::bind(&addr.parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    if let Err(e) = listen::serve(app, listeners).await {
+        error!("Server failed: {}", e);
+        state.proof_flusher.flush(&state).await;
+        telemetry.shutdown();
+        std::process::exit(1);
+    }
+    state.proof_flusher.flush(&state).await;
+    telemetry.shutdown();
 }
 
-async fn serve_index() -> impl IntoResponse {
-    Html(include_str!("../static/index.html"))
+#[derive(Deserialize)]
+struct HealthParams {
+    // Also prove and verify a tiny canned proof
+    #[serde(default)]
+    deep: bool,
 }
 
-async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
+async fn health_check(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<HealthParams>,
+) -> axum::response::Response {
     let binary_exists = Path::new(&state.zkengine_binary).exists();
     let wasm_dir_exists = Path::new(&state.wasm_dir).exists();
     
-    Json(json!({
+    let mut health = json!({
         "status": "ok",
         "zkengine_binary": state.zkengine_binary,
         "binary_exists": binary_exists,
         "wasm_dir": state.wasm_dir,
         "wasm_dir_exists": wasm_dir_exists,
         "proofs_dir": state.proofs_dir,
-        "langchain_url": state.langchain_url,
-    }))
+        "langchain_url": state.config.langchain_url(),
+        "llm_provider": state.intent_provider.name(),
+        "workers": {
+            "prove": state.prove_pool.status(),
+            "verify": state.verify_pool.status(),
+        },
+    });
+    if !params.deep {
+        return Json(health).into_response();
+    }
+
+    // A failed self-test answers 503 so monitors alert on it
+    let (result, cached) = state.self_test.run(&state).await;
+    let status = if result.passed { axum::http::StatusCode::OK } else { axum::http::StatusCode::SERVICE_UNAVAILABLE };
+    if !result.passed {
+        health["status"] = json!("failing");
+    }
+    health["self_test"] = json!(result);
+    health["self_test"]["cached"] = json!(cached);
+    (status, Json(health)).into_response()
 }
 
 async fn langchain_health(State(state): State<AppState>) -> impl IntoResponse {
-    let client = reqwest::Client::new();
-    match client.get(&format!("{}/health", state.langchain_url)).send().await {
+    let breaker = state.llm_breaker.status();
+    let client = outbound::client(outbound::Integration::LangChain);
+    match client.get(format!("{}/health", state.config.langchain_url())).send().await {
         Ok(response) => {
             if response.status().is_success() {
                 let health_data: serde_json::Value = response.json().await.unwrap_or_default();
                 Json(json!({
                     "langchain_service": "healthy",
-                    "details": health_data
+                    "details": health_data,
+                    "circuit_breaker": breaker
                 }))
             } else {
                 Json(json!({
                     "langchain_service": "unhealthy",
-                    "error": "Service returned non-200 status"
+                    "error": "Service returned non-200 status",
+                    "circuit_breaker": breaker
                 }))
             }
         },
         Err(e) => Json(json!({
             "langchain_service": "unreachable",
-            "error": e.to_string()
+            "error": e.to_string(),
+            "circuit_breaker": breaker
         }))
     }
 }
 
-async fn list_proofs(State(state): State<AppState>) -> impl IntoResponse {
-    let proofs = state.proof_store.lock().await;
-    let proofs_list: Vec<&ProofRecord> = proofs.values().collect();
+#[derive(Deserialize)]
+struct ListProofsParams {
+    // Only proofs with every one of these comma-separated tags
+    tag: Option<String>,
+}
+
+async fn list_proofs(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<ListProofsParams>,
+) -> impl IntoResponse {
+    let wanted: Vec<String> = params.tag.as_deref().unwrap_or_default()
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let proofs_list: Vec<ProofRecord> = state.proof_store
+        .filter_map(|proof| wanted.iter().all(|tag| proof.tags.contains(tag)).then(|| proof.clone()))
+        .await;
     Json(json!({
         "proofs": proofs_list,
         "count": proofs_list.len()
@@ -297,475 +814,855 @@ async fn list_proofs(State(state): State<AppState>) -> impl IntoResponse {
 async fn get_proof(
     State(state): State<AppState>,
     axum::extract::Path(id): axum::extract::Path<String>,
-) -> impl IntoResponse {
-    let proofs = state.proof_store.lock().await;
-    match proofs.get(&id) {
-        Some(proof) => Json(json!({
+) -> Result<Json<serde_json::Value>, AppError> {
+    let id = retention::resolve_proof_id(&state, &id).await?;
+    match state.proof_store.get(&id).await {
+        Some(proof) => Ok(Json(json!({
             "success": true,
             "proof": proof
-        })),
-        None => Json(json!({
-            "success": false,
-            "error": "Proof not found"
-        }))
+        }))),
+        None => Err(AppError::proof_not_found()),
+    }
+}
+
+// A proof's verification history, oldest first
+async fn get_proof_verifications(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let id = retention::resolve_proof_id(&state, &id).await?;
+    match state.proof_store.get(&id).await {
+        Some(proof) => Ok(Json(json!({
+            "success": true,
+            "proof_id": id,
+            "verifications": proof.verifications,
+            "count": proof.verifications.len()
+        }))),
+        None => Err(AppError::proof_not_found()),
+    }
+}
+
+// Verify a completed proof and answer with the result
+async fn verify_proof(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let id = retention::resolve_proof_id(&state, &id).await?;
+    let complete = state.proof_store.read(&id, |p| matches!(p.status, ProofStatus::Complete)).await == Some(true);
+    if !complete {
+        return Err(AppError::new(ErrorCode::ProofNotReady, "Proof not complete"));
+    }
+    match verify_proof_async(state.clone(), id.clone(), None).await {
+        Ok(verification) => Ok(Json(json!({
+            "success": true,
+            "verification": verification
+        }))),
+        Err(error) => Err(error),
+    }
+}
+
+// Callers only see their own conversations; see HistoryStore::get
+async fn get_session_history(
+    State(state): State<AppState>,
+    Extension(identity): Extension<auth::Identity>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match state.history.get(&identity, &id).await {
+        Some(turns) => Ok(Json(json!({
+            "success": true,
+            "session_id": id,
+            "turns": turns
+        }))),
+        None => Err(AppError::new(ErrorCode::NotFound, "Session not found")),
+    }
+}
+
+#[derive(Deserialize)]
+struct CompareParams {
+    a: String,
+    b: String,
+}
+
+async fn compare_proofs(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<CompareParams>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match compare::compare(&state, &params.a, &params.b).await {
+        Ok(comparison) => Ok(Json(json!({
+            "success": true,
+            "comparison": comparison
+        }))),
+        Err(error) => Err(error),
+    }
+}
+
+async fn get_workflow(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match state.workflows.get(&id).await {
+        Some(workflow) => Ok(Json(json!({
+            "success": true,
+            "workflow": workflow
+        }))),
+        None => Err(AppError::new(ErrorCode::NotFound, "Workflow not found")),
+    }
+}
+
+async fn get_stats(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({
+        "success": true,
+        "stats": analytics::compute(&state).await
+    }))
+}
+
+async fn get_analytics(
+    State(state): State<AppState>,
+    axum::extract::Query(params): axum::extract::Query<analytics::TrendParams>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match analytics::trends(&state, &params).await {
+        Ok(functions) => Ok(Json(json!({
+            "success": true,
+            "days": params.days.unwrap_or(30),
+            "functions": functions
+        }))),
+        Err(error) => Err(error),
     }
 }
 
 async fn generate_proof(
     State(state): State<AppState>,
     Json(request): Json<serde_json::Value>,
-) -> impl IntoResponse {
-    let proof_id = Uuid::new_v4().to_string();
-    
+) -> Result<Json<serde_json::Value>, AppError> {
     // Parse request
     let wasm_file = request["wasm_file"].as_str().unwrap_or("fibonacci.wat");
     let function = request["function"].as_str().unwrap_or("main");
-    let args = request["arguments"].as_array()
+    // Numbers are accepted as well as strings; anything else is an error
+    let args: Option<Vec<String>> = request["arguments"].as_array()
         .map(|arr| arr.iter()
-            .filter_map(|v| v.as_str().map(String::from))
-            .collect::<Vec<_>>())
-        .unwrap_or_default();
+            .map(|v| v.as_str().map(String::from).or_else(|| v.as_i64().map(|n| n.to_string())))
+            .collect())
+        .unwrap_or(Some(Vec::new()));
+    let Some(args) = args else {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "arguments must be strings or integers"));
+    };
     let step_size = request["step_size"].as_u64().unwrap_or(50);
+    let tags: Option<Vec<String>> = request["tags"].as_array()
+        .map(|arr| arr.iter().map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or(Some(Vec::new()));
+    let tags = tags.ok_or_else(|| AppError::new(ErrorCode::InvalidArguments, "tags must be strings")).and_then(|tags| tags::normalize(&tags))?;
+    let expires_at = revocation::parse_expiry(&request["expires_at"])?;
+    let custom_metadata = custom_metadata::parse(&request["metadata"])?;
+    let name = proof_names::parse(&request["name"])?;
     
+    state.flags.check_module(wasm_file)?;
+    let wasm_path = catalog::validate_request(&state.wasm_dir, wasm_file, &args, step_size)?;
+    let encoded_args = catalog::encode_module_arguments(wasm_file, &args)?;
     let metadata = ProofMetadata {
-        wasm_path: format!("{}/{}", state.wasm_dir, wasm_file),
+        wasm_path: wasm_path.display().to_string(),
         function: function.to_string(),
-        arguments: args.clone(),
+        arguments: args,
         step_size,
+        statement: None,
     };
     
-    // Create proof record
-    let proof_record = ProofRecord {
-        id: proof_id.clone(),
-        timestamp: Utc::now(),
-        metadata: metadata.clone(),
-        metrics: ProofMetrics {
-            generation_time_secs: 0.0,
-            file_size_mb: 0.0,
-            file_hash: String::new(),
-            peak_memory_mb: None,
-        },
-        status: ProofStatus::Pending,
-        file_path: None,
-    };
-    
-    state.proof_store.lock().await.insert(proof_id.clone(), proof_record.clone());
-    
-    // Save to disk
-    {
-        let proofs = state.proof_store.lock().await;
-        if let Err(e) = save_proofs_to_disk(&*proofs).await {
-            error!("Failed to save proofs to disk: {}", e);
-        }
-    }
-    
-    // Spawn proof generation
-    let state_clone = state.clone();
-    let proof_id_clone = proof_id.clone();
-    tokio::spawn(async move {
-        generate_real_proof(state_clone, proof_id_clone, metadata, args).await;
-    });
+    let options = ProofOptions { tags, custom_metadata, expires_at, name, ..Default::default() };
+    let started = start_proof(&state, metadata, encoded_args, None, options).await?;
     
-    Json(json!({
+    Ok(Json(json!({
         "success": true,
-        "proof_id": proof_id,
-        "message": "Proof generation started"
-    }))
+        "proof_id": started.id,
+        "cached": started.cached,
+        "message": if started.cached { "An identical proof was already generated" } else { "Proof generation started" }
+    })))
 }
 
-async fn cleanup_old_proofs(State(state): State<AppState>) -> impl IntoResponse {
-    let mut proofs = state.proof_store.lock().await;
-    let cutoff = Utc::now() - chrono::Duration::days(7); // Keep last 7 days
-    
-    let before_count = proofs.len();
-    proofs.retain(|_, proof| proof.timestamp > cutoff);
-    let after_count = proofs.len();
-    
-    if let Err(e) = save_proofs_to_disk(&*proofs).await {
-        error!("Failed to save proofs after cleanup: {}", e);
+async fn delete_proof(
+    State(state): State<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match retention::delete_proof(&state, &id).await {
+        Ok(_) => Ok(Json(json!({
+            "success": true,
+            "proof_id": id
+        }))),
+        Err(error) => Err(error),
+    }
+}
+
+async fn archive_proofs(
+    State(state): State<AppState>,
+    Json(request): Json<serde_json::Value>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let Some(days) = request["older_than_days"].as_i64().filter(|d| *d >= 0) else {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "older_than_days must be a non-negative integer"));
+    };
+    match retention::archive_older_than(&state, days).await {
+        Ok(archived) => Ok(Json(json!({
+            "success": true,
+            "archived": archived,
+            "remaining": state.proof_store.len().await
+        }))),
+        Err(error) => Err(error),
     }
+}
+
+async fn cleanup_old_proofs(State(state): State<AppState>) -> impl IntoResponse {
+    let removed = retention::delete_older_than(&state, state.config.retention_days()).await;
+    let remaining = state.proof_store.len().await;
     
     Json(json!({
         "message": "Cleaned up old proofs",
-        "removed": before_count - after_count,
-        "remaining": after_count
+        "removed": removed,
+        "remaining": remaining
     }))
 }
 
-async fn websocket_handler(
-    ws: WebSocketUpgrade,
-    State(state): State<AppState>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| websocket_connection(socket, state))
+struct NlResponse {
+    message: String,
+    data: Option<WsEvent>,
 }
 
-async fn websocket_connection(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver) = socket.split();
-    
-    // Subscribe to broadcast channel
-    let mut rx = state.tx.subscribe();
+// Circuit for each function the intent providers know about
+fn wasm_file_for(function: &str) -> Option<String> {
+    catalog::wasm_file(function)
+}
+
+// Start the proof described by an intent and announce it to the session.
+// Returns the proof ID, or an error for functions without a circuit.
+async fn launch_intent_proof(state: &AppState, session_id: &str, intent: &llm::ProofIntent) -> Result<String, AppError> {
+    spawn_intent_proof(state, session_id, intent).await.map(|(proof_id, _)| proof_id)
+}
+
+// Like `launch_intent_proof`, also returning the generation task so callers
+// can wait for the proof to finish
+async fn spawn_intent_proof(
+    state: &AppState,
+    session_id: &str,
+    intent: &llm::ProofIntent,
+) -> Result<(String, tokio::task::JoinHandle<()>), AppError> {
+    let spec = catalog::find(&intent.function)
+        .ok_or_else(|| AppError::new(ErrorCode::UnknownFunction, format!("Unknown function: {}", intent.function)))?;
+    spec.check_arguments(&intent.arguments)?;
+    catalog::check_step_size(intent.step_size)?;
+    let wasm_file = wasm_file_for(spec.name).unwrap_or_else(|| spec.wasm_file.to_string());
     
-    // Send welcome message
-    let welcome = WsMessage {
-        msg_type: "message".to_string(),
-        content: "Connected to zkEngine Agent Kit! Try 'prove device location in San Francisco' or 'help'.".to_string(),
-        data: None,
+    let metadata = ProofMetadata {
+        wasm_path: format!("{}/{}", state.wasm_dir, wasm_file),
+        function: "main".to_string(),
+        arguments: intent.arguments.clone(),
+        step_size: intent.step_size,
+        statement: None,
     };
-    sender.send(Message::Text(serde_json::to_string(&welcome).unwrap())).await.ok();
     
-    // Spawn task to receive broadcast messages
-    let send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            if sender.send(Message::Text(serde_json::to_string(&msg).unwrap())).await.is_err() {
-                break;
-            }
-        }
-    });
+    // Places become their location codes
+    let processed_args = spec.encode_arguments(&intent.arguments)?;
     
-    while let Some(msg) = receiver.next().await {
-        if let Ok(msg) = msg {
-            match msg {
-                Message::Text(text) => {
-                    if let Ok(chat_msg) = serde_json::from_str::<ChatMessage>(&text) {
-                        let response = process_nl_command(&state, &chat_msg.message).await;
-                        // Only send a message if there's content
-                        if !response.message.is_empty() {
-                            let ws_msg = WsMessage {
-                                msg_type: "message".to_string(),
-                                content: response.message,
-                                data: response.data,
-                            };
-                            // Broadcast to all clients
-                            let _ = state.tx.send(ws_msg);
-                        } else if let Some(data) = response.data {
-                            // Send data-only message if no text content
-                            let ws_msg = WsMessage {
-                                msg_type: "message".to_string(),
-                                content: String::new(),
-                                data: Some(data),
-                            };
-                            let _ = state.tx.send(ws_msg);
-                        }
-                    }
-                }
-                Message::Close(_) => break,
-                _ => {}
-            }
-        }
+    if let Some(proof) = state.proof_cache.reuse(state, &metadata, &[]).await {
+        let language = state.language(Some(session_id)).await;
+        state.publish(WsMessage::event(
+            i18n::text(language, i18n::Text::ProofReused {
+                proof_id: &proof.id,
+                secs: proof.metrics.generation_time_secs,
+            }),
+            WsEvent::ProofComplete {
+                proof_id: proof.id.clone(),
+                status: "complete".to_string(),
+                function: proof.metadata.function.clone(),
+                arguments: proof.metadata.arguments.clone(),
+                step_size: proof.metadata.step_size,
+                time: proof.metrics.generation_time_secs,
+                size: proof.metrics.file_size_mb,
+                hash: proof.metrics.file_hash.clone(),
+                cached: true,
+            },
+        ).for_session(Some(session_id.to_string())));
+        state.history.record_proof(&state.conversation_id(session_id).await, &proof.id).await;
+        // Nothing to generate
+        return Ok((proof.id, tokio::spawn(async {})));
     }
     
-    send_task.abort();
+    let proof_id = create_proof_record(state, metadata.clone(), Some(session_id.to_string()), ProofOptions::default()).await;
+    
+    // Send SINGLE proof starting message with correct format
+    let language = state.language(Some(session_id)).await;
+    let start_msg = WsMessage::event(
+        i18n::text(language, i18n::Text::ProofStarted { function: &intent.function, arguments: &intent.arguments }),
+        WsEvent::ProofStart {
+            proof_id: proof_id.clone(),
+            function: intent.function.clone(),
+            arguments: intent.arguments.clone(),
+            wasm_file,
+            step_size: intent.step_size,
+        },
+    ).for_session(Some(session_id.to_string()));
+    state.publish(start_msg);
+    state.history.record_proof(&state.conversation_id(session_id).await, &proof_id).await;
+    
+    let generation = spawn_proof_generation(state, &proof_id, metadata, processed_args);
+    
+    Ok((proof_id, generation))
 }
 
-struct NlResponse {
-    message: String,
-    data: Option<serde_json::Value>,
+// Average run time of earlier proofs of the same circuit, preferring ones
+// with the same step size
+async fn estimate_proof_secs(state: &AppState, wasm_file: &str, step_size: u64) -> Option<f64> {
+    // (step size, seconds) of each completed proof
+    let completed: Vec<(u64, f64)> = state.proof_store
+        .filter_map_where(
+            |entry| matches!(entry.status, ProofStatus::Complete),
+            |p| p.metadata.wasm_path.ends_with(wasm_file).then_some((p.metadata.step_size, p.metrics.generation_time_secs)),
+        )
+        .await;
+    let same_step: Vec<f64> = completed.iter()
+        .filter(|(step, _)| *step == step_size)
+        .map(|(_, secs)| *secs)
+        .collect();
+    let sample = if same_step.is_empty() { completed.into_iter().map(|(_, secs)| secs).collect() } else { same_step };
+    if sample.is_empty() {
+        return None;
+    }
+    Some(sample.iter().sum::<f64>() / sample.len() as f64)
 }
 
-// Add LangChain processing function
-async fn process_with_langchain(
-    langchain_url: &str, 
-    message: &str, 
-    session_id: Option<String>
-) -> Result<LangChainResponse, anyhow::Error> {
-    let client = reqwest::Client::new();
+// Hold back an expensive intent as a proposal the client has to approve.
+// Returns the message and `proof_proposal` event to send when it was held back.
+async fn propose_if_expensive(
+    state: &AppState,
+    session_id: &str,
+    intent: &llm::ProofIntent,
+    verify_after: bool,
+) -> Option<(String, WsEvent)> {
+    let wasm_file = wasm_file_for(&intent.function)?;
+    let estimated_secs = estimate_proof_secs(state, &wasm_file, intent.step_size).await;
+    let reason = state.proposals.confirmation_reason(intent, estimated_secs)?;
+    let proposal_id = state.proposals.propose(session_id, proposals::PendingAction::Proof { intent: intent.clone(), verify_after }).await;
+    info!("Proof of {} needs confirmation: {}", intent.function, reason);
     
-    let request = LangChainRequest {
-        message: message.to_string(),
-        session_id,
-        context: None,
+    let message = format!(
+        "Proving {}({}) needs your confirmation: {}. Reply 'confirm' to start it or 'cancel' to drop it.",
+        intent.function, intent.arguments.join(", "), reason
+    );
+    Some((message, WsEvent::ProofProposal {
+        proposal_id,
+        function: intent.function.clone(),
+        arguments: intent.arguments.clone(),
+        step_size: intent.step_size,
+        estimated_secs,
+        reason,
+    }))
+}
+
+// Approve or deny a session's pending proposal (its latest if no ID is given)
+async fn resolve_proposal(state: &AppState, session_id: &str, proposal_id: Option<&str>, approve: bool) -> NlResponse {
+    let Some(proposal) = state.proposals.take(session_id, proposal_id).await else {
+        return NlResponse {
+            message: "No pending proposal found. It may have expired.".to_string(),
+            data: None,
+        };
     };
-    
-    let response = client
-        .post(&format!("{}/chat", langchain_url))
-        .json(&request)
-        .timeout(std::time::Duration::from_secs(30))
-        .send()
-        .await?;
-    
-    if !response.status().is_success() {
-        let error_text = response.text().await?;
-        return Err(anyhow::anyhow!("LangChain service error: {}", error_text));
+    match proposal.action {
+        proposals::PendingAction::Proof { intent, .. } if !approve => NlResponse {
+            message: format!("Cancelled the proof of {}", intent.function),
+            data: None,
+        },
+        proposals::PendingAction::Proof { intent, verify_after } => launch_or_run_workflow(state, session_id, &intent, verify_after).await,
+        proposals::PendingAction::DeleteProof { proof_id } if !approve => NlResponse {
+            message: format!("Kept proof {}", short_id(&proof_id)),
+            data: None,
+        },
+        proposals::PendingAction::DeleteProof { proof_id } => match retention::delete_proof(state, &proof_id).await {
+            Ok(_) => NlResponse {
+                message: match trash::retention() {
+                    Some(retention) => format!(
+                        "Moved proof {} to the trash; it can be restored for {} hours",
+                        short_id(&proof_id), retention.num_hours()
+                    ),
+                    None => format!("Deleted proof {}", short_id(&proof_id)),
+                },
+                data: None,
+            },
+            Err(error) => NlResponse {
+                message: String::new(),
+                data: Some(WsEvent::error(error)),
+            },
+        },
+        proposals::PendingAction::ArchiveProofs { .. } if !approve => NlResponse {
+            message: "Cancelled archiving".to_string(),
+            data: None,
+        },
+        proposals::PendingAction::ArchiveProofs { older_than_days } => {
+            match retention::archive_older_than(state, older_than_days).await {
+                Ok(archived) => NlResponse {
+                    message: format!("Archived {} proofs older than {} days", archived, older_than_days),
+                    data: None,
+                },
+                Err(error) => NlResponse {
+                    message: String::new(),
+                    data: Some(WsEvent::error(error)),
+                },
+            }
+        }
     }
-    
-    let langchain_response: LangChainResponse = response.json().await?;
-    Ok(langchain_response)
 }
 
-// UPDATED: process_nl_command function with custom proof support
-async fn process_nl_command(state: &AppState, input: &str) -> NlResponse {
-    let input_lower = input.to_lowercase();
-    
-    // PRIORITY: Handle list and verify commands BEFORE LangChain
-    if input_lower.contains("list") && (input_lower.contains("proof") || input_lower.contains("all")) {
-        info!("Handling list proofs command");
-        let proofs = state.proof_store.lock().await;
-        let proofs_list: Vec<&ProofRecord> = proofs.values().collect();
-        info!("Found {} proofs", proofs_list.len());
-        
+// Start an intent's proof, or ask the client first when it is expensive.
+// With `verify_after` the proof runs as a prove-then-verify workflow.
+async fn start_intent_proof(state: &AppState, session_id: &str, intent: llm::ProofIntent, verify_after: bool) -> NlResponse {
+    if let Err(error) = state.require_role(session_id, auth::Role::Prover).await {
+        return NlResponse { message: error.message, data: None };
+    }
+    if let Some((message, proposal)) = propose_if_expensive(state, session_id, &intent, verify_after).await {
         return NlResponse {
-            message: format!("Found {} proofs in history", proofs_list.len()),
-            data: Some(json!({
-                "type": "proof_list",
-                "proofs": proofs_list
-            })),
+            message,
+            data: Some(proposal),
         };
     }
-    
-    if input_lower.contains("list") && input_lower.contains("verification") {
-        info!("Handling list verifications command");
-        let verifications = state.verification_store.lock().await;
-        
-        return NlResponse {
-            message: format!("Found {} verifications in history", verifications.len()),
-            data: Some(json!({
-                "type": "verification_list",
-                "verifications": *verifications
-            })),
-        };
+    launch_or_run_workflow(state, session_id, &intent, verify_after).await
+}
+
+async fn launch_or_run_workflow(state: &AppState, session_id: &str, intent: &llm::ProofIntent, verify_after: bool) -> NlResponse {
+    let launched = if verify_after {
+        workflows::prove_and_verify(state, session_id, intent).await
+    } else {
+        launch_intent_proof(state, session_id, intent).await
+    };
+    match launched {
+        Ok(_) => NlResponse {
+            message: String::new(),
+            data: None,
+        },
+        Err(error) => NlResponse {
+            message: String::new(),
+            data: Some(WsEvent::error(error)),
+        },
+    }
+}
+
+// A proof's public.json, as JSON when it parses and as text otherwise
+async fn read_public_outputs(state: &AppState, proof_id: &str) -> Option<serde_json::Value> {
+    let public_file = format!("{}/{}/public.json", state.proofs_dir, proof_id);
+    let public = tokio::fs::read_to_string(&public_file).await.ok()?;
+    Some(serde_json::from_str(&public).unwrap_or(json!(public)))
+}
+
+// Context sent with every chat message: the callable tools plus the
+// session's most recent proofs (CONTEXT_RECENT_PROOFS, default 5) with their
+// status, latest verification and public outputs, so the model can answer
+// "did my last proof verify?" or re-run a proof with different arguments
+async fn session_context(state: &AppState, session_id: &str) -> serde_json::Value {
+    let limit = std::env::var("CONTEXT_RECENT_PROOFS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(5);
+    
+    let recent = state.proof_store
+        .newest(|entry| entry.session_id.as_deref() == Some(session_id), Some(limit))
+        .await;
+    
+    let mut proofs = Vec::with_capacity(recent.len());
+    for proof in recent {
+        let mut entry = tools::summary(&proof);
+        if let ProofStatus::Failed(error) = &proof.status {
+            entry["error"] = json!(error);
+        }
+        if let Some(verification) = state.verification_store.lock().await
+            .iter()
+            .rev()
+            .find(|v| v.proof_id == proof.id)
+        {
+            entry["verified"] = json!(verification.is_valid);
+        }
+        if let Some(public) = read_public_outputs(state, &proof.id).await {
+            entry["public_outputs"] = public;
+        }
+        proofs.push(entry);
     }
     
-    // Handle verification commands
-    if input_lower.contains("verify") {
-        // Extract proof ID if specified
-        let proof_id = if input_lower.contains("proof") {
-            // Pattern: "verify proof <id>"
-            let parts: Vec<&str> = input.split_whitespace().collect();
-            if parts.len() >= 3 {
-                Some(parts[2].to_string())
-            } else {
-                None
+    json!({
+        "tools": tools::definitions(),
+        "recent_proofs": proofs,
+    })
+}
+
+// Send one request to the intent provider, relaying reply fragments as
+// `chat_delta` messages while it is still generating, then publish the reply
+#[tracing::instrument(name = "llm.request", skip_all, fields(provider = state.intent_provider.name()))]
+async fn ask_intent_provider(
+    state: &AppState,
+    session_id: &str,
+    request: &llm::IntentRequest,
+) -> anyhow::Result<llm::IntentResponse> {
+    let stream_id = Uuid::new_v4().to_string();
+    let (delta_tx, mut delta_rx) = tokio::sync::mpsc::unbounded_channel();
+    let relay = async {
+        let mut streamed = false;
+        while let Some(delta) = delta_rx.recv().await {
+            streamed = true;
+            state.publish(WsMessage::event(
+                delta,
+                WsEvent::ChatDelta {
+                    session_id: session_id.to_string(),
+                    stream_id: stream_id.clone(),
+                },
+            ).for_session(Some(session_id.to_string())));
+        }
+        streamed
+    };
+    
+    let (result, streamed) = tokio::join!(
+        state.intent_provider.process_streaming(request, delta_tx),
+        relay,
+    );
+    let response = result?;
+    
+    // ALWAYS send the natural language response first if it exists
+    if !response.response.is_empty() {
+        let nl_msg = WsMessage::event(
+            response.response.clone(),
+            WsEvent::Chat {
+                session_id: response.session_id.clone(),
+                from_langchain: true,
+                stream_id: streamed.then(|| stream_id.clone()),
+            },
+        ).for_session(Some(session_id.to_string()));
+        state.publish(nl_msg);
+        state.history.record_response(&state.conversation_id(session_id).await, &response.response).await;
+    }
+    
+    Ok(response)
+}
+
+// Find the proof a command's selector refers to
+async fn resolve_selector(state: &AppState, selector: commands::ProofSelector) -> Result<String, AppError> {
+    let (wasm_file, not_found) = match selector {
+        commands::ProofSelector::Id(id) => return retention::resolve_proof_id(state, &id).await,
+        commands::ProofSelector::Latest => (
+            None,
+            "No proof found to verify. Generate a proof first or specify a proof ID.".to_string(),
+        ),
+        commands::ProofSelector::LatestOf(function) => (
+            wasm_file_for(&function),
+            format!("No completed {} proof found to verify.", function),
+        ),
+    };
+    state.proof_store
+        .filter_map_where(
+            |entry| matches!(entry.status, ProofStatus::Complete),
+            |p| {
+                let candidate = p.validity.is_current()
+                    && wasm_file.as_deref().is_none_or(|wasm| p.metadata.wasm_path.ends_with(wasm));
+                candidate.then(|| (p.timestamp, p.id.clone()))
+            },
+        )
+        .await
+        .into_iter()
+        .max()
+        .map(|(_, id)| id)
+        .ok_or(AppError::new(ErrorCode::ProofNotFound, not_found))
+}
+
+// Run a command recognised by the command grammar
+async fn execute_command(state: &AppState, session_id: &str, command: commands::Command) -> NlResponse {
+    if let Err(error) = state.require_role(session_id, command.required_role()).await {
+        return NlResponse { message: error.message, data: None };
+    }
+    match command {
+        commands::Command::ListProofs { filter } => {
+            info!("Handling list proofs command");
+            let proofs_list = filter.select(&state.proof_store).await;
+            info!("Found {} proofs", proofs_list.len());
+            
+            let noun = if filter == commands::ProofFilter::default() { "proofs" } else { "matching proofs" };
+            NlResponse {
+                message: format!("Found {} {} in history", proofs_list.len(), noun),
+                data: Some(WsEvent::ProofList { proofs: proofs_list }),
             }
-        } else {
-            // Just "verify" - get the last proof
-            let proofs = state.proof_store.lock().await;
-            proofs.values()
-                .filter(|p| matches!(p.status, ProofStatus::Complete))
-                .max_by_key(|p| &p.timestamp)
-                .map(|p| p.id.clone())
-        };
-        
-        if let Some(id) = proof_id {
+        }
+        commands::Command::ListVerifications => {
+            info!("Handling list verifications command");
+            let verifications = state.verification_store.lock().await;
+            
+            NlResponse {
+                message: format!("Found {} verifications in history", verifications.len()),
+                data: Some(WsEvent::VerificationList { verifications: verifications.clone() }),
+            }
+        }
+        commands::Command::Export { proof_id, format } => {
+            // Check it now so problems are reported in chat rather than on download
+            let proof_id = match retention::resolve_proof_id(state, &proof_id).await {
+                Ok(id) => id,
+                Err(error) => return NlResponse { message: error.message, data: None },
+            };
+            if let Err(error) = export::check(state, &proof_id, format).await {
+                return NlResponse { message: error.message, data: None };
+            }
+            let url = export::download_url(&proof_id, format);
+            NlResponse {
+                message: format!("Proof {} is ready to download as {}: {}", short_id(&proof_id), format.name(), url),
+                data: Some(WsEvent::ProofExport { proof_id, format: format.name().to_string(), url }),
+            }
+        }
+        commands::Command::Help => NlResponse {
+            message: catalog::help_text(&state.wasm_dir),
+            data: None,
+        },
+        commands::Command::Stats => {
+            let stats = analytics::compute(state).await;
+            NlResponse {
+                message: analytics::describe(&stats),
+                data: Some(WsEvent::Stats { stats }),
+            }
+        }
+        commands::Command::Compare { a, b } => match compare::compare(state, &a, &b).await {
+            Ok(comparison) => NlResponse {
+                message: compare::describe(&comparison),
+                data: Some(WsEvent::ProofComparison { comparison }),
+            },
+            Err(error) => NlResponse { message: error.message, data: None },
+        },
+        commands::Command::Verify { target } => {
+            let id = match resolve_selector(state, target).await {
+                Ok(id) => id,
+                Err(error) => return NlResponse { message: error.message, data: None },
+            };
             info!("Starting verification for proof: {}", id);
             
             // Spawn verification task
             let state_clone = state.clone();
             let id_clone = id.clone();
-            tokio::spawn(async move {
-                verify_proof_async(state_clone, id_clone).await;
-            });
+            let session_id = session_id.to_string();
+            tokio::spawn(request_id::inherit(async move {
+                verify_proof_async(state_clone, id_clone, Some(session_id)).await.ok();
+            }.in_current_span()));
             
-            return NlResponse {
-                message: format!("Starting verification for proof {}", &id[..8]),
-                data: Some(json!({
-                    "type": "verification_start",
-                    "proof_id": id
-                })),
+            NlResponse {
+                message: format!("Starting verification for proof {}", short_id(&id)),
+                data: Some(WsEvent::VerificationStart { proof_id: id }),
+            }
+        }
+        commands::Command::ProveCustom { wasm_file } => {
+            let wasm_file = wasm_file.unwrap_or_else(|| "custom.wat".to_string());
+            if let Err(error) = state.flags.check_module(&wasm_file) {
+                return NlResponse { message: error.message, data: None };
+            }
+            
+            // No arguments needed - values are hardcoded in the C code
+            let args: Vec<String> = vec!["0".to_string()];
+            
+            info!("Processing custom proof: wasm={}, args={:?} (dummy arg for hardcoded values)", wasm_file, args);
+            let wasm_path = match catalog::validate_request(&state.wasm_dir, &wasm_file, &args, 50) {
+                Ok(path) => path,
+                Err(error) => return NlResponse { message: error.message, data: None },
             };
-        } else {
-            return NlResponse {
-                message: "No proof found to verify. Generate a proof first or specify a proof ID.".to_string(),
+            
+            let metadata = ProofMetadata {
+                wasm_path: wasm_path.display().to_string(),
+                function: "main".to_string(),
+                arguments: args.clone(),
+                step_size: 50,
+                statement: None,
+            };
+            
+            let proof_id = create_proof_record(state, metadata.clone(), Some(session_id.to_string()), ProofOptions::default()).await;
+            
+            // Send proof starting message
+            let start_msg = WsMessage::event(
+                format!("Starting custom proof generation with WASM: {} (using hardcoded values)", wasm_file),
+                WsEvent::ProofStart {
+                    proof_id: proof_id.clone(),
+                    function: "main".to_string(),
+                    arguments: args.clone(),
+                    wasm_file,
+                    step_size: 50,
+                },
+            ).for_session(Some(session_id.to_string()));
+            state.publish(start_msg);
+            state.history.record_proof(&state.conversation_id(session_id).await, &proof_id).await;
+            
+            spawn_proof_generation(state, &proof_id, metadata, args);
+            
+            NlResponse {
+                message: String::new(),
                 data: None,
+            }
+        }
+        commands::Command::RunTemplate { name } => {
+            match templates::run(state, &name, Default::default(), Some(session_id)).await {
+                Ok(_) => NlResponse { message: String::new(), data: None },
+                Err(error) => NlResponse { message: error.message, data: None },
+            }
+        }
+        commands::Command::ReviewProof { proof_id, approve, reason } => {
+            let identity = state.identity(session_id).await;
+            let result = if approve {
+                approvals::approve(state, &proof_id, &identity, reason).await.map(|proof| match proof.status {
+                    ProofStatus::AwaitingApproval => format!("Approved proof {}; it needs more approvals", short_id(&proof.id)),
+                    _ => format!("Approved proof {}; it is starting", short_id(&proof.id)),
+                })
+            } else {
+                let reason = reason.unwrap_or_else(|| "rejected in chat".to_string());
+                approvals::reject(state, &proof_id, &identity, &reason).await
+                    .map(|()| format!("Rejected proof {}", proof_id))
             };
+            NlResponse { message: result.unwrap_or_else(|error| error.message), data: None }
+        }
+        commands::Command::CancelProof { proof_id } => {
+            let identity = state.identity(session_id).await;
+            match cancellation::cancel(state, &proof_id, &identity).await {
+                // The proof_cancelled event says so
+                Ok(_) => NlResponse { message: String::new(), data: None },
+                Err(error) => NlResponse { message: error.message, data: None },
+            }
+        }
+        commands::Command::RerunProof { proof_id } => {
+            match lineage::rerun(state, &proof_id, Default::default(), Some(session_id)).await {
+                // The proof_start event says so
+                Ok(_) => NlResponse { message: String::new(), data: None },
+                Err(error) => NlResponse { message: error.message, data: None },
+            }
+        }
+        commands::Command::DeleteProof { proof_id } => {
+            let proof_id = match retention::resolve_proof_id(state, &proof_id).await {
+                Ok(id) => id,
+                Err(error) => return NlResponse { message: error.message, data: None },
+            };
+            let consequence = match trash::retention() {
+                Some(retention) => format!("It stays in the trash for {} hours.", retention.num_hours()),
+                None => "This removes its record and proof files.".to_string(),
+            };
+            let description = state.proof_store.read(&proof_id, |proof| format!(
+                "Delete proof {} ({}, {})? {}",
+                short_id(&proof.id), proof.metadata.function, proof.status.describe(), consequence
+            )).await;
+            let Some(description) = description else {
+                return NlResponse { message: AppError::proof_not_found().message, data: None };
+            };
+            let action = proposals::PendingAction::DeleteProof { proof_id };
+            propose_action(state, session_id, action, "delete", description).await
+        }
+        commands::Command::ArchiveProofs { older_than_days } => {
+            let count = retention::count_older_than(state, older_than_days).await;
+            if count == 0 {
+                return NlResponse {
+                    message: format!("No proofs are older than {} days", older_than_days),
+                    data: None,
+                };
+            }
+            let description = format!(
+                "Archive {} proofs older than {} days? They will no longer be listed.",
+                count, older_than_days
+            );
+            let action = proposals::PendingAction::ArchiveProofs { older_than_days };
+            propose_action(state, session_id, action, "archive", description).await
         }
     }
+}
+
+// Hold back a destructive command until the client confirms it
+async fn propose_action(
+    state: &AppState,
+    session_id: &str,
+    action: proposals::PendingAction,
+    name: &str,
+    description: String,
+) -> NlResponse {
+    let proposal_id = state.proposals.propose(session_id, action).await;
+    NlResponse {
+        message: format!("{} Reply 'confirm' to continue or 'cancel' to stop.", description),
+        data: Some(WsEvent::ActionProposal {
+            proposal_id,
+            action: name.to_string(),
+            description,
+        }),
+    }
+}
+
+// UPDATED: process_nl_command function with custom proof support
+#[tracing::instrument(name = "chat.command", skip_all, fields(session_id = %session_id))]
+async fn process_nl_command(state: &AppState, session_id: &str, input: &str) -> NlResponse {
+    let input_lower = input.to_lowercase();
     
-    // Handle custom proof commands
-if input_lower.contains("prove custom") {
-    // Extract WASM file
-    // Pattern: "prove custom <wasm_file>" (no arguments needed now)
-    let parts: Vec<&str> = input.split_whitespace().collect();
-    
-    // Find the wasm file (should be after "custom")
-    let wasm_file = if let Some(custom_idx) = parts.iter().position(|&s| s == "custom") {
-        if custom_idx + 1 < parts.len() {
-            parts[custom_idx + 1].to_string()
-        } else {
-            "custom.wat".to_string()
+    // Answers to a pending proposal
+    if state.proposals.has_pending(session_id).await {
+        match input_lower.trim().trim_end_matches(['.', '!']) {
+            "yes" | "y" | "ok" | "confirm" | "approve" | "go ahead" => {
+                return resolve_proposal(state, session_id, None, true).await;
+            }
+            "no" | "n" | "cancel" | "deny" | "abort" => {
+                return resolve_proposal(state, session_id, None, false).await;
+            }
+            _ => {}
         }
-    } else {
-        "custom.wat".to_string()
-    };
-    
-    // No arguments needed - values are hardcoded in the C code
-    let args: Vec<String> = vec!["0".to_string()];
-    
-    info!("Processing custom proof: wasm={}, args={:?} (dummy arg for hardcoded values)", wasm_file, args);
+    }
     
-    let proof_id = Uuid::new_v4().to_string();
-    let metadata = ProofMetadata {
-        wasm_path: format!("{}/{}", state.wasm_dir, wasm_file),
-        function: "main".to_string(),
-        arguments: args.clone(),
-        step_size: 50,
-    };
+    // Slash commands are deterministic and never reach the LLM
+    if let Some(slash) = slash::parse(input) {
+        return match slash {
+            Ok(slash::Slash::Command(command)) => execute_command(state, session_id, command).await,
+            Ok(slash::Slash::Prove { intent, verify_after }) => start_intent_proof(state, session_id, intent, verify_after).await,
+            Ok(slash::Slash::Confirm(approve)) => resolve_proposal(state, session_id, None, approve).await,
+            Err(message) => NlResponse { message, data: None },
+        };
+    }
     
-    // Create proof record
-    let proof_record = ProofRecord {
-        id: proof_id.clone(),
-        timestamp: Utc::now(),
-        metadata: metadata.clone(),
-        metrics: ProofMetrics {
-            generation_time_secs: 0.0,
-            file_size_mb: 0.0,
-            file_hash: String::new(),
-            peak_memory_mb: None,
-        },
-        status: ProofStatus::Pending,
-        file_path: None,
+    // "... and verify it" turns a proof request into a prove-then-verify workflow
+    let (input, verify_after) = match commands::strip_verify_suffix(input) {
+        Some(proof_request) => (proof_request, true),
+        None => (input, false),
     };
     
-    state.proof_store.lock().await.insert(proof_id.clone(), proof_record);
+    // PRIORITY: Handle commands from the grammar BEFORE LangChain
+    match commands::parse(input) {
+        commands::Parse::Command(command) => return execute_command(state, session_id, command).await,
+        commands::Parse::Ambiguous(message) => return NlResponse { message, data: None },
+        commands::Parse::NoMatch => {}
+    }
     
-    // Save to disk
-    {
-        let proofs = state.proof_store.lock().await;
-        if let Err(e) = save_proofs_to_disk(&*proofs).await {
-            error!("Failed to save proofs to disk: {}", e);
+    // Without LLM routing the local parser is all there is
+    if !state.flags.enabled(flags::Flag::LlmRouting) {
+        if let Some(intent) = intent_parser::parse(input) {
+            info!("Local parser matched {}({:?})", intent.function, intent.arguments);
+            return start_intent_proof(state, session_id, intent, verify_after).await;
         }
+        return NlResponse {
+            message: format!("Free-form requests are turned off on this server. {}", catalog::examples()),
+            data: None,
+        };
     }
     
-    // Send proof starting message
-    let start_msg = WsMessage {
-        msg_type: "message".to_string(),
-        content: format!("Starting custom proof generation with WASM: {} (using hardcoded values)", wasm_file),
-        data: Some(json!({ 
-            "type": "proof_start",
-            "proof_id": proof_id,
-            "function": "main",
-            "arguments": args,
-            "wasm_file": wasm_file,
-            "step_size": 50
-        })),
+    // Look the session up on its own: a guard held in the struct literal
+    // would still be held when `language` locks the store again
+    let llm_session_id = state.session_store.lock().await.get(session_id).map(|s| s.llm_session_id.clone());
+    let mut request = llm::IntentRequest {
+        message: input.to_string(),
+        session_id: llm_session_id,
+        context: Some(session_context(state, session_id).await),
+        tool_results: Vec::new(),
+        language: state.language(Some(session_id)).await,
     };
-    let _ = state.tx.send(start_msg);
     
-    // Spawn proof generation
-    let state_clone = state.clone();
-    let proof_id_clone = proof_id.clone();
-    tokio::spawn(async move {
-        generate_real_proof(state_clone, proof_id_clone, metadata, args).await;
-    });
-    
-    return NlResponse {
-        message: String::new(),
-        data: None,
+    // First, ALWAYS try the intent provider for ANY input to get natural language processing.
+    // The model may call tools first; their results are sent back until it answers.
+    let mut rounds = 0;
+    let result = loop {
+        match ask_intent_provider(state, session_id, &request).await {
+            Ok(mut response) if !response.tool_calls.is_empty() && rounds < tools::MAX_TOOL_ROUNDS => {
+                rounds += 1;
+                for call in std::mem::take(&mut response.tool_calls) {
+                    let result = tools::execute(state, session_id, &call).await;
+                    request.tool_results.push(llm::ToolResult { call, result });
+                }
+            }
+            result => break result,
+        }
     };
-}
-
-    
-    let session_id = Some("default".to_string());
     
-    // First, ALWAYS try LangChain for ANY input to get natural language processing
-    match process_with_langchain(&state.langchain_url, input, session_id.clone()).await {
+    match result {
         Ok(langchain_response) => {
-            // ALWAYS send the natural language response first if it exists
-            if !langchain_response.response.is_empty() {
-                let nl_msg = WsMessage {
-                    msg_type: "message".to_string(),
-                    content: langchain_response.response.clone(),
-                    data: Some(json!({ 
-                        "session_id": langchain_response.session_id,
-                        "from_langchain": true 
-                    })),
-                };
-                // Send the natural language response immediately
-                let _ = state.tx.send(nl_msg);
-            }
-            
             // Check for proof generation
-            if langchain_response.requires_proof && langchain_response.intent.is_some() {
-                let intent = langchain_response.intent.unwrap();
-                
-                // Map function name to WASM file
-                let wasm_file = match intent.function.as_str() {
-                    "prove_location" => "prove_location.wat",
-                    "fibonacci" => "fib.wat",
-                    "add" => "add.wat",
-                    "multiply" => "multiply.wat",
-                    "factorial" => "factorial_i32.wat",
-                    "is_even" => "is_even.wat",
-                    "square" => "square.wat",
-                    "max" => "max.wat",
-                    "count_until" => "count_until.wat",
-                    "prove_kyc" => "prove_kyc.wat",
-                    "prove_ai_content" => "prove_ai_content.wat",
-                    _ => {
-                        return NlResponse {
-                            message: String::new(),
-                            data: Some(json!({
-                                "error": format!("Unknown function: {}", intent.function)
-                            })),
-                        };
-                    }
-                };
-                
-                let proof_id = Uuid::new_v4().to_string();
-                let metadata = ProofMetadata {
-                    wasm_path: format!("{}/{}", state.wasm_dir, wasm_file),
-                    function: "main".to_string(),
-                    arguments: intent.arguments.clone(),
-                    step_size: intent.step_size,
-                };
-                
-                // Create proof record
-                let proof_record = ProofRecord {
-                    id: proof_id.clone(),
-                    timestamp: Utc::now(),
-                    metadata: metadata.clone(),
-                    metrics: ProofMetrics {
-                        generation_time_secs: 0.0,
-                        file_size_mb: 0.0,
-                        file_hash: String::new(),
-                        peak_memory_mb: None,
-                    },
-                    status: ProofStatus::Pending,
-                    file_path: None,
-                };
-                
-                state.proof_store.lock().await.insert(proof_id.clone(), proof_record);
-                
-                // Save to disk
-                {
-                    let proofs = state.proof_store.lock().await;
-                    if let Err(e) = save_proofs_to_disk(&*proofs).await {
-                        error!("Failed to save proofs to disk: {}", e);
-                    }
-                }
-                
-                // Convert arguments for location proofs
-                let processed_args = if intent.function == "prove_location" {
-                    convert_location_args(&intent.arguments)
-                } else {
-                    intent.arguments.clone()
-                };
-                
-                // Send SINGLE proof starting message with correct format
-                let start_msg = WsMessage {
-                    msg_type: "message".to_string(),
-                    content: format!("Starting proof generation for {} with arguments {:?}", intent.function, intent.arguments),
-                    data: Some(json!({ 
-                        "type": "proof_start",
-                        "proof_id": proof_id,
-                        "function": intent.function,
-                        "arguments": intent.arguments,
-                        "wasm_file": wasm_file,
-                        "step_size": intent.step_size
-                    })),
-                };
-                let _ = state.tx.send(start_msg);
-                
-                // Spawn proof generation
-                let state_clone = state.clone();
-                let proof_id_clone = proof_id.clone();
-                tokio::spawn(async move {
-                    generate_real_proof(state_clone, proof_id_clone, metadata, processed_args).await;
-                });
-                
-                return NlResponse {
-                    message: String::new(),
-                    data: None,
-                };
+            if let Some(intent) = langchain_response.intent.filter(|_| langchain_response.requires_proof) {
+                return start_intent_proof(state, session_id, intent, verify_after).await;
             }
             
             // Just conversation - response already sent
@@ -775,108 +1672,169 @@ if input_lower.contains("prove custom") {
             };
         },
         Err(e) => {
-            warn!("LangChain processing failed: {}", e);
-            // Fall back to local command processing
+            warn!("{} intent processing failed: {}", state.intent_provider.name(), e);
+            // Fall back to the built-in rule-based parser
+            if let Some(intent) = intent_parser::parse(input).filter(|_| state.llm_local_fallback) {
+                info!("Local parser matched {}({:?})", intent.function, intent.arguments);
+                return start_intent_proof(state, session_id, intent, verify_after).await;
+            }
         }
     }
     
-    // Fallback for when LangChain is unavailable
+    // Fallback for when the provider is unavailable and the local parser didn't match
+    let message = match state.intent_provider.name() {
+        "langchain" => format!("LangChain service unavailable. Please check if it's running on port 8002. {}", catalog::examples()),
+        name => format!("The {} intent provider is unavailable. {}", name, catalog::examples()),
+    };
     NlResponse {
-        message: "LangChain service unavailable. Please check if it's running on port 8002.".to_string(),
+        message,
         data: None,
     }
 }
 
+// zkEngine invocations: `prove --wasm <WASM> --step <STEP> --out-dir <DIR> [ARGS...]`
+// writes proof.bin and public.json; `verify --step <STEP> <PROOF> <PUBLIC>`
+// exits successfully when the proof is valid. Both run inside the sandbox.
+// A proof verifies only with the step size it was made with.
+const DEFAULT_STEP_SIZE: u64 = 50;
+
+fn prove_command(sandbox: &sandbox::Sandbox, binary: &str, wasm_path: &Path, step_size: u64, out_dir: &Path, args: &[String]) -> Command {
+    let mut cmd = Command::new(binary);
+    sandbox.apply(&mut cmd, &[wasm_path], &[out_dir]);
+    cmd.arg("prove")
+        .arg("--wasm").arg(wasm_path)
+        .arg("--step").arg(step_size.to_string())
+        .arg("--out-dir").arg(out_dir)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    cmd
+}
+
+fn verify_command(sandbox: &sandbox::Sandbox, binary: &str, step_size: u64, proof_file: &Path, public_file: &Path) -> Command {
+    let mut cmd = Command::new(binary);
+    sandbox.apply(&mut cmd, &[proof_file, public_file], &[]);
+    cmd.arg("verify")
+        .arg("--step").arg(step_size.to_string())
+        .arg(proof_file)
+        .arg(public_file)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    cmd
+}
+
 // FIXED: verify_proof_async function with correct command structure
-async fn verify_proof_async(state: AppState, proof_id: String) {
+// Returns the verification it recorded, or why none could be made; either
+// way the outcome is also published to the session
+#[tracing::instrument(name = "proof.verify", skip_all, fields(proof_id = %proof_id))]
+async fn verify_proof_async(state: AppState, proof_id: String, session_id: Option<String>) -> Result<VerificationRecord, AppError> {
     let start_time = Instant::now();
     
     // Get the proof record
-    let proof_record = {
-        let proofs = state.proof_store.lock().await;
-        proofs.get(&proof_id).cloned()
-    };
+    let proof_record = state.proof_store.get(&proof_id).await;
     
+    let language = state.language(session_id.as_deref()).await;
     let Some(proof) = proof_record else {
-        let _ = state.tx.send(WsMessage {
-            msg_type: "message".to_string(),
-            content: format!("Proof {} not found", &proof_id[..8]),
-            data: Some(json!({
-                "type": "verification_complete",
-                "proof_id": proof_id,
-                "is_valid": false,
-                "error": "Proof not found"
-            })),
-        });
-        return;
+        state.publish(WsMessage::event(
+            i18n::text(language, i18n::Text::VerificationNotFound { proof_id: &proof_id }),
+            WsEvent::verification_failed(&proof_id, AppError::proof_not_found()),
+        ).for_session(session_id.clone()));
+        return Err(AppError::proof_not_found());
     };
     
     // Check if proof is complete
     if !matches!(proof.status, ProofStatus::Complete) {
-        let _ = state.tx.send(WsMessage {
-            msg_type: "message".to_string(),
-            content: format!("Proof {} is not complete yet", &proof_id[..8]),
-            data: Some(json!({
-                "type": "verification_complete", 
-                "proof_id": proof_id,
-                "is_valid": false,
-                "error": "Proof not complete"
-            })),
-        });
-        return;
+        let error = AppError::new(ErrorCode::ProofNotReady, "Proof not complete");
+        state.publish(WsMessage::event(
+            i18n::text(language, i18n::Text::VerificationNotComplete { proof_id: &proof_id }),
+            WsEvent::verification_failed(&proof_id, error.clone()),
+        ).for_session(session_id.clone()));
+        return Err(error);
     }
     
     // Get the proof file path
     let Some(proof_file_path) = &proof.file_path else {
-        let _ = state.tx.send(WsMessage {
-            msg_type: "message".to_string(),
-            content: format!("Proof file not found for {}", &proof_id[..8]),
-            data: Some(json!({
-                "type": "verification_complete",
-                "proof_id": proof_id, 
-                "is_valid": false,
-                "error": "Proof file not found"
-            })),
-        });
-        return;
+        let error = AppError::new(ErrorCode::ProofFileMissing, "Proof file not found");
+        state.publish(WsMessage::event(
+            i18n::text(language, i18n::Text::VerificationNoFile { proof_id: &proof_id }),
+            WsEvent::verification_failed(&proof_id, error.clone()),
+        ).for_session(session_id.clone()));
+        return Err(error);
     };
     
     info!("Verifying proof {} using file {}", proof_id, proof_file_path);
+
+    // A modified proof file is reported as tampering rather than handed to
+    // zkEngine, which could only call it invalid
+    let actual_hash = calculate_file_hash(Path::new(proof_file_path)).await;
+    if actual_hash != "error" && !proof.metrics.file_hash.is_empty() && actual_hash != proof.metrics.file_hash {
+        let error = AppError::new(
+            ErrorCode::ArtifactTampered,
+            format!("proof.bin hashes to {}, but {} was recorded when it was generated", actual_hash, proof.metrics.file_hash),
+        );
+        return Ok(reject_tampered_proof(&state, &proof, error, session_id, language).await);
+    }
+    // So is a proof whose module can no longer be told apart from another
+    if let Err(found) = check_module(&proof).await {
+        return Ok(reject_tampered_proof(&state, &proof, AppError::new(ErrorCode::ArtifactTampered, found), session_id, language).await);
+    }
     
     // Clone values for the blocking task
     let zkengine_binary = state.zkengine_binary.clone();
+    let sandbox = state.sandbox.clone();
     let proof_file_path_clone = proof_file_path.clone();
+    let step_size = proof.metadata.step_size;
     
-    // Run verification in a blocking task
-    let verification_result = tokio::task::spawn_blocking(move || {
-        // Build correct verification command: wasm_file verify --step <STEP> <PROOF> <PUBLIC>
-        let proof_dir = std::path::Path::new(&proof_file_path_clone).parent().unwrap();
-        let public_file = proof_dir.join("public.json");
-        
-        let mut cmd = Command::new(&zkengine_binary);
-        cmd.arg("verify")
-            .arg("--step").arg("50")
-            .arg(&proof_file_path_clone)  // proof.bin file
-            .arg(&public_file);           // public.json file
-        
-        cmd.stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        
-        info!("Executing verification command: {:?}", cmd);
-        cmd.output()
-    }).await;
+    // An untouched proof verified before gets the same verdict
+    let public_file = Path::new(proof_file_path).with_file_name("public.json");
+    let cache_key = verify_cache::Key::of(actual_hash, &public_file, step_size).await;
+    let cached = cache_key.as_ref().and_then(|key| state.verify_cache.get(key));
+    let from_cache = cached.is_some();
+
+    // Run verification in a blocking task once a verifier slot is free
+    let span = tracing::info_span!("zkengine.verify");
+    let verification_result = match cached {
+        Some(verdict) => {
+            info!("Proof {} was verified before; using the cached result", proof_id);
+            Ok(Ok(verdict))
+        }
+        None => {
+            let slot = state.verify_pool.slot().await;
+            tokio::task::spawn_blocking(move || {
+                let _slot = slot;
+                let _span = span.enter();
+                // Build correct verification command: wasm_file verify --step <STEP> <PROOF> <PUBLIC>
+                let proof_dir = std::path::Path::new(&proof_file_path_clone).parent().unwrap();
+                let public_file = proof_dir.join("public.json");
+                
+                let mut cmd = verify_command(&sandbox, &zkengine_binary, step_size, Path::new(&proof_file_path_clone), &public_file);
+                info!("Executing verification command: {:?}", cmd);
+                cmd.output()
+            }).await.map(|result| result.map(|output| {
+                let is_valid = output.status.success();
+                let verdict = verify_cache::Verdict {
+                    is_valid,
+                    error: (!is_valid).then(|| String::from_utf8_lossy(&output.stderr).to_string()),
+                };
+                if let Some(key) = cache_key {
+                    state.verify_cache.insert(key, verdict.clone());
+                }
+                verdict
+            }))
+        }
+    };
     
     let duration = start_time.elapsed();
     let verification_id = Uuid::new_v4().to_string();
     
     match verification_result {
-        Ok(Ok(output)) => {
-            let is_valid = output.status.success();
-            let error_msg = if !is_valid {
-                Some(String::from_utf8_lossy(&output.stderr).to_string())
-            } else {
-                None
-            };
+        Ok(Ok(verdict)) => {
+            let is_valid = verdict.is_valid;
+            let error_msg = verdict.error;
+            let error_code = (!is_valid).then_some(ErrorCode::VerifierMismatch);
+            if let Some(error) = &error_msg {
+                alert_verification_failed(&state, &proof, &AppError::new(ErrorCode::VerifierMismatch, error.trim()));
+            }
             
             // Create verification record
             let verification_record = VerificationRecord {
@@ -886,88 +1844,313 @@ async fn verify_proof_async(state: AppState, proof_id: String) {
                 is_valid,
                 verification_time_secs: duration.as_secs_f64(),
                 error: error_msg.clone(),
+                error_code,
+                cached: from_cache,
+                upload: None,
+                peers: Vec::new(),
+                receipt: None,
             };
             
             // Store verification result
-            {
-                let mut verifications = state.verification_store.lock().await;
-                verifications.push(verification_record);
-                
-                // Save to disk
-                if let Err(e) = save_verifications_to_disk(&*verifications).await {
-                    error!("Failed to save verifications to disk: {}", e);
-                }
-            }
+            let verification_record = store_verification(&state, verification_record).await;
             
             // Send verification result
             let result_message = if is_valid {
-                format!("✅ Proof {} is VALID! Verified in {:.3}s", &proof_id[..8], duration.as_secs_f64())
+                i18n::text(language, i18n::Text::VerificationValid { proof_id: &proof_id, secs: duration.as_secs_f64() })
             } else {
-                format!("❌ Proof {} is INVALID. Error: {}", &proof_id[..8], error_msg.clone().unwrap_or_default())
+                let error = error_msg.clone().unwrap_or_default();
+                i18n::text(language, i18n::Text::VerificationInvalid { proof_id: &proof_id, error: &error })
             };
             
-            let _ = state.tx.send(WsMessage {
-                msg_type: "message".to_string(),
-                content: result_message,
-                data: Some(json!({
-                    "type": "verification_complete",
-                    "verification_id": verification_id,
-                    "proof_id": proof_id,
-                    "is_valid": is_valid,
-                    "verification_time_secs": duration.as_secs_f64(),
-                    "error": error_msg
-                })),
-            });
+            state.publish(WsMessage::event(
+                result_message,
+                WsEvent::VerificationComplete {
+                    verification_id: Some(verification_id),
+                    proof_id: proof_id.clone(),
+                    is_valid,
+                    verification_time_secs: Some(duration.as_secs_f64()),
+                    error: error_msg,
+                    error_code,
+                    cached: from_cache,
+                },
+            ).for_session(session_id.clone()));
+            Ok(verification_record)
         }
         Ok(Err(e)) => {
             error!("Failed to execute zkEngine verify: {}", e);
-            let _ = state.tx.send(WsMessage {
-                msg_type: "message".to_string(),
-                content: format!("Verification failed: {}", e),
-                data: Some(json!({
-                    "type": "verification_complete",
-                    "proof_id": proof_id,
-                    "is_valid": false,
-                    "error": format!("Execution error: {}", e)
-                })),
-            });
+            let error = AppError::new(ErrorCode::ProverUnavailable, format!("Execution error: {}", e));
+            alert_verification_failed(&state, &proof, &error);
+            state.publish(WsMessage::event(
+                i18n::text(language, i18n::Text::VerificationError { error: &e.to_string() }),
+                WsEvent::verification_failed(&proof_id, error.clone()),
+            ).for_session(session_id.clone()));
+            Err(error)
         }
         Err(e) => {
             error!("Task join error during verification: {}", e);
-            let _ = state.tx.send(WsMessage {
-                msg_type: "message".to_string(),
-                content: "Internal verification error".to_string(),
-                data: Some(json!({
-                    "type": "verification_complete", 
-                    "proof_id": proof_id,
-                    "is_valid": false,
-                    "error": "Internal error"
-                })),
-            });
+            let error = AppError::new(ErrorCode::Internal, "Internal error");
+            alert_verification_failed(&state, &proof, &error);
+            state.publish(WsMessage::event(
+                i18n::text(language, i18n::Text::VerificationError { error: "internal error" }),
+                WsEvent::verification_failed(&proof_id, error.clone()),
+            ).for_session(session_id.clone()));
+            Err(error)
+        }
+    }
+}
+
+// Fail verification of a proof whose file or module changed since it was
+// generated: recorded in its history, logged to the audit target and
+// alerted on
+async fn reject_tampered_proof(
+    state: &AppState,
+    proof: &ProofRecord,
+    error: AppError,
+    session_id: Option<String>,
+    language: i18n::Language,
+) -> VerificationRecord {
+    warn!(
+        target: "audit",
+        proof_id = %proof.id,
+        detail = %error.message,
+        "Proof was modified after generation; refusing to verify it",
+    );
+    state.notifier.notify(
+        notifications::Alert::new(
+            notifications::AlertKind::ArtifactCorrupted,
+            format!("Proof {} ({}) was modified after generation", short_id(&proof.id), analytics::circuit(proof)),
+        )
+        .proof(&proof.id, &analytics::circuit(proof))
+        .error(&error.message, Some(error.code)),
+    );
+
+    let verification = VerificationRecord {
+        id: Uuid::new_v4().to_string(),
+        proof_id: proof.id.clone(),
+        timestamp: Utc::now(),
+        is_valid: false,
+        verification_time_secs: 0.0,
+        error: Some(error.message.clone()),
+        error_code: Some(error.code),
+        cached: false,
+        upload: None,
+        peers: Vec::new(),
+        receipt: None,
+    };
+    let verification = store_verification(state, verification).await;
+
+    state.publish(WsMessage::event(
+        i18n::text(language, i18n::Text::VerificationTampered { proof_id: &proof.id }),
+        WsEvent::VerificationComplete {
+            verification_id: Some(verification.id.clone()),
+            proof_id: proof.id.clone(),
+            is_valid: false,
+            verification_time_secs: None,
+            error: Some(error.message),
+            error_code: Some(error.code),
+            cached: false,
+        },
+    ).for_session(session_id));
+    verification
+}
+
+// Sign a receipt for a verification, save it and record it on its proof
+async fn store_verification(state: &AppState, mut verification: VerificationRecord) -> VerificationRecord {
+    verification.receipt = Some(receipts::issue(state, &verification).await);
+    let summary = VerificationSummary::from(&verification);
+    {
+        let mut verifications = state.verification_store.lock().await;
+        verifications.push(verification.clone());
+        if let Err(e) = save_verifications_to_disk(&verifications).await {
+            error!("Failed to save verifications to disk: {}", e);
+        }
+    }
+    if !verification.proof_id.is_empty() {
+        link_verification(state, &verification.proof_id, summary).await;
+    }
+    verification
+}
+
+// Record a verification on its proof
+async fn link_verification(state: &AppState, proof_id: &str, summary: VerificationSummary) {
+    let record = state.proof_store.update(proof_id, |proof| {
+        proof.verifications.push(summary);
+        proof.clone()
+    }).await;
+    let Some(record) = record else {
+        return;
+    };
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+}
+
+fn alert_verification_failed(state: &AppState, proof: &ProofRecord, error: &AppError) {
+    state.notifier.notify(
+        notifications::Alert::new(
+            notifications::AlertKind::VerificationFailed,
+            format!("Verification of proof {} ({}) failed: {}", short_id(&proof.id), analytics::circuit(proof), error.message),
+        )
+        .proof(&proof.id, &analytics::circuit(proof))
+        .error(&error.message, Some(error.code)),
+    );
+}
+
+// A proof that was started, or an identical completed one that was reused
+// (see proof_cache.rs)
+struct StartedProof {
+    id: String,
+    cached: bool,
+}
+
+// What a proof is started with besides the computation itself
+#[derive(Default)]
+struct ProofOptions {
+    // See tags.rs
+    tags: Vec<String>,
+    // See custom_metadata.rs
+    custom_metadata: custom_metadata::CustomMetadata,
+    // See revocation.rs
+    expires_at: Option<DateTime<Utc>>,
+    // See proof_names.rs; checked by start_proof
+    name: Option<String>,
+    // See lineage.rs
+    parent_id: Option<String>,
+    rerun: Option<lineage::Rerun>,
+}
+
+// Create a pending proof record, persist it and spawn generation, unless
+// an identical proof is already complete. Fails only when the proof's name
+// is taken.
+// `args` are the arguments actually passed to zkEngine.
+async fn start_proof(
+    state: &AppState,
+    metadata: ProofMetadata,
+    args: Vec<String>,
+    session_id: Option<String>,
+    options: ProofOptions,
+) -> Result<StartedProof, AppError> {
+    // A reused proof would keep its own expiry, metadata and name
+    if options.expires_at.is_none() && options.custom_metadata.is_empty() && options.name.is_none() {
+        if let Some(proof) = state.proof_cache.reuse(state, &metadata, &options.tags).await {
+            return Ok(StartedProof { id: proof.id, cached: true });
+        }
+    }
+    // Held until the record with the name is in the store
+    let _claim = match &options.name {
+        Some(name) => Some(proof_names::claim(state, name, None).await?),
+        None => None,
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), session_id, options).await;
+    spawn_proof_generation(state, &proof_id, metadata, args);
+    Ok(StartedProof { id: proof_id, cached: false })
+}
+
+// Insert a queued proof record and persist the store
+async fn create_proof_record(
+    state: &AppState,
+    metadata: ProofMetadata,
+    session_id: Option<String>,
+    options: ProofOptions,
+) -> String {
+    let proof_id = Uuid::new_v4().to_string();
+    
+    let proof_record = ProofRecord {
+        id: proof_id.clone(),
+        timestamp: Utc::now(),
+        metadata,
+        metrics: ProofMetrics {
+            generation_time_secs: 0.0,
+            file_size_mb: 0.0,
+            file_hash: String::new(),
+            peak_memory_mb: None,
+            circuit: None,
+        },
+        status: ProofStatus::Queued,
+        file_path: None,
+        session_id,
+        request_id: request_id::current(),
+        signature: None,
+        verifications: Vec::new(),
+        name: options.name,
+        tags: options.tags,
+        custom_metadata: options.custom_metadata,
+        validity: revocation::Validity::expiring(options.expires_at),
+        approvals: Vec::new(),
+        origin: origin::current(),
+        parent_id: options.parent_id,
+        rerun: options.rerun,
+    };
+    if let Some(origin) = &proof_record.origin {
+        info!(
+            target: "audit",
+            proof_id = %proof_id,
+            channel = origin.channel.name(),
+            subject = origin.subject.as_deref().unwrap_or("-"),
+            client_ip = origin.client_ip.as_deref().unwrap_or("-"),
+            "Proof requested"
+        );
+    }
+    
+    // Insert and save to disk
+    state.proof_store.insert(proof_record.clone()).await;
+    state.proof_flusher.mark_dirty();
+    let in_flight = state.proof_store.count_indexed(|p| p.status.in_progress()).await;
+    cluster::store_proof(state, &proof_record).await;
+    let threshold = state.notifier.queue_threshold;
+    if threshold > 0 && in_flight >= threshold {
+        state.notifier.notify(notifications::Alert::new(
+            notifications::AlertKind::QueueSaturated,
+            format!("{} proofs in flight (alert threshold {})", in_flight, threshold),
+        ));
+    }
+    
+    proof_id
+}
+
+fn spawn_proof_generation(
+    state: &AppState,
+    proof_id: &str,
+    metadata: ProofMetadata,
+    args: Vec<String>,
+) -> tokio::task::JoinHandle<()> {
+    let state_clone = state.clone();
+    let proof_id_clone = proof_id.to_string();
+    tokio::spawn(request_id::inherit(async move {
+        if let Err(error) = approvals::wait(&state_clone, &proof_id_clone, &metadata).await {
+            update_proof_failed(&state_clone, &proof_id_clone, error).await;
+            return;
         }
+        run_proof_generation(state_clone, proof_id_clone, metadata, args).await;
+    }.in_current_span()))
+}
+
+// Generate a proof here or, in a cluster, on whichever instance is free
+async fn run_proof_generation(state: AppState, proof_id: String, metadata: ProofMetadata, args: Vec<String>) {
+    match state.cluster.clone() {
+        Some(cluster) => cluster::run_queued(state, cluster, proof_id, args).await,
+        None => generate_real_proof(state, proof_id, metadata, args).await,
     }
 }
 
 // FIXED: generate_real_proof function - remove duplicate messages
+#[tracing::instrument(name = "proof.generate", skip_all, fields(proof_id = %proof_id, function = %metadata.function))]
 async fn generate_real_proof(
     state: AppState,
     proof_id: String,
     metadata: ProofMetadata,
     args: Vec<String>,
 ) {
+    // Stays queued until a prover slot is free
+    let _slot = state.prove_pool.slot().await;
     let start_time = Instant::now();
     
-    // Update status to running (NO WebSocket message here - already sent)
-    {
-        let mut proofs = state.proof_store.lock().await;
-        if let Some(proof) = proofs.get_mut(&proof_id) {
-            proof.status = ProofStatus::Running;
-        }
-        // Save to disk
-        if let Err(e) = save_proofs_to_disk(&*proofs).await {
-            error!("Failed to save proofs to disk: {}", e);
-        }
-    }
+    // Move on to proving unless it was cancelled while queued;
+    // proof_start was already sent, so only emit a progress event
+    let Some(record) = advance(&state, &proof_id, ProofStatus::Proving { step: None, total: None }).await else {
+        info!("Proof {} was cancelled before it started", short_id(&proof_id));
+        return;
+    };
+    let session_id = record.session_id.clone();
     
     // Create proof directory
     let proof_dir = format!("{}/{}", state.proofs_dir, proof_id);
@@ -976,147 +2159,350 @@ async fn generate_real_proof(
     // Check if WASM file exists
     if !Path::new(&metadata.wasm_path).exists() {
         error!("WASM file not found: {}", metadata.wasm_path);
-        update_proof_failed(&state, &proof_id, "WASM file not found").await;
+        update_proof_failed(&state, &proof_id, AppError::new(ErrorCode::WasmNotFound, "WASM file not found")).await;
         return;
     }
     
-    // Clone values needed inside the closure
-    let zkengine_binary = state.zkengine_binary.clone();
-    let wasm_path = metadata.wasm_path.clone();
-    let step_size = metadata.step_size;
-    let proof_dir_clone = proof_dir.clone();
-    let args_vec: Vec<String> = args.clone();
-    
+    let log_path = prover_logs::log_path(&state.proofs_dir, &proof_id);
     info!("Running zkEngine command for proof {}", proof_id);
-    
-    match tokio::task::spawn_blocking(move || {
-        let mut cmd = Command::new(&zkengine_binary);
-        cmd.arg("prove")
-            .arg("--wasm").arg(&wasm_path)
-            .arg("--step").arg(step_size.to_string())
-            .arg("--out-dir").arg(&proof_dir_clone);
-        
-        // Add arguments
-        for arg in args_vec {
-            cmd.arg(arg);
-        }
-        
-        cmd.stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-        
-        info!("Executing command: {:?}", cmd);
-        cmd.output()
-    }).await {
-        Ok(Ok(output)) => {
+    let (started, pid) = oneshot::channel();
+    let run = run_prover(
+        state.sandbox.clone(),
+        state.zkengine_binary.clone(),
+        &metadata,
+        args,
+        PathBuf::from(&proof_dir),
+        log_path.clone(),
+        move |pid| {
+            let _ = started.send(pid);
+        },
+    );
+    // Watched in this task, so its writes are dropped with the job's once
+    // a cluster lease is lost
+    tokio::pin!(run);
+    let result = tokio::select! {
+        result = &mut run => result,
+        () = watch_prover(&state, &proof_id, &log_path, pid) => run.await,
+    };
+    match result {
+        Ok(Ok(status)) => {
             let duration = start_time.elapsed();
             
-            if output.status.success() {
-                // Find the generated proof file
-                if let Ok(entries) = fs::read_dir(&proof_dir) {
-                    for entry in entries.filter_map(Result::ok) {
-                        let path = entry.path();
-                        if path.extension().and_then(|s| s.to_str()) == Some("bin") {
-                            // Calculate metrics
-                            let file_size = fs::metadata(&path)
-                                .map(|m| m.len() as f64 / 1_048_576.0)
-                                .unwrap_or(0.0);
-                            
-                            let file_hash = calculate_file_hash(&path).await;
-                            
-                            // Update proof record
-                            let mut proofs = state.proof_store.lock().await;
-                            if let Some(proof) = proofs.get_mut(&proof_id) {
-                                proof.status = ProofStatus::Complete;
-                                proof.file_path = Some(path.to_string_lossy().to_string());
-                                proof.metrics = ProofMetrics {
-                                    generation_time_secs: duration.as_secs_f64(),
-                                    file_size_mb: file_size,
-                                    file_hash: file_hash.clone(),
-                                    peak_memory_mb: None,
-                                };
-                            }
-                            
-                            // Save to disk
-                            if let Err(e) = save_proofs_to_disk(&*proofs).await {
-                                error!("Failed to save proofs to disk: {}", e);
-                            }
-                            
-                            // Send SINGLE success message
-                            let _ = state.tx.send(WsMessage {
-                                msg_type: "message".to_string(),
-                                content: format!(
-                                    "Proof generated successfully! ID: {} Time: {:.1}s Size: {:.1}MB",
-                                    &proof_id[..8],
-                                    duration.as_secs_f64(),
-                                    file_size
-                                ),
-                                data: Some(json!({ 
-                                    "type": "proof_complete",
-                                    "proof_id": proof_id,
-                                    "status": "complete",
-                                    "function": metadata.function,
-                                    "arguments": metadata.arguments,
-                                    "step_size": metadata.step_size,
-                                    "time": duration.as_secs_f64(),
-                                    "size": file_size,
-                                    "hash": file_hash.clone()
-                                })),
-                            });
-                            
-                            return;
+            if status.success() {
+                if advance(&state, &proof_id, ProofStatus::Finalizing).await.is_none() {
+                    return;
+                }
+                if let Some(output) = collect_proof(&state.signer, &proof_id, Path::new(&proof_dir), &log_path, &metadata, duration).await {
+                    let file_size = output.metrics.file_size_mb;
+                    let file_hash = output.metrics.file_hash.clone();
+                    
+                    // Only a proof still finalizing completes, as in `advance`;
+                    // one cancelled or failed meanwhile keeps that status
+                    let record = state.proof_store.update(&proof_id, |proof| {
+                        if !matches!(proof.status, ProofStatus::Finalizing) {
+                            return None;
                         }
-                    }
+                        proof.status = ProofStatus::Complete;
+                        proof.file_path = Some(output.file_path.to_string_lossy().to_string());
+                        proof.metrics = output.metrics;
+                        proof.signature = Some(output.signature);
+                        proof.metadata.statement = Some(output.statement);
+                        Some(proof.clone())
+                    }).await.flatten();
+                    let Some(record) = record else {
+                        info!("Proof {} left finalizing before it completed", short_id(&proof_id));
+                        return;
+                    };
+                    state.proof_flusher.mark_dirty();
+                    cluster::store_proof(&state, &record).await;
+                    
+                    // Send SINGLE success message
+                    let language = state.language(session_id.as_deref()).await;
+                    state.publish(WsMessage::event(
+                        i18n::text(language, i18n::Text::ProofComplete {
+                            proof_id: &proof_id,
+                            secs: duration.as_secs_f64(),
+                            size_mb: file_size,
+                        }),
+                        WsEvent::ProofComplete {
+                            proof_id: proof_id.clone(),
+                            status: "complete".to_string(),
+                            function: metadata.function.clone(),
+                            arguments: metadata.arguments.clone(),
+                            step_size: metadata.step_size,
+                            time: duration.as_secs_f64(),
+                            size: file_size,
+                            hash: file_hash,
+                            cached: false,
+                        },
+                    ).for_session(session_id));
+                    
+                    return;
                 }
                 
                 // No proof file found
-                update_proof_failed(&state, &proof_id, "Proof file not found after generation").await;
+                let error = AppError::new(ErrorCode::ProofFileMissing, "Proof file not found after generation");
+                update_proof_failed(&state, &proof_id, error).await;
             } else {
-                let error = String::from_utf8_lossy(&output.stderr);
-                error!("zkEngine command failed: {}", error);
-                update_proof_failed(&state, &proof_id, &format!("zkEngine error: {}", error)).await;
+                let error = prover_error(status, &log_path).await;
+                error!("zkEngine command failed ({}): {}", status, error.message);
+                update_proof_failed(&state, &proof_id, error).await;
             }
         }
         Ok(Err(e)) => {
             error!("Failed to execute zkEngine: {}", e);
-            update_proof_failed(&state, &proof_id, &format!("Execution error: {}", e)).await;
+            let error = AppError::new(ErrorCode::ProverUnavailable, format!("Execution error: {}", e));
+            update_proof_failed(&state, &proof_id, error).await;
         }
         Err(e) => {
             error!("Task join error: {}", e);
-            update_proof_failed(&state, &proof_id, "Internal error").await;
+            update_proof_failed(&state, &proof_id, AppError::new(ErrorCode::Internal, "Internal error")).await;
+        }
+    }
+}
+
+// Move a proof to its next stage and tell its session; `None`, leaving it
+// alone, if it was cancelled (or deleted) meanwhile
+async fn advance(state: &AppState, proof_id: &str, status: ProofStatus) -> Option<ProofRecord> {
+    let record = state.proof_store.update(proof_id, |proof| {
+        if matches!(proof.status, ProofStatus::Cancelled) {
+            return None;
+        }
+        proof.status = status;
+        Some(proof.clone())
+    }).await.flatten()?;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    state.publish(WsMessage::event(
+        String::new(),
+        WsEvent::progress(proof_id, &record.status),
+    ).for_session(record.session_id.clone()));
+    Some(record)
+}
+
+// While zkEngine runs: pass on the steps it reports in its log, and kill
+// it if the proof is cancelled, here or on another instance. Returns once
+// it has killed zkEngine, and is dropped when the run ends.
+async fn watch_prover(state: &AppState, proof_id: &str, log_path: &Path, pid: oneshot::Receiver<u32>) {
+    let Ok(pid) = pid.await else {
+        return;
+    };
+    let mut offset = 0;
+    let mut last = None;
+    loop {
+        tokio::time::sleep(PROVER_WATCH_INTERVAL).await;
+        if state.proof_store.read(proof_id, |p| matches!(p.status, ProofStatus::Cancelled)).await == Some(true) {
+            cancellation::kill(proof_id, pid);
+            return;
+        }
+        let output = prover_logs::read_from(log_path, &mut offset).await;
+        let Some((step, total)) = prover_logs::step_progress(&output).filter(|progress| last != Some(*progress)) else {
+            continue;
+        };
+        last = Some((step, total));
+        let record = state.proof_store.update(proof_id, |proof| match proof.status {
+            ProofStatus::Proving { .. } => {
+                proof.status = ProofStatus::Proving { step: Some(step), total: Some(total) };
+                Some(proof.clone())
+            }
+            _ => None,
+        }).await.flatten();
+        let Some(record) = record else {
+            continue;
+        };
+        state.proof_flusher.mark_dirty();
+        cluster::store_proof(state, &record).await;
+        state.publish(WsMessage::event(
+            String::new(),
+            WsEvent::progress(proof_id, &record.status),
+        ).for_session(record.session_id.clone()));
+    }
+}
+
+// Run zkEngine for one proof, sending its output to the proof's log so it
+// can be followed live. `on_start` gets zkEngine's pid once it is running.
+async fn run_prover(
+    sandbox: Arc<sandbox::Sandbox>,
+    zkengine_binary: String,
+    metadata: &ProofMetadata,
+    args: Vec<String>,
+    proof_dir: PathBuf,
+    log_path: PathBuf,
+    on_start: impl FnOnce(u32) + Send + 'static,
+) -> Result<std::io::Result<ExitStatus>, tokio::task::JoinError> {
+    let wasm_path = metadata.wasm_path.clone();
+    let step_size = metadata.step_size;
+    let span = tracing::info_span!("zkengine.prove", step_size);
+    tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        // Prove the proof's own copy of the module, so what is recorded is
+        // what was proven even if the wasm directory changes later
+        let module = module_copy(&proof_dir, &wasm_path);
+        fs::copy(&wasm_path, &module)?;
+        let mut cmd = prove_command(&sandbox, &zkengine_binary, &module, step_size, &proof_dir, &args);
+        info!("Executing command: {:?}", cmd);
+        let mut log = fs::File::create(&log_path)?;
+        writeln!(log, "$ {:?}", cmd)?;
+        cmd.stdout(log.try_clone()?).stderr(log);
+        let mut child = cmd.spawn()?;
+        on_start(child.id());
+        child.wait()
+    }).await
+}
+
+// What a successful zkEngine run produced
+struct ProofOutput {
+    file_path: PathBuf,
+    metrics: ProofMetrics,
+    signature: signing::ProofSignature,
+    statement: Statement,
+}
+
+// Measure and sign the proof zkEngine wrote to `proof_dir`, with the circuit
+// statistics from its log; `None` if it wrote no proof file
+async fn collect_proof(
+    signer: &signing::ProofSigner,
+    proof_id: &str,
+    proof_dir: &Path,
+    log_path: &Path,
+    metadata: &ProofMetadata,
+    duration: Duration,
+) -> Option<ProofOutput> {
+    let file_path = fs::read_dir(proof_dir).ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().and_then(|s| s.to_str()) == Some("bin"))?;
+    let file_size = fs::metadata(&file_path)
+        .map(|m| m.len() as f64 / 1_048_576.0)
+        .unwrap_or(0.0);
+    let file_hash = calculate_file_hash(&file_path).await;
+    let public_path = proof_dir.join("public.json");
+    let signature = signer.sign(proof_id, &file_hash, &module_copy(proof_dir, &metadata.wasm_path), &public_path).await;
+    let module = Path::new(&metadata.wasm_path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let statement = Statement {
+        wasm_hash: signature.wasm_hash.clone(),
+        public_inputs: catalog::public_inputs(module, &metadata.arguments),
+        claimed_output: tokio::fs::read_to_string(&public_path).await.ok()
+            .map(|public| serde_json::from_str(&public).unwrap_or(json!(public))),
+    };
+    let circuit = tokio::fs::read_to_string(log_path).await.ok()
+        .and_then(|log| prover_logs::circuit_stats(prover_logs::output(&log), metadata.step_size));
+    Some(ProofOutput {
+        file_path,
+        metrics: ProofMetrics {
+            generation_time_secs: duration.as_secs_f64(),
+            file_size_mb: file_size,
+            file_hash,
+            peak_memory_mb: None,
+            circuit,
+        },
+        signature,
+        statement,
+    })
+}
+
+// Where a proof keeps the copy of the module it was made with
+fn module_copy(proof_dir: &Path, wasm_path: &str) -> PathBuf {
+    let extension = Path::new(wasm_path).extension().and_then(|e| e.to_str()).unwrap_or("wasm");
+    proof_dir.join(format!("module.{}", extension))
+}
+
+// Check that the module a proof was made with is still at hand, unchanged:
+// the proof's own copy or, for proofs made before copies were kept, the
+// module in the wasm directory. The error says what was found instead.
+async fn check_module(proof: &ProofRecord) -> Result<(), String> {
+    let expected = match (&proof.metadata.statement, &proof.signature) {
+        (Some(statement), _) => &statement.wasm_hash,
+        (None, Some(signature)) => &signature.wasm_hash,
+        (None, None) => return Ok(()),
+    };
+    let Some(proof_dir) = proof.file_path.as_deref().and_then(|path| Path::new(path).parent()) else {
+        return Ok(());
+    };
+    let mut found = None;
+    for path in [module_copy(proof_dir, &proof.metadata.wasm_path), PathBuf::from(&proof.metadata.wasm_path)] {
+        match verifier::sha256_file(&path).await {
+            Ok(hash) if &hash == expected => return Ok(()),
+            Ok(hash) => found = found.or(Some(format!("{} hashes to {}", path.display(), hash))),
+            Err(_) => {}
         }
     }
+    let found = found.unwrap_or_else(|| format!("{} is gone", proof.metadata.wasm_path));
+    Err(format!("{}, but the proof was made with a module hashing to {}", found, expected))
+}
+
+// Error for a failed zkEngine run, quoting the end of its log
+async fn prover_error(status: ExitStatus, log_path: &Path) -> AppError {
+    let log = tokio::fs::read_to_string(log_path).await.unwrap_or_default();
+    let output = prover_logs::tail(prover_logs::output(&log), FAILURE_LOG_LINES).trim();
+    // No exit code means zkEngine was killed by a signal
+    let code = if status.code().is_some() { ErrorCode::ProverFailed } else { ErrorCode::ProverCrashed };
+    AppError::new(code, format!("zkEngine error: {}", output))
 }
 
 // FIXED: update_proof_failed function
-async fn update_proof_failed(state: &AppState, proof_id: &str, error: &str) {
-    let mut proofs = state.proof_store.lock().await;
-    if let Some(proof) = proofs.get_mut(proof_id) {
-        proof.status = ProofStatus::Failed(error.to_string());
+async fn update_proof_failed(state: &AppState, proof_id: &str, error: AppError) {
+    let record = state.proof_store.update(proof_id, |proof| {
+        // A cancelled proof's run ends in an error; it stays cancelled
+        if matches!(proof.status, ProofStatus::Cancelled) {
+            return None;
+        }
+        proof.status = ProofStatus::Failed(error.clone());
+        Some(proof.clone())
+    }).await;
+    if matches!(record, Some(None)) {
+        return;
     }
-    
-    // Save to disk
-    if let Err(e) = save_proofs_to_disk(&*proofs).await {
-        error!("Failed to save proofs to disk: {}", e);
+    let record = record.flatten();
+    state.proof_flusher.mark_dirty();
+    let session_id = record.as_ref().and_then(|p| p.session_id.clone());
+    let function = record.as_ref().map(analytics::circuit).unwrap_or_default();
+    if let Some(record) = &record {
+        cluster::store_proof(state, record).await;
     }
+    state.notifier.notify(
+        notifications::Alert::new(
+            notifications::AlertKind::ProofFailed,
+            format!("Proof {} ({}) failed: {}", short_id(proof_id), function, error.message),
+        )
+        .proof(proof_id, &function)
+        .error(&error.message, Some(error.code)),
+    );
     
-    let _ = state.tx.send(WsMessage {
-        msg_type: "message".to_string(),
-        content: format!("Proof generation failed: {}", error),
-        data: Some(json!({ 
-            "type": "proof_failed",
-            "proof_id": proof_id, 
-            "error": error 
-        })),
-    });
+    let language = state.language(session_id.as_deref()).await;
+    state.publish(WsMessage::event(
+        i18n::text(language, i18n::Text::ProofFailed { error: &error.message }),
+        WsEvent::ProofFailed {
+            proof_id: proof_id.to_string(),
+            error: error.message,
+            code: error.code,
+        },
+    ).for_session(session_id));
 }
 
-async fn calculate_file_hash(path: &Path) -> String {
-    match tokio::fs::read(path).await {
-        Ok(contents) => {
-            let mut hasher = Sha256::new();
-            hasher.update(&contents);
-            format!("{:x}", hasher.finalize())
-        }
-        Err(_) => "error".to_string(),
+// A previous run's queued and unfinished proofs have no task behind them
+// any more, so they're marked failed instead of staying in progress. In
+// cluster mode their jobs are picked up again from the queue.
+async fn fail_interrupted_proofs(state: &AppState) {
+    if state.cluster.is_some() {
+        return;
+    }
+    let interrupted = state.proof_store.ids(|entry| entry.status.in_progress()).await;
+    if interrupted.is_empty() {
+        return;
+    }
+    warn!("Marking {} proofs interrupted by the last shutdown as failed", interrupted.len());
+    for proof_id in interrupted {
+        let error = AppError::new(ErrorCode::Internal, "Interrupted by a server restart");
+        update_proof_failed(state, &proof_id, error).await;
     }
 }
+
+// First 8 characters of an ID, as shown in chat
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+async fn calculate_file_hash(path: &Path) -> String {
+    verifier::sha256_file(path).await.unwrap_or_else(|_| "error".to_string())
+}
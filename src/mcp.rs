@@ -0,0 +1,295 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
+};
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc,
+};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    auth::{Identity, Role},
+    rpc::{RpcRequest, RpcResponse, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND},
+    errors::{AppError, ErrorCode},
+    origin::{self, Channel, Origin},
+    retention,
+    tools, AppState, ProofStatus,
+};
+
+// Model Context Protocol server exposing the same tools the chat LLM gets,
+// plus artifact download. Available over stdio (`--mcp-stdio`) and over
+// SSE: GET /mcp/sse opens the event stream and announces the endpoint to
+// POST JSON-RPC messages to; responses arrive on the stream.
+
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+// Proof files larger than this are not inlined by fetch_proof_artifact
+const MAX_ARTIFACT_BYTES: u64 = 10 * 1024 * 1024;
+
+pub struct McpSession {
+    // Where responses go; `None` for stdio, which answers inline
+    tx: Option<mpsc::UnboundedSender<Value>>,
+    pub role: Role,
+    // Recorded on proofs the session's tool calls start
+    origin: Origin,
+}
+
+// Open MCP sessions by ID
+pub type McpSessions = Arc<Mutex<HashMap<String, McpSession>>>;
+
+fn tool_definitions() -> Value {
+    let mut definitions: Vec<Value> = tools::definitions()
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tool| json!({
+            "name": tool["name"],
+            "description": tool["description"],
+            "inputSchema": tool["parameters"],
+        }))
+        .collect();
+    definitions.push(json!({
+        "name": "fetch_proof_artifact",
+        "description": "Download a completed proof file and its public inputs.",
+        "inputSchema": {
+            "type": "object",
+            "properties": { "proof_id": { "type": "string" } },
+            "required": ["proof_id"]
+        }
+    }));
+    json!(definitions)
+}
+
+#[derive(Deserialize)]
+struct ToolCallParams {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+// Handle one JSON-RPC message. Returns `None` for notifications.
+async fn handle(state: &AppState, session_id: &str, value: Value) -> Option<RpcResponse> {
+    let request = match serde_json::from_value::<RpcRequest>(value) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => return Some(RpcResponse::error(Value::Null, INVALID_REQUEST, "Invalid Request")),
+    };
+    // Notifications (e.g. notifications/initialized) need no reply
+    let id = request.id?;
+
+    Some(match request.method.as_str() {
+        "initialize" => RpcResponse::success(id, json!({
+            "protocolVersion": request.params["protocolVersion"].as_str().unwrap_or(MCP_PROTOCOL_VERSION),
+            "capabilities": { "tools": { "listChanged": false } },
+            "serverInfo": { "name": "zkengine-agentkit", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => RpcResponse::success(id, json!({})),
+        "tools/list" => RpcResponse::success(id, json!({ "tools": tool_definitions() })),
+        "tools/call" => match serde_json::from_value::<ToolCallParams>(request.params) {
+            Ok(params) => {
+                info!("MCP tool call {} from session {}", params.name, session_id);
+                RpcResponse::success(id, call_tool(state, session_id, params).await)
+            }
+            Err(e) => RpcResponse::error(id, INVALID_PARAMS, e.to_string()),
+        },
+        method => RpcResponse::error(id, METHOD_NOT_FOUND, format!("Method not found: {}", method)),
+    })
+}
+
+// Tool errors are reported in the result (`isError`) rather than as
+// JSON-RPC errors, as MCP expects
+async fn call_tool(state: &AppState, session_id: &str, params: ToolCallParams) -> Value {
+    let arguments = if params.arguments.is_null() { json!({}) } else { params.arguments };
+    let result = if params.name == "fetch_proof_artifact" {
+        fetch_proof_artifact(state, arguments).await
+    } else {
+        let origin = state.mcp_sessions.lock().unwrap().get(session_id)
+            .map(|s| s.origin.clone())
+            .unwrap_or_else(|| Origin::new(Channel::Mcp));
+        origin::scope(origin, tools::call(state, session_id, &params.name, arguments))
+            .await
+            .map(|result| json!([{ "type": "text", "text": result.to_string() }]))
+    };
+    match result {
+        Ok(content) => json!({ "content": content, "isError": false }),
+        Err(error) => json!({ "content": [{ "type": "text", "text": tools::error_result(&error).to_string() }], "isError": true }),
+    }
+}
+
+async fn fetch_proof_artifact(state: &AppState, arguments: Value) -> Result<Value, AppError> {
+    let proof_id = arguments["proof_id"].as_str()
+        .ok_or_else(|| AppError::new(ErrorCode::InvalidArguments, "proof_id is required"))?;
+    // A unique prefix or name, like everywhere else a proof ID is taken
+    let proof_id = retention::resolve_proof_id(state, proof_id).await?;
+    let proof = state.proof_store.get(&proof_id).await.ok_or_else(AppError::proof_not_found)?;
+    if !matches!(proof.status, ProofStatus::Complete) {
+        return Err(AppError::new(ErrorCode::ProofNotReady, "Proof not complete"));
+    }
+    let missing = |e: &dyn std::fmt::Display| AppError::new(ErrorCode::ProofFileMissing, e.to_string());
+    let file_path = proof.file_path.ok_or_else(|| missing(&"Proof file not found"))?;
+
+    let size = tokio::fs::metadata(&file_path).await.map_err(|e| missing(&e))?.len();
+    if size > MAX_ARTIFACT_BYTES {
+        return Err(AppError::new(
+            ErrorCode::TooLarge,
+            format!("Proof file is {} bytes, larger than the {} byte limit", size, MAX_ARTIFACT_BYTES),
+        ));
+    }
+    let bytes = tokio::fs::read(&file_path).await.map_err(|e| missing(&e))?;
+    let public = tokio::fs::read_to_string(format!("{}/{}/public.json", state.proofs_dir, proof_id))
+        .await
+        .unwrap_or_default();
+
+    Ok(json!([
+        {
+            "type": "resource",
+            "resource": {
+                "uri": format!("zkengine://proofs/{}/proof.bin", proof_id),
+                "mimeType": "application/octet-stream",
+                "blob": base64::engine::general_purpose::STANDARD.encode(bytes),
+            }
+        },
+        {
+            "type": "resource",
+            "resource": {
+                "uri": format!("zkengine://proofs/{}/public.json", proof_id),
+                "mimeType": "application/json",
+                "text": public,
+            }
+        }
+    ]))
+}
+
+// Serve MCP over stdin/stdout, one JSON-RPC message per line
+pub async fn serve_stdio(state: AppState) {
+    let session_id = format!("mcp-{}", Uuid::new_v4());
+    info!("MCP server listening on stdio (session {})", session_id);
+    // Whoever launched the process has full access
+    state.mcp_sessions.lock().unwrap().insert(session_id.clone(), McpSession {
+        tx: None,
+        role: Role::Admin,
+        origin: Origin::new(Channel::Mcp),
+    });
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(value) => handle(&state, &session_id, value).await,
+            Err(_) => Some(RpcResponse::error(Value::Null, -32700, "Parse error")),
+        };
+        if let Some(response) = response {
+            let mut out = serde_json::to_string(&response).unwrap();
+            out.push('\n');
+            if stdout.write_all(out.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    }
+    state.mcp_sessions.lock().unwrap().remove(&session_id);
+    info!("MCP stdio session {} closed", session_id);
+}
+
+#[derive(Deserialize)]
+pub struct SseParams {
+    token: Option<String>,
+}
+
+// Removes the session when its event stream is dropped
+struct SessionGuard {
+    sessions: McpSessions,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.lock().unwrap().remove(&self.session_id);
+        info!("MCP SSE session {} closed", self.session_id);
+    }
+}
+
+pub async fn sse_handler(
+    State(state): State<AppState>,
+    Query(params): Query<SseParams>,
+    client: Option<Extension<Origin>>,
+    headers: HeaderMap,
+) -> Response {
+    let identity = if state.auth.is_enabled() {
+        let bearer = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let identity = match bearer.or(params.token.as_deref()) {
+            Some(token) => state.auth.authenticate(token).await,
+            None => None,
+        };
+        match identity {
+            Some(identity) => identity,
+            None => {
+                warn!("Rejected MCP connection with missing or invalid token");
+                return (StatusCode::UNAUTHORIZED, "Invalid API token").into_response();
+            }
+        }
+    } else {
+        Identity::anonymous()
+    };
+
+    let session_id = format!("mcp-{}", Uuid::new_v4());
+    let (tx, rx) = mpsc::unbounded_channel();
+    let origin = Origin::new(Channel::Mcp).identity(&identity).client(client.as_deref());
+    state.mcp_sessions.lock().unwrap().insert(session_id.clone(), McpSession { tx: Some(tx), role: identity.role, origin });
+    info!("MCP SSE session {} opened for {} ({})", session_id, identity.subject, identity.role.name());
+
+    let guard = SessionGuard { sessions: state.mcp_sessions.clone(), session_id: session_id.clone() };
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("/mcp/messages?session_id={}", session_id));
+    let messages = UnboundedReceiverStream::new(rx).map(move |message: Value| {
+        let _ = &guard;
+        Event::default().event("message").data(message.to_string())
+    });
+    let stream = tokio_stream::once(endpoint).chain(messages).map(Ok::<_, Infallible>);
+
+    Sse::new(stream).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct MessageParams {
+    session_id: String,
+}
+
+pub async fn message_handler(
+    State(state): State<AppState>,
+    Query(params): Query<MessageParams>,
+    Json(message): Json<Value>,
+) -> Response {
+    let Some(tx) = state.mcp_sessions.lock().unwrap().get(&params.session_id).and_then(|s| s.tx.clone()) else {
+        return (StatusCode::NOT_FOUND, "Unknown MCP session").into_response();
+    };
+    // Tool calls can take a while (verification); answer on the stream
+    tokio::spawn(crate::request_id::inherit(async move {
+        if let Some(response) = handle(&state, &params.session_id, message).await {
+            let _ = tx.send(serde_json::to_value(response).unwrap());
+        }
+    }));
+    StatusCode::ACCEPTED.into_response()
+}
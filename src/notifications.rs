@@ -0,0 +1,191 @@
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::{
+    errors::ErrorCode,
+    outbound::{self, Integration},
+};
+
+// Failure alerts sent to webhooks, so operators hear about broken proofs
+// without watching the logs. Configured from the environment:
+//
+//   ALERT_WEBHOOK_URL         generic webhook, receives the alert as JSON
+//   ALERT_SLACK_WEBHOOK_URL   Slack incoming webhook, receives {"text": ...}
+//   ALERT_EVENTS              kinds to send (default: proof_failed,verification_failed,
+//                             queue_saturated,artifact_corrupted)
+//   ALERT_RATE_LIMIT_SECS     at most one alert per kind in this window (default 300);
+//                             the next one reports how many were suppressed
+//   ALERT_TEMPLATE            message text, with {kind}, {summary}, {proof_id},
+//                             {function}, {error}, {code} and {suppressed} placeholders
+//   ALERT_QUEUE_THRESHOLD     proofs in flight that count as saturation (default 10)
+
+const DEFAULT_TEMPLATE: &str = "[zkEngine] {summary}";
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    ProofFailed,
+    VerificationFailed,
+    QueueSaturated,
+    // A stored proof stopped verifying or its file changed (see reverify.rs)
+    ArtifactCorrupted,
+}
+
+impl AlertKind {
+    fn name(&self) -> &'static str {
+        match self {
+            AlertKind::ProofFailed => "proof_failed",
+            AlertKind::VerificationFailed => "verification_failed",
+            AlertKind::QueueSaturated => "queue_saturated",
+            AlertKind::ArtifactCorrupted => "artifact_corrupted",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [AlertKind::ProofFailed, AlertKind::VerificationFailed, AlertKind::QueueSaturated, AlertKind::ArtifactCorrupted]
+            .into_iter()
+            .find(|kind| kind.name() == name.trim())
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Alert {
+    pub kind: AlertKind,
+    // One-line description, used by the default template
+    pub summary: String,
+    pub proof_id: Option<String>,
+    pub function: Option<String>,
+    pub error: Option<String>,
+    pub code: Option<ErrorCode>,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, summary: impl Into<String>) -> Self {
+        Alert { kind, summary: summary.into(), proof_id: None, function: None, error: None, code: None }
+    }
+
+    pub fn proof(mut self, proof_id: &str, function: &str) -> Self {
+        self.proof_id = Some(proof_id.to_string());
+        self.function = Some(function.to_string());
+        self
+    }
+
+    pub fn error(mut self, error: impl Into<String>, code: Option<ErrorCode>) -> Self {
+        self.error = Some(error.into());
+        self.code = code;
+        self
+    }
+}
+
+enum Sink {
+    Webhook(String),
+    Slack(String),
+}
+
+#[derive(Default)]
+struct RateState {
+    last_sent: Option<Instant>,
+    suppressed: usize,
+}
+
+pub struct Notifier {
+    sinks: Vec<Sink>,
+    kinds: Vec<AlertKind>,
+    rate_limit: Duration,
+    template: String,
+    pub queue_threshold: usize,
+    rate: Mutex<HashMap<AlertKind, RateState>>,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let sinks: Vec<Sink> = [
+            var("ALERT_WEBHOOK_URL").map(Sink::Webhook),
+            var("ALERT_SLACK_WEBHOOK_URL").map(Sink::Slack),
+        ].into_iter().flatten().collect();
+        let kinds = match var("ALERT_EVENTS") {
+            Some(names) => names.split(',').filter_map(AlertKind::parse).collect(),
+            None => vec![AlertKind::ProofFailed, AlertKind::VerificationFailed, AlertKind::QueueSaturated, AlertKind::ArtifactCorrupted],
+        };
+        if !sinks.is_empty() {
+            info!("Sending {} alerts to {} sink(s)", kinds.iter().map(|k| k.name()).collect::<Vec<_>>().join(", "), sinks.len());
+        }
+        Notifier {
+            sinks,
+            kinds,
+            rate_limit: Duration::from_secs(var("ALERT_RATE_LIMIT_SECS").and_then(|v| v.parse().ok()).unwrap_or(300)),
+            template: var("ALERT_TEMPLATE").unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+            queue_threshold: var("ALERT_QUEUE_THRESHOLD").and_then(|v| v.parse().ok()).unwrap_or(10),
+            rate: Mutex::new(HashMap::new()),
+            client: outbound::client(Integration::Alerts),
+        }
+    }
+
+    // How many earlier alerts of this kind were suppressed, or `None` if
+    // this one is inside the rate limit window and should be dropped
+    fn admit(&self, kind: AlertKind) -> Option<usize> {
+        let mut rate = self.rate.lock().unwrap();
+        let state = rate.entry(kind).or_default();
+        if state.last_sent.is_some_and(|at| at.elapsed() < self.rate_limit) {
+            state.suppressed += 1;
+            return None;
+        }
+        state.last_sent = Some(Instant::now());
+        Some(std::mem::take(&mut state.suppressed))
+    }
+
+    fn render(&self, alert: &Alert, suppressed: usize) -> String {
+        let mut text = self.template
+            .replace("{kind}", alert.kind.name())
+            .replace("{summary}", &alert.summary)
+            .replace("{proof_id}", alert.proof_id.as_deref().unwrap_or(""))
+            .replace("{function}", alert.function.as_deref().unwrap_or(""))
+            .replace("{error}", alert.error.as_deref().unwrap_or(""))
+            .replace("{code}", &alert.code.map(|c| json!(c).as_str().unwrap_or_default().to_string()).unwrap_or_default())
+            .replace("{suppressed}", &suppressed.to_string());
+        if suppressed > 0 && !self.template.contains("{suppressed}") {
+            text.push_str(&format!(" (+{} similar alerts suppressed)", suppressed));
+        }
+        text
+    }
+
+    // Send an alert to every sink in the background
+    pub fn notify(self: &Arc<Self>, alert: Alert) {
+        if self.sinks.is_empty() || !self.kinds.contains(&alert.kind) {
+            return;
+        }
+        let Some(suppressed) = self.admit(alert.kind) else {
+            return;
+        };
+        let notifier = self.clone();
+        tokio::spawn(async move {
+            let text = notifier.render(&alert, suppressed);
+            for sink in &notifier.sinks {
+                let (url, body): (&str, Value) = match sink {
+                    Sink::Webhook(url) => (url, json!({
+                        "alert": alert,
+                        "message": text,
+                        "suppressed": suppressed,
+                        "timestamp": Utc::now(),
+                    })),
+                    Sink::Slack(url) => (url, json!({ "text": text })),
+                };
+                let result = notifier.client.post(url).json(&body).timeout(Duration::from_secs(10)).send().await;
+                match result {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => warn!("Alert webhook returned {}", response.status()),
+                    Err(e) => warn!("Failed to send alert: {}", e),
+                }
+            }
+        });
+    }
+}
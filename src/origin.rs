@@ -0,0 +1,129 @@
+use axum::{
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+
+use crate::{
+    auth::{Identity, Role},
+    AppState,
+};
+
+// Who and what asked for each proof, kept on its record as `origin` so an
+// agent's behaviour can be audited later: the channel the request came in
+// on, the caller's identity (the API key's name or the JWT subject) and
+// role, and the client's address and user agent. The session, if any, is
+// the record's `session_id`.
+//
+// Like the request ID (see request_id.rs), the origin belongs to the
+// request being handled: the HTTP middleware sets it for REST calls, the
+// WebSocket, gRPC, MCP and chat bridge handlers for theirs, and
+// create_proof_record copies it onto the record. Client addresses come from
+// X-Forwarded-For only with RATE_LIMIT_TRUST_PROXY, as for rate limiting.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Rest,
+    // Chat over the WebSocket
+    Chat,
+    // JSON-RPC over the WebSocket (see rpc.rs)
+    JsonRpc,
+    Grpc,
+    Mcp,
+    Slack,
+    Discord,
+    // `zkp-agentkit prove` on the server's host
+    Cli,
+}
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Rest => "rest",
+            Channel::Chat => "chat",
+            Channel::JsonRpc => "json_rpc",
+            Channel::Grpc => "grpc",
+            Channel::Mcp => "mcp",
+            Channel::Slack => "slack",
+            Channel::Discord => "discord",
+            Channel::Cli => "cli",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Origin {
+    pub channel: Channel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl Origin {
+    pub fn new(channel: Channel) -> Self {
+        Origin { channel, subject: None, role: None, client_ip: None, user_agent: None }
+    }
+
+    pub fn identity(mut self, identity: &Identity) -> Self {
+        self.subject = Some(identity.subject.clone());
+        self.role = Some(identity.role);
+        self
+    }
+
+    // The client of the HTTP request a connection was opened with
+    pub fn client(mut self, client: Option<&Origin>) -> Self {
+        if let Some(client) = client {
+            self.client_ip = client.client_ip.clone();
+            self.user_agent = client.user_agent.clone();
+        }
+        self
+    }
+}
+
+tokio::task_local! {
+    static ORIGIN: Origin;
+}
+
+// Origin of the request being handled, if any
+pub fn current() -> Option<Origin> {
+    ORIGIN.try_with(Origin::clone).ok()
+}
+
+// Run `future` on behalf of a request from `origin`
+pub async fn scope<F: Future>(origin: Origin, future: F) -> F::Output {
+    ORIGIN.scope(origin, future).await
+}
+
+// Carry the current origin into a spawned task
+pub async fn inherit<F: Future>(future: F) -> F::Output {
+    match current() {
+        Some(origin) => scope(origin, future).await,
+        None => future.await,
+    }
+}
+
+// Runs inside auth::middleware, so the caller's identity is known. The
+// origin is also added to the request's extensions, for handlers whose work
+// outlives the request (WebSocket and MCP connections).
+pub async fn middleware<B>(State(state): State<AppState>, mut request: Request<B>, next: Next<B>) -> Response {
+    let mut origin = Origin::new(Channel::Rest);
+    if let Some(identity) = request.extensions().get::<Identity>() {
+        origin = origin.identity(identity);
+    }
+    origin.client_ip = state.rate_limiter.client_ip(&request);
+    origin.user_agent = request.headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    request.extensions_mut().insert(origin.clone());
+    scope(origin, next.run(request)).await
+}
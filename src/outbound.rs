@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+use tracing::{error, info};
+
+// HTTP clients for calls out of the server. Each integration can go through
+// its own proxy, trust its own CA and present a client certificate (mTLS):
+//
+//   <PREFIX>_PROXY         proxy URL for all of the integration's requests,
+//                          e.g. http://proxy.internal:3128 or socks5://...
+//   <PREFIX>_CA_CERT       PEM file of extra CA certificates to trust
+//   <PREFIX>_CLIENT_CERT   PEM client certificate chain, with
+//   <PREFIX>_CLIENT_KEY    its PKCS#8 PEM private key
+//
+// where PREFIX is the integration's (see Integration::prefix) or, for
+// settings every integration shares, OUTBOUND. Without a proxy setting the
+// usual HTTP_PROXY, HTTPS_PROXY and NO_PROXY variables apply. A setting
+// that can't be loaded is logged and the integration uses default settings,
+// so a bad certificate shows up as failed requests rather than a server
+// that won't start.
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Integration {
+    LangChain,
+    // OpenAI, Anthropic and Ollama providers
+    Llm,
+    Webhooks,
+    Alerts,
+    Siem,
+    ChatBridge,
+    Jwks,
+    Secrets,
+    Federation,
+    Ipfs,
+}
+
+impl Integration {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Integration::LangChain => "LANGCHAIN",
+            Integration::Llm => "LLM",
+            Integration::Webhooks => "WEBHOOK",
+            Integration::Alerts => "ALERT",
+            Integration::Siem => "SIEM",
+            Integration::ChatBridge => "CHAT_BRIDGE",
+            Integration::Jwks => "JWKS",
+            Integration::Secrets => "SECRETS",
+            Integration::Federation => "FEDERATION",
+            Integration::Ipfs => "IPFS",
+        }
+    }
+}
+
+static CLIENTS: OnceLock<Mutex<HashMap<Integration, reqwest::Client>>> = OnceLock::new();
+
+// The integration's client, built on first use; clones share its connection pool
+pub fn client(integration: Integration) -> reqwest::Client {
+    let mut clients = CLIENTS.get_or_init(Default::default).lock().unwrap();
+    clients
+        .entry(integration)
+        .or_insert_with(|| match build(integration) {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Outbound HTTP settings for {} are invalid, using defaults: {:#}", integration.prefix(), e);
+                reqwest::Client::new()
+            }
+        })
+        .clone()
+}
+
+fn build(integration: Integration) -> anyhow::Result<reqwest::Client> {
+    let prefix = integration.prefix();
+    // The integration's own setting, else the shared one
+    let setting = |name: &str| -> Option<(String, String)> {
+        [prefix, "OUTBOUND"].into_iter().find_map(|p| {
+            let var = format!("{}_{}", p, name);
+            std::env::var(&var).ok().filter(|v| !v.is_empty()).map(|value| (var, value))
+        })
+    };
+    let read = |(var, path): (String, String)| {
+        std::fs::read(&path).map_err(|e| anyhow::anyhow!("{} ({}): {}", var, path, e))
+    };
+
+    let mut builder = reqwest::Client::builder();
+    if let Some((var, url)) = setting("PROXY") {
+        let proxy = reqwest::Proxy::all(&url).map_err(|e| anyhow::anyhow!("{}: {}", var, e))?;
+        info!("{} requests go through proxy {}", prefix, url);
+        builder = builder.proxy(proxy);
+    }
+    if let Some(ca) = setting("CA_CERT") {
+        let var = ca.0.clone();
+        let pem = read(ca)?;
+        let certificates = pem_certificates(&pem);
+        if certificates.is_empty() {
+            anyhow::bail!("{} holds no PEM certificates", var);
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&certificate).map_err(|e| anyhow::anyhow!("{}: {}", var, e))?);
+        }
+    }
+    match (setting("CLIENT_CERT"), setting("CLIENT_KEY")) {
+        (Some(cert), Some(key)) => {
+            let var = cert.0.clone();
+            let identity = reqwest::Identity::from_pkcs8_pem(&read(cert)?, &read(key)?).map_err(|e| anyhow::anyhow!("{}: {}", var, e))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => anyhow::bail!("{0}_CLIENT_CERT and {0}_CLIENT_KEY must be set together", prefix),
+    }
+    Ok(builder.build()?)
+}
+
+// Each certificate in a PEM bundle, since a CA file often holds a chain
+fn pem_certificates(pem: &[u8]) -> Vec<Vec<u8>> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+    let text = String::from_utf8_lossy(pem);
+    let mut certificates = Vec::new();
+    let mut rest = text.as_ref();
+    while let Some(start) = rest.find(BEGIN) {
+        let Some(length) = rest[start..].find(END) else {
+            break;
+        };
+        let end = start + length + END.len();
+        certificates.push(rest.as_bytes()[start..end].to_vec());
+        rest = &rest[end..];
+    }
+    certificates
+}
@@ -0,0 +1,410 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    catalog, create_proof_record,
+    errors::{AppError, ErrorCode},
+    origin, read_public_outputs, request_id, short_id, spawn_proof_generation, verify_proof_async, AppState,
+    ProofMetadata, ProofOptions, ProofStatus, DEFAULT_STEP_SIZE,
+};
+
+// Pipelines: proofs run one after another, where a step's arguments can use
+// the public output of the steps before it. A step names a catalog
+// function, a module or a saved template (see templates.rs):
+//
+//   POST /api/pipelines
+//   {"name": "onboard", "steps": [
+//     {"name": "kyc", "function": "prove_kyc", "arguments": ["12345", "1"], "require": "1"},
+//     {"name": "where", "function": "prove_location", "arguments": ["london", "{{kyc}}"]},
+//     {"name": "gate", "template": "release-funds", "arguments": ["{{where}}"], "verify": true}
+//   ]}
+//
+// "{{kyc}}" is the kyc step's output: the first integer in its public.json,
+// or "{{kyc.a.0}}" for the value at a path in it. A step with "require" only
+// passes when its output equals that value, and with "verify" only once its
+// proof verifies, so a last step can gate an action on everything before
+// it. The first step that fails stops the pipeline; the rest are skipped.
+//
+//   GET /api/pipelines        pipelines, newest first
+//   GET /api/pipelines/:id    one pipeline with each step's status
+
+const PIPELINES_DB_FILE: &str = "./pipelines_db.json";
+const MAX_STEPS: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Complete,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PipelineStatus {
+    Running,
+    Complete,
+    Failed,
+}
+
+// A step as requested
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StepSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    // Literals or {{step}} references; a template's own when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require: Option<String>,
+    #[serde(default)]
+    pub verify: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StepRecord {
+    #[serde(flatten)]
+    pub spec: StepSpec,
+    pub status: StepStatus,
+    // Arguments as proved, references resolved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_arguments: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PipelineRecord {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub status: PipelineStatus,
+    pub steps: Vec<StepRecord>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct PipelineRequest {
+    #[serde(default)]
+    name: Option<String>,
+    steps: Vec<StepSpec>,
+}
+
+// Pipelines, persisted like the proof store
+pub struct PipelineStore {
+    pipelines: Mutex<HashMap<String, PipelineRecord>>,
+}
+
+impl PipelineStore {
+    pub async fn load() -> Self {
+        let mut pipelines: HashMap<String, PipelineRecord> = match tokio::fs::read_to_string(PIPELINES_DB_FILE).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Failed to load pipelines from {}: {}", PIPELINES_DB_FILE, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        // Pipelines don't survive a restart; ones that were running have failed
+        for pipeline in pipelines.values_mut().filter(|p| p.status == PipelineStatus::Running) {
+            pipeline.status = PipelineStatus::Failed;
+            for step in pipeline.steps.iter_mut() {
+                match step.status {
+                    StepStatus::Running => {
+                        step.status = StepStatus::Failed;
+                        step.error = Some("The server restarted while this step ran".to_string());
+                        step.error_code = Some(ErrorCode::Internal);
+                    }
+                    StepStatus::Pending => step.status = StepStatus::Skipped,
+                    _ => {}
+                }
+            }
+        }
+        PipelineStore { pipelines: Mutex::new(pipelines) }
+    }
+
+    async fn update(&self, id: &str, apply: impl FnOnce(&mut PipelineRecord)) {
+        let mut pipelines = self.pipelines.lock().await;
+        if let Some(pipeline) = pipelines.get_mut(id) {
+            apply(pipeline);
+            pipeline.updated_at = Utc::now();
+            save(&pipelines).await;
+        }
+    }
+}
+
+async fn save(pipelines: &HashMap<String, PipelineRecord>) {
+    let result = match serde_json::to_string_pretty(pipelines) {
+        Ok(json) => tokio::fs::write(PIPELINES_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save pipelines to disk: {}", e);
+    }
+}
+
+// A {{step}} or {{step.path}} reference: the step name and the path
+fn reference(argument: &str) -> Option<(&str, Vec<&str>)> {
+    let inner = argument.trim().strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    let mut parts = inner.split('.');
+    let step = parts.next().filter(|step| !step.is_empty())?;
+    Some((step, parts.collect()))
+}
+
+// The first integer in a step's public outputs, or the one at `path`
+fn output_value(public: &Value, path: &[&str]) -> Option<String> {
+    let mut value = public;
+    for key in path {
+        value = match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            Value::Object(fields) => fields.get(*key)?,
+            _ => return None,
+        };
+    }
+    first_integer(value)
+}
+
+fn first_integer(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => n.as_i64().map(|n| n.to_string()),
+        Value::String(s) => s.trim().parse::<i64>().ok().map(|n| n.to_string()),
+        Value::Array(items) => items.iter().find_map(first_integer),
+        Value::Object(fields) => fields.values().find_map(first_integer),
+        _ => None,
+    }
+}
+
+// Check the request before anything runs: step names are unique and
+// references only point back
+async fn check(state: &AppState, request: &PipelineRequest) -> Result<(), AppError> {
+    let invalid = |message: String| Err(AppError::new(ErrorCode::InvalidArguments, message));
+    if request.steps.is_empty() || request.steps.len() > MAX_STEPS {
+        return invalid(format!("A pipeline has 1 to {} steps", MAX_STEPS));
+    }
+    for (i, step) in request.steps.iter().enumerate() {
+        let name_ok = !step.name.is_empty() && step.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !name_ok {
+            return invalid(format!("Step {} needs a name of letters, digits, '-' or '_'", i + 1));
+        }
+        if request.steps[..i].iter().any(|earlier| earlier.name == step.name) {
+            return invalid(format!("Two steps are named {}", step.name));
+        }
+        let targets = [&step.function, &step.wasm_file, &step.template].iter().filter(|t| t.is_some()).count();
+        if targets != 1 {
+            return invalid(format!("Step {} needs exactly one of function, wasm_file or template", step.name));
+        }
+        if let Some(template) = &step.template {
+            if state.templates.get(template).await.is_none() {
+                return Err(AppError::new(ErrorCode::NotFound, format!("No template named {}", template)));
+            }
+        }
+        if let Some(function) = &step.function {
+            if catalog::find(function).is_none() {
+                return Err(AppError::new(ErrorCode::UnknownFunction, format!("Unknown function: {}", function)));
+            }
+        }
+        for argument in step.arguments.iter().flatten() {
+            if let Some((target, _)) = reference(argument) {
+                if !request.steps[..i].iter().any(|earlier| earlier.name == target) {
+                    return invalid(format!("Step {} refers to {}, which isn't an earlier step", step.name, target));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Module, function, argument templates and step size of a step
+async fn target(state: &AppState, spec: &StepSpec) -> Result<(String, String, Vec<String>, u64), AppError> {
+    if let Some(name) = &spec.template {
+        let template = state.templates.get(name).await
+            .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No template named {}", name)))?;
+        let arguments = spec.arguments.clone().unwrap_or(template.arguments);
+        return Ok((template.wasm_file, template.function, arguments, spec.step_size.unwrap_or(template.step_size)));
+    }
+    let (wasm_file, function) = match (&spec.function, &spec.wasm_file) {
+        (Some(function), _) => {
+            let wasm_file = catalog::wasm_file(function)
+                .ok_or_else(|| AppError::new(ErrorCode::UnknownFunction, format!("Unknown function: {}", function)))?;
+            (wasm_file, function.clone())
+        }
+        (None, Some(wasm_file)) => (wasm_file.clone(), "main".to_string()),
+        (None, None) => return Err(AppError::new(ErrorCode::InvalidArguments, "The step has nothing to prove")),
+    };
+    Ok((wasm_file, function, spec.arguments.clone().unwrap_or_default(), spec.step_size.unwrap_or(DEFAULT_STEP_SIZE)))
+}
+
+// Run one step to completion; returns its output and public outputs
+async fn run_step(
+    state: &AppState,
+    pipeline_id: &str,
+    index: usize,
+    spec: &StepSpec,
+    outputs: &HashMap<String, Value>,
+) -> Result<(Option<String>, Value), AppError> {
+    let (wasm_file, function, arguments, step_size) = target(state, spec).await?;
+    let arguments = arguments
+        .iter()
+        .map(|argument| match reference(argument) {
+            Some((step, path)) => outputs.get(step).and_then(|public| output_value(public, &path)).ok_or_else(|| {
+                AppError::new(ErrorCode::InvalidArguments, format!("Step {} has no output at {}", step, argument.trim()))
+            }),
+            None => Ok(argument.clone()),
+        })
+        .collect::<Result<Vec<String>, AppError>>()?;
+
+    state.flags.check_module(&wasm_file)?;
+    let wasm_path = catalog::validate_request(&state.wasm_dir, &wasm_file, &arguments, step_size)?;
+    let encoded = catalog::encode_module_arguments(&wasm_file, &arguments)?;
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function,
+        arguments: arguments.clone(),
+        step_size,
+        statement: None,
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), None, ProofOptions::default()).await;
+    state.pipelines.update(pipeline_id, |pipeline| {
+        let step = &mut pipeline.steps[index];
+        step.resolved_arguments = arguments;
+        step.proof_id = Some(proof_id.clone());
+    }).await;
+    let _ = spawn_proof_generation(state, &proof_id, metadata, encoded).await;
+
+    let status = state.proof_store.read(&proof_id, |p| p.status.clone()).await;
+    match status {
+        Some(ProofStatus::Complete) => {}
+        Some(ProofStatus::Failed(error)) => return Err(error),
+        _ => return Err(AppError::new(ErrorCode::ProofNotReady, "The proof did not complete")),
+    }
+    if spec.verify {
+        let verification = verify_proof_async(state.clone(), proof_id.clone(), None).await?;
+        if !verification.is_valid {
+            let message = verification.error.unwrap_or_else(|| "The proof did not verify".to_string());
+            return Err(AppError::new(verification.error_code.unwrap_or(ErrorCode::VerifierMismatch), message));
+        }
+    }
+
+    let public = read_public_outputs(state, &proof_id).await.unwrap_or(Value::Null);
+    let output = output_value(&public, &[]);
+    if let Some(required) = &spec.require {
+        if output.as_deref() != Some(required.trim()) {
+            return Err(AppError::new(
+                ErrorCode::InvalidArguments,
+                format!("Step {} output {} where {} is required", spec.name, output.as_deref().unwrap_or("nothing"), required.trim()),
+            ));
+        }
+    }
+    Ok((output, public))
+}
+
+// Run the steps in order, stopping at the first that fails
+async fn execute(state: AppState, id: String) {
+    let specs: Vec<StepSpec> = state.pipelines.pipelines.lock().await
+        .get(&id)
+        .map(|p| p.steps.iter().map(|step| step.spec.clone()).collect())
+        .unwrap_or_default();
+    let mut outputs: HashMap<String, Value> = HashMap::new();
+    for (index, spec) in specs.iter().enumerate() {
+        state.pipelines.update(&id, |pipeline| pipeline.steps[index].status = StepStatus::Running).await;
+        match run_step(&state, &id, index, spec, &outputs).await {
+            Ok((output, public)) => {
+                outputs.insert(spec.name.clone(), public);
+                state.pipelines.update(&id, |pipeline| {
+                    let step = &mut pipeline.steps[index];
+                    step.status = StepStatus::Complete;
+                    step.output = output;
+                }).await;
+            }
+            Err(error) => {
+                warn!("Pipeline {} failed at step {}: {}", short_id(&id), spec.name, error.message);
+                state.pipelines.update(&id, |pipeline| {
+                    let step = &mut pipeline.steps[index];
+                    step.status = StepStatus::Failed;
+                    step.error = Some(error.message);
+                    step.error_code = Some(error.code);
+                    for later in pipeline.steps[index + 1..].iter_mut() {
+                        later.status = StepStatus::Skipped;
+                    }
+                    pipeline.status = PipelineStatus::Failed;
+                }).await;
+                return;
+            }
+        }
+    }
+    state.pipelines.update(&id, |pipeline| pipeline.status = PipelineStatus::Complete).await;
+    info!("Pipeline {} complete: {} step(s)", short_id(&id), specs.len());
+}
+
+async fn start(state: &AppState, request: PipelineRequest) -> Result<PipelineRecord, AppError> {
+    check(state, &request).await?;
+    let now = Utc::now();
+    let pipeline = PipelineRecord {
+        id: Uuid::new_v4().to_string(),
+        name: request.name,
+        status: PipelineStatus::Running,
+        steps: request.steps.into_iter().map(|spec| StepRecord {
+            spec,
+            status: StepStatus::Pending,
+            resolved_arguments: Vec::new(),
+            proof_id: None,
+            output: None,
+            error: None,
+            error_code: None,
+        }).collect(),
+        created_at: now,
+        updated_at: now,
+    };
+    {
+        let mut pipelines = state.pipelines.pipelines.lock().await;
+        pipelines.insert(pipeline.id.clone(), pipeline.clone());
+        save(&pipelines).await;
+    }
+    info!("Pipeline {} started with {} step(s)", short_id(&pipeline.id), pipeline.steps.len());
+    tokio::spawn(request_id::inherit(origin::inherit(execute(state.clone(), pipeline.id.clone())).in_current_span()));
+    Ok(pipeline)
+}
+
+pub async fn start_handler(State(state): State<AppState>, Json(request): Json<PipelineRequest>) -> Response {
+    match start(&state, request).await {
+        Ok(pipeline) => Json(json!({ "success": true, "pipeline": pipeline })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> Json<Value> {
+    let mut pipelines: Vec<PipelineRecord> = state.pipelines.pipelines.lock().await.values().cloned().collect();
+    pipelines.sort_by_key(|pipeline| std::cmp::Reverse(pipeline.created_at));
+    Json(json!({ "success": true, "pipelines": pipelines }))
+}
+
+pub async fn get_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.pipelines.pipelines.lock().await.get(&id).cloned() {
+        Some(pipeline) => Json(json!({ "success": true, "pipeline": pipeline })).into_response(),
+        None => AppError::new(ErrorCode::NotFound, format!("No pipeline {}", id)).into_response(),
+    }
+}
@@ -0,0 +1,128 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+use std::{path::Path, time::Duration};
+
+use crate::{
+    flags::Flag,
+    outbound::{self, Integration},
+    AppState,
+};
+
+// Kubernetes-style probes.
+//
+// `/healthz` is liveness: it answers as long as the process can serve
+// requests and never looks at dependencies, so an LLM outage doesn't get
+// the pod restarted.
+//
+// `/readyz` is readiness: 200 when this instance can do useful work, 503
+// otherwise, with every check and its detail in the body so the failing one
+// is obvious.
+
+const LANGCHAIN_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({ "status": "alive" }))
+}
+
+fn check(ok: bool, detail: impl Into<Value>) -> Value {
+    json!({ "ok": ok, "detail": detail.into() })
+}
+
+// Proof and verification databases loaded and being saved, and the proofs
+// directory writable
+async fn storage(state: &AppState) -> Value {
+    if !state.storage_errors.is_empty() {
+        return check(false, state.storage_errors.join("; "));
+    }
+    if let Some(e) = state.proof_flusher.last_error() {
+        return check(false, format!("Failed to save proofs: {}", e));
+    }
+    let probe = Path::new(&state.proofs_dir).join(".readyz");
+    match tokio::fs::write(&probe, b"").await {
+        Ok(()) => {
+            tokio::fs::remove_file(&probe).await.ok();
+            check(true, "loaded")
+        }
+        Err(e) => check(false, format!("{} is not writable: {}", state.proofs_dir, e)),
+    }
+}
+
+fn zkengine(state: &AppState) -> Value {
+    if !Path::new(&state.zkengine_binary).is_file() {
+        return check(false, format!("zkEngine binary not found at {}", state.zkengine_binary));
+    }
+    if !Path::new(&state.wasm_dir).is_dir() {
+        return check(false, format!("WASM directory not found at {}", state.wasm_dir));
+    }
+    check(true, state.zkengine_binary.as_str())
+}
+
+// Proofs start as soon as they're requested, so work is always accepted;
+// the detail shows how much is in flight
+async fn queue(state: &AppState) -> Value {
+    let in_flight = state.proof_store.count_indexed(|p| p.status.in_progress()).await;
+    check(true, format!("{} proofs in flight", in_flight))
+}
+
+// The intent provider must be usable unless the local parser can stand in
+async fn llm(state: &AppState) -> Value {
+    if !state.flags.enabled(Flag::LlmRouting) {
+        return check(true, "LLM routing turned off; using the local parser");
+    }
+    let reachable = match state.intent_provider.name() {
+        "langchain" => {
+            let response = outbound::client(Integration::LangChain)
+                .get(format!("{}/health", state.config.langchain_url()))
+                .timeout(LANGCHAIN_PROBE_TIMEOUT)
+                .send()
+                .await;
+            match response {
+                Ok(response) if response.status().is_success() => Ok(()),
+                Ok(response) => Err(format!("LangChain service returned {}", response.status())),
+                Err(e) => Err(format!("LangChain service unreachable: {}", e)),
+            }
+        }
+        // Hosted providers have no cheap health call; trust the circuit breaker
+        name => match state.llm_breaker.status()["state"].as_str() {
+            Some("open") => Err(format!("{} circuit breaker is open", name)),
+            _ => Ok(()),
+        },
+    };
+    match reachable {
+        Ok(()) => check(true, format!("{} reachable", state.intent_provider.name())),
+        Err(error) if state.llm_local_fallback => check(true, format!("{}; using local parser fallback", error)),
+        Err(error) => check(false, error),
+    }
+}
+
+// The shared database, in cluster mode
+async fn cluster(state: &AppState) -> Option<Value> {
+    let cluster = state.cluster.as_ref()?;
+    Some(match cluster.status().await {
+        Ok(detail) => check(true, detail),
+        Err(error) => check(false, error),
+    })
+}
+
+pub async fn readyz(State(state): State<AppState>) -> Response {
+    let mut checks = json!({
+        "storage": storage(&state).await,
+        "zkengine": zkengine(&state),
+        "queue": queue(&state).await,
+        "llm": llm(&state).await,
+    });
+    if let Some(cluster) = cluster(&state).await {
+        checks["cluster"] = cluster;
+    }
+    let ready = checks.as_object().into_iter().flatten().all(|(_, c)| c["ok"] == true);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": checks,
+    });
+    (status, Json(body)).into_response()
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::{cluster, verifier, AppState, ProofMetadata, ProofRecord, ProofStatus};
+
+// Reuse of completed proofs. A request to prove exactly what an earlier
+// proof proved (the same module, function, arguments and step size) gets
+// that proof back instead of minutes of zkEngine time, as long as its proof
+// file is still there and hashes to what was recorded. The module is matched
+// by the sha256 its signature records, so a module rebuilt under the same
+// name is a miss. Expired and revoked proofs are never reused.
+//
+//   PROOF_CACHE   false or 0 always proves afresh (default on)
+//
+// Proofs started from the REST, JSON-RPC and gRPC APIs and from chat are
+// reused; requests with an expiry of their own always get a new proof.
+
+pub struct ProofCache {
+    enabled: bool,
+}
+
+impl ProofCache {
+    pub fn from_env() -> Self {
+        ProofCache {
+            enabled: std::env::var("PROOF_CACHE").map(|v| v != "false" && v != "0").unwrap_or(true),
+        }
+    }
+
+    // An intact completed proof of the same statement, newest first, with
+    // the request's tags added to it
+    pub async fn reuse(&self, state: &AppState, metadata: &ProofMetadata, tags: &[String]) -> Option<ProofRecord> {
+        if !self.enabled {
+            return None;
+        }
+        let wasm_hash = verifier::sha256_file(Path::new(&metadata.wasm_path)).await.ok()?;
+        let mut candidates = state.proof_store
+            .filter_map(|p| {
+                let same = matches!(p.status, ProofStatus::Complete)
+                    && p.validity.is_current()
+                    && p.metadata.function == metadata.function
+                    && p.metadata.arguments == metadata.arguments
+                    && p.metadata.step_size == metadata.step_size
+                    && p.signature.as_ref().is_some_and(|s| s.wasm_hash == wasm_hash);
+                same.then(|| p.clone())
+            })
+            .await;
+        candidates.sort_by_key(|p| std::cmp::Reverse(p.timestamp));
+        for proof in candidates {
+            if !intact(&proof).await {
+                continue;
+            }
+            info!("Proof {} already proves this; reusing it", proof.id);
+            if tags.iter().all(|tag| proof.tags.contains(tag)) {
+                return Some(proof);
+            }
+            let record = state.proof_store.update(&proof.id, |proof| {
+                for tag in tags {
+                    if !proof.tags.contains(tag) {
+                        proof.tags.push(tag.clone());
+                    }
+                }
+                proof.clone()
+            }).await?;
+            state.proof_flusher.mark_dirty();
+            cluster::store_proof(state, &record).await;
+            return Some(record);
+        }
+        None
+    }
+}
+
+// The proof file exists and still hashes to the recorded hash
+async fn intact(proof: &ProofRecord) -> bool {
+    let Some(file_path) = &proof.file_path else {
+        return false;
+    };
+    match verifier::sha256_file(Path::new(file_path)).await {
+        Ok(hash) if hash == proof.metrics.file_hash => true,
+        Ok(_) => {
+            warn!("Proof {} no longer matches its recorded hash; not reusing it", proof.id);
+            false
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use crate::{
+    schema::{self, Layout},
+    ProofRecord, ProofStatus, PROOFS_DB_FILE,
+};
+
+// The proof database on disk: one JSON file per proof record in
+// ./proofs_db, and an index (./proofs_db/index.json) with the ID, status,
+// timestamp and the few fields looked up across proofs (name, parent, chat
+// session) of every proof. Only the index is read at startup; records are
+// read when they're needed (see proof_store.rs), so startup time and memory
+// don't grow with the size of the records.
+//
+// A record with no index entry, such as one `zkp-agentkit prove` stored
+// while a server was rewriting the index, is added to the index when it
+// is next loaded.
+//
+// A proofs_db.json from before this layout is split into it on first start
+// and renamed to proofs_db.json.migrated. Records and the index are
+// versioned (see schema.rs).
+
+pub const PROOFS_DB_DIR: &str = "./proofs_db";
+const INDEX_FILE: &str = "index.json";
+
+// What is kept in memory for every proof
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexEntry {
+    pub id: String,
+    pub status: ProofStatus,
+    pub timestamp: DateTime<Utc>,
+    // So names resolve without reading records; see proof_names.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    // So lineages are found without reading records; see lineage.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    // So a chat session's proofs are found without reading records
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    // Set on entries from an index that predates a field, see schema.rs
+    #[serde(default, skip_serializing)]
+    rebuild: bool,
+}
+
+impl IndexEntry {
+    pub fn of(proof: &ProofRecord) -> Self {
+        IndexEntry {
+            id: proof.id.clone(),
+            status: proof.status.clone(),
+            timestamp: proof.timestamp,
+            name: proof.name.clone(),
+            parent_id: proof.parent_id.clone(),
+            session_id: proof.session_id.clone(),
+            rebuild: false,
+        }
+    }
+}
+
+fn record_path(id: &str) -> anyhow::Result<PathBuf> {
+    // IDs are UUIDs; anything that could leave the directory is refused
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        anyhow::bail!("invalid proof ID {:?}", id);
+    }
+    Ok(Path::new(PROOFS_DB_DIR).join(format!("{}.json", id)))
+}
+
+// Write through a temporary file so a crash never leaves half a file
+async fn write_atomically(path: &Path, contents: Vec<u8>) -> std::io::Result<()> {
+    let temporary = path.with_extension("json.tmp");
+    tokio::fs::write(&temporary, contents).await?;
+    tokio::fs::rename(&temporary, path).await
+}
+
+// The index, with any records missing from it, migrating a proofs_db.json
+// first if there is one. `link` is applied to the records being migrated.
+pub async fn load_index(link: impl FnOnce(&mut HashMap<String, ProofRecord>)) -> anyhow::Result<Vec<IndexEntry>> {
+    let index_path = Path::new(PROOFS_DB_DIR).join(INDEX_FILE);
+    if !index_path.exists() {
+        if let Some(mut proofs) = schema::PROOF.load::<HashMap<String, ProofRecord>>(Layout::Map, PROOFS_DB_FILE).await? {
+            link(&mut proofs);
+            save_all(&proofs).await?;
+            tokio::fs::rename(PROOFS_DB_FILE, format!("{}.migrated", PROOFS_DB_FILE)).await?;
+            info!("Moved {} proofs from {} to {}", proofs.len(), PROOFS_DB_FILE, PROOFS_DB_DIR);
+        }
+    }
+    let mut index: Vec<IndexEntry> = schema::PROOF_INDEX.load(Layout::List, &index_path).await?.unwrap_or_default();
+    let mut rebuilt = 0;
+    for entry in index.iter_mut().filter(|entry| entry.rebuild) {
+        match load(&entry.id).await {
+            Ok(Some(record)) => {
+                *entry = IndexEntry::of(&record);
+                rebuilt += 1;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Keeping the old index entry of unreadable proof record {}: {}", entry.id, e),
+        }
+    }
+    if rebuilt > 0 {
+        save_index(&index).await?;
+        info!("Rebuilt {} entries of {} from their records", rebuilt, INDEX_FILE);
+    }
+    let indexed: HashSet<String> = index.iter().map(|entry| entry.id.clone()).collect();
+    let unindexed: Vec<String> = record_ids().await?.into_iter().filter(|id| !indexed.contains(id)).collect();
+    let listed = index.len();
+    for id in &unindexed {
+        match load(id).await {
+            Ok(Some(record)) => index.push(IndexEntry::of(&record)),
+            Ok(None) => {}
+            Err(e) => warn!("Skipping unreadable proof record {}: {}", id, e),
+        }
+    }
+    if index.len() > listed {
+        info!("Added {} proofs missing from {} to it", index.len() - listed, INDEX_FILE);
+    }
+    Ok(index)
+}
+
+// IDs of the records in the directory
+async fn record_ids() -> anyhow::Result<Vec<String>> {
+    let mut entries = match tokio::fs::read_dir(PROOFS_DB_DIR).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut ids = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if let Some(id) = name.strip_suffix(".json").filter(|id| name != INDEX_FILE && record_path(id).is_ok()) {
+            ids.push(id.to_string());
+        }
+    }
+    Ok(ids)
+}
+
+pub async fn save_index(entries: &[IndexEntry]) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(PROOFS_DB_DIR).await?;
+    write_atomically(&Path::new(PROOFS_DB_DIR).join(INDEX_FILE), schema::PROOF_INDEX.encode(&entries)?).await?;
+    Ok(())
+}
+
+// One record; None if there is no file for it
+pub async fn load(id: &str) -> anyhow::Result<Option<ProofRecord>> {
+    match tokio::fs::read(record_path(id)?).await {
+        Ok(json) => Ok(Some(schema::PROOF.decode(Layout::One, &json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn save(record: &ProofRecord) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(PROOFS_DB_DIR).await?;
+    write_atomically(&record_path(&record.id)?, schema::PROOF.encode(record)?).await?;
+    Ok(())
+}
+
+pub async fn delete(id: &str) -> anyhow::Result<()> {
+    match tokio::fs::remove_file(record_path(id)?).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// Every record, for the offline CLI and seeding a cluster database
+pub async fn load_all() -> anyhow::Result<HashMap<String, ProofRecord>> {
+    let mut proofs = HashMap::new();
+    for entry in load_index(|_| {}).await? {
+        if let Some(record) = load(&entry.id).await? {
+            proofs.insert(record.id.clone(), record);
+        }
+    }
+    Ok(proofs)
+}
+
+// Write every record and the index
+async fn save_all(proofs: &HashMap<String, ProofRecord>) -> anyhow::Result<()> {
+    for record in proofs.values() {
+        save(record).await?;
+    }
+    let index: Vec<IndexEntry> = proofs.values().map(IndexEntry::of).collect();
+    save_index(&index).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_proof;
+
+    #[test]
+    fn entries_from_older_indexes_are_rebuilt() {
+        let old = serde_json::json!({
+            "schema_version": 2,
+            "data": [{ "id": "a", "status": "queued", "timestamp": Utc::now() }],
+        });
+        let index: Vec<IndexEntry> = schema::PROOF_INDEX.decode(Layout::List, &serde_json::to_vec(&old).unwrap()).unwrap();
+        assert!(index[0].rebuild);
+
+        let mut proof = test_proof("b");
+        proof.session_id = Some("chat".to_string());
+        let saved = schema::PROOF_INDEX.encode(&[IndexEntry::of(&proof)]).unwrap();
+        let index: Vec<IndexEntry> = schema::PROOF_INDEX.decode(Layout::List, &saved).unwrap();
+        assert!(!index[0].rebuild);
+        assert_eq!(index[0].session_id.as_deref(), Some("chat"));
+    }
+}
@@ -0,0 +1,144 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::info;
+
+use crate::{
+    cluster,
+    errors::{AppError, ErrorCode},
+    retention, short_id, AppState, ProofRecord,
+};
+
+// Names for proofs, e.g. "nightly-kyc-2024-06-01", that work anywhere a
+// proof ID does: REST paths, JSON-RPC, gRPC, the tools and chat ("verify
+// proof nightly-kyc-2024-06-01"). A name is given with `"name"` when a
+// proof is started, or set later:
+//
+//   PUT /api/proofs/:id/name   {"name": "nightly-kyc-2024-06-01"}, or null to remove it
+//   GET /api/names             every name and its proof ID
+//   GET /api/names/:name       the proof with that name
+//
+// A server has one namespace: a name belongs to at most one proof, and can
+// be reused once that proof is renamed or deleted. Names are 3 to 64
+// lowercase letters, digits, '-', '_' or '.', start with a letter and have
+// at least one digit or separator, so chat can tell them from words. They
+// are matched case-insensitively, after full IDs and before ID prefixes.
+
+// Names are handed out one at a time, so two requests can't both take one.
+// In a cluster each instance hands out its own; they share the proofs.
+#[derive(Default)]
+pub struct ProofNames {
+    assigning: Mutex<()>,
+}
+
+pub fn normalize(name: &str) -> Result<String, AppError> {
+    let name = name.trim().to_lowercase();
+    let digit_or_separator = |c: char| c.is_ascii_digit() || matches!(c, '-' | '_' | '.');
+    let valid = (3..=64).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || digit_or_separator(c))
+        && name.contains(digit_or_separator);
+    if !valid {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("Invalid proof name '{}'", name)));
+    }
+    Ok(name)
+}
+
+// Whether a word in chat could be a proof name
+pub fn looks_like_name(word: &str) -> bool {
+    normalize(word).is_ok()
+}
+
+// A request's `name`; nothing when it is absent or null
+pub fn parse(value: &Value) -> Result<Option<String>, AppError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::String(name) => normalize(name).map(Some),
+        _ => Err(AppError::new(ErrorCode::InvalidArguments, "name must be a string")),
+    }
+}
+
+// The proof with this name
+pub async fn find(state: &AppState, name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    state.proof_store.ids(|entry| entry.name.as_deref() == Some(name.as_str())).await.into_iter().next()
+}
+
+// Check that `name` is free, or already `proof_id`'s. The name stays
+// reserved until the guard is dropped, which should be once it is on the
+// proof.
+pub async fn claim<'a>(state: &'a AppState, name: &str, proof_id: Option<&str>) -> Result<MutexGuard<'a, ()>, AppError> {
+    let guard = state.proof_names.assigning.lock().await;
+    match find(state, name).await {
+        Some(owner) if Some(owner.as_str()) != proof_id => Err(AppError::new(
+            ErrorCode::NameTaken,
+            format!("Proof {} is already named {}", short_id(&owner), name),
+        )),
+        _ => Ok(guard),
+    }
+}
+
+// Give a proof a name, or take its name away
+pub async fn rename(state: &AppState, id: &str, name: Option<String>) -> Result<ProofRecord, AppError> {
+    let id = retention::resolve_proof_id(state, id).await?;
+    let _claim = match &name {
+        Some(name) => Some(claim(state, name, Some(&id)).await?),
+        None => None,
+    };
+    let record = state.proof_store.update(&id, |proof| {
+        proof.name = name;
+        proof.clone()
+    }).await.ok_or_else(AppError::proof_not_found)?;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    match &record.name {
+        Some(name) => info!("Proof {} named {}", short_id(&id), name),
+        None => info!("Proof {} unnamed", short_id(&id)),
+    }
+    Ok(record)
+}
+
+#[derive(Deserialize)]
+pub struct RenameRequest {
+    #[serde(default)]
+    name: Value,
+}
+
+pub async fn rename_handler(State(state): State<AppState>, Path(id): Path<String>, Json(request): Json<RenameRequest>) -> Response {
+    let renamed = match parse(&request.name) {
+        Ok(name) => rename(&state, &id, name).await,
+        Err(error) => Err(error),
+    };
+    match renamed {
+        Ok(proof) => Json(json!({ "success": true, "proof_id": proof.id, "name": proof.name })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let names: BTreeMap<String, String> = state.proof_store
+        .filter_map_indexed(|entry| Some((entry.name.clone()?, entry.id.clone())))
+        .await
+        .into_iter()
+        .collect();
+    Json(json!({ "success": true, "count": names.len(), "names": names }))
+}
+
+pub async fn get_handler(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    let proof = match find(&state, &name).await {
+        Some(id) => state.proof_store.get(&id).await,
+        None => None,
+    };
+    match proof {
+        Some(proof) => Json(json!({ "success": true, "proof": proof })).into_response(),
+        None => {
+            let error = AppError::new(ErrorCode::ProofNotFound, format!("No proof is named {}", name));
+            error.into_response()
+        }
+    }
+}
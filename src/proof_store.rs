@@ -0,0 +1,565 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::{RwLock, RwLockWriteGuard};
+use tracing::{error, warn};
+
+use crate::{
+    proof_db::{self, IndexEntry},
+    ProofRecord,
+};
+
+// Proof records by ID, split over shards with a lock each, so listing
+// proofs, status updates from workers and WebSocket handlers don't queue on
+// one lock. Reads share a shard; an update only holds the shard its proof
+// is in. Nothing holds more than one shard at a time, and guards never
+// leave this module, so callers can't hold a lock across an await. Whole
+// store views (snapshot, filter_map, ...) visit the shards one after the
+// other and so aren't a single point in time, which is fine for listings
+// and stats.
+//
+// Every proof's index entry (ID, status, timestamp, name, parent, session)
+// is always in memory; the full record is read from proofs_db (see
+// proof_db.rs) the first time it's needed. After each save the least
+// recently used records beyond PROOF_STORE_RESIDENT (default 5000) are
+// dropped from memory again, never one with unsaved changes. Queries on the
+// index alone (count_indexed, ids, filter_map_indexed) never read from disk;
+// the rest read the records that aren't in memory, so they narrow by index
+// entry first where they can (filter_map_where, remove_where) or read only
+// the page they answer with (newest). In cluster mode every record stays in
+// memory, as the shared database is where they're kept.
+
+const SHARDS: usize = 16;
+const DEFAULT_RESIDENT: usize = 5000;
+
+struct Slot {
+    entry: IndexEntry,
+    // None until it's read from disk, and after eviction
+    body: Option<ProofRecord>,
+    // Last use, for eviction
+    used: AtomicU64,
+}
+
+type Shard = HashMap<String, Slot>;
+
+pub struct ProofStore {
+    shards: Vec<RwLock<Shard>>,
+    hasher: RandomState,
+    // Records live in proofs_db and may be dropped from memory
+    backed: bool,
+    resident: usize,
+    clock: AtomicU64,
+    // Proofs changed since the last save; a proof not in the store was removed
+    changed: Mutex<HashSet<String>>,
+}
+
+impl ProofStore {
+    fn empty(backed: bool) -> Self {
+        ProofStore {
+            shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+            backed,
+            resident: std::env::var("PROOF_STORE_RESIDENT").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_RESIDENT),
+            clock: AtomicU64::new(0),
+            changed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Every record in memory, not saved to proofs_db (cluster mode)
+    pub fn new(proofs: HashMap<String, ProofRecord>) -> Self {
+        let store = ProofStore::empty(false);
+        for (id, proof) in proofs {
+            let slot = store.slot(IndexEntry::of(&proof), Some(proof));
+            // Not shared yet, so the lock is always free
+            store.shard(&id).try_write().expect("new store is unshared").insert(id, slot);
+        }
+        store
+    }
+
+    // Records read from proofs_db as they're needed
+    pub fn indexed(index: Vec<IndexEntry>) -> Self {
+        let store = ProofStore::empty(true);
+        for entry in index {
+            let id = entry.id.clone();
+            let slot = store.slot(entry, None);
+            store.shard(&id).try_write().expect("new store is unshared").insert(id, slot);
+        }
+        store
+    }
+
+    fn shard(&self, id: &str) -> &RwLock<Shard> {
+        &self.shards[self.hasher.hash_one(id) as usize % SHARDS]
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn slot(&self, entry: IndexEntry, body: Option<ProofRecord>) -> Slot {
+        Slot { entry, body, used: AtomicU64::new(self.tick()) }
+    }
+
+    fn touch(&self, slot: &Slot) {
+        slot.used.store(self.tick(), Ordering::Relaxed);
+    }
+
+    fn mark_changed(&self, id: &str) {
+        if self.backed {
+            self.changed.lock().unwrap().insert(id.to_string());
+        }
+    }
+
+    // A record that isn't in memory
+    async fn fetch(&self, id: &str) -> Option<ProofRecord> {
+        if !self.backed {
+            return None;
+        }
+        match proof_db::load(id).await {
+            Ok(Some(record)) => Some(record),
+            Ok(None) => {
+                warn!("Proof {} is in the index but has no record", id);
+                None
+            }
+            Err(e) => {
+                error!("Failed to read proof {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    // The shard `id` is in, write-locked, with its record in memory; None
+    // when there is no such proof. The record is read with no lock held.
+    async fn loaded(&self, id: &str) -> Option<RwLockWriteGuard<'_, Shard>> {
+        let mut fetched = None;
+        loop {
+            let mut shard = self.shard(id).write().await;
+            let slot = shard.get_mut(id)?;
+            if slot.body.is_none() {
+                match fetched.take() {
+                    Some(record) => slot.body = Some(record),
+                    None => {
+                        drop(shard);
+                        fetched = Some(self.fetch(id).await?);
+                        continue;
+                    }
+                }
+            }
+            self.touch(slot);
+            return Some(shard);
+        }
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ProofRecord> {
+        self.read(id, ProofRecord::clone).await
+    }
+
+    // Read part of a proof without copying the whole record
+    pub async fn read<R>(&self, id: &str, f: impl FnOnce(&ProofRecord) -> R) -> Option<R> {
+        {
+            let shard = self.shard(id).read().await;
+            let slot = shard.get(id)?;
+            if let Some(body) = &slot.body {
+                self.touch(slot);
+                return Some(f(body));
+            }
+        }
+        let shard = self.loaded(id).await?.downgrade();
+        shard.get(id).and_then(|slot| slot.body.as_ref()).map(f)
+    }
+
+    pub async fn insert(&self, proof: ProofRecord) {
+        let id = proof.id.clone();
+        let slot = self.slot(IndexEntry::of(&proof), Some(proof));
+        let mut shard = self.shard(&id).write().await;
+        shard.insert(id.clone(), slot);
+        self.mark_changed(&id);
+    }
+
+    // Change a proof in place; None when there is no such proof
+    pub async fn update<R>(&self, id: &str, f: impl FnOnce(&mut ProofRecord) -> R) -> Option<R> {
+        let mut shard = self.loaded(id).await?;
+        let slot = shard.get_mut(id)?;
+        let body = slot.body.as_mut()?;
+        let result = f(body);
+        slot.entry = IndexEntry::of(body);
+        self.mark_changed(id);
+        Some(result)
+    }
+
+    pub async fn remove(&self, id: &str) -> Option<ProofRecord> {
+        self.remove_if(id, |_| Ok::<(), ()>(())).await.ok().flatten()
+    }
+
+    // Remove a proof if `allow` says so, checked under the same lock.
+    // Ok(None) when there is no such proof.
+    pub async fn remove_if<E>(&self, id: &str, allow: impl FnOnce(&ProofRecord) -> Result<(), E>) -> Result<Option<ProofRecord>, E> {
+        let Some(mut shard) = self.loaded(id).await else {
+            return Ok(None);
+        };
+        if let Some(body) = shard.get(id).and_then(|slot| slot.body.as_ref()) {
+            allow(body)?;
+        }
+        let removed = shard.remove(id).and_then(|slot| slot.body);
+        self.mark_changed(id);
+        Ok(removed)
+    }
+
+    // Remove every proof `remove` picks among those whose index entry `pick`
+    // selects, returning them. Each is checked again as it's removed.
+    pub async fn remove_where(
+        &self,
+        pick: impl FnMut(&IndexEntry) -> bool,
+        mut remove: impl FnMut(&ProofRecord) -> bool,
+    ) -> Vec<ProofRecord> {
+        let mut ids = Vec::new();
+        self.visit(pick, |p| {
+            if remove(p) {
+                ids.push(p.id.clone());
+            }
+        })
+        .await;
+        let mut removed = Vec::new();
+        for id in ids {
+            let still = self.remove_if(&id, |p| if remove(p) { Ok(()) } else { Err(()) }).await;
+            removed.extend(still.ok().flatten());
+        }
+        removed
+    }
+
+    // Replace the whole store, e.g. with the cluster's copy
+    pub async fn replace(&self, proofs: HashMap<String, ProofRecord>) {
+        let mut by_shard: Vec<Shard> = (0..SHARDS).map(|_| HashMap::new()).collect();
+        for (id, proof) in proofs {
+            let slot = self.slot(IndexEntry::of(&proof), Some(proof));
+            by_shard[self.hasher.hash_one(&id) as usize % SHARDS].insert(id, slot);
+        }
+        for (shard, slots) in self.shards.iter().zip(by_shard) {
+            let mut shard = shard.write().await;
+            let old = std::mem::replace(&mut *shard, slots);
+            for id in old.keys().chain(shard.keys()) {
+                self.mark_changed(id);
+            }
+        }
+    }
+
+    // Every record whose index entry `pick` selects: those in memory under
+    // their shard's read lock, the rest read from disk with no lock held
+    async fn visit(&self, mut pick: impl FnMut(&IndexEntry) -> bool, mut f: impl FnMut(&ProofRecord)) {
+        for shard in &self.shards {
+            let unloaded: Vec<String> = {
+                let shard = shard.read().await;
+                let mut unloaded = Vec::new();
+                for slot in shard.values().filter(|slot| pick(&slot.entry)) {
+                    match &slot.body {
+                        Some(body) => f(body),
+                        None => unloaded.push(slot.entry.id.clone()),
+                    }
+                }
+                unloaded
+            };
+            for id in unloaded {
+                if let Some(record) = self.fetch(&id).await {
+                    f(&record);
+                }
+            }
+        }
+    }
+
+    pub async fn filter_map<T>(&self, f: impl FnMut(&ProofRecord) -> Option<T>) -> Vec<T> {
+        self.filter_map_where(|_| true, f).await
+    }
+
+    // filter_map over the proofs whose index entry `pick` selects; no other
+    // record is read
+    pub async fn filter_map_where<T>(
+        &self,
+        pick: impl FnMut(&IndexEntry) -> bool,
+        mut f: impl FnMut(&ProofRecord) -> Option<T>,
+    ) -> Vec<T> {
+        let mut found = Vec::new();
+        self.visit(pick, |p| found.extend(f(p))).await;
+        found
+    }
+
+    // The newest `limit` proofs whose index entry `pick` selects, newest
+    // first. Only those records are read.
+    pub async fn newest(&self, mut pick: impl FnMut(&IndexEntry) -> bool, limit: Option<usize>) -> Vec<ProofRecord> {
+        let mut picked = self.filter_map_indexed(|entry| pick(entry).then(|| (entry.timestamp, entry.id.clone()))).await;
+        picked.sort_unstable_by(|a, b| b.cmp(a));
+        if let Some(limit) = limit {
+            picked.truncate(limit);
+        }
+        let mut proofs = Vec::with_capacity(picked.len());
+        for (_, id) in picked {
+            proofs.extend(self.get(&id).await);
+        }
+        proofs
+    }
+
+    // Copies of every proof
+    pub async fn snapshot(&self) -> Vec<ProofRecord> {
+        self.filter_map(|p| Some(p.clone())).await
+    }
+
+    // Proofs whose index entry `f` picks, without reading any record
+    pub async fn count_indexed(&self, mut f: impl FnMut(&IndexEntry) -> bool) -> usize {
+        let mut count = 0;
+        for shard in &self.shards {
+            count += shard.read().await.values().filter(|slot| f(&slot.entry)).count();
+        }
+        count
+    }
+
+    // IDs of the proofs whose index entry `f` picks
+    pub async fn ids(&self, mut f: impl FnMut(&IndexEntry) -> bool) -> Vec<String> {
+        self.filter_map_indexed(|entry| f(entry).then(|| entry.id.clone())).await
+    }
+
+    // filter_map over the index entries alone
+    pub async fn filter_map_indexed<T>(&self, mut f: impl FnMut(&IndexEntry) -> Option<T>) -> Vec<T> {
+        let mut found = Vec::new();
+        for shard in &self.shards {
+            found.extend(shard.read().await.values().filter_map(|slot| f(&slot.entry)));
+        }
+        found
+    }
+
+    pub async fn len(&self) -> usize {
+        self.count_indexed(|_| true).await
+    }
+
+    // Whether changes are saved to proofs_db
+    pub fn is_backed(&self) -> bool {
+        self.backed
+    }
+
+    // What changed since the last call, to be saved: the record of each
+    // changed proof, None for removed ones
+    pub async fn take_changes(&self) -> Vec<(String, Option<ProofRecord>)> {
+        let ids: Vec<String> = self.changed.lock().unwrap().iter().cloned().collect();
+        let mut changes = Vec::with_capacity(ids.len());
+        for id in ids {
+            // Under the shard lock, so the record can't be evicted in between
+            let shard = self.shard(&id).read().await;
+            self.changed.lock().unwrap().remove(&id);
+            match shard.get(&id) {
+                Some(slot) => changes.extend(slot.body.clone().map(|body| (id, Some(body)))),
+                None => changes.push((id, None)),
+            }
+        }
+        changes
+    }
+
+    // Changes that failed to save, to be tried again
+    pub fn restore_changes(&self, ids: impl IntoIterator<Item = String>) {
+        self.changed.lock().unwrap().extend(ids);
+    }
+
+    pub async fn index(&self) -> Vec<IndexEntry> {
+        let mut index = Vec::new();
+        for shard in &self.shards {
+            index.extend(shard.read().await.values().map(|slot| slot.entry.clone()));
+        }
+        index
+    }
+
+    // Drop the least recently used saved records beyond the limit from memory
+    pub async fn evict(&self) {
+        if !self.backed {
+            return;
+        }
+        let mut in_memory = Vec::new();
+        for shard in &self.shards {
+            let shard = shard.read().await;
+            in_memory.extend(shard.values()
+                .filter(|slot| slot.body.is_some())
+                .map(|slot| (slot.used.load(Ordering::Relaxed), slot.entry.id.clone())));
+        }
+        if in_memory.len() <= self.resident {
+            return;
+        }
+        in_memory.sort_unstable();
+        let excess = in_memory.len() - self.resident;
+        for (_, id) in in_memory.into_iter().take(excess) {
+            let mut shard = self.shard(&id).write().await;
+            // Checked under the shard lock, which changes take
+            if self.changed.lock().unwrap().contains(&id) {
+                continue;
+            }
+            if let Some(slot) = shard.get_mut(&id) {
+                slot.body = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_proof, ProofStatus};
+    use std::sync::Arc;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_updates_are_not_lost() {
+        let store = Arc::new(ProofStore::new(HashMap::new()));
+        for i in 0..8 {
+            store.insert(test_proof(&format!("proof-{}", i))).await;
+        }
+        let mut tasks = Vec::new();
+        for task in 0..16 {
+            let store = store.clone();
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..50 {
+                    let id = format!("proof-{}", task % 8);
+                    store.update(&id, |proof| proof.tags.push(task.to_string())).await.unwrap();
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+        for i in 0..8 {
+            let tags = store.read(&format!("proof-{}", i), |proof| proof.tags.len()).await;
+            assert_eq!(tags, Some(100));
+        }
+    }
+
+    #[tokio::test]
+    async fn remove_if_checks_under_the_lock() {
+        let store = ProofStore::new(HashMap::new());
+        store.insert(test_proof("a")).await;
+        let kept = store.remove_if("a", |proof| if proof.tags.is_empty() { Err("untagged") } else { Ok(()) }).await;
+        assert_eq!(kept.err(), Some("untagged"));
+        assert!(store.get("a").await.is_some());
+
+        store.update("a", |proof| proof.tags.push("old".to_string())).await;
+        let removed = store.remove_if("a", |proof| if proof.tags.is_empty() { Err("untagged") } else { Ok(()) }).await;
+        assert_eq!(removed.ok().flatten().map(|proof| proof.id), Some("a".to_string()));
+        assert_eq!(store.len().await, 0);
+        assert!(matches!(store.remove_if("a", |_| Err("unreachable")).await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn replace_swaps_every_shard() {
+        let store = ProofStore::new(HashMap::new());
+        for id in ["a", "b", "c"] {
+            store.insert(test_proof(id)).await;
+        }
+        let mut replacement = test_proof("d");
+        replacement.status = ProofStatus::Complete;
+        store.replace(HashMap::from([("d".to_string(), replacement)])).await;
+
+        let mut ids = store.ids(|_| true).await;
+        ids.sort();
+        assert_eq!(ids, ["d"]);
+        assert_eq!(store.count_indexed(|entry| matches!(entry.status, ProofStatus::Complete)).await, 1);
+    }
+
+    // IDs whose record is in memory
+    fn resident(store: &ProofStore) -> Vec<String> {
+        let mut ids: Vec<String> = store.shards.iter()
+            .flat_map(|shard| {
+                let shard = shard.try_read().unwrap();
+                shard.values().filter(|slot| slot.body.is_some()).map(|slot| slot.entry.id.clone()).collect::<Vec<_>>()
+            })
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn updates_keep_the_index_in_step() {
+        let store = ProofStore::new(HashMap::new());
+        store.insert(test_proof("a")).await;
+        store.update("a", |proof| {
+            proof.status = ProofStatus::Complete;
+            proof.name = Some("first".to_string());
+            proof.session_id = Some("chat".to_string());
+        }).await;
+        let index = store.index().await;
+        assert_eq!(index.len(), 1);
+        assert!(matches!(index[0].status, ProofStatus::Complete));
+        assert_eq!(index[0].name.as_deref(), Some("first"));
+        assert_eq!(index[0].session_id.as_deref(), Some("chat"));
+    }
+
+    #[tokio::test]
+    async fn newest_answers_a_page_from_the_index() {
+        let store = ProofStore::new(HashMap::new());
+        let now = chrono::Utc::now();
+        for (i, id) in ["old", "middle", "new", "other"].into_iter().enumerate() {
+            let mut proof = test_proof(id);
+            proof.timestamp = now + chrono::Duration::seconds(i as i64);
+            proof.session_id = (id != "other").then(|| "chat".to_string());
+            store.insert(proof).await;
+        }
+        let page = store.newest(|entry| entry.session_id.as_deref() == Some("chat"), Some(2)).await;
+        let ids: Vec<&str> = page.iter().map(|proof| proof.id.as_str()).collect();
+        assert_eq!(ids, ["new", "middle"]);
+    }
+
+    #[tokio::test]
+    async fn eviction_keeps_unsaved_and_recently_used_records() {
+        let mut store = ProofStore::indexed(Vec::new());
+        store.resident = 1;
+        for id in ["a", "b", "c"] {
+            store.insert(test_proof(id)).await;
+        }
+        store.evict().await;
+        assert_eq!(resident(&store), ["a", "b", "c"], "unsaved records stay");
+
+        assert_eq!(store.take_changes().await.len(), 3);
+        store.read("b", |_| ()).await;
+        store.evict().await;
+        assert_eq!(resident(&store), ["b"]);
+        assert_eq!(store.len().await, 3, "evicted proofs stay in the index");
+        assert_eq!(store.ids(|entry| entry.id == "c").await, ["c"]);
+    }
+
+    #[tokio::test]
+    async fn evicted_records_are_read_back_from_proofs_db() {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut proof = test_proof(&id);
+        proof.tags.push("kept".to_string());
+        proof_db::save(&proof).await.unwrap();
+
+        let mut store = ProofStore::indexed(vec![IndexEntry::of(&proof)]);
+        store.resident = 0;
+        assert!(resident(&store).is_empty());
+        let tags = store.read(&id, |proof| proof.tags.clone()).await;
+        store.update(&id, |proof| proof.tags.push("changed".to_string())).await;
+        let changes = store.take_changes().await;
+        store.evict().await;
+        let evicted = resident(&store).is_empty();
+
+        proof_db::delete(&id).await.unwrap();
+        let _ = std::fs::remove_dir(proof_db::PROOFS_DB_DIR);
+        assert_eq!(tags, Some(vec!["kept".to_string()]));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].1.as_ref().map(|proof| proof.tags.len()), Some(2));
+        assert!(evicted);
+    }
+
+    #[tokio::test]
+    async fn filtered_visits_only_see_what_the_index_picks() {
+        let store = ProofStore::new(HashMap::new());
+        let mut complete = test_proof("complete");
+        complete.status = ProofStatus::Complete;
+        store.insert(complete).await;
+        store.insert(test_proof("queued")).await;
+        let mut seen = Vec::new();
+        let found = store
+            .filter_map_where(|entry| matches!(entry.status, ProofStatus::Complete), |proof| {
+                seen.push(proof.id.clone());
+                Some(proof.id.clone())
+            })
+            .await;
+        assert_eq!(found, ["complete"]);
+        assert_eq!(seen, ["complete"]);
+    }
+}
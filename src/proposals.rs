@@ -0,0 +1,104 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::llm::ProofIntent;
+
+// How long a proposal waits for the client's answer
+const PROPOSAL_TTL_SECS: i64 = 300;
+
+// What happens once a proposal is approved
+pub enum PendingAction {
+    // `verify_after` runs the proof as a prove-then-verify workflow
+    Proof { intent: ProofIntent, verify_after: bool },
+    DeleteProof { proof_id: String },
+    ArchiveProofs { older_than_days: i64 },
+}
+
+// An expensive proof or destructive command waiting for the client to
+// approve it
+pub struct Proposal {
+    pub session_id: String,
+    pub action: PendingAction,
+    pub created_at: DateTime<Utc>,
+}
+
+// Proofs above PROOF_CONFIRM_STEP_SIZE (default 500) or with an estimated
+// run time above PROOF_CONFIRM_ESTIMATED_SECS (default 300) need the
+// client's approval before they are started. Setting a threshold to 0
+// disables that check.
+pub struct ProposalStore {
+    step_size_threshold: u64,
+    estimated_secs_threshold: f64,
+    pending: Mutex<HashMap<String, Proposal>>,
+}
+
+impl ProposalStore {
+    pub fn from_env() -> Self {
+        ProposalStore {
+            step_size_threshold: std::env::var("PROOF_CONFIRM_STEP_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(500),
+            estimated_secs_threshold: std::env::var("PROOF_CONFIRM_ESTIMATED_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300.0),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Why the intent needs approval, or `None` if it can start right away
+    pub fn confirmation_reason(&self, intent: &ProofIntent, estimated_secs: Option<f64>) -> Option<String> {
+        if self.step_size_threshold > 0 && intent.step_size > self.step_size_threshold {
+            return Some(format!(
+                "step size {} is above the limit of {}",
+                intent.step_size, self.step_size_threshold
+            ));
+        }
+        match estimated_secs {
+            Some(secs) if self.estimated_secs_threshold > 0.0 && secs > self.estimated_secs_threshold => Some(format!(
+                "estimated run time {:.0}s is above the limit of {:.0}s",
+                secs, self.estimated_secs_threshold
+            )),
+            _ => None,
+        }
+    }
+
+    pub async fn propose(&self, session_id: &str, action: PendingAction) -> String {
+        let id = Uuid::new_v4().to_string();
+        let mut pending = self.pending.lock().await;
+        pending.retain(|_, p| Utc::now() - p.created_at < Duration::seconds(PROPOSAL_TTL_SECS));
+        pending.insert(id.clone(), Proposal {
+            session_id: session_id.to_string(),
+            action,
+            created_at: Utc::now(),
+        });
+        id
+    }
+
+    // Remove and return a session's proposal: the given one, or its most
+    // recent one when no ID is given. Expired proposals are not returned.
+    pub async fn take(&self, session_id: &str, proposal_id: Option<&str>) -> Option<Proposal> {
+        let mut pending = self.pending.lock().await;
+        let id = match proposal_id {
+            Some(id) => id.to_string(),
+            None => pending.iter()
+                .filter(|(_, p)| p.session_id == session_id)
+                .max_by_key(|(_, p)| p.created_at)
+                .map(|(id, _)| id.clone())?,
+        };
+        if pending.get(&id)?.session_id != session_id {
+            return None;
+        }
+        pending.remove(&id)
+            .filter(|p| Utc::now() - p.created_at < Duration::seconds(PROPOSAL_TTL_SECS))
+    }
+
+    pub async fn has_pending(&self, session_id: &str) -> bool {
+        self.pending.lock().await.values().any(|p| {
+            p.session_id == session_id && Utc::now() - p.created_at < Duration::seconds(PROPOSAL_TTL_SECS)
+        })
+    }
+}
@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analytics::Stats,
+    auth::Identity,
+    errors::{AppError, ErrorCode},
+    events::Topic,
+    ProofRecord, ProofStatus, VerificationRecord,
+};
+
+// The envelope's version, kind and channel are shared with the client crate
+pub use zkp_agentkit_client::types::{Channel, MessageKind, PROTOCOL_VERSION};
+
+// Envelope sent to WebSocket clients:
+// {"version": 1, "channel": "event", "type": "message", "content": "...", "data": {"type": "proof_start", ...}, "seq": 7}
+// Older clients that only look at `content` and `data.type` keep working.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WsMessage {
+    // Envelopes from before versioning have neither a version nor a
+    // channel; they read as version 0 on the chat channel
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default = "unversioned_channel")]
+    pub channel: Channel,
+    #[serde(rename = "type")]
+    pub kind: MessageKind,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<WsEvent>,
+    // Routing target: only the matching WebSocket session receives the message.
+    // `None` marks a global event, delivered to sockets that opted in.
+    #[serde(skip)]
+    pub session_id: Option<String>,
+    // Monotonic sequence number assigned on publish, used for replay
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    // OpenTelemetry trace the message was produced under, when tracing is exported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    // Request (HTTP call or WebSocket message) that caused the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+fn unversioned_channel() -> Channel {
+    Channel::Chat
+}
+
+impl WsMessage {
+    pub fn new(content: impl Into<String>, data: Option<WsEvent>) -> Self {
+        let kind = match &data {
+            Some(WsEvent::Error { .. }) => MessageKind::Error,
+            Some(WsEvent::Subscription { .. }) => MessageKind::Subscription,
+            _ => MessageKind::Message,
+        };
+        let channel = match &data {
+            None | Some(WsEvent::Chat { .. } | WsEvent::ChatDelta { .. }) => Channel::Chat,
+            Some(_) => Channel::Event,
+        };
+        WsMessage {
+            version: PROTOCOL_VERSION,
+            channel,
+            kind,
+            content: content.into(),
+            data,
+            session_id: None,
+            seq: None,
+            trace_id: None,
+            request_id: None,
+        }
+    }
+
+    pub fn event(content: impl Into<String>, event: WsEvent) -> Self {
+        Self::new(content, Some(event))
+    }
+
+    // Address the message to a single session (`None` keeps it global)
+    pub fn for_session(mut self, session_id: Option<String>) -> Self {
+        self.session_id = session_id;
+        self
+    }
+}
+
+// Structured payload carried in `data`, tagged by `type`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsEvent {
+    Welcome {
+        session_id: String,
+        identity: Identity,
+    },
+    Chat {
+        session_id: String,
+        from_langchain: bool,
+        // Set when the reply was streamed first as `chat_delta` messages;
+        // this message then carries the complete text
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream_id: Option<String>,
+    },
+    // A fragment of a reply still being generated; `content` holds the new text
+    ChatDelta {
+        session_id: String,
+        stream_id: String,
+    },
+    Error {
+        error: String,
+        #[serde(default)]
+        code: ErrorCode,
+    },
+    Subscription {
+        subscription: serde_json::Value,
+    },
+    // The connection fell behind on a topic channel. Events after
+    // `resync_from` are replayed from the buffer; if `complete` is false the
+    // buffer no longer held all of them and the client should refetch state.
+    Lagged {
+        topic: Topic,
+        dropped: u64,
+        resync_from: u64,
+        complete: bool,
+    },
+    ProofStart {
+        proof_id: String,
+        function: String,
+        arguments: Vec<String>,
+        wasm_file: String,
+        step_size: u64,
+    },
+    // An expensive proof is waiting for the client to approve or deny it
+    ProofProposal {
+        proposal_id: String,
+        function: String,
+        arguments: Vec<String>,
+        step_size: u64,
+        estimated_secs: Option<f64>,
+        reason: String,
+    },
+    // A destructive chat command (delete, archive) waiting for confirmation
+    ActionProposal {
+        proposal_id: String,
+        action: String,
+        description: String,
+    },
+    // The proof moved to a new stage, or zkEngine reported another step
+    ProofProgress {
+        proof_id: String,
+        stage: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        step: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        total: Option<u64>,
+    },
+    ProofComplete {
+        proof_id: String,
+        status: String,
+        function: String,
+        arguments: Vec<String>,
+        step_size: u64,
+        time: f64,
+        size: f64,
+        hash: String,
+        // An earlier identical proof was reused rather than generated
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        cached: bool,
+    },
+    ProofFailed {
+        proof_id: String,
+        error: String,
+        #[serde(default)]
+        code: ErrorCode,
+    },
+    ProofCancelled {
+        proof_id: String,
+    },
+    ProofList {
+        proofs: Vec<ProofRecord>,
+    },
+    VerificationStart {
+        proof_id: String,
+    },
+    VerificationComplete {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        verification_id: Option<String>,
+        proof_id: String,
+        is_valid: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        verification_time_secs: Option<f64>,
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_code: Option<ErrorCode>,
+        // The result came from the verification cache
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        cached: bool,
+    },
+    VerificationList {
+        verifications: Vec<VerificationRecord>,
+    },
+    Stats {
+        stats: Stats,
+    },
+    ProofComparison {
+        comparison: serde_json::Value,
+    },
+    // A multi-step workflow (prove then verify) moved to a new stage
+    WorkflowUpdate {
+        workflow_id: String,
+        stage: String,
+        proof_id: Option<String>,
+        is_valid: Option<bool>,
+        error: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error_code: Option<ErrorCode>,
+    },
+    // Download link for an exported proof
+    ProofExport {
+        proof_id: String,
+        format: String,
+        url: String,
+    },
+}
+
+impl WsEvent {
+    pub fn error(error: AppError) -> Self {
+        WsEvent::Error { error: error.message, code: error.code }
+    }
+
+    pub fn progress(proof_id: &str, status: &ProofStatus) -> Self {
+        let (step, total) = match status {
+            ProofStatus::Proving { step, total } => (*step, *total),
+            _ => (None, None),
+        };
+        WsEvent::ProofProgress { proof_id: proof_id.to_string(), stage: status.name().to_string(), step, total }
+    }
+
+    pub fn verification_failed(proof_id: &str, error: AppError) -> Self {
+        WsEvent::VerificationComplete {
+            verification_id: None,
+            proof_id: proof_id.to_string(),
+            is_valid: false,
+            verification_time_secs: None,
+            error: Some(error.message),
+            error_code: Some(error.code),
+            cached: false,
+        }
+    }
+
+    // Ephemeral events are delivered live but not kept for replay
+    pub fn is_ephemeral(&self) -> bool {
+        matches!(self, WsEvent::ChatDelta { .. })
+    }
+
+    // Proof the event refers to, if any
+    pub fn proof_id(&self) -> Option<&str> {
+        match self {
+            WsEvent::ProofStart { proof_id, .. }
+            | WsEvent::ProofProgress { proof_id, .. }
+            | WsEvent::ProofComplete { proof_id, .. }
+            | WsEvent::ProofFailed { proof_id, .. }
+            | WsEvent::ProofCancelled { proof_id }
+            | WsEvent::ProofExport { proof_id, .. }
+            | WsEvent::VerificationStart { proof_id }
+            | WsEvent::VerificationComplete { proof_id, .. } => Some(proof_id),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn wire(message: &WsMessage) -> Value {
+        serde_json::to_value(message).unwrap()
+    }
+
+    fn proof_start() -> WsEvent {
+        WsEvent::ProofStart {
+            proof_id: "p1".to_string(),
+            function: "fibonacci".to_string(),
+            arguments: vec!["10".to_string()],
+            wasm_file: "fib.wat".to_string(),
+            step_size: 50,
+        }
+    }
+
+    #[test]
+    fn event_envelope_keeps_the_fields_old_clients_read() {
+        let message = WsMessage::event("Generating proof", proof_start()).for_session(Some("s1".to_string()));
+        assert_eq!(wire(&message), json!({
+            "version": PROTOCOL_VERSION,
+            "channel": "event",
+            "type": "message",
+            "content": "Generating proof",
+            "data": {
+                "type": "proof_start",
+                "proof_id": "p1",
+                "function": "fibonacci",
+                "arguments": ["10"],
+                "wasm_file": "fib.wat",
+                "step_size": 50,
+            },
+        }));
+    }
+
+    #[test]
+    fn protocol_version_is_1() {
+        assert_eq!(PROTOCOL_VERSION, 1);
+    }
+
+    #[test]
+    fn chat_and_error_envelopes() {
+        let chat = wire(&WsMessage::new("Hello", None));
+        assert_eq!(chat, json!({ "version": 1, "channel": "chat", "type": "message", "content": "Hello" }));
+
+        let error = wire(&WsMessage::event("No such proof", WsEvent::error(AppError::proof_not_found())));
+        assert_eq!(error["type"], "error");
+        assert_eq!(error["channel"], "event");
+        assert_eq!(error["data"], json!({ "type": "error", "error": "Proof not found", "code": "proof_not_found" }));
+    }
+
+    #[test]
+    fn sequence_and_request_ids_are_sent_only_when_set() {
+        let mut message = WsMessage::event("Verifying", WsEvent::VerificationStart { proof_id: "p1".to_string() });
+        assert!(wire(&message).get("seq").is_none());
+        message.seq = Some(7);
+        message.request_id = Some("r1".to_string());
+        let value = wire(&message);
+        assert_eq!(value["seq"], 7);
+        assert_eq!(value["request_id"], "r1");
+        assert!(value.get("session_id").is_none());
+    }
+
+    #[test]
+    fn event_tags() {
+        let tags = [
+            (WsEvent::ChatDelta { session_id: "s".into(), stream_id: "t".into() }, "chat_delta"),
+            (WsEvent::ProofCancelled { proof_id: "p".into() }, "proof_cancelled"),
+            (WsEvent::VerificationStart { proof_id: "p".into() }, "verification_start"),
+            (WsEvent::progress("p", &ProofStatus::Proving { step: Some(3), total: Some(10) }), "proof_progress"),
+            (WsEvent::verification_failed("p", AppError::proof_not_found()), "verification_complete"),
+            (WsEvent::ActionProposal { proposal_id: "a".into(), action: "delete".into(), description: "d".into() }, "action_proposal"),
+            (WsEvent::ProofExport { proof_id: "p".into(), format: "bundle".into(), url: "/u".into() }, "proof_export"),
+        ];
+        for (event, tag) in tags {
+            assert_eq!(serde_json::to_value(&event).unwrap()["type"], tag);
+        }
+    }
+
+    #[test]
+    fn progress_carries_stage_and_steps() {
+        let event = WsEvent::progress("p1", &ProofStatus::Proving { step: Some(3), total: Some(10) });
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({ "type": "proof_progress", "proof_id": "p1", "stage": "proving", "step": 3, "total": 10 })
+        );
+        let event = WsEvent::progress("p1", &ProofStatus::Queued);
+        assert_eq!(serde_json::to_value(&event).unwrap(), json!({ "type": "proof_progress", "proof_id": "p1", "stage": "queued" }));
+    }
+
+    #[test]
+    fn envelopes_round_trip() {
+        let message = WsMessage::event("Generating proof", proof_start());
+        let read: WsMessage = serde_json::from_value(wire(&message)).unwrap();
+        assert_eq!(wire(&read), wire(&message));
+    }
+
+    // As the server sent them before the envelope was versioned
+    #[test]
+    fn unversioned_envelopes_still_deserialize() {
+        let old = json!({
+            "type": "message",
+            "content": "Proof not found",
+            "data": { "type": "verification_complete", "proof_id": "p1", "is_valid": false, "error": "Proof not found" },
+        });
+        let message: WsMessage = serde_json::from_value(old).unwrap();
+        assert_eq!(message.version, 0);
+        assert_eq!(message.channel, Channel::Chat);
+        assert_eq!(message.kind, MessageKind::Message);
+        match message.data {
+            Some(WsEvent::VerificationComplete { proof_id, is_valid, error, error_code, cached, .. }) => {
+                assert_eq!(proof_id, "p1");
+                assert!(!is_valid);
+                assert_eq!(error.as_deref(), Some("Proof not found"));
+                assert_eq!(error_code, None);
+                assert!(!cached);
+            }
+            _ => panic!("expected verification_complete"),
+        }
+
+        let old = json!({ "type": "message", "content": "Generating", "data": {
+            "type": "proof_start", "proof_id": "p1", "function": "main", "arguments": ["10"], "wasm_file": "fib.wat", "step_size": 50,
+        }});
+        let message: WsMessage = serde_json::from_value(old).unwrap();
+        assert!(matches!(message.data, Some(WsEvent::ProofStart { step_size: 50, .. })));
+    }
+
+    #[test]
+    fn events_without_newer_fields_deserialize() {
+        let error: WsEvent = serde_json::from_value(json!({ "type": "error", "error": "boom" })).unwrap();
+        assert!(matches!(error, WsEvent::Error { code, .. } if code == ErrorCode::default()));
+
+        let complete: WsEvent = serde_json::from_value(json!({
+            "type": "proof_complete", "proof_id": "p1", "status": "complete", "function": "fibonacci",
+            "arguments": ["10"], "step_size": 50, "time": 1.5, "size": 0.2, "hash": "ab",
+        })).unwrap();
+        assert!(matches!(complete, WsEvent::ProofComplete { cached: false, .. }));
+
+        let chat: WsEvent = serde_json::from_value(json!({ "type": "chat", "session_id": "s1", "from_langchain": true })).unwrap();
+        assert!(matches!(chat, WsEvent::Chat { stream_id: None, .. }));
+    }
+}
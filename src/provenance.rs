@@ -0,0 +1,412 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    catalog, create_proof_record,
+    errors::{AppError, ErrorCode},
+    export, request_id, short_id, signing, spawn_proof_generation, verifier::sha256_hex,
+    AppState, ProofMetadata, ProofOptions, ProofStatus, DEFAULT_STEP_SIZE,
+};
+
+// Provenance for AI-generated content. A generator posts the content, or
+// its sha256, with the model that produced it:
+//
+//   POST /api/provenance  {"content": "...", "mime_type": "text/plain",
+//                          "model": {"provider": "openai", "name": "gpt-4o"},
+//                          "generated_at": "2025-01-01T00:00:00Z"}
+//
+// ("content_base64" for binary content, "content_hash" to keep the content
+// to yourself). Text is canonicalized before hashing: line endings become
+// \n and trailing whitespace is dropped, so a copy that went through an
+// editor still matches. The content hash and model metadata are committed
+// to, prove_ai_content is proved over the commitment, and once the proof is
+// done a manifest in the shape of a C2PA manifest is signed with the proof
+// signing key:
+//
+//   GET /api/provenance?content_hash=...   records, optionally for some content
+//   GET /api/provenance/:id                one record and its status
+//   GET /api/provenance/:id/manifest       the manifest, as a download
+//
+// The commitment is sha256 of these lines joined with '\n':
+//
+//   zkengine-agentkit-ai-content-v1
+//   <content_hash>    sha256 of the canonical content, hex
+//   <provider>
+//   <model>
+//   <model version>   empty when not given
+//   <generated_at>    RFC 3339
+//
+// and the manifest signature covers the lines:
+//
+//   zkengine-agentkit-provenance-v1
+//   <manifest id>
+//   <content_hash>
+//   <commitment>
+//   <proof id>
+//   <proof_hash>      sha256 of the proof file, hex
+//   <signed_at>       RFC 3339
+//   <issuer>          ID of the key that signed it
+
+const PROVENANCE_DB_FILE: &str = "./provenance_db.json";
+const COMMITMENT_PREFIX: &str = "zkengine-agentkit-ai-content-v1";
+const MANIFEST_PREFIX: &str = "zkengine-agentkit-provenance-v1";
+// IPTC digital source type for content made by a generative model
+const TRAINED_ALGORITHMIC_MEDIA: &str = "http://cv.iptc.org/newscodes/digitalsourcetype/trainedAlgorithmicMedia";
+
+// provider_id values prove_ai_content recognizes
+const PROVIDER_IDS: [(&str, u32); 2] = [("openai", 0x4F50454E), ("anthropic", 0x414E54)];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModelInfo {
+    pub provider: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ProvenanceStatus {
+    Proving,
+    Complete,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProvenanceRecord {
+    pub id: String,
+    pub content_hash: String,
+    pub commitment: String,
+    pub mime_type: String,
+    pub model: ModelInfo,
+    pub generated_at: DateTime<Utc>,
+    pub proof_id: String,
+    pub status: ProvenanceStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+}
+
+#[derive(Deserialize)]
+pub struct IngestRequest {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    content_base64: Option<String>,
+    #[serde(default)]
+    content_hash: Option<String>,
+    #[serde(default)]
+    mime_type: Option<String>,
+    model: ModelInfo,
+    #[serde(default)]
+    generated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    content_hash: Option<String>,
+}
+
+// Provenance records, persisted like the proof store
+pub struct ProvenanceStore {
+    records: Mutex<HashMap<String, ProvenanceRecord>>,
+}
+
+impl ProvenanceStore {
+    pub async fn load() -> Self {
+        let records = match tokio::fs::read_to_string(PROVENANCE_DB_FILE).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Failed to load provenance records from {}: {}", PROVENANCE_DB_FILE, e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        ProvenanceStore { records: Mutex::new(records) }
+    }
+
+    async fn update(&self, id: &str, apply: impl FnOnce(&mut ProvenanceRecord)) -> Option<ProvenanceRecord> {
+        let mut records = self.records.lock().await;
+        let record = records.get_mut(id).map(|record| {
+            apply(record);
+            record.clone()
+        })?;
+        save(&records).await;
+        Some(record)
+    }
+}
+
+async fn save(records: &HashMap<String, ProvenanceRecord>) {
+    let result = match serde_json::to_string_pretty(records) {
+        Ok(json) => tokio::fs::write(PROVENANCE_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save provenance records to disk: {}", e);
+    }
+}
+
+// Text as it is hashed: \n line endings, no trailing whitespace
+fn canonical_text(text: &str) -> String {
+    text.replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string()
+}
+
+fn content_hash(request: &IngestRequest) -> Result<String, AppError> {
+    let invalid = |message: &str| AppError::new(ErrorCode::InvalidArguments, message.to_string());
+    match (&request.content, &request.content_base64, &request.content_hash) {
+        (Some(text), None, None) => Ok(sha256_hex(canonical_text(text).as_bytes())),
+        (None, Some(encoded), None) => base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map(|bytes| sha256_hex(&bytes))
+            .map_err(|_| invalid("content_base64 isn't valid base64")),
+        (None, None, Some(hash)) => {
+            let hash = hash.trim().to_lowercase();
+            if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid("content_hash must be a sha256 hash in hex"));
+            }
+            Ok(hash)
+        }
+        _ => Err(invalid("Give exactly one of content, content_base64 or content_hash")),
+    }
+}
+
+fn commitment(content_hash: &str, model: &ModelInfo, generated_at: &str) -> String {
+    let lines = [
+        COMMITMENT_PREFIX,
+        content_hash,
+        &model.provider,
+        &model.name,
+        model.version.as_deref().unwrap_or_default(),
+        generated_at,
+    ];
+    sha256_hex(lines.join("\n").as_bytes())
+}
+
+// prove_ai_content's provider_id: the circuit's code for known providers,
+// otherwise derived from the name
+fn provider_id(provider: &str) -> u32 {
+    match PROVIDER_IDS.iter().find(|(name, _)| name.eq_ignore_ascii_case(provider)) {
+        Some((_, id)) => *id,
+        None => u32::from_str_radix(&sha256_hex(provider.to_lowercase().as_bytes())[..8], 16).unwrap_or(0) >> 1,
+    }
+}
+
+// prove_ai_content's content_hash: the first 31 bits of the commitment
+fn circuit_hash(commitment: &str) -> u32 {
+    (u32::from_str_radix(&commitment[..8], 16).unwrap_or(0) >> 1).max(1)
+}
+
+async fn ingest(state: &AppState, request: IngestRequest) -> Result<ProvenanceRecord, AppError> {
+    if request.model.provider.trim().is_empty() || request.model.name.trim().is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "model.provider and model.name are required"));
+    }
+    let content_hash = content_hash(&request)?;
+    let generated_at = request.generated_at.unwrap_or_else(Utc::now);
+    let commitment = commitment(&content_hash, &request.model, &generated_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    let wasm_file = catalog::wasm_file("prove_ai_content").unwrap_or_else(|| "prove_ai_content.wat".to_string());
+    let arguments = vec![circuit_hash(&commitment).to_string(), provider_id(&request.model.provider).to_string()];
+    let wasm_path = catalog::validate_request(&state.wasm_dir, &wasm_file, &arguments, DEFAULT_STEP_SIZE)?;
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function: "prove_ai_content".to_string(),
+        arguments: arguments.clone(),
+        step_size: DEFAULT_STEP_SIZE,
+        statement: None,
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), None, ProofOptions { tags: vec!["provenance".to_string()], ..Default::default() }).await;
+    let generation = spawn_proof_generation(state, &proof_id, metadata, arguments);
+
+    let record = ProvenanceRecord {
+        id: Uuid::new_v4().to_string(),
+        content_hash,
+        commitment,
+        mime_type: request.mime_type.unwrap_or_else(|| "text/plain".to_string()),
+        model: request.model,
+        generated_at,
+        proof_id: proof_id.clone(),
+        status: ProvenanceStatus::Proving,
+        created_at: Utc::now(),
+        manifest: None,
+        error: None,
+        error_code: None,
+    };
+    {
+        let mut records = state.provenance.records.lock().await;
+        records.insert(record.id.clone(), record.clone());
+        save(&records).await;
+    }
+    info!("Provenance {}: proving {} for content {}", short_id(&record.id), short_id(&proof_id), short_id(&record.content_hash));
+
+    let state = state.clone();
+    let id = record.id.clone();
+    tokio::spawn(request_id::inherit(async move {
+        let _ = generation.await;
+        finish(&state, &id, &proof_id).await;
+    }.in_current_span()));
+    Ok(record)
+}
+
+// Sign the manifest once the proof is done
+async fn finish(state: &AppState, id: &str, proof_id: &str) {
+    let proof = state.proof_store.get(proof_id).await;
+    let proof = match proof {
+        Some(proof) if matches!(proof.status, ProofStatus::Complete) => proof,
+        proof => {
+            let error = match proof.map(|p| p.status) {
+                Some(ProofStatus::Failed(error)) => error,
+                Some(_) => AppError::new(ErrorCode::ProofNotReady, "Proof did not complete"),
+                None => AppError::proof_not_found(),
+            };
+            warn!("Provenance {} failed: {}", short_id(id), error.message);
+            state.provenance.update(id, |record| {
+                record.status = ProvenanceStatus::Failed;
+                record.error = Some(error.message);
+                record.error_code = Some(error.code);
+            }).await;
+            return;
+        }
+    };
+
+    state.provenance.update(id, |record| {
+        record.manifest = Some(manifest(state, record, &proof.metrics.file_hash, proof.signature.as_ref()));
+        record.status = ProvenanceStatus::Complete;
+    }).await;
+    info!("Provenance {} complete: manifest signed for proof {}", short_id(id), short_id(proof_id));
+}
+
+fn manifest(state: &AppState, record: &ProvenanceRecord, proof_hash: &str, proof_signature: Option<&signing::ProofSignature>) -> Value {
+    let signed_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let (issuer, signature) = state.signer.sign_message(|key_id| {
+        [MANIFEST_PREFIX, &record.id, &record.content_hash, &record.commitment, &record.proof_id, proof_hash, &signed_at, key_id].join("\n")
+    });
+    let content_hash_b64 = hex_to_base64(&record.content_hash);
+    json!({
+        "claim_generator": format!("zkengine-agentkit/{}", env!("CARGO_PKG_VERSION")),
+        "instance_id": format!("xmp:iid:{}", record.id),
+        "format": record.mime_type,
+        "assertions": [
+            {
+                "label": "c2pa.actions",
+                "data": {
+                    "actions": [{
+                        "action": "c2pa.created",
+                        "when": record.generated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+                        "digitalSourceType": TRAINED_ALGORITHMIC_MEDIA,
+                        "softwareAgent": {
+                            "name": format!("{}/{}", record.model.provider, record.model.name),
+                            "version": record.model.version,
+                        },
+                    }],
+                },
+            },
+            {
+                "label": "c2pa.hash.data",
+                "data": { "alg": "sha256", "hash": content_hash_b64, "name": "content" },
+            },
+            {
+                "label": "org.zkengine.proof",
+                "data": {
+                    "function": "prove_ai_content",
+                    "proof_id": record.proof_id,
+                    "proof_hash": proof_hash,
+                    "commitment": record.commitment,
+                    "proof_signature": proof_signature,
+                    "proof_url": format!("/api/proofs/{}", record.proof_id),
+                    "bundle_url": export::download_url(&record.proof_id, export::ExportFormat::Bundle),
+                },
+            },
+        ],
+        "signature": {
+            "format": MANIFEST_PREFIX,
+            "alg": "ed25519",
+            "issuer": issuer,
+            "time": signed_at,
+            "value": signature,
+        },
+    })
+}
+
+fn hex_to_base64(hex: &str) -> String {
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .filter_map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn not_found(id: &str) -> Response {
+    AppError::new(ErrorCode::NotFound, format!("No provenance record {}", id)).into_response()
+}
+
+pub async fn ingest_handler(State(state): State<AppState>, Json(request): Json<IngestRequest>) -> Response {
+    match ingest(&state, request).await {
+        Ok(record) => Json(json!({ "success": true, "provenance": record })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn list_handler(State(state): State<AppState>, Query(query): Query<ListQuery>) -> Json<Value> {
+    let content_hash = query.content_hash.map(|hash| hash.trim().to_lowercase());
+    let mut records: Vec<ProvenanceRecord> = state.provenance.records.lock().await
+        .values()
+        .filter(|record| content_hash.as_deref().is_none_or(|hash| record.content_hash == hash))
+        .cloned()
+        .collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+    Json(json!({ "success": true, "provenance": records }))
+}
+
+pub async fn get_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match state.provenance.records.lock().await.get(&id).cloned() {
+        Some(record) => Json(json!({ "success": true, "provenance": record })).into_response(),
+        None => not_found(&id),
+    }
+}
+
+pub async fn manifest_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let record = state.provenance.records.lock().await.get(&id).cloned();
+    match record {
+        Some(ProvenanceRecord { manifest: Some(manifest), .. }) => {
+            let file_name = format!("manifest-{}.json", short_id(&id));
+            (
+                [
+                    (header::CONTENT_TYPE, "application/json".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+                ],
+                serde_json::to_string_pretty(&manifest).unwrap_or_default(),
+            )
+                .into_response()
+        }
+        Some(record) => {
+            let error = match record.status {
+                ProvenanceStatus::Failed => AppError::new(record.error_code.unwrap_or(ErrorCode::Internal), record.error.unwrap_or_default()),
+                _ => AppError::new(ErrorCode::ProofNotReady, "The proof is still being generated"),
+            };
+            error.into_response()
+        }
+        None => not_found(&id),
+    }
+}
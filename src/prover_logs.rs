@@ -0,0 +1,199 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::{convert::Infallible, io::SeekFrom, path::PathBuf, time::Duration};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use zkp_agentkit_client::types::CircuitStats;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    retention, AppState,
+};
+
+// zkEngine's stdout and stderr for each proof are written to
+// <proofs_dir>/<id>/prover.log while it runs. GET /api/proofs/:id/logs
+// returns the log (optionally only the last `tail` lines); with
+// `follow=true` it streams the log as server-sent events until the proof
+// finishes, like `tail -f`.
+
+const LOG_FILE: &str = "prover.log";
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn log_path(proofs_dir: &str, proof_id: &str) -> PathBuf {
+    PathBuf::from(proofs_dir).join(proof_id).join(LOG_FILE)
+}
+
+// The log without its first line, the command that was run
+pub fn output(log: &str) -> &str {
+    match log.strip_prefix("$ ").and_then(|l| l.split_once('\n')) {
+        Some((_, output)) => output,
+        None => log,
+    }
+}
+
+// Last `lines` lines of a log
+pub fn tail(log: &str, lines: usize) -> &str {
+    if lines == 0 {
+        return "";
+    }
+    match log.trim_end().rmatch_indices('\n').nth(lines - 1) {
+        Some((index, _)) => &log[index + 1..],
+        None => log,
+    }
+}
+
+// The last "step N/M" or "step N of M" in some of zkEngine's output: how
+// far proving has got, when zkEngine reports it
+pub fn step_progress(output: &str) -> Option<(u64, u64)> {
+    output.lines().rev().find_map(|line| {
+        let line = line.to_ascii_lowercase();
+        line.match_indices("step").find_map(|(index, _)| {
+            let rest = line[index + 4..].trim_start_matches(|c: char| c == 's' || c == ':' || c.is_whitespace());
+            let (step, rest) = leading_number(rest)?;
+            let rest = rest.trim_start();
+            let rest = rest.strip_prefix('/').or_else(|| rest.strip_prefix("of"))?;
+            let (total, _) = leading_number(rest.trim_start())?;
+            (total > 0 && step <= total).then_some((step, total))
+        })
+    })
+}
+
+fn leading_number(text: &str) -> Option<(u64, &str)> {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    Some((text[..end].parse().ok()?, &text[end..]))
+}
+
+// The size of a finished proof's circuit from zkEngine's output, for the
+// counts it reports as "<label>: N" or "N <label>". Folding rounds it
+// doesn't report are the last step progress's total, or else the steps
+// over the step size. `None` if nothing was found.
+pub fn circuit_stats(output: &str, step_size: u64) -> Option<CircuitStats> {
+    let steps = first_count(output, &["execution steps", "steps executed", "instructions executed", "trace length"]);
+    // Nova reports the primary circuit before the secondary one
+    let constraints = first_count(output, &["constraints"]);
+    let folding_rounds = first_count(output, &["folding steps", "folding rounds", "folds"])
+        .or_else(|| step_progress(output).map(|(_, total)| total))
+        .or_else(|| steps.filter(|_| step_size > 0).map(|steps| steps.div_ceil(step_size)));
+    let stats = CircuitStats { steps, constraints, folding_rounds };
+    (stats != CircuitStats::default()).then_some(stats)
+}
+
+fn first_count(output: &str, labels: &[&str]) -> Option<u64> {
+    output.lines().find_map(|line| {
+        let line = line.to_ascii_lowercase();
+        labels.iter().find_map(|label| {
+            let index = line.find(label)?;
+            let after = &line[index + label.len()..];
+            count_prefix(after.trim_start_matches(|c: char| c == ':' || c == '=' || c.is_whitespace()))
+                .or_else(|| count_prefix(after.split_once(':')?.1.trim_start()))
+                .or_else(|| {
+                    let before = line[..index].trim_end();
+                    count(&before[before.trim_end_matches(is_count_char).len()..])
+                })
+        })
+    })
+}
+
+// Counts may be written with separators, as in 1,234,567 or 1_234_567
+fn is_count_char(c: char) -> bool {
+    c.is_ascii_digit() || c == ',' || c == '_'
+}
+
+fn count_prefix(text: &str) -> Option<u64> {
+    count(&text[..text.find(|c: char| !is_count_char(c)).unwrap_or(text.len())])
+}
+
+fn count(text: &str) -> Option<u64> {
+    text.chars().filter(char::is_ascii_digit).collect::<String>().parse().ok()
+}
+
+// Whatever was written to the log after `offset`, moving `offset` past it
+pub async fn read_from(path: &std::path::Path, offset: &mut u64) -> String {
+    let mut bytes = Vec::new();
+    if let Ok(mut file) = tokio::fs::File::open(path).await {
+        if file.seek(SeekFrom::Start(*offset)).await.is_ok() {
+            *offset += file.read_to_end(&mut bytes).await.unwrap_or(0) as u64;
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[derive(Deserialize)]
+pub struct LogParams {
+    tail: Option<usize>,
+    #[serde(default)]
+    follow: bool,
+}
+
+async fn is_running(state: &AppState, proof_id: &str) -> bool {
+    state.proof_store
+        .read(proof_id, |p| p.status.in_progress())
+        .await
+        .unwrap_or(false)
+}
+
+pub async fn logs_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<LogParams>,
+) -> Response {
+    let proof_id = match retention::resolve_proof_id(&state, &id).await {
+        Ok(proof_id) => proof_id,
+        Err(error) => return error.into_response(),
+    };
+    let path = log_path(&state.proofs_dir, &proof_id);
+
+    if params.follow {
+        return follow(state, proof_id, path, params.tail).into_response();
+    }
+
+    let log = match tokio::fs::read_to_string(&path).await {
+        Ok(log) => log,
+        Err(_) => return AppError::new(ErrorCode::NotFound, "No prover log for this proof").into_response(),
+    };
+    let status = state.proof_store.read(&proof_id, |p| p.status.name()).await;
+    Json(json!({
+        "success": true,
+        "proof_id": proof_id,
+        "status": status,
+        "log": params.tail.map_or(log.as_str(), |lines| tail(&log, lines)),
+    })).into_response()
+}
+
+// Stream `log` events with new output as it is written, then an `end`
+// event carrying the proof's final status
+fn follow(state: AppState, proof_id: String, path: PathBuf, tail_lines: Option<usize>) -> impl IntoResponse {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut offset = 0;
+        let mut first = true;
+        loop {
+            // Check before reading so output written just before the proof
+            // finished is still sent
+            let running = is_running(&state, &proof_id).await;
+            let mut chunk = read_from(&path, &mut offset).await;
+            if first {
+                chunk = tail_lines.map_or(chunk.clone(), |lines| tail(&chunk, lines).to_string());
+                first = false;
+            }
+            if !chunk.is_empty() && tx.send(Event::default().event("log").data(chunk)).is_err() {
+                return;
+            }
+            if !running {
+                let status = state.proof_store.read(&proof_id, |p| p.status.name()).await.unwrap_or("deleted");
+                let _ = tx.send(Event::default().event("end").data(status));
+                return;
+            }
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+        }
+    });
+    let stream = UnboundedReceiverStream::new(rx).map(Ok::<_, Infallible>);
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,195 @@
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, HeaderValue, Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+use crate::{
+    auth,
+    errors::{AppError, ErrorCode},
+    AppState,
+};
+
+// Per-client rate limits for the HTTP API, so one misbehaving agent can't
+// monopolize the prover. Clients are keyed by their bearer token (the
+// `Authorization` header or `?token=`) or, without one, by IP address.
+// Starting a proof (including KYC attestations, provenance manifests,
+// templates and pipelines), or verifying a stored or uploaded one, draws
+// from its own, much smaller budget than reads; public share links count as
+// reads.
+// Over-budget requests get 429 with a `Retry-After` header.
+//
+//   RATE_LIMIT_READS_PER_MIN      reads per minute (default 120)
+//   RATE_LIMIT_READ_BURST         reads allowed back to back (default 60)
+//   RATE_LIMIT_GENERATE_PER_MIN   proof generations per minute (default 10)
+//   RATE_LIMIT_GENERATE_BURST     generations allowed back to back (default 5)
+//   RATE_LIMIT_TRUST_PROXY        key by the first X-Forwarded-For address (default false)
+//
+// A rate of 0 disables that budget. Probes, static files and the WebSocket
+// upgrade are never limited. The limits can be reloaded without a restart.
+
+// Forget idle clients once this many are tracked
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Class {
+    Read,
+    Generate,
+}
+
+impl Class {
+    fn of<B>(request: &Request<B>) -> Option<Self> {
+        let path = request.uri().path();
+        let starts_proof = matches!(path, "/api/proofs/generate" | "/api/verify/upload" | "/api/kyc" | "/api/provenance" | "/api/pipelines")
+            || (path.starts_with("/api/templates/") && path.ends_with("/run"))
+            || (path.starts_with("/api/proofs/") && path.ends_with("/verify"));
+        if request.method() == Method::POST && starts_proof {
+            Some(Class::Generate)
+        } else if path.starts_with("/api/") || path.starts_with("/mcp/") || path.starts_with("/share/") {
+            Some(Class::Read)
+        } else {
+            None
+        }
+    }
+}
+
+// Token bucket: holds up to `burst` tokens, refilled at `per_sec`
+#[derive(Clone, Copy)]
+struct Budget {
+    per_sec: f64,
+    burst: f64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Limits {
+    read: Option<Budget>,
+    generate: Option<Budget>,
+    trust_proxy: bool,
+}
+
+impl Limits {
+    fn from_env() -> Self {
+        let number = |name: &str, default: f64| {
+            std::env::var(name).ok().and_then(|v| v.parse::<f64>().ok()).filter(|v| *v >= 0.0).unwrap_or(default)
+        };
+        let budget = |rate: &str, rate_default: f64, burst: &str, burst_default: f64| {
+            let per_min = number(rate, rate_default);
+            (per_min > 0.0).then(|| Budget { per_sec: per_min / 60.0, burst: number(burst, burst_default).max(1.0) })
+        };
+        Limits {
+            read: budget("RATE_LIMIT_READS_PER_MIN", 120.0, "RATE_LIMIT_READ_BURST", 60.0),
+            generate: budget("RATE_LIMIT_GENERATE_PER_MIN", 10.0, "RATE_LIMIT_GENERATE_BURST", 5.0),
+            trust_proxy: std::env::var("RATE_LIMIT_TRUST_PROXY").is_ok_and(|v| v == "true" || v == "1"),
+        }
+    }
+}
+
+pub struct RateLimiter {
+    limits: RwLock<Limits>,
+    buckets: Mutex<HashMap<(Class, String), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn from_env() -> Self {
+        RateLimiter {
+            limits: RwLock::new(Limits::from_env()),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Re-read the limits. Clients keep their buckets, which are capped at
+    // the new burst on their next request.
+    pub fn reload(&self) {
+        *self.limits.write().unwrap() = Limits::from_env();
+    }
+
+    fn budget(&self, class: Class) -> Option<Budget> {
+        let limits = self.limits.read().unwrap();
+        match class {
+            Class::Read => limits.read,
+            Class::Generate => limits.generate,
+        }
+    }
+
+    // Take a token for `client`, or say how long until one is available
+    fn acquire(&self, class: Class, client: &str) -> Result<(), Duration> {
+        let Some(budget) = self.budget(class) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            // Clients that would be back at a full bucket lose nothing by being forgotten
+            buckets.retain(|(class, _), bucket| {
+                self.budget(*class).is_some_and(|b| {
+                    bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * b.per_sec < b.burst
+                })
+            });
+        }
+        let bucket = buckets
+            .entry((class, client.to_string()))
+            .or_insert(Bucket { tokens: budget.burst, updated: now });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * budget.per_sec).min(budget.burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / budget.per_sec))
+        }
+    }
+
+    // Bearer token (hashed, so keys aren't held in memory) or client IP
+    fn client_key<B>(&self, request: &Request<B>) -> String {
+        if let Some(token) = auth::request_token(request) {
+            let digest = Sha256::digest(token.as_bytes());
+            return format!("token:{:x}", digest)[..22].to_string();
+        }
+        format!("ip:{}", self.client_ip(request).unwrap_or_else(|| "unknown".to_string()))
+    }
+
+    // The first X-Forwarded-For address when proxies are trusted, otherwise
+    // the peer's
+    pub fn client_ip<B>(&self, request: &Request<B>) -> Option<String> {
+        let forwarded = self.limits.read().unwrap().trust_proxy
+            .then(|| request.headers().get("x-forwarded-for")?.to_str().ok()?.split(',').next().map(|ip| ip.trim().to_string()))
+            .flatten();
+        let peer = || request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip().to_string());
+        forwarded.or_else(peer)
+    }
+}
+
+pub async fn middleware<B>(State(state): State<AppState>, request: Request<B>, next: Next<B>) -> Response {
+    let Some(class) = Class::of(&request) else {
+        return next.run(request).await;
+    };
+    let client = state.rate_limiter.client_key(&request);
+    match state.rate_limiter.acquire(class, &client) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+            warn!("Rate limited {} ({:?}), retry in {}s", client, class, retry_after);
+            let error = AppError::new(
+                ErrorCode::RateLimited,
+                format!("Too many requests; retry in {} seconds", retry_after),
+            );
+            let mut response = error.into_response();
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            response
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::SecondsFormat;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::{
+    calculate_file_hash,
+    errors::{AppError, ErrorCode},
+    short_id, AppState, VerificationRecord,
+};
+
+// Signed receipts for verifications, for audit trails that outlive the
+// server's own records. Each verification gets one when it is stored,
+// signed with the proof signing key (see signing.rs), and it can be
+// downloaded at GET /api/verifications/:id/receipt. The signed message is
+// these lines joined with '\n':
+//
+//   zkengine-agentkit-verification-receipt-v1
+//   <verification id>
+//   <proof id>      empty for uploaded proofs
+//   <proof_hash>    sha256 of the proof file, hex
+//   <wasm_hash>     sha256 of the WASM module, hex; empty when unknown
+//   <step_size>
+//   <result>        valid or invalid
+//   <verifier>      ID of the key that signed the receipt
+//   <verified_at>   RFC 3339
+
+const RECEIPT_PREFIX: &str = "zkengine-agentkit-verification-receipt-v1";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Receipt {
+    pub format: String,
+    pub verification_id: String,
+    pub proof_id: String,
+    pub proof_hash: String,
+    pub wasm_hash: String,
+    pub step_size: u64,
+    pub result: String,
+    pub verifier: String,
+    pub verified_at: String,
+    pub algorithm: String,
+    // Base64
+    pub signature: String,
+}
+
+impl Receipt {
+    pub fn message(&self) -> String {
+        [
+            RECEIPT_PREFIX,
+            &self.verification_id,
+            &self.proof_id,
+            &self.proof_hash,
+            &self.wasm_hash,
+            &self.step_size.to_string(),
+            &self.result,
+            &self.verifier,
+            &self.verified_at,
+        ]
+        .join("\n")
+    }
+}
+
+// sha256 of a file, or empty when it can't be read
+async fn hash_or_empty(path: &std::path::Path) -> String {
+    match calculate_file_hash(path).await {
+        hash if hash == "error" => String::new(),
+        hash => hash,
+    }
+}
+
+// What was verified: proof hash, wasm hash and step size
+async fn subject(state: &AppState, verification: &VerificationRecord) -> (String, String, u64) {
+    if let Some(upload) = &verification.upload {
+        let wasm_hash = match &upload.wasm {
+            Some(wasm) => {
+                let file_name = std::path::Path::new(wasm).file_name().map(PathBuf::from).unwrap_or_default();
+                hash_or_empty(&PathBuf::from(&state.wasm_dir).join(file_name)).await
+            }
+            None => String::new(),
+        };
+        return (upload.proof_hash.clone(), wasm_hash, upload.step_size);
+    }
+    let Some(proof) = state.proof_store.get(&verification.proof_id).await else {
+        return (String::new(), String::new(), 0);
+    };
+    let wasm_hash = match &proof.signature {
+        Some(signature) => signature.wasm_hash.clone(),
+        None => hash_or_empty(std::path::Path::new(&proof.metadata.wasm_path)).await,
+    };
+    (proof.metrics.file_hash, wasm_hash, proof.metadata.step_size)
+}
+
+// Sign a receipt for a verification about to be stored
+pub async fn issue(state: &AppState, verification: &VerificationRecord) -> Receipt {
+    let (proof_hash, wasm_hash, step_size) = subject(state, verification).await;
+    let mut receipt = Receipt {
+        format: RECEIPT_PREFIX.to_string(),
+        verification_id: verification.id.clone(),
+        proof_id: verification.proof_id.clone(),
+        proof_hash,
+        wasm_hash,
+        step_size,
+        result: if verification.is_valid { "valid" } else { "invalid" }.to_string(),
+        verifier: String::new(),
+        verified_at: verification.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+        algorithm: "ed25519".to_string(),
+        signature: String::new(),
+    };
+    let (_, signature) = state.signer.sign_message(|key_id| {
+        receipt.verifier = key_id.to_string();
+        receipt.message()
+    });
+    receipt.signature = signature;
+    receipt
+}
+
+pub async fn receipt_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let receipt = state.verification_store.lock().await
+        .iter()
+        .find(|v| v.id == id)
+        .map(|v| v.receipt.clone());
+    match receipt {
+        Some(Some(receipt)) => {
+            let file_name = format!("receipt-{}.json", short_id(&id));
+            (
+                [
+                    (header::CONTENT_TYPE, "application/json".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", file_name)),
+                ],
+                serde_json::to_string_pretty(&receipt).unwrap_or_default(),
+            )
+                .into_response()
+        }
+        Some(None) => {
+            let error = AppError::new(ErrorCode::NotFound, "This verification was recorded before receipts were issued");
+            error.into_response()
+        }
+        None => {
+            let error = AppError::new(ErrorCode::NotFound, format!("No verification {}", id));
+            error.into_response()
+        }
+    }
+}
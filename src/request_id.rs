@@ -0,0 +1,67 @@
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::{future::Future, time::Instant};
+use tracing::info;
+use uuid::Uuid;
+
+// Correlation IDs. Every HTTP request and every inbound WebSocket message
+// gets a request ID (a caller-supplied `x-request-id` is kept if it looks
+// sane). The ID is logged, returned in the `x-request-id` response header,
+// copied into WebSocket events and stored on proof records created while
+// handling it, so a client report can be matched to server logs.
+
+pub const HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: Option<String>;
+}
+
+// ID of the request being handled, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok().flatten()
+}
+
+pub fn generate() -> String {
+    Uuid::new_v4().to_string()
+}
+
+// Run `future` on behalf of request `id`
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(Some(id), future).await
+}
+
+// Carry the current request ID into a spawned task
+pub fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    REQUEST_ID.scope(current(), future)
+}
+
+fn from_header(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?;
+    let sane = !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    sane.then(|| id.to_string())
+}
+
+pub async fn middleware<B>(request: Request<B>, next: Next<B>) -> Response {
+    let id = request.headers().get(HEADER).and_then(from_header).unwrap_or_else(generate);
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let start = Instant::now();
+
+    let mut response = scope(id.clone(), next.run(request)).await;
+
+    info!(
+        request_id = %id,
+        "{} {} -> {} in {}ms",
+        method,
+        path,
+        response.status().as_u16(),
+        start.elapsed().as_millis()
+    );
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,174 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::llm::{DeltaSender, IntentProvider, IntentRequest, IntentResponse};
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+// Opens after LLM_BREAKER_THRESHOLD consecutive failed calls (default 5) and
+// rejects calls for LLM_BREAKER_COOLDOWN_SECS (default 30). After the
+// cooldown a single trial call is let through: success closes the breaker,
+// failure opens it again.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+}
+
+impl CircuitBreaker {
+    pub fn from_env() -> Self {
+        CircuitBreaker {
+            threshold: env_u64("LLM_BREAKER_THRESHOLD", 5).max(1) as u32,
+            cooldown: Duration::from_secs(env_u64("LLM_BREAKER_COOLDOWN_SECS", 30)),
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    // Whether a call may go ahead now
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() >= self.cooldown && !state.trial_in_flight => {
+                state.trial_in_flight = true;
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.trial_in_flight || state.consecutive_failures >= self.threshold {
+            if state.opened_at.is_none() || state.trial_in_flight {
+                warn!("LLM circuit breaker opened after {} consecutive failures", state.consecutive_failures);
+            }
+            state.opened_at = Some(Instant::now());
+            state.trial_in_flight = false;
+        }
+    }
+
+    pub fn status(&self) -> serde_json::Value {
+        let state = self.state.lock().unwrap();
+        let (name, retry_in_secs) = match state.opened_at {
+            None => ("closed", None),
+            Some(_) if state.trial_in_flight => ("half_open", None),
+            Some(opened_at) => ("open", Some(self.cooldown.saturating_sub(opened_at.elapsed()).as_secs())),
+        };
+        json!({
+            "state": name,
+            "consecutive_failures": state.consecutive_failures,
+            "threshold": self.threshold,
+            "cooldown_secs": self.cooldown.as_secs(),
+            "retry_in_secs": retry_in_secs,
+        })
+    }
+}
+
+// Wraps a provider with per-attempt timeouts (LLM_ATTEMPT_TIMEOUT_SECS,
+// default 30), retries with exponential backoff (LLM_RETRIES, default 2)
+// and the circuit breaker. While the breaker is open calls fail immediately
+// so callers drop straight to the local parser.
+pub struct ResilientProvider {
+    inner: Box<dyn IntentProvider>,
+    breaker: Arc<CircuitBreaker>,
+    retries: u32,
+    attempt_timeout: Duration,
+}
+
+impl ResilientProvider {
+    pub fn from_env(inner: Box<dyn IntentProvider>, breaker: Arc<CircuitBreaker>) -> Self {
+        ResilientProvider {
+            inner,
+            breaker,
+            retries: env_u64("LLM_RETRIES", 2) as u32,
+            attempt_timeout: Duration::from_secs(env_u64("LLM_ATTEMPT_TIMEOUT_SECS", 30)),
+        }
+    }
+
+    fn backoff(attempt: u32) -> Duration {
+        Duration::from_millis(500 * 2u64.pow(attempt.min(5)))
+    }
+
+    // One attempt, forwarding deltas to `deltas`. Also reports whether any
+    // delta was forwarded, since a partially streamed reply can't be retried.
+    async fn attempt(&self, request: &IntentRequest, deltas: &DeltaSender) -> (anyhow::Result<IntentResponse>, bool) {
+        let (attempt_tx, mut attempt_rx) = mpsc::unbounded_channel();
+        let call = async {
+            match tokio::time::timeout(
+                self.attempt_timeout,
+                self.inner.process_streaming(request, attempt_tx),
+            ).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!("timed out after {}s", self.attempt_timeout.as_secs())),
+            }
+        };
+        let forward = async {
+            let mut streamed = false;
+            while let Some(delta) = attempt_rx.recv().await {
+                streamed = true;
+                let _ = deltas.send(delta);
+            }
+            streamed
+        };
+        tokio::join!(call, forward)
+    }
+}
+
+#[async_trait]
+impl IntentProvider for ResilientProvider {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn process(&self, request: &IntentRequest) -> anyhow::Result<IntentResponse> {
+        let (deltas, _) = mpsc::unbounded_channel();
+        self.process_streaming(request, deltas).await
+    }
+
+    async fn process_streaming(&self, request: &IntentRequest, deltas: DeltaSender) -> anyhow::Result<IntentResponse> {
+        if !self.breaker.allow() {
+            return Err(anyhow::anyhow!("{} circuit breaker is open", self.inner.name()));
+        }
+
+        let mut attempt = 0;
+        loop {
+            let (result, streamed) = self.attempt(request, &deltas).await;
+            match result {
+                Ok(response) => {
+                    self.breaker.record_success();
+                    return Ok(response);
+                }
+                Err(e) if attempt < self.retries && !streamed => {
+                    warn!("{} attempt {} failed, retrying: {}", self.inner.name(), attempt + 1, e);
+                    tokio::time::sleep(Self::backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.breaker.record_failure();
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
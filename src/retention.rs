@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, path::Path};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    cluster,
+    errors::{AppError, ErrorCode},
+    proof_db::IndexEntry,
+    proof_names,
+    schema::{self, Layout},
+    tags, trash, AppState, ProofRecord,
+};
+
+// Deletion and archiving of proofs, shared by the REST API and chat commands.
+// Removal by age skips proofs with a protected tag (see tags.rs). Deleted
+// proofs go to the trash first (see trash.rs).
+
+const ARCHIVE_DB_FILE: &str = "./proofs_archive_db.json";
+
+static ARCHIVING: Mutex<()> = Mutex::const_new(());
+
+// Remove a proof record, moving it to the trash or, without one, deleting
+// its files
+pub async fn delete_proof(state: &AppState, proof_id: &str) -> Result<ProofRecord, AppError> {
+    let removed = state.proof_store.remove_if(proof_id, |proof| match proof.status.in_progress() {
+        true => Err(AppError::new(ErrorCode::ProofNotReady, "Proof is still being generated")),
+        false => Ok(()),
+    }).await?.ok_or_else(AppError::proof_not_found)?;
+    discard(state, std::slice::from_ref(&removed)).await?;
+    info!("Deleted proof {}", proof_id);
+    Ok(removed)
+}
+
+// Finish removing proofs taken out of the store. If the trash can't be
+// saved they are put back.
+async fn discard(state: &AppState, removed: &[ProofRecord]) -> Result<(), AppError> {
+    let trashed = trash::retention().is_some();
+    if trashed {
+        if let Err(error) = trash::put(state, removed).await {
+            for proof in removed {
+                state.proof_store.insert(proof.clone()).await;
+            }
+            return Err(error);
+        }
+    }
+    let ids: Vec<String> = removed.iter().map(|proof| proof.id.clone()).collect();
+    state.proof_flusher.mark_dirty();
+    cluster::delete_proofs(state, &ids).await;
+    if !trashed {
+        for proof_id in &ids {
+            remove_proof_files(state, proof_id).await;
+        }
+    }
+    Ok(())
+}
+
+// Whether a proof is old enough to be removed by age
+fn expired(proof: &ProofRecord, cutoff: DateTime<Utc>) -> bool {
+    proof.timestamp <= cutoff && !tags::is_protected(proof)
+}
+
+// The part of `expired` the index answers, so only old proofs are read
+fn old(entry: &IndexEntry, cutoff: DateTime<Utc>) -> bool {
+    entry.timestamp <= cutoff
+}
+
+// Delete every proof older than `days`; returns how many were removed
+pub async fn delete_older_than(state: &AppState, days: i64) -> usize {
+    let cutoff = Utc::now() - chrono::Duration::days(days);
+    let removed = state.proof_store.remove_where(|e| old(e, cutoff), |p| expired(p, cutoff)).await;
+    if removed.is_empty() {
+        return 0;
+    }
+    match discard(state, &removed).await {
+        Ok(()) => removed.len(),
+        Err(error) => {
+            warn!("Kept {} old proofs: {}", removed.len(), error.message);
+            0
+        }
+    }
+}
+
+// How many proofs are older than `days`
+pub async fn count_older_than(state: &AppState, days: i64) -> usize {
+    let cutoff = Utc::now() - chrono::Duration::days(days);
+    state.proof_store.filter_map_where(|e| old(e, cutoff), |p| expired(p, cutoff).then_some(())).await.len()
+}
+
+// Move proofs older than `days` out of the active store into the archive
+// file. Their proof files are kept so they can still be restored by hand.
+pub async fn archive_older_than(state: &AppState, days: i64) -> Result<usize, AppError> {
+    let cutoff = Utc::now() - chrono::Duration::days(days);
+    // One archive run at a time, so two runs don't overwrite each other's
+    // additions to the archive file
+    let _archiving = ARCHIVING.lock().await;
+    let old: Vec<ProofRecord> = state.proof_store.filter_map_where(|e| old(e, cutoff), |p| expired(p, cutoff).then(|| p.clone())).await;
+    if old.is_empty() {
+        return Ok(0);
+    }
+
+    let mut archive = load_archive().await;
+    for proof in &old {
+        archive.insert(proof.id.clone(), proof.clone());
+    }
+    let storage_error = |e: &dyn std::fmt::Display| AppError::new(ErrorCode::StorageError, format!("Failed to write proof archive: {}", e));
+    let json = schema::PROOF.encode(&archive).map_err(|e| storage_error(&e))?;
+    tokio::fs::write(ARCHIVE_DB_FILE, json).await.map_err(|e| storage_error(&e))?;
+
+    // Only what was archived; a proof that became protected meanwhile stays
+    let archived: Vec<String> = state.proof_store
+        .remove_where(|e| archive.contains_key(&e.id), |p| expired(p, cutoff))
+        .await
+        .into_iter()
+        .map(|proof| proof.id)
+        .collect();
+    state.proof_flusher.mark_dirty();
+    cluster::delete_proofs(state, &archived).await;
+    info!("Archived {} proofs older than {} days", archived.len(), days);
+    Ok(archived.len())
+}
+
+// An unreadable archive is moved aside (see schema.rs) and a new one begun
+async fn load_archive() -> HashMap<String, ProofRecord> {
+    schema::PROOF.load(Layout::Map, ARCHIVE_DB_FILE).await.unwrap_or_else(|e| {
+        warn!("Failed to read proof archive: {}", e);
+        None
+    }).unwrap_or_default()
+}
+
+pub async fn remove_proof_files(state: &AppState, proof_id: &str) {
+    let proof_dir = format!("{}/{}", state.proofs_dir, proof_id);
+    if Path::new(&proof_dir).exists() {
+        if let Err(e) = tokio::fs::remove_dir_all(&proof_dir).await {
+            warn!("Failed to remove proof files in {}: {}", proof_dir, e);
+        }
+    }
+}
+
+// Find the proof a full ID, proof name (see proof_names.rs) or unique ID
+// prefix (such as the 8-character short IDs shown in chat) refers to
+pub async fn resolve_proof_id(state: &AppState, id: &str) -> Result<String, AppError> {
+    let matches = state.proof_store.ids(|p| p.id.starts_with(id)).await;
+    if matches.iter().any(|m| m == id) {
+        return Ok(id.to_string());
+    }
+    if let Some(named) = proof_names::find(state, id).await {
+        return Ok(named);
+    }
+    match matches.as_slice() {
+        [only] => Ok(only.clone()),
+        [] => Err(AppError::new(ErrorCode::ProofNotFound, format!("No proof matches {}", id))),
+        _ => Err(AppError::new(
+            ErrorCode::AmbiguousProofId,
+            format!("{} matches {} proofs; use more characters", id, matches.len()),
+        )),
+    }
+}
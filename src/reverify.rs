@@ -0,0 +1,264 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::{
+    analytics,
+    errors::{AppError, ErrorCode},
+    notifications, short_id, verifier, AppState, ProofRecord, ProofStatus,
+};
+
+// Periodic re-verification of stored proofs, to catch disk corruption of
+// long-lived attestations before someone relies on them. Each run checks
+// the proofs that were checked longest ago: the proof file must still hash
+// to `metrics.file_hash` and still pass zkEngine verify. Proofs that don't
+// are flagged and raise an `artifact_corrupted` alert once.
+//
+//   REVERIFY_INTERVAL_SECS   time between runs (default: off)
+//   REVERIFY_SAMPLE          proofs checked per run (default 0, all of them)
+//
+// GET /api/admin/reverify reports the last run and the flagged proofs;
+// POST runs a check now.
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    // proof.bin is gone or unreadable
+    Missing,
+    // proof.bin no longer matches the hash recorded when it was made
+    HashChanged,
+    // The module it was made with is gone or changed (see check_module)
+    ModuleChanged,
+    // zkEngine rejects the proof
+    Invalid,
+}
+
+impl Problem {
+    fn code(&self) -> ErrorCode {
+        match self {
+            Problem::Missing => ErrorCode::ProofFileMissing,
+            Problem::HashChanged | Problem::ModuleChanged => ErrorCode::ArtifactTampered,
+            Problem::Invalid => ErrorCode::VerifierMismatch,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Finding {
+    pub proof_id: String,
+    pub problem: Problem,
+    pub detail: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RunSummary {
+    pub started_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub checked: usize,
+    // Proofs flagged for the first time by this run
+    pub newly_flagged: usize,
+}
+
+pub struct Reverifier {
+    interval: Option<Duration>,
+    sample: usize,
+    // Proof ID -> when it was last re-verified
+    checked: Mutex<HashMap<String, DateTime<Utc>>>,
+    flagged: Mutex<HashMap<String, Finding>>,
+    last_run: Mutex<Option<RunSummary>>,
+    // Held while a run is in progress
+    running: tokio::sync::Mutex<()>,
+}
+
+impl Reverifier {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        Reverifier {
+            interval: var("REVERIFY_INTERVAL_SECS").filter(|&secs| secs > 0).map(Duration::from_secs),
+            sample: var("REVERIFY_SAMPLE").unwrap_or(0) as usize,
+            checked: Mutex::new(HashMap::new()),
+            flagged: Mutex::new(HashMap::new()),
+            last_run: Mutex::new(None),
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    // Complete proofs, least recently checked (or never checked) first.
+    // Deleted proofs are forgotten.
+    fn due(&self, proofs: Vec<ProofRecord>) -> Vec<ProofRecord> {
+        let stored: HashSet<&str> = proofs.iter().map(|p| p.id.as_str()).collect();
+        self.flagged.lock().unwrap().retain(|id, _| stored.contains(id.as_str()));
+        let mut checked = self.checked.lock().unwrap();
+        checked.retain(|id, _| stored.contains(id.as_str()));
+        drop(stored);
+        let mut due: Vec<ProofRecord> = proofs.into_iter().filter(|p| matches!(p.status, ProofStatus::Complete)).collect();
+        due.sort_by_key(|p| checked.get(&p.id).copied());
+        if self.sample > 0 {
+            due.truncate(self.sample);
+        }
+        due
+    }
+
+    // Flag a proof, alerting only the first time it's found broken
+    fn flag(&self, state: &AppState, proof: &ProofRecord, problem: Problem, detail: String) -> bool {
+        let mut flagged = self.flagged.lock().unwrap();
+        if flagged.get(&proof.id).is_some_and(|f| f.problem == problem) {
+            return false;
+        }
+        warn!("Re-verification flagged proof {}: {:?}: {}", proof.id, problem, detail);
+        state.notifier.notify(
+            notifications::Alert::new(
+                notifications::AlertKind::ArtifactCorrupted,
+                format!("Stored proof {} ({}) failed re-verification: {}", short_id(&proof.id), analytics::circuit(proof), detail),
+            )
+            .proof(&proof.id, &analytics::circuit(proof))
+            .error(&detail, Some(problem.code())),
+        );
+        flagged.insert(proof.id.clone(), Finding {
+            proof_id: proof.id.clone(),
+            problem,
+            detail,
+            detected_at: Utc::now(),
+        });
+        true
+    }
+
+    pub async fn run(&self, state: &AppState) -> Result<RunSummary, AppError> {
+        let Ok(_running) = self.running.try_lock() else {
+            return Err(AppError::new(ErrorCode::ProofNotReady, "A re-verification run is already in progress"));
+        };
+        let started_at = Utc::now();
+        let start_time = Instant::now();
+        let proofs: Vec<ProofRecord> = state.proof_store.snapshot().await;
+        let due = self.due(proofs);
+
+        let mut checked = 0;
+        let mut newly_flagged = 0;
+        for proof in &due {
+            let Some(result) = check(state, proof).await else {
+                continue;
+            };
+            checked += 1;
+            self.checked.lock().unwrap().insert(proof.id.clone(), Utc::now());
+            match result {
+                Ok(()) => {
+                    if self.flagged.lock().unwrap().remove(&proof.id).is_some() {
+                        info!("Proof {} verifies again; no longer flagged", proof.id);
+                    }
+                }
+                Err((problem, detail)) => {
+                    if self.flag(state, proof, problem, detail) {
+                        newly_flagged += 1;
+                    }
+                }
+            }
+        }
+
+        let summary = RunSummary {
+            started_at,
+            duration_secs: start_time.elapsed().as_secs_f64(),
+            checked,
+            newly_flagged,
+        };
+        info!(
+            "Re-verified {} stored proofs in {:.1}s; {} newly flagged, {} flagged in total",
+            summary.checked,
+            summary.duration_secs,
+            summary.newly_flagged,
+            self.flagged.lock().unwrap().len(),
+        );
+        *self.last_run.lock().unwrap() = Some(summary.clone());
+        Ok(summary)
+    }
+}
+
+// Ok when the proof is intact, None when it couldn't be checked (zkEngine
+// unavailable), which says nothing about the proof itself
+async fn check(state: &AppState, proof: &ProofRecord) -> Option<Result<(), (Problem, String)>> {
+    let Some(file_path) = &proof.file_path else {
+        return Some(Err((Problem::Missing, "The proof has no file".to_string())));
+    };
+    let hash = match verifier::sha256_file(Path::new(file_path)).await {
+        Ok(hash) => hash,
+        Err(e) => return Some(Err((Problem::Missing, format!("Failed to read {}: {}", file_path, e)))),
+    };
+    if hash != proof.metrics.file_hash {
+        return Some(Err((
+            Problem::HashChanged,
+            format!("proof.bin hashes to {}, but {} was recorded", hash, proof.metrics.file_hash),
+        )));
+    }
+
+    if let Err(found) = crate::check_module(proof).await {
+        return Some(Err((Problem::ModuleChanged, found)));
+    }
+
+    let proof_file = Path::new(file_path);
+    let public_file = proof_file.with_file_name("public.json");
+    let _slot = state.verify_pool.slot().await;
+    let output = match verifier::run(&state.sandbox, &state.zkengine_binary, proof.metadata.step_size, proof_file, &public_file).await {
+        Ok(output) => output,
+        Err(error) => {
+            warn!("Could not re-verify proof {}: {}", proof.id, error.message);
+            return None;
+        }
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Some(Err((Problem::Invalid, format!("zkEngine verify failed ({}): {}", output.status, stderr))));
+    }
+    Some(Ok(()))
+}
+
+// Start the schedule, if REVERIFY_INTERVAL_SECS is set
+pub fn spawn(state: &AppState) {
+    let Some(interval) = state.reverifier.interval else {
+        return;
+    };
+    let sample = match state.reverifier.sample {
+        0 => "all stored proofs".to_string(),
+        n => format!("up to {} stored proofs", n),
+    };
+    info!("Re-verifying {} every {:?}", sample, interval);
+    let state = state.clone();
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if let Err(error) = state.reverifier.run(&state).await {
+                warn!("{}", error.message);
+            }
+        }
+    });
+}
+
+pub async fn status_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let reverifier = &state.reverifier;
+    let mut flagged: Vec<Finding> = reverifier.flagged.lock().unwrap().values().cloned().collect();
+    flagged.sort_by_key(|f| f.detected_at);
+    Json(json!({
+        "success": true,
+        "interval_secs": reverifier.interval.map(|i| i.as_secs()),
+        "sample": reverifier.sample,
+        "last_run": reverifier.last_run.lock().unwrap().clone(),
+        "flagged": flagged,
+    }))
+}
+
+pub async fn run_handler(State(state): State<AppState>) -> impl IntoResponse {
+    match state.reverifier.run(&state).await {
+        Ok(summary) => Json(json!({ "success": true, "run": summary })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,125 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+use serde_json::json;
+use tracing::warn;
+
+use crate::{
+    cluster,
+    errors::{AppError, ErrorCode},
+    retention, AppState, ProofRecord,
+};
+
+// Expiry and revocation of proofs. A proof can be given an "expires_at"
+// (RFC 3339) when it is generated over REST or JSON-RPC, and revoked later:
+//
+//   POST /api/proofs/:id/revoke   {"reason": "key compromise"}; admins only
+//
+// Proof records carry "expired" and "revoked" flags wherever they are
+// returned, worked out when they are written out. Expired and revoked proofs
+// are skipped by "verify the latest proof"; they can still be verified by
+// ID. Revocations are logged to the audit target with their reason and
+// can't be undone.
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Revocation {
+    pub revoked_at: DateTime<Utc>,
+    pub reason: String,
+}
+
+// Flattened into ProofRecord
+#[derive(Deserialize, Clone, Default)]
+pub struct Validity {
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub revocation: Option<Revocation>,
+}
+
+impl Validity {
+    pub fn expiring(expires_at: Option<DateTime<Utc>>) -> Self {
+        Validity { expires_at, revocation: None }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.revocation.is_some()
+    }
+
+    // Neither expired nor revoked
+    pub fn is_current(&self) -> bool {
+        !self.is_expired() && !self.is_revoked()
+    }
+}
+
+impl Serialize for Validity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(expires_at) = &self.expires_at {
+            map.serialize_entry("expires_at", expires_at)?;
+        }
+        if let Some(revocation) = &self.revocation {
+            map.serialize_entry("revocation", revocation)?;
+        }
+        map.serialize_entry("expired", &self.is_expired())?;
+        map.serialize_entry("revoked", &self.is_revoked())?;
+        map.end()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RevokeRequest {
+    reason: String,
+}
+
+// An "expires_at" request field: absent or null for none, otherwise an
+// RFC 3339 time in the future
+pub fn parse_expiry(value: &serde_json::Value) -> Result<Option<DateTime<Utc>>, AppError> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let expires_at = value.as_str()
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|at| at.with_timezone(&Utc))
+        .ok_or_else(|| AppError::new(ErrorCode::InvalidArguments, "expires_at must be an RFC 3339 time"))?;
+    if expires_at <= Utc::now() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "expires_at is in the past"));
+    }
+    Ok(Some(expires_at))
+}
+
+pub async fn revoke(state: &AppState, id: &str, reason: &str) -> Result<ProofRecord, AppError> {
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "A reason is needed to revoke a proof"));
+    }
+    let id = retention::resolve_proof_id(state, id).await?;
+    let record = state.proof_store.update(&id, |proof| {
+        if proof.validity.is_revoked() {
+            return Err(AppError::new(ErrorCode::InvalidArguments, format!("Proof {} is already revoked", proof.id)));
+        }
+        proof.validity.revocation = Some(Revocation { revoked_at: Utc::now(), reason: reason.to_string() });
+        Ok(proof.clone())
+    }).await.ok_or_else(AppError::proof_not_found)??;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    warn!(target: "audit", proof_id = %id, reason = %reason, "Proof revoked");
+    Ok(record)
+}
+
+pub async fn revoke_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<RevokeRequest>,
+) -> Response {
+    match revoke(&state, &id, &request.reason).await {
+        Ok(proof) => Json(json!({ "success": true, "proof": proof })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
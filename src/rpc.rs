@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{
+    auth::Role, catalog, custom_metadata, proof_names, retention, revocation, start_proof, tags, verify_proof_async, AppState,
+    ProofMetadata, ProofOptions, ProofRecord, ProofStatus,
+};
+
+// Standard JSON-RPC 2.0 error codes
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+// Application error codes
+const PROOF_NOT_FOUND: i64 = -32001;
+const PROOF_NOT_READY: i64 = -32002;
+const FORBIDDEN: i64 = -32003;
+
+#[derive(Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    // Absent for notifications, which get no response
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into() }
+    }
+}
+
+impl RpcResponse {
+    pub fn success(id: Value, result: Value) -> Self {
+        RpcResponse { jsonrpc: "2.0", id, result: Some(result), error: None }
+    }
+
+    pub fn error(id: Value, code: i64, message: impl Into<String>) -> Self {
+        RpcResponse { jsonrpc: "2.0", id, result: None, error: Some(RpcError::new(code, message)) }
+    }
+}
+
+// Whether a raw WebSocket payload is JSON-RPC framed rather than chat
+pub fn is_rpc(value: &Value) -> bool {
+    value.get("jsonrpc").is_some()
+}
+
+// Parse and dispatch a JSON-RPC message. Returns `None` for notifications.
+pub async fn handle(state: &AppState, session_id: &str, value: Value) -> Option<RpcResponse> {
+    let request = match serde_json::from_value::<RpcRequest>(value) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        _ => return Some(RpcResponse::error(Value::Null, INVALID_REQUEST, "Invalid Request")),
+    };
+
+    info!("JSON-RPC {} from session {}", request.method, session_id);
+    let outcome = dispatch(state, session_id, &request.method, request.params).await;
+
+    let id = request.id?;
+    Some(match outcome {
+        Ok(result) => RpcResponse { jsonrpc: "2.0", id, result: Some(result), error: None },
+        Err(error) => RpcResponse { jsonrpc: "2.0", id, result: None, error: Some(error) },
+    })
+}
+
+#[derive(Deserialize)]
+struct GenerateProofParams {
+    wasm_file: String,
+    #[serde(default = "default_function")]
+    function: String,
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default = "default_step_size")]
+    step_size: u64,
+    #[serde(default)]
+    tags: Vec<String>,
+    // RFC 3339; see revocation.rs
+    #[serde(default)]
+    expires_at: Value,
+    // See custom_metadata.rs
+    #[serde(default)]
+    metadata: Value,
+    // See proof_names.rs
+    #[serde(default)]
+    name: Value,
+}
+
+fn default_function() -> String {
+    "main".to_string()
+}
+
+fn default_step_size() -> u64 {
+    50
+}
+
+#[derive(Deserialize)]
+struct ProofIdParams {
+    proof_id: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ListProofsParams {
+    // Filter by status name: queued, awaiting_approval, proving,
+    // finalizing, complete, failed or cancelled
+    status: Option<String>,
+}
+
+// A full ID, unique prefix or proof name (see proof_names.rs)
+async fn resolve(state: &AppState, proof_id: &str) -> Result<String, RpcError> {
+    retention::resolve_proof_id(state, proof_id).await.map_err(|e| RpcError::new(PROOF_NOT_FOUND, e.message))
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+async fn dispatch(state: &AppState, session_id: &str, method: &str, params: Value) -> Result<Value, RpcError> {
+    let required = match method {
+        "generateProof" | "verifyProof" => Role::Prover,
+        _ => Role::Viewer,
+    };
+    state.require_role(session_id, required).await.map_err(|e| RpcError::new(FORBIDDEN, e.message))?;
+    match method {
+        "generateProof" => {
+            let params: GenerateProofParams = parse_params(params)?;
+            state.flags.check_module(&params.wasm_file).map_err(|e| RpcError::new(FORBIDDEN, e.message))?;
+            let wasm_path = catalog::validate_request(&state.wasm_dir, &params.wasm_file, &params.arguments, params.step_size)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let arguments = catalog::encode_module_arguments(&params.wasm_file, &params.arguments)
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let metadata = ProofMetadata {
+                wasm_path: wasm_path.display().to_string(),
+                function: params.function,
+                arguments: params.arguments,
+                step_size: params.step_size,
+                statement: None,
+            };
+            let tags = tags::normalize(&params.tags).map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let expires_at = revocation::parse_expiry(&params.expires_at).map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let custom_metadata = custom_metadata::parse(&params.metadata).map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let name = proof_names::parse(&params.name).map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let options = ProofOptions { tags, custom_metadata, expires_at, name, ..Default::default() };
+            let started = start_proof(state, metadata, arguments, Some(session_id.to_string()), options)
+                .await
+                .map_err(|e| RpcError::new(INVALID_PARAMS, e.message))?;
+            let status = if started.cached { "complete" } else { "queued" };
+            Ok(json!({ "proof_id": started.id, "status": status, "cached": started.cached }))
+        }
+        "verifyProof" => {
+            let params: ProofIdParams = parse_params(params)?;
+            let proof_id = resolve(state, &params.proof_id).await?;
+            let status = state.proof_store.read(&proof_id, |p| p.status.clone()).await;
+            match status {
+                None => Err(RpcError::new(PROOF_NOT_FOUND, "Proof not found")),
+                Some(ProofStatus::Complete) => {
+                    let state_clone = state.clone();
+                    let session_id = session_id.to_string();
+                    let verified_id = proof_id.clone();
+                    tokio::spawn(async move {
+                        verify_proof_async(state_clone, verified_id, Some(session_id)).await.ok();
+                    });
+                    Ok(json!({ "proof_id": proof_id, "status": "verifying" }))
+                }
+                Some(_) => Err(RpcError::new(PROOF_NOT_READY, "Proof not complete")),
+            }
+        }
+        "getProof" => {
+            let params: ProofIdParams = parse_params(params)?;
+            match state.proof_store.get(&resolve(state, &params.proof_id).await?).await {
+                Some(proof) => Ok(json!(proof)),
+                None => Err(RpcError::new(PROOF_NOT_FOUND, "Proof not found")),
+            }
+        }
+        "listProofs" => {
+            let params: ListProofsParams = if params.is_null() { ListProofsParams::default() } else { parse_params(params)? };
+            let proofs_list: Vec<ProofRecord> = state.proof_store
+                .filter_map_where(|entry| params.status.as_deref().is_none_or(|s| entry.status.is_named(s)), |p| Some(p.clone()))
+                .await;
+            Ok(json!({ "proofs": proofs_list, "count": proofs_list.len() }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tracing::{info, warn};
+
+// Resource limits for zkEngine child processes, so a malicious or buggy
+// module can't exhaust the host or read unrelated files. Configured from the
+// environment:
+//
+//   PROVER_MAX_MEMORY_MB        address space limit (RLIMIT_AS)
+//   PROVER_MAX_CPU_SECS         CPU time limit (RLIMIT_CPU); the prover is killed past it
+//   PROVER_MAX_FILE_MB          largest file the prover may write (RLIMIT_FSIZE)
+//   PROVER_SANDBOX              restrict filesystem access with Landlock (default false):
+//                               read-only access to system directories, the binary and
+//                               the module, read-write access to the proof's directory
+//   PROVER_SANDBOX_READ_PATHS   extra read-only paths, colon separated
+//   PROVER_SANDBOX_WRITE_PATHS  extra read-write paths, colon separated
+//   PROVER_THREADS              worker threads per zkEngine run (RAYON_NUM_THREADS)
+//   PROVER_CPUS                 CPUs zkEngine runs on, e.g. "2-7" or "0,2,4" (Linux);
+//                               the rest stay free for the API and other jobs
+//
+// Limits of 0 (the default) are unlimited. With PROVER_SANDBOX on a kernel
+// without Landlock, zkEngine refuses to start rather than run unconfined
+// (spawning it fails with "operation not supported").
+// How many runs share these threads and CPUs at once is set by
+// PROVE_WORKERS and VERIFY_WORKERS (see workers.rs).
+
+// Read-only for every sandboxed prover: shared libraries, config and devices
+const SYSTEM_READ_PATHS: &[&str] = &["/usr", "/lib", "/lib64", "/bin", "/sbin", "/etc", "/dev", "/proc"];
+const SYSTEM_WRITE_PATHS: &[&str] = &["/dev/null"];
+
+pub struct Sandbox {
+    memory_bytes: Option<u64>,
+    cpu_secs: Option<u64>,
+    file_bytes: Option<u64>,
+    filesystem: bool,
+    read_paths: Vec<PathBuf>,
+    write_paths: Vec<PathBuf>,
+    threads: Option<u64>,
+    cpus: Vec<usize>,
+}
+
+// A CPU list such as "0-3,8"
+fn parse_cpus(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last): (usize, usize) = (first.trim().parse().ok()?, last.trim().parse().ok()?);
+                if first > last {
+                    return None;
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(part.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+impl Sandbox {
+    pub fn from_env() -> Self {
+        let limit = |name: &str, scale: u64| {
+            std::env::var(name).ok().and_then(|v| v.trim().parse::<u64>().ok()).filter(|v| *v > 0).map(|v| v * scale)
+        };
+        let paths = |name: &str| -> Vec<PathBuf> {
+            std::env::var(name)
+                .unwrap_or_default()
+                .split(':')
+                .filter(|p| !p.trim().is_empty())
+                .map(|p| PathBuf::from(p.trim()))
+                .collect()
+        };
+        let sandbox = Sandbox {
+            memory_bytes: limit("PROVER_MAX_MEMORY_MB", 1024 * 1024),
+            cpu_secs: limit("PROVER_MAX_CPU_SECS", 1),
+            file_bytes: limit("PROVER_MAX_FILE_MB", 1024 * 1024),
+            filesystem: std::env::var("PROVER_SANDBOX").is_ok_and(|v| v == "true" || v == "1"),
+            read_paths: paths("PROVER_SANDBOX_READ_PATHS"),
+            write_paths: paths("PROVER_SANDBOX_WRITE_PATHS"),
+            threads: limit("PROVER_THREADS", 1),
+            cpus: std::env::var("PROVER_CPUS").map_or_else(|_| Vec::new(), |list| {
+                parse_cpus(&list).unwrap_or_else(|| {
+                    warn!("Ignoring PROVER_CPUS={:?}: expected a list such as 0-3,8", list);
+                    Vec::new()
+                })
+            }),
+        };
+        let mb = |bytes: Option<u64>| bytes.map_or("unlimited".to_string(), |b| format!("{} MB", b / (1024 * 1024)));
+        info!(
+            "zkEngine limits: memory {}, CPU {}, file size {}, filesystem sandbox {}, threads {}, CPUs {}",
+            mb(sandbox.memory_bytes),
+            sandbox.cpu_secs.map_or("unlimited".to_string(), |s| format!("{}s", s)),
+            mb(sandbox.file_bytes),
+            if sandbox.filesystem { "on" } else { "off" },
+            sandbox.threads.map_or("default".to_string(), |n| n.to_string()),
+            if sandbox.cpus.is_empty() { "all".to_string() } else { format!("{:?}", sandbox.cpus) },
+        );
+        sandbox
+    }
+
+    // Confine a zkEngine command that reads `readable` and writes into
+    // `writable`. The limits are applied in the child between fork and exec.
+    pub fn apply(&self, cmd: &mut Command, readable: &[&Path], writable: &[&Path]) {
+        if let Some(threads) = self.threads {
+            cmd.env("RAYON_NUM_THREADS", threads.to_string());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            let limits = [
+                (libc::RLIMIT_AS, self.memory_bytes),
+                (libc::RLIMIT_CPU, self.cpu_secs),
+                (libc::RLIMIT_FSIZE, self.file_bytes),
+            ];
+            // A ruleset that can't be built is reported here; the child
+            // only gets a bare OS error, which needs no allocation
+            #[cfg(target_os = "linux")]
+            let mut ruleset = self.filesystem.then(|| {
+                self.ruleset(cmd.get_program(), readable, writable).map_err(|e| {
+                    warn!("Failed to build the filesystem sandbox: {}", e);
+                    std::io::Error::from_raw_os_error(libc::EPERM)
+                })
+            });
+            #[cfg(target_os = "linux")]
+            let affinity = self.affinity();
+            #[cfg(not(target_os = "linux"))]
+            let _ = (readable, writable);
+
+            // Safety: the closure runs in the child of a multi-threaded
+            // process between fork and exec, where another thread may have
+            // held the allocator's lock, so it must not allocate. It only
+            // makes system calls on state built before the fork (the limits,
+            // CPU mask and Landlock ruleset) and fails with bare OS errors,
+            // which is all the parent is told of a failure anyway.
+            unsafe {
+                cmd.pre_exec(move || {
+                    for (resource, limit) in limits {
+                        if let Some(limit) = limit {
+                            let rlimit = libc::rlimit { rlim_cur: limit as libc::rlim_t, rlim_max: limit as libc::rlim_t };
+                            if libc::setrlimit(resource, &rlimit) != 0 {
+                                return Err(std::io::Error::last_os_error());
+                            }
+                        }
+                    }
+                    #[cfg(target_os = "linux")]
+                    if let Some(set) = &affinity {
+                        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), set) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                    #[cfg(target_os = "linux")]
+                    if let Some(ruleset) = ruleset.take() {
+                        landlock_restrict(ruleset)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (cmd, readable, writable);
+    }
+
+    // The PROVER_CPUS mask, built in the parent like the Landlock rules
+    #[cfg(target_os = "linux")]
+    fn affinity(&self) -> Option<libc::cpu_set_t> {
+        if self.cpus.is_empty() {
+            return None;
+        }
+        // Safety: cpu_set_t is a plain bit set, and CPUs past its size are
+        // skipped
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for &cpu in self.cpus.iter().filter(|&&cpu| cpu < libc::CPU_SETSIZE as usize) {
+                libc::CPU_SET(cpu, &mut set);
+            }
+            Some(set)
+        }
+    }
+
+    // Landlock rules for one command; built in the parent so the child
+    // only has to enforce them
+    #[cfg(target_os = "linux")]
+    fn ruleset(&self, program: &std::ffi::OsStr, readable: &[&Path], writable: &[&Path]) -> Result<landlock::RulesetCreated, String> {
+        use landlock::{path_beneath_rules, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, ABI};
+
+        let abi = ABI::V5;
+        // The binary's directory, so scripts and helpers next to it keep working
+        let program_dir = Path::new(program).parent().filter(|dir| !dir.as_os_str().is_empty()).map(Path::to_path_buf);
+        let read: Vec<PathBuf> = SYSTEM_READ_PATHS.iter()
+            .map(PathBuf::from)
+            .chain(program_dir)
+            .chain(readable.iter().map(|p| p.to_path_buf()))
+            .chain(self.read_paths.iter().cloned())
+            .collect();
+        let write: Vec<PathBuf> = SYSTEM_WRITE_PATHS.iter()
+            .map(PathBuf::from)
+            .chain(writable.iter().map(|p| p.to_path_buf()))
+            .chain(self.write_paths.iter().cloned())
+            .collect();
+
+        Ruleset::default()
+            .handle_access(AccessFs::from_all(abi))
+            .and_then(|ruleset| ruleset.create())
+            .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(&read, AccessFs::from_read(abi))))
+            .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(&write, AccessFs::from_all(abi))))
+            .map_err(|e| e.to_string())
+    }
+}
+
+// Enforce the filesystem rules on the child, refusing to run unconfined.
+// Runs between fork and exec, so nothing here may allocate (see `apply`).
+#[cfg(target_os = "linux")]
+fn landlock_restrict(ruleset: std::io::Result<landlock::RulesetCreated>) -> std::io::Result<()> {
+    use landlock::RulesetStatus;
+
+    let status = ruleset?.restrict_self().map_err(|_| std::io::Error::from_raw_os_error(libc::EPERM))?;
+    // The kernel has no Landlock
+    if status.ruleset == RulesetStatus::NotEnforced {
+        return Err(std::io::Error::from_raw_os_error(libc::EOPNOTSUPP));
+    }
+    Ok(())
+}
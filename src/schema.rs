@@ -0,0 +1,137 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use tracing::warn;
+
+// Versions of the records kept on disk. Files are written as
+//
+//   {"schema_version": 1, "data": <record, list of records or map of records>}
+//
+// and read back through the schema's migrations, each of which upgrades one
+// record by one version on the raw JSON, before it is deserialized. A change
+// to ProofRecord or VerificationRecord that old files can't be read as adds
+// a migration to its schema, so history written by older versions keeps
+// loading. Files from before versioning (no envelope) are version 0.
+//
+// A file that still can't be read, or was written by a newer version, is
+// moved aside to <file>.unreadable-<unix time> instead of being overwritten
+// by the next save, and the error is reported by /readyz.
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+pub struct Schema {
+    name: &'static str,
+    // migrations[n] upgrades a record from version n to n + 1, so the
+    // current version is migrations.len()
+    migrations: &'static [Migration],
+}
+
+// Files from before versioning are read as they are
+fn unversioned(_: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+// Version 2: "pending" and "running" became the queued and proving stages
+fn staged_status(record: &mut Value) -> Result<(), String> {
+    let Some(status) = record.get_mut("status") else {
+        return Ok(());
+    };
+    match status.as_str() {
+        Some("pending") => *status = json!("queued"),
+        Some("running") => *status = json!({ "proving": {} }),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Index version 3: entries carry their proof's chat session. Older entries
+// are marked to be rebuilt from their records once, when the index is loaded.
+fn indexed_session(entry: &mut Value) -> Result<(), String> {
+    if let Value::Object(fields) = entry {
+        fields.insert("rebuild".to_string(), json!(true));
+    }
+    Ok(())
+}
+
+pub const PROOF: Schema = Schema { name: "proof", migrations: &[unversioned, staged_status] };
+pub const PROOF_INDEX: Schema = Schema { name: "proof index entry", migrations: &[unversioned, staged_status, indexed_session] };
+pub const VERIFICATION: Schema = Schema { name: "verification", migrations: &[unversioned] };
+
+// How records are laid out in a file
+#[derive(Clone, Copy)]
+pub enum Layout {
+    One,
+    List,
+    Map,
+}
+
+impl Layout {
+    fn records(self, data: &mut Value) -> Vec<&mut Value> {
+        match (self, data) {
+            (Layout::One, data) => vec![data],
+            (Layout::List, Value::Array(items)) => items.iter_mut().collect(),
+            (Layout::Map, Value::Object(fields)) => fields.values_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Schema {
+    pub fn version(&self) -> u64 {
+        self.migrations.len() as u64
+    }
+
+    pub fn encode<T: Serialize>(&self, data: &T) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(&json!({ "schema_version": self.version(), "data": data }))
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, layout: Layout, json: &[u8]) -> anyhow::Result<T> {
+        self.decode_value(layout, serde_json::from_slice(json)?)
+    }
+
+    // Records kept outside files, such as cluster rows, have no envelope and
+    // go through every migration, so those must leave current records alone
+    pub fn decode_value<T: DeserializeOwned>(&self, layout: Layout, value: Value) -> anyhow::Result<T> {
+        let (version, mut data) = match value {
+            Value::Object(mut fields) if fields.contains_key("schema_version") => {
+                let version = fields.get("schema_version").and_then(Value::as_u64)
+                    .ok_or_else(|| anyhow::anyhow!("schema_version is not a number"))?;
+                (version, fields.remove("data").unwrap_or(Value::Null))
+            }
+            value => (0, value),
+        };
+        if version > self.version() {
+            anyhow::bail!(
+                "{} records are at schema version {}, but this build reads up to {}; it was written by a newer version",
+                self.name, version, self.version()
+            );
+        }
+        for (from, migrate) in self.migrations.iter().enumerate().skip(version as usize) {
+            for record in layout.records(&mut data) {
+                migrate(record).map_err(|e| anyhow::anyhow!("migrating a {} record from version {}: {}", self.name, from, e))?;
+            }
+        }
+        Ok(serde_json::from_value(data)?)
+    }
+
+    // A whole-file store; None when there is no file yet
+    pub async fn load<T: DeserializeOwned>(&self, layout: Layout, path: impl AsRef<Path>) -> anyhow::Result<Option<T>> {
+        let path = path.as_ref();
+        let json = match tokio::fs::read(path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match self.decode(layout, &json) {
+            Ok(data) => Ok(Some(data)),
+            Err(e) => {
+                let aside = format!("{}.unreadable-{}", path.display(), chrono::Utc::now().timestamp());
+                match tokio::fs::rename(path, &aside).await {
+                    Ok(()) => warn!("Moved unreadable {} to {}", path.display(), aside),
+                    Err(rename_error) => warn!("Failed to move unreadable {} aside: {}", path.display(), rename_error),
+                }
+                Err(anyhow::anyhow!("{} (kept as {})", e, aside))
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tracing::{info, warn};
+
+use crate::outbound::{self, Integration};
+
+// Credentials (API_KEYS, PROOF_SIGNING_KEY, JWT_HS256_SECRET,
+// OPENAI_API_KEY, ANTHROPIC_API_KEY and any other secret a feature asks for)
+// come from a secrets provider, falling back to environment variables of the
+// same name. Values are reloaded periodically, and consumers read them at
+// use time, so rotating a secret at the source takes effect without a
+// restart. Configured from the environment:
+//
+//   SECRETS_PROVIDER       env (default), file, vault or aws
+//   SECRETS_REFRESH_SECS   how often to reload secrets (default 300; 0 loads them once)
+//
+//   file:   SECRETS_DIR    one file per secret, named after it (default /run/secrets)
+//   vault:  VAULT_ADDR, VAULT_TOKEN, VAULT_NAMESPACE (optional) and
+//           VAULT_SECRET_PATH, the secret's API path (default secret/data/zkengine);
+//           KV v1 and v2 engines both work
+//   aws:    AWS_SECRET_ID, a Secrets Manager secret holding a JSON object of
+//           names to values; AWS_REGION, AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY,
+//           AWS_SESSION_TOKEN (optional) and AWS_SECRETS_ENDPOINT (optional)
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Somewhere secrets can be loaded from
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Every secret the provider holds, by name
+    async fn load(&self) -> anyhow::Result<HashMap<String, String>>;
+}
+
+// Docker and Kubernetes style secret files
+struct FileProvider {
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl SecretsProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn load(&self) -> anyhow::Result<HashMap<String, String>> {
+        let mut secrets = HashMap::new();
+        let mut entries = tokio::fs::read_dir(&self.dir)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}: {}", self.dir.display(), e))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Kubernetes keeps its bookkeeping in dot directories
+            if name.starts_with('.') || !tokio::fs::metadata(entry.path()).await?.is_file() {
+                continue;
+            }
+            let value = tokio::fs::read_to_string(entry.path()).await?;
+            secrets.insert(name, value.trim_end_matches(['\r', '\n']).to_string());
+        }
+        Ok(secrets)
+    }
+}
+
+// One HashiCorp Vault secret whose fields are the secrets
+struct VaultProvider {
+    client: reqwest::Client,
+    addr: String,
+    token: String,
+    namespace: Option<String>,
+    path: String,
+}
+
+#[async_trait]
+impl SecretsProvider for VaultProvider {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn load(&self) -> anyhow::Result<HashMap<String, String>> {
+        let url = format!("{}/v1/{}", self.addr.trim_end_matches('/'), self.path.trim_start_matches('/'));
+        let mut request = self.client.get(&url).header("X-Vault-Token", &self.token).timeout(HTTP_TIMEOUT);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let body: Value = request.send().await?.error_for_status()?.json().await?;
+        // KV v2 nests the fields under data.data, next to data.metadata
+        let data = &body["data"];
+        let fields = match data.get("metadata") {
+            Some(_) => &data["data"],
+            None => data,
+        };
+        string_fields(fields).ok_or_else(|| anyhow::anyhow!("{} is not a key/value secret", url))
+    }
+}
+
+// One AWS Secrets Manager secret holding a JSON object of secrets
+struct AwsProvider {
+    client: reqwest::Client,
+    secret_id: String,
+    region: String,
+    endpoint: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl AwsProvider {
+    // Signature Version 4 headers for a GetSecretValue call
+    fn signed_headers(&self, host: &str, body: &str) -> Vec<(String, String)> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut headers = vec![
+            ("content-type".to_string(), "application/x-amz-json-1.1".to_string()),
+            ("host".to_string(), host.to_string()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+            ("x-amz-target".to_string(), "secretsmanager.GetSecretValue".to_string()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers.sort();
+
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+        let signed_names = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_request = format!(
+            "POST\n/\n\n{}\n{}\n{:x}",
+            canonical_headers, signed_names, Sha256::digest(body.as_bytes())
+        );
+        let scope = format!("{}/{}/secretsmanager/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date, scope, Sha256::digest(canonical_request.as_bytes())
+        );
+        let key = ["secretsmanager", "aws4_request"].iter().fold(
+            hmac(&hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date), &self.region),
+            |key, part| hmac(&key, part),
+        );
+        let signature: String = hmac(&key, &string_to_sign).iter().map(|b| format!("{:02x}", b)).collect();
+
+        headers.push((
+            "authorization".to_string(),
+            format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", self.access_key, scope, signed_names, signature),
+        ));
+        headers
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for AwsProvider {
+    fn name(&self) -> &'static str {
+        "aws"
+    }
+
+    async fn load(&self) -> anyhow::Result<HashMap<String, String>> {
+        let url = reqwest::Url::parse(&self.endpoint)?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(anyhow::anyhow!("invalid AWS endpoint {}", self.endpoint)),
+        };
+        let body = json!({ "SecretId": self.secret_id }).to_string();
+        let mut request = self.client.post(url).body(body.clone()).timeout(HTTP_TIMEOUT);
+        for (name, value) in self.signed_headers(&host, &body) {
+            if name != "host" {
+                request = request.header(name, value);
+            }
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("GetSecretValue failed ({}): {}", status, text.trim()));
+        }
+        let body: Value = response.json().await?;
+        let secret: Value = body["SecretString"].as_str()
+            .ok_or_else(|| anyhow::anyhow!("{} has no SecretString", self.secret_id))
+            .and_then(|s| serde_json::from_str(s).map_err(|_| anyhow::anyhow!("{} is not a JSON object", self.secret_id)))?;
+        string_fields(&secret).ok_or_else(|| anyhow::anyhow!("{} is not a JSON object", self.secret_id))
+    }
+}
+
+// Fields of a JSON object as strings
+fn string_fields(value: &Value) -> Option<HashMap<String, String>> {
+    let fields = value.as_object()?.iter().map(|(name, value)| {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        (name.clone(), value)
+    });
+    Some(fields.collect())
+}
+
+pub struct Secrets {
+    provider: Option<Box<dyn SecretsProvider>>,
+    values: RwLock<HashMap<String, String>>,
+    refresh: Duration,
+}
+
+// A named secret, read each time it's used so rotations apply
+#[derive(Clone)]
+pub struct Secret {
+    secrets: Arc<Secrets>,
+    name: &'static str,
+}
+
+impl Secret {
+    pub fn value(&self) -> Option<String> {
+        self.secrets.get(self.name)
+    }
+}
+
+impl Secrets {
+    // Build the provider selected by SECRETS_PROVIDER and load its secrets
+    pub async fn from_env() -> anyhow::Result<Arc<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let required = |name: &str, provider: &str| {
+            var(name).ok_or_else(|| anyhow::anyhow!("{} is required for SECRETS_PROVIDER={}", name, provider))
+        };
+        let provider: Option<Box<dyn SecretsProvider>> = match var("SECRETS_PROVIDER").unwrap_or_else(|| "env".to_string()).to_lowercase().as_str() {
+            "env" => None,
+            "file" => Some(Box::new(FileProvider {
+                dir: PathBuf::from(var("SECRETS_DIR").unwrap_or_else(|| "/run/secrets".to_string())),
+            })),
+            "vault" => Some(Box::new(VaultProvider {
+                client: outbound::client(Integration::Secrets),
+                addr: required("VAULT_ADDR", "vault")?,
+                token: required("VAULT_TOKEN", "vault")?,
+                namespace: var("VAULT_NAMESPACE"),
+                path: var("VAULT_SECRET_PATH").unwrap_or_else(|| "secret/data/zkengine".to_string()),
+            })),
+            "aws" => {
+                let region = required("AWS_REGION", "aws")?;
+                Some(Box::new(AwsProvider {
+                    client: outbound::client(Integration::Secrets),
+                    secret_id: required("AWS_SECRET_ID", "aws")?,
+                    endpoint: var("AWS_SECRETS_ENDPOINT").unwrap_or_else(|| format!("https://secretsmanager.{}.amazonaws.com/", region)),
+                    region,
+                    access_key: required("AWS_ACCESS_KEY_ID", "aws")?,
+                    secret_key: required("AWS_SECRET_ACCESS_KEY", "aws")?,
+                    session_token: var("AWS_SESSION_TOKEN"),
+                }))
+            }
+            other => return Err(anyhow::anyhow!("Unknown SECRETS_PROVIDER: {}", other)),
+        };
+        let secrets = Secrets {
+            provider,
+            values: RwLock::new(HashMap::new()),
+            refresh: Duration::from_secs(var("SECRETS_REFRESH_SECS").and_then(|v| v.parse().ok()).unwrap_or(300)),
+        };
+        if let Some(provider) = &secrets.provider {
+            let values = provider.load().await.map_err(|e| anyhow::anyhow!("failed to load secrets from {}: {}", provider.name(), e))?;
+            info!("Loaded {} secrets from the {} provider", values.len(), provider.name());
+            *secrets.values.write().unwrap() = values;
+        }
+        Ok(Arc::new(secrets))
+    }
+
+    // Fixed values, falling back to the environment, for tests
+    #[cfg(test)]
+    pub fn with_values(values: &[(&str, &str)]) -> Arc<Self> {
+        let values = values.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        Arc::new(Secrets { provider: None, values: RwLock::new(values), refresh: Duration::ZERO })
+    }
+
+    // From the provider, else the environment variable of the same name
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = self.values.read().unwrap().get(name).cloned();
+        value.or_else(|| std::env::var(name).ok()).filter(|v| !v.trim().is_empty())
+    }
+
+    pub fn secret(self: &Arc<Self>, name: &'static str) -> Secret {
+        Secret { secrets: self.clone(), name }
+    }
+
+    // Reload the secrets in the background; a failed reload keeps the
+    // previous values
+    pub fn spawn_refresh(self: &Arc<Self>) {
+        let Some(provider) = &self.provider else { return };
+        if self.refresh.is_zero() {
+            return;
+        }
+        info!("Reloading {} secrets every {:?}", provider.name(), self.refresh);
+        let secrets = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(secrets.refresh).await;
+                secrets.reload().await;
+            }
+        });
+    }
+
+    async fn reload(&self) {
+        let Some(provider) = &self.provider else { return };
+        let values = match provider.load().await {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("Failed to reload secrets from {}: {}", provider.name(), e);
+                return;
+            }
+        };
+        let mut current = self.values.write().unwrap();
+        // Names only; values never reach the logs
+        let mut changed: Vec<String> = values.iter()
+            .filter(|(name, value)| current.get(*name) != Some(value))
+            .map(|(name, _)| name.clone())
+            .chain(current.keys().filter(|name| !values.contains_key(*name)).cloned())
+            .collect();
+        if !changed.is_empty() {
+            changed.sort();
+            info!("Secrets rotated: {}", changed.join(", "));
+        }
+        *current = values;
+    }
+}
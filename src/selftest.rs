@@ -0,0 +1,148 @@
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    prove_command, verify_command, AppState,
+};
+
+// zkEngine self-test for `/api/health?deep=true`: proves and verifies
+// add(1, 2) in a scratch directory and reports how long the round trip took.
+// It catches a missing or broken binary, or a prover/verifier parameter
+// mismatch, before users do. Results are cached briefly so frequent
+// monitoring doesn't keep the prover busy.
+
+const FUNCTION: &str = "add";
+const ARGUMENTS: [&str; 2] = ["1", "2"];
+const STEP_SIZE: u64 = 50;
+
+#[derive(Serialize, Clone)]
+pub struct SelfTestResult {
+    pub passed: bool,
+    pub prove_ms: Option<u128>,
+    pub verify_ms: Option<u128>,
+    pub round_trip_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
+    pub tested_at: chrono::DateTime<chrono::Utc>,
+}
+
+pub struct SelfTest {
+    // Most recent result; the lock also keeps self-tests from overlapping
+    last: Mutex<Option<(Instant, SelfTestResult)>>,
+    cache_for: Duration,
+    timeout: Duration,
+}
+
+impl SelfTest {
+    pub fn from_env() -> Self {
+        let secs = |name: &str, default: u64| {
+            Duration::from_secs(std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default))
+        };
+        SelfTest {
+            last: Mutex::new(None),
+            cache_for: secs("HEALTH_SELF_TEST_CACHE_SECS", 30),
+            timeout: secs("HEALTH_SELF_TEST_TIMEOUT_SECS", 120),
+        }
+    }
+
+    // Run the self-test, or reuse a result younger than the cache window.
+    // Returns the result and whether it came from the cache.
+    pub async fn run(&self, state: &AppState) -> (SelfTestResult, bool) {
+        let mut last = self.last.lock().await;
+        if let Some((at, result)) = last.as_ref() {
+            if at.elapsed() < self.cache_for {
+                return (result.clone(), true);
+            }
+        }
+        let result = self.execute(state).await;
+        if result.passed {
+            info!("zkEngine self-test passed in {}ms", result.round_trip_ms);
+        } else {
+            warn!("zkEngine self-test failed: {}", result.error.as_deref().unwrap_or_default());
+        }
+        *last = Some((Instant::now(), result.clone()));
+        (result, false)
+    }
+
+    async fn execute(&self, state: &AppState) -> SelfTestResult {
+        let start = Instant::now();
+        let scratch = Path::new(&state.proofs_dir).join(format!(".selftest-{}", Uuid::new_v4()));
+        let (prove_ms, verify_ms, error) = match self.prove_and_verify(state, &scratch).await {
+            Ok((prove_ms, verify_ms)) => (Some(prove_ms), Some(verify_ms), None),
+            Err((prove_ms, error)) => (prove_ms, None, Some(error)),
+        };
+        tokio::fs::remove_dir_all(&scratch).await.ok();
+        SelfTestResult {
+            passed: error.is_none(),
+            prove_ms,
+            verify_ms,
+            round_trip_ms: start.elapsed().as_millis(),
+            error_code: error.as_ref().map(|e| e.code),
+            error: error.map(|e| e.message),
+            tested_at: chrono::Utc::now(),
+        }
+    }
+
+    // Timings in milliseconds; on failure, the prove time if proving succeeded
+    async fn prove_and_verify(&self, state: &AppState, scratch: &Path) -> Result<(u128, u128), (Option<u128>, AppError)> {
+        let spec = catalog::find(FUNCTION)
+            .ok_or_else(|| (None, AppError::new(ErrorCode::UnknownFunction, format!("{} is not in the function catalog", FUNCTION))))?;
+        let wasm_path = PathBuf::from(&state.wasm_dir).join(spec.wasm_file);
+        if !wasm_path.exists() {
+            return Err((None, AppError::new(ErrorCode::WasmNotFound, format!("Self-test WASM not found: {}", wasm_path.display()))));
+        }
+        tokio::fs::create_dir_all(scratch).await.map_err(|e| {
+            (None, AppError::new(ErrorCode::StorageError, format!("Failed to create {}: {}", scratch.display(), e)))
+        })?;
+
+        let arguments: Vec<String> = ARGUMENTS.iter().map(|a| a.to_string()).collect();
+        let prove = prove_command(&state.sandbox, &state.zkengine_binary, &wasm_path, STEP_SIZE, scratch, &arguments);
+        let prove_ms = self.run_step("prove", prove).await.map_err(|e| (None, e))?;
+
+        let proof_file = scratch.join("proof.bin");
+        let public_file = scratch.join("public.json");
+        if !proof_file.exists() {
+            return Err((Some(prove_ms), AppError::new(ErrorCode::ProofFileMissing, "zkEngine prove succeeded but wrote no proof.bin")));
+        }
+        let verify = verify_command(&state.sandbox, &state.zkengine_binary, STEP_SIZE, &proof_file, &public_file);
+        let verify_ms = self.run_step("verify", verify).await.map_err(|e| (Some(prove_ms), e))?;
+        Ok((prove_ms, verify_ms))
+    }
+
+    // Run one zkEngine command, killing it if it outlives the timeout
+    async fn run_step(&self, name: &str, command: Command) -> Result<u128, AppError> {
+        let start = Instant::now();
+        let mut command = tokio::process::Command::from(command);
+        command.kill_on_drop(true);
+        let output = match tokio::time::timeout(self.timeout, command.output()).await {
+            Err(_) => {
+                return Err(AppError::new(ErrorCode::Timeout, format!("zkEngine {} timed out after {:?}", name, self.timeout)));
+            }
+            Ok(Err(e)) => {
+                return Err(AppError::new(ErrorCode::ProverUnavailable, format!("Failed to run zkEngine {}: {}", name, e)));
+            }
+            Ok(Ok(output)) => output,
+        };
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let code = match (name, output.status.code()) {
+                (_, None) => ErrorCode::ProverCrashed,
+                ("verify", _) => ErrorCode::VerifierMismatch,
+                _ => ErrorCode::ProverFailed,
+            };
+            return Err(AppError::new(code, format!("zkEngine {} failed ({}): {}", name, output.status, stderr.trim())));
+        }
+        Ok(start.elapsed().as_millis())
+    }
+}
@@ -0,0 +1,268 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{Html, IntoResponse, Json, Response},
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    read_public_outputs, retention, short_id,
+    signing::ProofSigner,
+    AppState, ProofStatus,
+};
+
+// Share links, so an agent can prove something to a counterparty without
+// giving it API access:
+//
+//   POST /api/proofs/:id/share   {"expires_in_hours": 24}   provers
+//   GET  /share/:token           public; HTML, or JSON with ?format=json or
+//                                an Accept: application/json header
+//
+// The shared page shows only the proof's latest verification, its public
+// outputs and the verification's signed receipt. Tokens aren't stored:
+// they are the base64url claims and signature, '.'-separated, signed with
+// the proof signing key over these lines joined with '\n':
+//
+//   zkengine-agentkit-share-v1
+//   <proof id>
+//   <expires_at>   RFC 3339
+//   <key_id>       ID of the key that signed it
+//
+// A link lasts SHARE_TTL_HOURS (default 72) unless the request says
+// otherwise, never past the proof's own expiry, and stops working when the
+// proof is revoked or deleted.
+
+const SHARE_PREFIX: &str = "zkengine-agentkit-share-v1";
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    proof_id: String,
+    expires_at: String,
+    key_id: String,
+}
+
+impl Claims {
+    fn message(&self) -> String {
+        [SHARE_PREFIX, &self.proof_id, &self.expires_at, &self.key_id].join("\n")
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct ShareRequest {
+    #[serde(default)]
+    expires_in_hours: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct ViewQuery {
+    #[serde(default)]
+    format: Option<String>,
+}
+
+fn default_ttl_hours() -> i64 {
+    std::env::var("SHARE_TTL_HOURS").ok().and_then(|v| v.parse().ok()).unwrap_or(72)
+}
+
+fn invalid_link() -> AppError {
+    AppError::new(ErrorCode::NotFound, "This share link is invalid or has expired")
+}
+
+// Sign a share token for a completed proof; returns it with its expiry
+pub async fn create(state: &AppState, id: &str, request: ShareRequest) -> Result<(String, DateTime<Utc>), AppError> {
+    let hours = request.expires_in_hours.unwrap_or_else(default_ttl_hours);
+    if hours <= 0 {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "expires_in_hours must be positive"));
+    }
+    let id = retention::resolve_proof_id(state, id).await?;
+    let proof = state.proof_store.get(&id).await.ok_or_else(AppError::proof_not_found)?;
+    if !matches!(proof.status, ProofStatus::Complete) {
+        return Err(AppError::new(ErrorCode::ProofNotReady, "Only completed proofs can be shared"));
+    }
+    if !proof.validity.is_current() {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "Expired or revoked proofs can't be shared"));
+    }
+    let mut expires_at = Utc::now() + Duration::hours(hours);
+    if let Some(proof_expiry) = proof.validity.expires_at {
+        expires_at = expires_at.min(proof_expiry);
+    }
+
+    let token = sign_token(&state.signer, &id, expires_at);
+    info!("Proof {} shared until {}", short_id(&id), expires_at.to_rfc3339_opts(SecondsFormat::Secs, true));
+    Ok((token, expires_at))
+}
+
+fn sign_token(signer: &ProofSigner, proof_id: &str, expires_at: DateTime<Utc>) -> String {
+    let mut claims = Claims {
+        proof_id: proof_id.to_string(),
+        expires_at: expires_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        key_id: String::new(),
+    };
+    let (_, signature) = signer.sign_message(|key_id| {
+        claims.key_id = key_id.to_string();
+        claims.message()
+    });
+    let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let signature = base64::engine::general_purpose::STANDARD.decode(signature).unwrap_or_default();
+    format!(
+        "{}.{}",
+        engine.encode(serde_json::to_vec(&claims).unwrap_or_default()),
+        engine.encode(signature),
+    )
+}
+
+// The claims of a token that is signed by us and hasn't expired
+fn check_token(signer: &ProofSigner, token: &str) -> Option<Claims> {
+    let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    let (claims, signature) = token.split_once('.')?;
+    let claims: Claims = serde_json::from_slice(&engine.decode(claims).ok()?).ok()?;
+    let signature = base64::engine::general_purpose::STANDARD.encode(engine.decode(signature).ok()?);
+    if !signer.check_message(&claims.key_id, &claims.message(), &signature) {
+        return None;
+    }
+    let expires_at = DateTime::parse_from_rfc3339(&claims.expires_at).ok()?;
+    (expires_at > Utc::now()).then_some(claims)
+}
+
+// What a share link shows
+async fn view(state: &AppState, token: &str) -> Result<Value, AppError> {
+    let claims = check_token(&state.signer, token).ok_or_else(invalid_link)?;
+    let proof = state.proof_store.get(&claims.proof_id).await.ok_or_else(invalid_link)?;
+    if !proof.validity.is_current() {
+        return Err(invalid_link());
+    }
+    let verification = state.verification_store.lock().await
+        .iter()
+        .rev()
+        .find(|v| v.proof_id == proof.id)
+        .map(|v| (v.is_valid, v.timestamp, v.receipt.clone()));
+    let (status, verified_at, receipt) = match verification {
+        Some((true, at, receipt)) => ("valid", Some(at), receipt),
+        Some((false, at, receipt)) => ("invalid", Some(at), receipt),
+        None => ("unverified", None, None),
+    };
+    Ok(json!({
+        "proof_id": proof.id,
+        "verification": { "status": status, "verified_at": verified_at },
+        "function": proof.metadata.function,
+        "statement": proof.metadata.statement,
+        "public_outputs": read_public_outputs(state, &proof.id).await,
+        "receipt": receipt,
+        "link_expires_at": claims.expires_at,
+    }))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render(view: &Value) -> String {
+    let pretty = |value: &Value| escape(&serde_json::to_string_pretty(value).unwrap_or_default());
+    format!(
+        "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>Shared proof {id}</title></head><body>\
+         <h1>Proof {id}</h1><p>Verification: <strong>{status}</strong> {verified_at}</p>\
+         <h2>Statement: {function}</h2><pre>{statement}</pre><h2>Public outputs</h2><pre>{outputs}</pre><h2>Receipt</h2><pre>{receipt}</pre>\
+         <p>This link expires at {expires}.</p></body></html>",
+        id = escape(view["proof_id"].as_str().unwrap_or_default()),
+        status = escape(view["verification"]["status"].as_str().unwrap_or_default()),
+        verified_at = escape(view["verification"]["verified_at"].as_str().unwrap_or_default()),
+        function = escape(view["function"].as_str().unwrap_or_default()),
+        statement = pretty(&view["statement"]),
+        outputs = pretty(&view["public_outputs"]),
+        receipt = pretty(&view["receipt"]),
+        expires = escape(view["link_expires_at"].as_str().unwrap_or_default()),
+    )
+}
+
+pub async fn create_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    request: Option<Json<ShareRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match create(&state, &id, request).await {
+        Ok((token, expires_at)) => Json(json!({
+            "success": true,
+            "token": token,
+            "url": format!("/share/{}", token),
+            "expires_at": expires_at,
+        }))
+        .into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn view_handler(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Query(query): Query<ViewQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let wants_json = query.format.as_deref() == Some("json")
+        || headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).is_some_and(|v| v.contains("application/json"));
+    match view(&state, &token).await {
+        Ok(view) if wants_json => Json(json!({ "success": true, "share": view })).into_response(),
+        Ok(view) => Html(render(&view)).into_response(),
+        Err(error) if wants_json => error.into_response(),
+        Err(error) => (error.code.http_status(), Html(format!("<p>{}</p>", escape(&error.message)))).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer() -> ProofSigner {
+        ProofSigner::with_seed([7; 32])
+    }
+
+    #[test]
+    fn signed_tokens_check_out() {
+        let signer = signer();
+        let token = sign_token(&signer, "proof-1", Utc::now() + Duration::hours(1));
+        let claims = check_token(&signer, &token).expect("valid token");
+        assert_eq!(claims.proof_id, "proof-1");
+    }
+
+    #[test]
+    fn expired_tokens_are_refused() {
+        let signer = signer();
+        let token = sign_token(&signer, "proof-1", Utc::now() - Duration::seconds(1));
+        assert!(check_token(&signer, &token).is_none());
+    }
+
+    #[test]
+    fn tampered_tokens_are_refused() {
+        let signer = signer();
+        let token = sign_token(&signer, "proof-1", Utc::now() + Duration::hours(1));
+        let (claims, signature) = token.split_once('.').unwrap();
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+        // Another proof, or a later expiry, under the original signature
+        let mut forged: Claims = serde_json::from_slice(&engine.decode(claims).unwrap()).unwrap();
+        forged.proof_id = "proof-2".to_string();
+        let forged = engine.encode(serde_json::to_vec(&forged).unwrap());
+        assert!(check_token(&signer, &format!("{}.{}", forged, signature)).is_none());
+
+        let mut later: Claims = serde_json::from_slice(&engine.decode(claims).unwrap()).unwrap();
+        later.expires_at = (Utc::now() + Duration::days(365)).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let later = engine.encode(serde_json::to_vec(&later).unwrap());
+        assert!(check_token(&signer, &format!("{}.{}", later, signature)).is_none());
+
+        let mut bytes = engine.decode(signature).unwrap();
+        bytes[0] ^= 1;
+        assert!(check_token(&signer, &format!("{}.{}", claims, engine.encode(bytes))).is_none());
+        assert!(check_token(&signer, claims).is_none());
+        assert!(check_token(&signer, "not.a-token").is_none());
+    }
+
+    #[test]
+    fn tokens_from_another_key_are_refused() {
+        let token = sign_token(&ProofSigner::with_seed([8; 32]), "proof-1", Utc::now() + Duration::hours(1));
+        assert!(check_token(&signer(), &token).is_none());
+    }
+}
@@ -0,0 +1,306 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpStream, UdpSocket},
+    sync::mpsc,
+};
+use tracing::{field::{Field, Visit}, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+use crate::outbound::{self, Integration};
+
+// Audit log export to a SIEM. Everything logged to the "audit" target
+// (approvals, rejections, revocations, tampered proofs, webhook changes,
+// ...) is also sent to an external collector, so compliance teams can
+// watch proof activity in their own monitoring stack:
+//
+//   SIEM_ENDPOINT      udp://host:514 or tcp://host:601 for syslog (RFC 5424;
+//                      TCP uses octet counting), or an http(s):// URL that
+//                      takes batches as POST bodies (e.g. Splunk HEC, Logstash)
+//   SIEM_FORMAT        json (default) or cef
+//   SIEM_AUTH_HEADER   Authorization header for HTTP, e.g. "Splunk <token>"
+//   SIEM_BUFFER        audit events held while the collector is slow or
+//                      down (default 10000)
+//
+// Logging never waits on the collector: events queue in the buffer, failed
+// sends are retried with backoff (HTTP batches and TCP messages are kept
+// until they go through), and once the buffer is full new events are
+// dropped and counted. The count goes out with the next event that fits,
+// as `dropped_events`, so gaps are visible in the SIEM.
+
+const DEFAULT_BUFFER: usize = 10_000;
+// Events per HTTP request
+const BATCH_SIZE: usize = 100;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// local0
+const SYSLOG_FACILITY: u8 = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Cef,
+}
+
+enum Endpoint {
+    Udp(String),
+    Tcp(String),
+    Http(String),
+}
+
+#[derive(Serialize)]
+struct AuditEvent {
+    timestamp: DateTime<Utc>,
+    level: String,
+    message: String,
+    #[serde(flatten)]
+    fields: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dropped_events: Option<u64>,
+    #[serde(skip)]
+    severity: Level,
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => {
+                self.fields.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name => {
+                self.fields.insert(name.to_string(), format!("{:?}", value));
+            }
+        }
+    }
+}
+
+pub struct SiemLayer {
+    queue: mpsc::Sender<AuditEvent>,
+    dropped: AtomicU64,
+}
+
+// The export layer, when SIEM_ENDPOINT is set; its sender runs on the
+// current tokio runtime
+pub fn layer() -> Option<SiemLayer> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let url = var("SIEM_ENDPOINT")?;
+    let endpoint = match url.split_once("://") {
+        Some(("udp", address)) => Endpoint::Udp(address.to_string()),
+        Some(("tcp", address)) => Endpoint::Tcp(address.to_string()),
+        Some(("http" | "https", _)) => Endpoint::Http(url.clone()),
+        _ => {
+            eprintln!("SIEM_ENDPOINT must be a udp://, tcp://, http:// or https:// URL; audit export is off");
+            return None;
+        }
+    };
+    let format = match var("SIEM_FORMAT").as_deref() {
+        None | Some("json") => Format::Json,
+        Some("cef") => Format::Cef,
+        Some(other) => {
+            eprintln!("Unknown SIEM_FORMAT {}; sending JSON", other);
+            Format::Json
+        }
+    };
+    let buffer = var("SIEM_BUFFER").and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_BUFFER).max(1);
+    let (queue, events) = mpsc::channel(buffer);
+    let exporter = Exporter { format, auth_header: var("SIEM_AUTH_HEADER"), client: outbound::client(Integration::Siem) };
+    tokio::spawn(exporter.run(endpoint, events));
+    Some(SiemLayer { queue, dropped: AtomicU64::new(0) })
+}
+
+impl<S: Subscriber> Layer<S> for SiemLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if metadata.target() != "audit" {
+            return;
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        let audit = AuditEvent {
+            timestamp: Utc::now(),
+            level: metadata.level().as_str().to_lowercase(),
+            message: fields.message,
+            fields: fields.fields,
+            dropped_events: (dropped > 0).then_some(dropped),
+            severity: *metadata.level(),
+        };
+        if self.queue.try_send(audit).is_err() {
+            // Put back the earlier count along with this event
+            self.dropped.fetch_add(dropped + 1, Ordering::Relaxed);
+        }
+    }
+}
+
+struct Exporter {
+    format: Format,
+    auth_header: Option<String>,
+    client: reqwest::Client,
+}
+
+// CEF escapes \ and | in the header, and \, = and line breaks in extensions
+fn cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn cef_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('=', "\\=").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+impl Exporter {
+    fn render(&self, event: &AuditEvent) -> String {
+        match self.format {
+            Format::Json => serde_json::to_string(event).unwrap_or_default(),
+            Format::Cef => {
+                let severity = match event.severity {
+                    level if level == Level::ERROR => 9,
+                    level if level == Level::WARN => 6,
+                    _ => 3,
+                };
+                // The message names the kind of event, e.g. "Proof revoked"
+                let signature: String = event.message.to_lowercase().chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .take(64)
+                    .collect();
+                let mut extensions = vec![
+                    format!("rt={}", event.timestamp.timestamp_millis()),
+                    format!("msg={}", cef_value(&event.message)),
+                ];
+                extensions.extend(event.fields.iter().map(|(key, value)| format!("{}={}", key, cef_value(value))));
+                if let Some(dropped) = event.dropped_events {
+                    extensions.push(format!("dropped_events={}", dropped));
+                }
+                format!(
+                    "CEF:0|zkEngine|AgentKit|{}|{}|{}|{}|{}",
+                    env!("CARGO_PKG_VERSION"),
+                    cef_header(&signature),
+                    cef_header(&event.message),
+                    severity,
+                    extensions.join(" ")
+                )
+            }
+        }
+    }
+
+    // RFC 5424 syslog line around a rendered event
+    fn syslog(&self, event: &AuditEvent) -> String {
+        let severity: u8 = match event.severity {
+            level if level == Level::ERROR => 3,
+            level if level == Level::WARN => 4,
+            _ => 6,
+        };
+        let hostname = std::env::var("HOSTNAME").ok().filter(|h| !h.is_empty()).unwrap_or_else(|| "-".to_string());
+        format!(
+            "<{}>1 {} {} zkengine-agentkit {} audit - {}",
+            SYSLOG_FACILITY * 8 + severity,
+            event.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            hostname,
+            std::process::id(),
+            self.render(event)
+        )
+    }
+
+    async fn run(self, endpoint: Endpoint, mut events: mpsc::Receiver<AuditEvent>) {
+        match endpoint {
+            Endpoint::Udp(address) => {
+                let socket = match UdpSocket::bind("0.0.0.0:0").await {
+                    Ok(socket) => socket,
+                    Err(e) => return tracing::error!("Audit export is off; no UDP socket: {}", e),
+                };
+                // UDP gives no delivery guarantee to retry on
+                while let Some(event) = events.recv().await {
+                    if let Err(e) = socket.send_to(self.syslog(&event).as_bytes(), &address).await {
+                        tracing::warn!("Failed to send an audit event to {}: {}", address, e);
+                    }
+                }
+            }
+            Endpoint::Tcp(address) => {
+                let mut stream: Option<TcpStream> = None;
+                let mut backoff = Duration::from_secs(1);
+                while let Some(event) = events.recv().await {
+                    let line = self.syslog(&event);
+                    let frame = format!("{} {}", line.len(), line);
+                    // Hold the event until it is sent; the buffer absorbs the rest
+                    loop {
+                        let result = match &mut stream {
+                            Some(connected) => connected.write_all(frame.as_bytes()).await,
+                            None => match TcpStream::connect(&address).await {
+                                Ok(connected) => {
+                                    stream = Some(connected);
+                                    continue;
+                                }
+                                Err(e) => Err(e),
+                            },
+                        };
+                        match result {
+                            Ok(()) => {
+                                backoff = Duration::from_secs(1);
+                                break;
+                            }
+                            Err(e) => {
+                                tracing::warn!("Audit export to {} failed, retrying in {:?}: {}", address, backoff, e);
+                                stream = None;
+                                tokio::time::sleep(backoff).await;
+                                backoff = (backoff * 2).min(MAX_BACKOFF);
+                            }
+                        }
+                    }
+                }
+            }
+            Endpoint::Http(url) => {
+                let mut backoff = Duration::from_secs(1);
+                while let Some(first) = events.recv().await {
+                    let mut batch = vec![first];
+                    while batch.len() < BATCH_SIZE {
+                        match events.try_recv() {
+                            Ok(event) => batch.push(event),
+                            Err(_) => break,
+                        }
+                    }
+                    let (body, content_type) = match self.format {
+                        Format::Json => (json!(batch).to_string(), "application/json"),
+                        Format::Cef => (batch.iter().map(|e| self.render(e)).collect::<Vec<_>>().join("\n"), "text/plain"),
+                    };
+                    loop {
+                        let mut request = self.client.post(&url)
+                            .header("Content-Type", content_type)
+                            .body(body.clone())
+                            .timeout(Duration::from_secs(10));
+                        if let Some(auth) = &self.auth_header {
+                            request = request.header("Authorization", auth);
+                        }
+                        let error = match request.send().await {
+                            Ok(response) if response.status().is_success() => break,
+                            Ok(response) => response.status().to_string(),
+                            Err(e) => e.to_string(),
+                        };
+                        tracing::warn!("Audit export to the SIEM failed, retrying in {:?}: {}", backoff, error);
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                    backoff = Duration::from_secs(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use axum::{extract::State, response::Json};
+use base64::Engine;
+use chrono::{SecondsFormat, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tracing::{info, warn};
+
+use crate::{
+    secrets::{Secret, Secrets},
+    AppState,
+};
+
+// Ed25519 signatures over completed proofs, so downstream systems can check
+// a proof came from this service untampered. The key comes from the
+// PROOF_SIGNING_KEY secret (base64 32-byte seed) or the file
+// PROOF_SIGNING_KEY_FILE (default ./proof_signing_key), which is generated on
+// first start. The public key is served at /.well-known/proof-signing-key,
+// along with keys rotated out since startup so older signatures still check.
+//
+// The signed message is these lines joined with '\n':
+//
+//   zkengine-agentkit-proof-v1
+//   <proof id>
+//   <proof_hash>           sha256 of the proof file, hex
+//   <wasm_hash>            sha256 of the WASM module, hex
+//   <public_outputs_hash>  sha256 of public.json (of nothing if absent), hex
+//   <signed_at>            RFC 3339, as stored
+//
+// Verifier nodes sign their results the same way, with the lines:
+//
+//   zkengine-agentkit-verification-v1
+//   <verification id>
+//   <proof_hash>           sha256 of the proof file, hex
+//   <result>               valid or invalid
+//   <signed_at>            RFC 3339, as stored
+
+const MESSAGE_PREFIX: &str = "zkengine-agentkit-proof-v1";
+const VERIFICATION_MESSAGE_PREFIX: &str = "zkengine-agentkit-verification-v1";
+const DEFAULT_KEY_FILE: &str = "./proof_signing_key";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProofSignature {
+    pub algorithm: String,
+    pub key_id: String,
+    pub proof_hash: String,
+    pub wasm_hash: String,
+    pub public_outputs_hash: String,
+    pub signed_at: String,
+    // Base64
+    pub signature: String,
+}
+
+// A verifier node's signature over one of its results
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VerificationSignature {
+    pub algorithm: String,
+    pub key_id: String,
+    pub signed_at: String,
+    // Base64
+    pub signature: String,
+}
+
+pub fn verification_message(verification_id: &str, proof_hash: &str, is_valid: bool, signed_at: &str) -> String {
+    let result = if is_valid { "valid" } else { "invalid" };
+    [VERIFICATION_MESSAGE_PREFIX, verification_id, proof_hash, result, signed_at].join("\n")
+}
+
+// Check a signature against a base64 Ed25519 public key
+pub fn check_signature(public_key: &str, message: &str, signature: &str) -> bool {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let key = engine.decode(public_key).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok());
+    let signature = engine.decode(signature).ok().and_then(|bytes| <[u8; 64]>::try_from(bytes).ok());
+    let (Some(key), Some(signature)) = (key, signature) else {
+        return false;
+    };
+    match VerifyingKey::from_bytes(&key) {
+        Ok(key) => key.verify(message.as_bytes(), &Signature::from_bytes(&signature)).is_ok(),
+        Err(_) => false,
+    }
+}
+
+pub struct ProofSigner {
+    source: Secret,
+    keys: Mutex<SignerKeys>,
+}
+
+struct SignerKeys {
+    // PROOF_SIGNING_KEY as last seen, to notice rotations
+    source_value: Option<String>,
+    key: SigningKey,
+    key_id: String,
+    retired: Vec<(String, VerifyingKey)>,
+}
+
+fn key_id(key: &SigningKey) -> String {
+    sha256_hex(key.verifying_key().as_bytes())[..16].to_string()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+async fn file_hash(path: &Path) -> String {
+    crate::verifier::sha256_file(path).await.unwrap_or_else(|_| sha256_hex(b""))
+}
+
+fn decode_seed(encoded: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded.trim())?;
+    bytes.try_into().map_err(|_| anyhow::anyhow!("signing key must be 32 bytes"))
+}
+
+// Read the key file, creating it with a fresh key if it doesn't exist
+fn load_or_create(path: &Path) -> anyhow::Result<[u8; 32]> {
+    if path.exists() {
+        return decode_seed(&std::fs::read_to_string(path)?);
+    }
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|e| anyhow::anyhow!("no randomness for a signing key: {}", e))?;
+    std::fs::write(path, base64::engine::general_purpose::STANDARD.encode(seed))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    info!("Generated proof signing key in {}", path.display());
+    Ok(seed)
+}
+
+impl ProofSigner {
+    pub fn from_env(secrets: &Arc<Secrets>) -> Self {
+        let source = secrets.secret("PROOF_SIGNING_KEY");
+        let source_value = source.value();
+        let seed = match &source_value {
+            Some(encoded) => decode_seed(encoded).map_err(|e| anyhow::anyhow!("PROOF_SIGNING_KEY: {}", e)),
+            None => {
+                let path = std::env::var("PROOF_SIGNING_KEY_FILE").unwrap_or_else(|_| DEFAULT_KEY_FILE.to_string());
+                load_or_create(Path::new(&path)).map_err(|e| anyhow::anyhow!("{}: {}", path, e))
+            }
+        };
+        let seed = seed.unwrap_or_else(|e| {
+            warn!("Failed to load the proof signing key ({}); using a temporary key", e);
+            let mut seed = [0u8; 32];
+            getrandom::getrandom(&mut seed).expect("no randomness for a signing key");
+            seed
+        });
+        let key = SigningKey::from_bytes(&seed);
+        let key_id = key_id(&key);
+        info!("Signing completed proofs with key {}", key_id);
+        ProofSigner { source, keys: Mutex::new(SignerKeys { source_value, key, key_id, retired: Vec::new() }) }
+    }
+
+    // A signer with a fixed key, for tests
+    #[cfg(test)]
+    pub fn with_seed(seed: [u8; 32]) -> Self {
+        let key = SigningKey::from_bytes(&seed);
+        let key_id = key_id(&key);
+        let source = Secrets::with_values(&[]).secret("PROOF_SIGNING_KEY");
+        ProofSigner { source, keys: Mutex::new(SignerKeys { source_value: None, key, key_id, retired: Vec::new() }) }
+    }
+
+    // Current key, switching to a new one when PROOF_SIGNING_KEY was rotated
+    fn current_key(&self) -> (SigningKey, String) {
+        let value = self.source.value();
+        let mut keys = self.keys.lock().unwrap();
+        if value.is_some() && value != keys.source_value {
+            match value.as_deref().map(decode_seed) {
+                Some(Ok(seed)) => {
+                    let key = SigningKey::from_bytes(&seed);
+                    let old_id = std::mem::replace(&mut keys.key_id, key_id(&key));
+                    let old_key = std::mem::replace(&mut keys.key, key);
+                    info!("Proof signing key rotated from {} to {}", old_id, keys.key_id);
+                    keys.retired.push((old_id, old_key.verifying_key()));
+                }
+                Some(Err(e)) => warn!("Ignoring the rotated PROOF_SIGNING_KEY: {}", e),
+                None => {}
+            }
+            keys.source_value = value;
+        }
+        (keys.key.clone(), keys.key_id.clone())
+    }
+
+    // Sign a completed proof; the proof hash is the one already stored in
+    // its metrics
+    pub async fn sign(&self, proof_id: &str, proof_hash: &str, wasm_path: &Path, public_path: &Path) -> ProofSignature {
+        let wasm_hash = file_hash(wasm_path).await;
+        let public_outputs_hash = file_hash(public_path).await;
+        let signed_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let message = [MESSAGE_PREFIX, proof_id, proof_hash, &wasm_hash, &public_outputs_hash, &signed_at].join("\n");
+        let (key, key_id) = self.current_key();
+        let signature = key.sign(message.as_bytes());
+        ProofSignature {
+            algorithm: "ed25519".to_string(),
+            key_id,
+            proof_hash: proof_hash.to_string(),
+            wasm_hash,
+            public_outputs_hash,
+            signed_at,
+            signature: base64::engine::general_purpose::STANDARD.encode(signature.to_bytes()),
+        }
+    }
+
+    // Sign a verifier node's result
+    pub fn sign_verification(&self, verification_id: &str, proof_hash: &str, is_valid: bool) -> VerificationSignature {
+        let signed_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let (key_id, signature) = self.sign_message(|_| verification_message(verification_id, proof_hash, is_valid, &signed_at));
+        VerificationSignature { algorithm: "ed25519".to_string(), key_id, signed_at, signature }
+    }
+
+    // Sign a message built for the current key's ID; returns the key ID and
+    // the base64 signature
+    pub fn sign_message(&self, message: impl FnOnce(&str) -> String) -> (String, String) {
+        let (key, key_id) = self.current_key();
+        let message = message(&key_id);
+        (key_id, base64::engine::general_purpose::STANDARD.encode(key.sign(message.as_bytes()).to_bytes()))
+    }
+
+    // Check a signature made by sign_message with the current key or one
+    // rotated out since startup
+    pub fn check_message(&self, key_id: &str, message: &str, signature: &str) -> bool {
+        let (key, current_id) = self.current_key();
+        let public_key = if key_id == current_id {
+            Some(key.verifying_key())
+        } else {
+            self.keys.lock().unwrap().retired.iter().find(|(id, _)| id == key_id).map(|(_, key)| *key)
+        };
+        public_key.is_some_and(|public_key| {
+            check_signature(&base64::engine::general_purpose::STANDARD.encode(public_key.as_bytes()), message, signature)
+        })
+    }
+
+    pub fn public_key(&self) -> Value {
+        let (key, key_id) = self.current_key();
+        let message_format = [MESSAGE_PREFIX, "<proof_id>", "<proof_hash>", "<wasm_hash>", "<public_outputs_hash>", "<signed_at>"].join("\n");
+        let retired: Vec<Value> = self.keys.lock().unwrap()
+            .retired
+            .iter()
+            .rev()
+            .map(|(id, public_key)| describe_key(id, public_key))
+            .collect();
+        let mut response = describe_key(&key_id, &key.verifying_key());
+        response["message_format"] = json!(message_format);
+        response["previous_keys"] = json!(retired);
+        response
+    }
+}
+
+fn describe_key(key_id: &str, public_key: &VerifyingKey) -> Value {
+    json!({
+        "algorithm": "ed25519",
+        "key_id": key_id,
+        "public_key": base64::engine::general_purpose::STANDARD.encode(public_key.as_bytes()),
+        "jwk": {
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "kid": key_id,
+            "use": "sig",
+            "x": base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(public_key.as_bytes()),
+        },
+    })
+}
+
+pub async fn public_key_handler(State(state): State<AppState>) -> Json<Value> {
+    Json(state.signer.public_key())
+}
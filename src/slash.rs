@@ -0,0 +1,212 @@
+use crate::{
+    catalog,
+    commands::{Command, ProofFilter, ProofSelector},
+    export::ExportFormat,
+    llm::ProofIntent,
+    ProofStatus,
+};
+
+// Slash commands: a deterministic alternative to natural language for power
+// users. They are parsed entirely here and never reach the LLM.
+//
+//   /prove <function> [args...] [--step N] [--verify]
+//   /verify [proof-id | last]
+//   /list [proofs | verifications] [--status S] [--function F] [--tag T] [--limit N]
+//   /compare <proof-id> <proof-id>
+//   /export <proof-id> [--format bundle|json]
+//   /delete <proof-id>
+//   /cancel <proof-id>
+//   /rerun <proof-id>
+//   /archive --older-than <days>
+//   /stats, /help, /confirm, /cancel
+
+pub const USAGE: &str = "Slash commands: /prove <function> [args...] [--step N] [--verify], /verify [id], \
+/list [proofs|verifications] [--status S] [--function F] [--tag T] [--limit N], /compare <a> <b>, \
+/export <id> [--format bundle|json], /delete <id>, /cancel <id>, /rerun <id>, /archive --older-than <days>, /stats, /help, /confirm, /cancel";
+
+pub enum Slash {
+    Command(Command),
+    Prove { intent: ProofIntent, verify_after: bool },
+    // Answer to the pending proposal
+    Confirm(bool),
+}
+
+// Positional arguments and `--name value` / `--name=value` / `--flag` options
+struct Args<'a> {
+    positional: Vec<&'a str>,
+    options: Vec<(&'a str, Option<&'a str>)>,
+}
+
+// Options that take no value
+const FLAGS: [&str; 1] = ["verify"];
+
+fn split_args<'a>(words: &[&'a str]) -> Args<'a> {
+    let mut args = Args { positional: Vec::new(), options: Vec::new() };
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        match word.strip_prefix("--") {
+            Some(option) => match option.split_once('=') {
+                Some((name, value)) => args.options.push((name, Some(value))),
+                None if FLAGS.contains(&option) => args.options.push((option, None)),
+                None => args.options.push((option, words.next().copied())),
+            },
+            None => args.positional.push(word),
+        }
+    }
+    args
+}
+
+impl<'a> Args<'a> {
+    fn option(&self, name: &str) -> Result<Option<&'a str>, String> {
+        match self.options.iter().find(|(n, _)| *n == name) {
+            Some((_, Some(value))) => Ok(Some(value)),
+            Some((_, None)) => Err(format!("--{} needs a value", name)),
+            None => Ok(None),
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        self.option(name)?
+            .map(|v| v.parse().map_err(|_| format!("--{} must be a number, got '{}'", name, v)))
+            .transpose()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| *n == name)
+    }
+
+    // Reject options the command doesn't know, so typos aren't silently ignored
+    fn only(&self, allowed: &[&str]) -> Result<(), String> {
+        match self.options.iter().find(|(n, _)| !allowed.contains(n)) {
+            Some((name, _)) => Err(format!("Unknown option --{}", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn function_spec(name: &str) -> Option<&'static catalog::FunctionSpec> {
+    let name = match name {
+        "fib" => "fibonacci",
+        "location" => "prove_location",
+        "kyc" => "prove_kyc",
+        "ai" | "ai_content" => "prove_ai_content",
+        "even" => "is_even",
+        "count" => "count_until",
+        name => name,
+    };
+    catalog::find(name)
+}
+
+fn one_id<'a>(args: &Args<'a>, usage: &str) -> Result<&'a str, String> {
+    match args.positional.as_slice() {
+        [id] => Ok(id),
+        _ => Err(format!("Usage: {}", usage)),
+    }
+}
+
+// `None` when the input isn't a slash command; otherwise the command or a
+// usage message explaining what was wrong
+pub fn parse(input: &str) -> Option<Result<Slash, String>> {
+    let rest = input.trim().strip_prefix('/')?;
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let (name, words) = words.split_first()?;
+    let args = split_args(words);
+    Some(parse_command(&name.to_lowercase(), &args))
+}
+
+fn parse_command(name: &str, args: &Args) -> Result<Slash, String> {
+    let command = |command| Ok(Slash::Command(command));
+    match name {
+        "prove" => {
+            args.only(&["step", "verify"])?;
+            let (function, arguments) = args.positional.split_first()
+                .ok_or("Usage: /prove <function> [args...] [--step N] [--verify]")?;
+            let spec = function_spec(function)
+                .ok_or_else(|| format!("Unknown function '{}'. Available: {}", function, catalog::names()))?;
+            if arguments.len() != spec.arguments.len() {
+                return Err(format!("Usage: /prove {} <{}>", spec.name, spec.argument_names().join("> <")));
+            }
+            let arguments: Vec<String> = arguments.iter().map(|a| a.to_string()).collect();
+            spec.check_arguments(&arguments).map_err(|e| e.message)?;
+            Ok(Slash::Prove {
+                intent: ProofIntent {
+                    function: spec.name.to_string(),
+                    explanation: format!("Generating proof for {}({})", spec.name, arguments.join(", ")),
+                    arguments,
+                    step_size: args.number("step")?.unwrap_or(50),
+                    complexity_reasoning: None,
+                },
+                verify_after: args.flag("verify"),
+            })
+        }
+        "verify" => {
+            args.only(&[])?;
+            let target = match args.positional.as_slice() {
+                [] | ["last" | "latest"] => ProofSelector::Latest,
+                [id] => ProofSelector::Id(id.to_string()),
+                _ => return Err("Usage: /verify [proof-id | last]".to_string()),
+            };
+            command(Command::Verify { target })
+        }
+        "list" => {
+            args.only(&["status", "function", "tag", "limit"])?;
+            match args.positional.as_slice() {
+                [] | ["proofs"] => {
+                    let filter = ProofFilter {
+                        status: args.option("status")?.map(str::to_lowercase),
+                        function: match args.option("function")? {
+                            Some(f) => Some(function_spec(f).ok_or_else(|| format!("Unknown function '{}'", f))?.name.to_string()),
+                            None => None,
+                        },
+                        tag: args.option("tag")?.map(str::to_lowercase),
+                        limit: args.number("limit")?,
+                    };
+                    if filter.status.as_deref().is_some_and(|s| !ProofStatus::NAMES.contains(&s)) {
+                        return Err(format!("--status must be one of {}", ProofStatus::NAMES.join(", ")));
+                    }
+                    command(Command::ListProofs { filter })
+                }
+                ["verifications"] if args.options.is_empty() => command(Command::ListVerifications),
+                _ => Err("Usage: /list [proofs|verifications] [--status S] [--function F] [--tag T] [--limit N]".to_string()),
+            }
+        }
+        "compare" => match args.positional.as_slice() {
+            [a, b] if args.options.is_empty() => command(Command::Compare { a: a.to_string(), b: b.to_string() }),
+            _ => Err("Usage: /compare <proof-id> <proof-id>".to_string()),
+        },
+        "export" => {
+            args.only(&["format"])?;
+            let proof_id = one_id(args, "/export <proof-id> [--format bundle|json]")?;
+            let format = match args.option("format")? {
+                Some(name) => ExportFormat::parse(name).ok_or("--format must be bundle or json")?,
+                None => ExportFormat::Bundle,
+            };
+            command(Command::Export { proof_id: proof_id.to_string(), format })
+        }
+        "delete" => {
+            args.only(&[])?;
+            command(Command::DeleteProof { proof_id: one_id(args, "/delete <proof-id>")?.to_string() })
+        }
+        "archive" => {
+            args.only(&["older-than"])?;
+            match args.number("older-than")? {
+                Some(days) if days >= 0 && args.positional.is_empty() => command(Command::ArchiveProofs { older_than_days: days }),
+                _ => Err("Usage: /archive --older-than <days>".to_string()),
+            }
+        }
+        "stats" => command(Command::Stats),
+        "help" => command(Command::Help),
+        // With an ID, stop that proof; without, turn down the proposal
+        "cancel" if !args.positional.is_empty() => {
+            args.only(&[])?;
+            command(Command::CancelProof { proof_id: one_id(args, "/cancel <proof-id>")?.to_string() })
+        }
+        "rerun" => {
+            args.only(&[])?;
+            command(Command::RerunProof { proof_id: one_id(args, "/rerun <proof-id>")?.to_string() })
+        }
+        "confirm" | "yes" => Ok(Slash::Confirm(true)),
+        "cancel" | "no" => Ok(Slash::Confirm(false)),
+        _ => Err(format!("Unknown command /{}. {}", name, USAGE)),
+    }
+}
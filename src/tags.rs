@@ -0,0 +1,110 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+    cluster,
+    errors::{AppError, ErrorCode},
+    retention, AppState, ProofRecord,
+};
+
+// Free-form tags on proofs, e.g. "compliance", "customer:acme" or
+// "nightly". They can be given when a proof is started (REST, JSON-RPC and
+// templates) and changed later with
+//
+//   PATCH /api/proofs/:id/tags   {"add": ["audit"], "remove": ["draft"]}
+//                                or {"tags": [...]} to replace them all
+//
+// Proof listings filter on them (GET /api/proofs?tag=..., "/list --tag",
+// the list_proofs tool). Proofs with a tag in RETENTION_PROTECTED_TAGS
+// (default "compliance", comma separated) are never removed by cleanup or
+// archiving by age; deleting one by ID still works.
+//
+// Tags are lowercased; they are up to 64 letters, digits, '-', '_', ':' or
+// '.', and a proof has at most MAX_TAGS of them.
+
+const MAX_TAGS: usize = 32;
+
+#[derive(Deserialize)]
+pub struct TagUpdate {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    add: Vec<String>,
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+fn normalize_tag(tag: &str) -> Result<String, AppError> {
+    let tag = tag.trim().to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.len() <= 64
+        && tag.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+    if !valid {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("Invalid tag '{}'", tag)));
+    }
+    Ok(tag)
+}
+
+// Lowercased, checked and without duplicates, in the order given
+pub fn normalize(tags: &[String]) -> Result<Vec<String>, AppError> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(AppError::new(ErrorCode::InvalidArguments, format!("A proof has at most {} tags", MAX_TAGS)));
+    }
+    Ok(normalized)
+}
+
+// Tags that keep proofs from being removed by age
+fn protected_tags() -> Vec<String> {
+    std::env::var("RETENTION_PROTECTED_TAGS")
+        .unwrap_or_else(|_| "compliance".to_string())
+        .split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub fn is_protected(proof: &ProofRecord) -> bool {
+    let protected = protected_tags();
+    proof.tags.iter().any(|tag| protected.contains(tag))
+}
+
+async fn update(state: &AppState, id: &str, update: TagUpdate) -> Result<ProofRecord, AppError> {
+    let id = retention::resolve_proof_id(state, id).await?;
+    let replacement = update.tags.as_deref().map(normalize).transpose()?;
+    let add = normalize(&update.add)?;
+    let remove = normalize(&update.remove)?;
+    let record = state.proof_store.update(&id, |proof| {
+        let mut tags = replacement.unwrap_or_else(|| proof.tags.clone());
+        tags.retain(|tag| !remove.contains(tag));
+        for tag in add {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        proof.tags = normalize(&tags)?;
+        Ok::<_, AppError>(proof.clone())
+    }).await.ok_or_else(AppError::proof_not_found)??;
+    state.proof_flusher.mark_dirty();
+    cluster::store_proof(state, &record).await;
+    info!("Proof {} tagged {:?}", id, record.tags);
+    Ok(record)
+}
+
+pub async fn update_handler(State(state): State<AppState>, Path(id): Path<String>, Json(request): Json<TagUpdate>) -> Response {
+    match update(&state, &id, request).await {
+        Ok(proof) => Json(json!({ "success": true, "proof_id": proof.id, "tags": proof.tags })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
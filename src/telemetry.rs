@@ -0,0 +1,95 @@
+use opentelemetry::{global, propagation::Injector, trace::{TraceContextExt, TracerProvider as _}};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{filter::LevelFilter, fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt};
+
+// Logging and OpenTelemetry tracing. Spans cover HTTP requests, WebSocket
+// messages, LLM calls, zkEngine runs and persistence; they are exported over
+// OTLP/HTTP when OTEL_EXPORTER_OTLP_ENDPOINT is set (e.g. Jaeger or Tempo at
+// http://localhost:4318). The trace ID is copied into WebSocket events so a
+// slow proof can be looked up from what the client saw.
+
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    // Flush spans still waiting in the batch exporter
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+pub fn init(log_to_stderr: bool) -> Telemetry {
+    let writer = if log_to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let registry = tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .with(crate::siem::layer());
+
+    if std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+        registry.init();
+        return Telemetry { provider: None };
+    }
+
+    let exporter = match opentelemetry_otlp::SpanExporter::builder().with_http().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            registry.init();
+            tracing::error!("Failed to set up OTLP trace export: {}", e);
+            return Telemetry { provider: None };
+        }
+    };
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "zkengine-agentkit".to_string());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build();
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = provider.tracer("zkengine-agentkit");
+    registry.with(tracing_opentelemetry::layer().with_tracer(tracer)).init();
+    tracing::info!("Exporting traces over OTLP");
+
+    Telemetry { provider: Some(provider) }
+}
+
+// Warnings and errors only, on stderr, for the local CLI commands whose
+// results go to stdout
+pub fn init_cli() {
+    tracing_subscriber::registry()
+        .with(LevelFilter::WARN)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+}
+
+// Trace ID of the current span, when tracing is exported
+pub fn current_trace_id() -> Option<String> {
+    let context = Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context.is_valid().then(|| span_context.trace_id().to_string())
+}
+
+struct RequestInjector(Vec<(String, String)>);
+
+impl Injector for RequestInjector {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.push((key.to_string(), value));
+    }
+}
+
+// Add `traceparent` so services we call (LangChain) join the trace
+pub fn propagate(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    let mut injector = RequestInjector(Vec::new());
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&Span::current().context(), &mut injector));
+    injector.0.into_iter().fold(request, |request, (key, value)| request.header(key, value))
+}
@@ -0,0 +1,240 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::{
+    catalog, create_proof_record,
+    errors::{AppError, ErrorCode},
+    lineage,
+    protocol::{WsEvent, WsMessage},
+    short_id, spawn_proof_generation, tags, AppState, ProofMetadata, ProofOptions, DEFAULT_STEP_SIZE,
+};
+
+// Saved proof requests. A template names a module with its function,
+// arguments and step size, so a proof that is run often doesn't need its
+// arguments typed out (or guessed by the LLM) every time:
+//
+//   POST   /api/templates             {"name": "nightly-kyc", "wasm_file": "prove_kyc.wat",
+//                                      "arguments": ["12345", "1"], "tags": ["nightly"]}
+//   GET    /api/templates?tag=...     templates, optionally with a tag
+//   GET    /api/templates/:name
+//   DELETE /api/templates/:name       admins only
+//   POST   /api/templates/:name/run   {"arguments": [...], "step_size": N}, both optional
+//
+// In chat, "run the nightly-kyc template" or "run template nightly-kyc".
+// Saving a template with an existing name replaces it. Templates are
+// checked like proof requests when saved and again when run. Proofs started
+// from a template carry its tags (see tags.rs), and each one's parent is
+// the template's previous proof (see lineage.rs).
+
+const TEMPLATES_DB_FILE: &str = "./templates_db.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Template {
+    pub name: String,
+    pub wasm_file: String,
+    #[serde(default = "default_function")]
+    pub function: String,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+    #[serde(default = "default_step_size")]
+    pub step_size: u64,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+    // The template's latest proof, the parent of its next (see lineage.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_proof_id: Option<String>,
+}
+
+fn default_function() -> String {
+    "main".to_string()
+}
+
+fn default_step_size() -> u64 {
+    DEFAULT_STEP_SIZE
+}
+
+#[derive(Deserialize, Default)]
+pub struct RunRequest {
+    #[serde(default)]
+    arguments: Option<Vec<String>>,
+    #[serde(default)]
+    step_size: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    #[serde(default)]
+    tag: Option<String>,
+}
+
+// Templates by name, persisted like the proof store
+pub struct TemplateStore {
+    templates: Mutex<BTreeMap<String, Template>>,
+}
+
+impl TemplateStore {
+    pub async fn load() -> Self {
+        let templates = match tokio::fs::read_to_string(TEMPLATES_DB_FILE).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Failed to load templates from {}: {}", TEMPLATES_DB_FILE, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        TemplateStore { templates: Mutex::new(templates) }
+    }
+
+    pub async fn get(&self, name: &str) -> Option<Template> {
+        self.templates.lock().await.get(&name.trim().to_lowercase()).cloned()
+    }
+}
+
+async fn save(templates: &BTreeMap<String, Template>) {
+    let result = match serde_json::to_string_pretty(templates) {
+        Ok(json) => tokio::fs::write(TEMPLATES_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save templates to disk: {}", e);
+    }
+}
+
+fn check_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "Template names are up to 64 letters, digits, '-' or '_'"));
+    }
+    Ok(())
+}
+
+async fn upsert(state: &AppState, mut template: Template) -> Result<Template, AppError> {
+    template.name = template.name.trim().to_lowercase();
+    check_name(&template.name)?;
+    state.flags.check_module(&template.wasm_file)?;
+    catalog::validate_request(&state.wasm_dir, &template.wasm_file, &template.arguments, template.step_size)?;
+    template.tags = tags::normalize(&template.tags)?;
+    template.updated_at = Utc::now();
+
+    let mut templates = state.templates.templates.lock().await;
+    template.last_proof_id = templates.get(&template.name).and_then(|saved| saved.last_proof_id.clone());
+    templates.insert(template.name.clone(), template.clone());
+    save(&templates).await;
+    info!("Template {} saved ({} {:?})", template.name, template.wasm_file, template.arguments);
+    Ok(template)
+}
+
+// Start a proof from a template, with the arguments or step size replaced
+// when given; returns the proof ID
+pub async fn run(
+    state: &AppState,
+    name: &str,
+    request: RunRequest,
+    session_id: Option<&str>,
+) -> Result<String, AppError> {
+    let template = state.templates.get(name).await
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, format!("No template named {}", name.trim())))?;
+    let arguments = request.arguments.unwrap_or_else(|| template.arguments.clone());
+    let step_size = request.step_size.unwrap_or(template.step_size);
+    state.flags.check_module(&template.wasm_file)?;
+    let wasm_path = catalog::validate_request(&state.wasm_dir, &template.wasm_file, &arguments, step_size)?;
+    let encoded = catalog::encode_module_arguments(&template.wasm_file, &arguments)?;
+    let metadata = ProofMetadata {
+        wasm_path: wasm_path.display().to_string(),
+        function: template.function.clone(),
+        arguments: arguments.clone(),
+        step_size,
+        statement: None,
+    };
+
+    let options = ProofOptions {
+        tags: template.tags.clone(),
+        parent_id: template.last_proof_id.clone(),
+        rerun: template.last_proof_id.is_some().then_some(lineage::Rerun::Template),
+        ..Default::default()
+    };
+    let proof_id = create_proof_record(state, metadata.clone(), session_id.map(str::to_string), options).await;
+    {
+        let mut templates = state.templates.templates.lock().await;
+        if let Some(saved) = templates.get_mut(&template.name) {
+            saved.last_proof_id = Some(proof_id.clone());
+            save(&templates).await;
+        }
+    }
+    if let Some(session_id) = session_id {
+        let start_msg = WsMessage::event(
+            format!("Running template {}: {}({})", template.name, template.function, arguments.join(", ")),
+            WsEvent::ProofStart {
+                proof_id: proof_id.clone(),
+                function: template.function.clone(),
+                arguments,
+                wasm_file: template.wasm_file.clone(),
+                step_size,
+            },
+        ).for_session(Some(session_id.to_string()));
+        state.publish(start_msg);
+        state.history.record_proof(&state.conversation_id(session_id).await, &proof_id).await;
+    }
+    spawn_proof_generation(state, &proof_id, metadata, encoded);
+    info!("Template {} started proof {}", template.name, short_id(&proof_id));
+    Ok(proof_id)
+}
+
+pub async fn list_handler(State(state): State<AppState>, Query(query): Query<ListQuery>) -> Json<Value> {
+    let tag = query.tag.map(|tag| tag.trim().to_lowercase());
+    let templates: Vec<Template> = state.templates.templates.lock().await
+        .values()
+        .filter(|template| tag.as_ref().is_none_or(|tag| template.tags.contains(tag)))
+        .cloned()
+        .collect();
+    Json(json!({ "success": true, "templates": templates }))
+}
+
+pub async fn get_handler(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    match state.templates.get(&name).await {
+        Some(template) => Json(json!({ "success": true, "template": template })).into_response(),
+        None => AppError::new(ErrorCode::NotFound, format!("No template named {}", name)).into_response(),
+    }
+}
+
+pub async fn save_handler(State(state): State<AppState>, Json(template): Json<Template>) -> Response {
+    match upsert(&state, template).await {
+        Ok(template) => Json(json!({ "success": true, "template": template })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn delete_handler(State(state): State<AppState>, Path(name): Path<String>) -> Response {
+    let mut templates = state.templates.templates.lock().await;
+    match templates.remove(&name.trim().to_lowercase()) {
+        Some(template) => {
+            save(&templates).await;
+            info!("Template {} deleted", template.name);
+            Json(json!({ "success": true, "name": template.name })).into_response()
+        }
+        None => AppError::new(ErrorCode::NotFound, format!("No template named {}", name)).into_response(),
+    }
+}
+
+pub async fn run_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    request: Option<Json<RunRequest>>,
+) -> Response {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    match run(&state, &name, request, None).await {
+        Ok(proof_id) => Json(json!({ "success": true, "proof_id": proof_id, "template": name })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,301 @@
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{
+    auth::Role,
+    catalog,
+    commands::ProofFilter,
+    errors::{AppError, ErrorCode},
+    launch_intent_proof,
+    llm::{ProofIntent, ToolCall},
+    propose_if_expensive,
+    protocol::WsMessage,
+    verify_proof_async, AppState, ProofRecord, ProofStatus,
+};
+
+// Upper bound on tool-call rounds for a single chat message
+pub const MAX_TOOL_ROUNDS: usize = 5;
+
+// Tools offered to the model in the request context, in JSON-schema form
+pub fn definitions() -> Value {
+    json!([
+        {
+            "name": "generate_proof",
+            "description": "Start generating a zero-knowledge proof. Returns the new proof ID.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "function": { "type": "string", "description": catalog::names() },
+                    "arguments": { "type": "array", "items": { "type": "string" } },
+                    "step_size": { "type": "integer", "default": 50 }
+                },
+                "required": ["function", "arguments"]
+            }
+        },
+        {
+            "name": "verify_proof",
+            "description": "Verify a completed proof and return the verification result.",
+            "parameters": {
+                "type": "object",
+                "properties": { "proof_id": { "type": "string" } },
+                "required": ["proof_id"]
+            }
+        },
+        {
+            "name": "list_proofs",
+            "description": "List proofs, newest first.",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "status": { "type": "string", "enum": ProofStatus::NAMES },
+                    "function": { "type": "string" },
+                    "tag": { "type": "string" },
+                    "limit": { "type": "integer", "default": 10 }
+                }
+            }
+        },
+        {
+            "name": "get_proof_status",
+            "description": "Get the status, metrics and latest verification of a proof.",
+            "parameters": {
+                "type": "object",
+                "properties": { "proof_id": { "type": "string" } },
+                "required": ["proof_id"]
+            }
+        }
+    ])
+}
+
+// The proof API as tools for external agent frameworks (GET /api/tools).
+// Parameters are the HTTP request fields, so a tool call maps straight onto
+// the `http` entry for it: `{proof_id}` in the path comes from the call's
+// arguments and the rest is the JSON body (POST) or query string (GET).
+struct ApiTool {
+    name: &'static str,
+    description: &'static str,
+    parameters: Value,
+    method: &'static str,
+    path: &'static str,
+}
+
+fn api_tools() -> Vec<ApiTool> {
+    let modules: Vec<String> = catalog::FUNCTIONS.iter().filter_map(|f| catalog::wasm_file(f.name)).collect();
+    let signatures: Vec<String> = catalog::FUNCTIONS.iter()
+        .filter_map(|f| Some(format!("{} ({}): {}", catalog::wasm_file(f.name)?, f.argument_names().join(", "), f.description)))
+        .collect();
+    vec![
+        ApiTool {
+            name: "generate_proof",
+            description: "Start generating a zero-knowledge proof. Returns the new proof ID; poll get_proof_status until it is complete or failed.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "wasm_file": {
+                        "type": "string",
+                        "enum": modules,
+                        "description": format!("Module to prove. {}", signatures.join("; ")),
+                    },
+                    "arguments": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The module's arguments, in order",
+                    },
+                    "step_size": { "type": "integer", "default": 50 },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["wasm_file", "arguments"]
+            }),
+            method: "POST",
+            path: "/api/proofs/generate",
+        },
+        ApiTool {
+            name: "verify_proof",
+            description: "Verify a completed proof and return the verification result.",
+            parameters: json!({
+                "type": "object",
+                "properties": { "proof_id": { "type": "string" } },
+                "required": ["proof_id"]
+            }),
+            method: "POST",
+            path: "/api/proofs/{proof_id}/verify",
+        },
+        ApiTool {
+            name: "list_proofs",
+            description: "List proofs with their status and metrics.",
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "tag": { "type": "string", "description": "Only proofs with every one of these comma-separated tags" }
+                }
+            }),
+            method: "GET",
+            path: "/api/proofs",
+        },
+        ApiTool {
+            name: "get_proof_status",
+            description: "Get the status, metrics and metadata of a proof.",
+            parameters: json!({
+                "type": "object",
+                "properties": { "proof_id": { "type": "string" } },
+                "required": ["proof_id"]
+            }),
+            method: "GET",
+            path: "/api/proofs/{proof_id}",
+        },
+    ]
+}
+
+#[derive(Deserialize)]
+pub struct ManifestParams {
+    // openai (default) or anthropic
+    format: Option<String>,
+}
+
+pub async fn manifest_handler(Query(params): Query<ManifestParams>) -> Response {
+    let format = params.format.as_deref().unwrap_or("openai").to_lowercase();
+    let tools = api_tools();
+    let schemas: Vec<Value> = match format.as_str() {
+        "openai" => tools.iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": { "name": tool.name, "description": tool.description, "parameters": tool.parameters },
+            }))
+            .collect(),
+        "anthropic" => tools.iter()
+            .map(|tool| json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
+            }))
+            .collect(),
+        _ => {
+            let error = AppError::new(ErrorCode::InvalidArguments, "format must be openai or anthropic");
+            return error.into_response();
+        }
+    };
+    let http: serde_json::Map<String, Value> = tools.iter()
+        .map(|tool| (tool.name.to_string(), json!({ "method": tool.method, "path": tool.path })))
+        .collect();
+    Json(json!({
+        "success": true,
+        "format": format,
+        "tools": schemas,
+        "http": http,
+    })).into_response()
+}
+
+#[derive(Deserialize)]
+struct GenerateArgs {
+    function: String,
+    #[serde(default)]
+    arguments: Vec<String>,
+    #[serde(default = "default_step_size")]
+    step_size: u64,
+}
+
+fn default_step_size() -> u64 {
+    50
+}
+
+#[derive(Deserialize)]
+struct ProofIdArgs {
+    proof_id: String,
+}
+
+pub fn summary(proof: &ProofRecord) -> Value {
+    json!({
+        "proof_id": proof.id,
+        "function": proof.metadata.function,
+        "wasm_file": proof.metadata.wasm_path.rsplit('/').next(),
+        "arguments": proof.metadata.arguments,
+        "status": proof.status.name(),
+        "timestamp": proof.timestamp,
+        "expired": proof.validity.is_expired(),
+        "revoked": proof.validity.is_revoked(),
+    })
+}
+
+// Run a tool call from the model. Failures are returned as
+// `{"error": ..., "code": ...}` so the model can react to them.
+pub async fn execute(state: &AppState, session_id: &str, tool_call: &ToolCall) -> Value {
+    info!("Tool call {} from session {}", tool_call.name, session_id);
+    let arguments = if tool_call.arguments.is_null() { json!({}) } else { tool_call.arguments.clone() };
+    match call(state, session_id, &tool_call.name, arguments).await {
+        Ok(result) => result,
+        Err(error) => error_result(&error),
+    }
+}
+
+pub fn error_result(error: &AppError) -> Value {
+    json!({ "error": error.message, "code": error.code })
+}
+
+fn parse<T: serde::de::DeserializeOwned>(arguments: Value) -> Result<T, AppError> {
+    serde_json::from_value(arguments).map_err(|e| AppError::new(ErrorCode::InvalidArguments, format!("Invalid arguments: {}", e)))
+}
+
+// Run a tool by name; shared by the LLM tool loop and the MCP server
+pub async fn call(state: &AppState, session_id: &str, name: &str, arguments: Value) -> Result<Value, AppError> {
+    let required = match name {
+        "generate_proof" | "verify_proof" => Role::Prover,
+        _ => Role::Viewer,
+    };
+    state.require_role(session_id, required).await?;
+    match name {
+        "generate_proof" => {
+            let args: GenerateArgs = parse(arguments)?;
+            let intent = ProofIntent {
+                explanation: format!("Generating proof for {}({})", args.function, args.arguments.join(", ")),
+                function: args.function,
+                arguments: args.arguments,
+                step_size: args.step_size,
+                complexity_reasoning: None,
+            };
+            if let Some((message, proposal)) = propose_if_expensive(state, session_id, &intent, false).await {
+                state.publish(WsMessage::event(message.clone(), proposal).for_session(Some(session_id.to_string())));
+                return Ok(json!({ "status": "awaiting_confirmation", "message": message }));
+            }
+            let proof_id = launch_intent_proof(state, session_id, &intent).await?;
+            // Complete already when an identical proof was reused
+            let status = state.proof_store.read(&proof_id, |p| p.status.name()).await.unwrap_or("queued");
+            Ok(json!({ "proof_id": proof_id, "status": status }))
+        }
+        "verify_proof" => {
+            let args: ProofIdArgs = parse(arguments)?;
+            match state.proof_store.read(&args.proof_id, |p| p.status.clone()).await {
+                None => return Err(AppError::proof_not_found()),
+                Some(ProofStatus::Complete) => {}
+                Some(_) => return Err(AppError::new(ErrorCode::ProofNotReady, "Proof not complete")),
+            }
+            let verification = verify_proof_async(state.clone(), args.proof_id.clone(), Some(session_id.to_string())).await?;
+            Ok(json!({ "proof_id": args.proof_id, "verification": verification }))
+        }
+        "list_proofs" => {
+            let mut filter: ProofFilter = parse(arguments)?;
+            let limit = filter.limit.take().unwrap_or(10);
+            let (count, matching) = filter.page(&state.proof_store, Some(limit)).await;
+            let proofs: Vec<Value> = matching.iter().map(summary).collect();
+            Ok(json!({ "proofs": proofs, "count": count }))
+        }
+        "get_proof_status" => {
+            let args: ProofIdArgs = parse(arguments)?;
+            let proof = state.proof_store.get(&args.proof_id).await
+                .ok_or_else(AppError::proof_not_found)?;
+            let verifications = state.verification_store.lock().await;
+            let latest = verifications.iter().rev().find(|v| v.proof_id == args.proof_id);
+            Ok(json!({
+                "proof": summary(&proof),
+                "error": match &proof.status { ProofStatus::Failed(e) => Some(e), _ => None },
+                "metrics": proof.metrics,
+                "latest_verification": latest,
+            }))
+        }
+        _ => Err(AppError::new(ErrorCode::InvalidArguments, format!("Unknown tool: {}", name))),
+    }
+}
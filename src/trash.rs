@@ -0,0 +1,229 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{
+    cluster,
+    errors::{AppError, ErrorCode},
+    proof_names, retention,
+    schema::{self, Layout},
+    short_id, AppState, ProofRecord,
+};
+
+// Deleting a proof, by DELETE /api/proofs/:id, chat or /api/cleanup, moves
+// it to the trash, where it stays for TRASH_RETENTION_HOURS (default 72; 0
+// deletes proofs at once) before it and its files are purged:
+//
+//   GET    /api/trash                 trashed proofs, most recently deleted first
+//   POST   /api/proofs/:id/restore    put a trashed proof back (admins only)
+//   DELETE /api/trash/:id             purge a trashed proof now (admins only)
+//
+// A trashed proof is out of the proof store, so it isn't listed, counted,
+// reused or found by ID, but its files are kept. Like the archive, the
+// trash belongs to the instance that deleted the proof. A restored proof
+// loses its name if another proof has taken it meanwhile.
+
+const TRASH_DB_FILE: &str = "./proofs_trash_db.json";
+// How often the purger looks for proofs whose time is up
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrashedProof {
+    #[serde(flatten)]
+    pub proof: ProofRecord,
+    pub deleted_at: DateTime<Utc>,
+}
+
+// Trashed proofs by ID, persisted like the archive
+pub struct Trash {
+    proofs: Mutex<HashMap<String, TrashedProof>>,
+}
+
+impl Trash {
+    // An unreadable trash is moved aside (see schema.rs) and a new one begun
+    pub async fn load() -> Self {
+        let proofs = schema::PROOF.load(Layout::Map, TRASH_DB_FILE).await.unwrap_or_else(|e| {
+            warn!("Failed to read the proof trash: {}", e);
+            None
+        }).unwrap_or_default();
+        Trash { proofs: Mutex::new(proofs) }
+    }
+}
+
+// How long deleted proofs stay in the trash; none when they are deleted at once
+pub fn retention() -> Option<Duration> {
+    let hours = std::env::var("TRASH_RETENTION_HOURS").ok().and_then(|v| v.parse().ok()).filter(|hours| *hours >= 0).unwrap_or(72);
+    (hours > 0).then(|| Duration::hours(hours))
+}
+
+async fn save(proofs: &HashMap<String, TrashedProof>) -> Result<(), AppError> {
+    let storage_error = |e: &dyn std::fmt::Display| AppError::new(ErrorCode::StorageError, format!("Failed to write the proof trash: {}", e));
+    let json = schema::PROOF.encode(proofs).map_err(|e| storage_error(&e))?;
+    tokio::fs::write(TRASH_DB_FILE, json).await.map_err(|e| storage_error(&e))
+}
+
+// Add proofs just removed from the store. Nothing is added unless all of
+// them are saved, so the caller can put them back.
+pub async fn put(state: &AppState, removed: &[ProofRecord]) -> Result<(), AppError> {
+    let mut proofs = state.trash.proofs.lock().await;
+    let deleted_at = Utc::now();
+    for proof in removed {
+        proofs.insert(proof.id.clone(), TrashedProof { proof: proof.clone(), deleted_at });
+    }
+    if let Err(error) = save(&proofs).await {
+        for proof in removed {
+            proofs.remove(&proof.id);
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
+// A full ID, proof name or unique ID prefix, as for proofs in the store
+fn resolve(proofs: &HashMap<String, TrashedProof>, id: &str) -> Result<String, AppError> {
+    if proofs.contains_key(id) {
+        return Ok(id.to_string());
+    }
+    let name = id.to_lowercase();
+    if let Some(named) = proofs.values().find(|trashed| trashed.proof.name.as_deref() == Some(name.as_str())) {
+        return Ok(named.proof.id.clone());
+    }
+    let matches: Vec<&String> = proofs.keys().filter(|key| key.starts_with(id)).collect();
+    match matches.as_slice() {
+        [only] => Ok(only.to_string()),
+        [] => Err(AppError::new(ErrorCode::ProofNotFound, format!("No proof in the trash matches {}", id))),
+        _ => Err(AppError::new(
+            ErrorCode::AmbiguousProofId,
+            format!("{} matches {} proofs in the trash; use more characters", id, matches.len()),
+        )),
+    }
+}
+
+// Put a trashed proof back in the store. It leaves the trash only once it
+// is saved in the store, so a failure leaves it in the trash.
+pub async fn restore(state: &AppState, id: &str) -> Result<ProofRecord, AppError> {
+    let mut proofs = state.trash.proofs.lock().await;
+    let id = resolve(&proofs, id)?;
+    let Some(trashed) = proofs.get(&id) else {
+        return Err(AppError::proof_not_found());
+    };
+
+    let mut proof = trashed.proof.clone();
+    // Held until the proof is back in the store
+    let _claim = match proof.name.clone() {
+        Some(name) => match proof_names::claim(state, &name, None).await {
+            Ok(claim) => Some(claim),
+            Err(error) => {
+                warn!("Restoring proof {} without its name: {}", short_id(&id), error.message);
+                proof.name = None;
+                None
+            }
+        },
+        None => None,
+    };
+    state.proof_store.insert(proof.clone()).await;
+    cluster::store_proof(state, &proof).await;
+    if let Err(e) = state.proof_flusher.persist(state).await {
+        unstore(state, &id).await;
+        return Err(AppError::new(ErrorCode::StorageError, format!("Failed to save the restored proof: {}", e)));
+    }
+    let removed = proofs.remove(&id);
+    if let Err(error) = save(&proofs).await {
+        proofs.extend(removed.map(|trashed| (id.clone(), trashed)));
+        unstore(state, &id).await;
+        return Err(error);
+    }
+    info!("Restored proof {} from the trash", short_id(&id));
+    Ok(proof)
+}
+
+// Take a proof whose restore failed back out of the store
+async fn unstore(state: &AppState, id: &str) {
+    state.proof_store.remove(id).await;
+    state.proof_flusher.mark_dirty();
+    cluster::delete_proofs(state, &[id.to_string()]).await;
+}
+
+// Delete trashed proofs for good: those whose time in the trash is up, or
+// the one given
+async fn purge(state: &AppState, only: Option<&str>) -> Result<Vec<String>, AppError> {
+    let mut proofs = state.trash.proofs.lock().await;
+    let purged: Vec<String> = match only {
+        Some(id) => vec![resolve(&proofs, id)?],
+        None => {
+            let cutoff = retention().map_or_else(Utc::now, |retention| Utc::now() - retention);
+            proofs.values().filter(|trashed| trashed.deleted_at <= cutoff).map(|trashed| trashed.proof.id.clone()).collect()
+        }
+    };
+    if purged.is_empty() {
+        return Ok(purged);
+    }
+    let removed: Vec<TrashedProof> = purged.iter().filter_map(|id| proofs.remove(id)).collect();
+    if let Err(error) = save(&proofs).await {
+        proofs.extend(removed.into_iter().map(|trashed| (trashed.proof.id.clone(), trashed)));
+        return Err(error);
+    }
+    drop(proofs);
+    for id in &purged {
+        retention::remove_proof_files(state, id).await;
+    }
+    Ok(purged)
+}
+
+pub fn spawn(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge(&state, None).await {
+                Ok(purged) if !purged.is_empty() => info!("Purged {} proofs from the trash", purged.len()),
+                Ok(_) => {}
+                Err(error) => warn!("Failed to purge the proof trash: {}", error.message),
+            }
+        }
+    });
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut trashed: Vec<TrashedProof> = state.trash.proofs.lock().await.values().cloned().collect();
+    trashed.sort_by_key(|trashed| std::cmp::Reverse(trashed.deleted_at));
+    let retention = retention();
+    let proofs: Vec<serde_json::Value> = trashed.iter()
+        .map(|trashed| json!({
+            "proof": trashed.proof,
+            "deleted_at": trashed.deleted_at,
+            "purge_at": retention.map(|retention| trashed.deleted_at + retention),
+        }))
+        .collect();
+    Json(json!({
+        "success": true,
+        "count": proofs.len(),
+        "retention_hours": retention.map_or(0, |retention| retention.num_hours()),
+        "proofs": proofs,
+    }))
+}
+
+pub async fn restore_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match restore(&state, &id).await {
+        Ok(proof) => Json(json!({ "success": true, "proof": proof })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn purge_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    match purge(&state, Some(&id)).await {
+        Ok(purged) => {
+            info!("Purged proof {} from the trash", short_id(&purged[0]));
+            Json(json!({ "success": true, "proof_id": purged[0] })).into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
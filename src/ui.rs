@@ -0,0 +1,114 @@
+use axum::{
+    response::Html,
+    routing::{get, get_service},
+    Router,
+};
+use clap::ValueEnum;
+use std::path::PathBuf;
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::{info, warn};
+
+use crate::AppState;
+
+// The web UI at / and /static. `--ui` (UI_MODE) picks where it comes from:
+//
+//   files      STATIC_DIR (default ./static), with UI_INDEX as the page at /
+//              (default: index.html in STATIC_DIR); edits show up on reload.
+//              Without that page, / serves the one the binary was built with.
+//   embedded   the static directory as it was at build time, compiled into
+//              the binary for single-binary deployments; needs
+//              `cargo build --release --features embed-ui`
+//   headless   no UI, for API-only installs; / and /static are 404s
+//
+// The default is embedded in builds with the embed-ui feature and files
+// otherwise.
+
+const BUILT_IN_INDEX: &str = include_str!("../static/index.html");
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiMode {
+    Files,
+    Embedded,
+    Headless,
+}
+
+impl UiMode {
+    pub fn build_default() -> Self {
+        if cfg!(feature = "embed-ui") {
+            UiMode::Embedded
+        } else {
+            UiMode::Files
+        }
+    }
+}
+
+// Routes serving the UI in `mode`
+pub fn router(mode: UiMode, static_dir: &str, index_file: Option<&str>) -> anyhow::Result<Router<AppState>> {
+    match mode {
+        UiMode::Files => {
+            let index = index_file.map(PathBuf::from).unwrap_or_else(|| PathBuf::from(static_dir).join("index.html"));
+            info!("Serving the UI from {}", static_dir);
+            let router = Router::new().nest_service("/static", ServeDir::new(static_dir));
+            if index.is_file() {
+                return Ok(router.route("/", get_service(ServeFile::new(index))));
+            }
+            warn!("UI page {} not found; serving the built-in one", index.display());
+            Ok(router.route("/", get(|| async { Html(BUILT_IN_INDEX) })))
+        }
+        UiMode::Embedded => embedded::router(),
+        UiMode::Headless => {
+            info!("Headless: serving the API only");
+            Ok(Router::new())
+        }
+    }
+}
+
+#[cfg(feature = "embed-ui")]
+mod embedded {
+    use axum::{
+        extract::Path,
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Router,
+    };
+    use tracing::info;
+
+    use crate::AppState;
+
+    // The proof and verification stores that sometimes sit in static/ stay out
+    #[derive(rust_embed::RustEmbed)]
+    #[folder = "static/"]
+    #[exclude = "*_db.json"]
+    struct Assets;
+
+    fn asset(path: &str) -> Response {
+        let Some(file) = Assets::get(path) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let hash: String = file.metadata.sha256_hash().iter().map(|b| format!("{:02x}", b)).collect();
+        let headers = [
+            (header::CONTENT_TYPE, file.metadata.mimetype().to_string()),
+            (header::ETAG, format!("\"{}\"", hash)),
+        ];
+        (headers, file.data).into_response()
+    }
+
+    pub fn router() -> anyhow::Result<Router<AppState>> {
+        info!("Serving the UI built into the binary ({} files)", Assets::iter().count());
+        Ok(Router::new()
+            .route("/", get(|| async { asset("index.html") }))
+            .route("/static/*path", get(|Path(path): Path<String>| async move { asset(&path) })))
+    }
+}
+
+#[cfg(not(feature = "embed-ui"))]
+mod embedded {
+    use axum::Router;
+
+    use crate::AppState;
+
+    pub fn router() -> anyhow::Result<Router<AppState>> {
+        anyhow::bail!("this build has no embedded UI; rebuild with --features embed-ui or use --ui files")
+    }
+}
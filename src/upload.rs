@@ -0,0 +1,181 @@
+use axum::{
+    extract::{multipart::Field, Multipart, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Instant;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    catalog,
+    errors::{AppError, ErrorCode},
+    short_id, store_verification, verifier, AppState, VerificationRecord, DEFAULT_STEP_SIZE,
+};
+
+// Verification of proofs generated elsewhere. POST /api/verify/upload takes
+// a multipart form with either
+//
+//   proof      proof.bin
+//   public     public.json
+//   wasm       the module the proof is of, recorded with the result (optional)
+//   step_size  the step size it was proven with (default 50)
+//
+// or `bundle`, an export from GET /api/proofs/:id/export?format=bundle,
+// whose step size and module are used unless the form gives its own. The
+// result is stored as a verification with no local proof behind it. Uploads
+// may be up to UPLOAD_MAX_MB (default 64).
+
+// What an uploaded proof claimed to be
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UploadedProof {
+    // files or bundle
+    pub source: String,
+    // sha256 of proof.bin, hex
+    pub proof_hash: String,
+    pub step_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<String>,
+    // The proof's ID on the server that exported the bundle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_proof_id: Option<String>,
+}
+
+#[derive(Default)]
+struct Form {
+    // proof.bin is written to disk as it arrives rather than held in memory
+    proof: Option<verifier::ProofFiles>,
+    public: Option<Vec<u8>>,
+    bundle: Option<Vec<u8>>,
+    wasm: Option<String>,
+    step_size: Option<String>,
+}
+
+pub fn max_upload_bytes() -> usize {
+    let megabytes = std::env::var("UPLOAD_MAX_MB").ok().and_then(|v| v.parse().ok()).unwrap_or(64);
+    megabytes * 1024 * 1024
+}
+
+fn invalid(e: axum::extract::multipart::MultipartError) -> AppError {
+    AppError::new(ErrorCode::InvalidArguments, format!("Invalid upload: {}", e))
+}
+
+// Copy an uploaded proof.bin to a scratch directory a chunk at a time
+async fn receive_proof(mut field: Field<'_>) -> Result<verifier::ProofFiles, AppError> {
+    let files = verifier::ProofFiles::temporary()?;
+    let storage_error = |e: std::io::Error| AppError::new(ErrorCode::StorageError, format!("Failed to write the uploaded proof: {}", e));
+    let mut file = tokio::fs::File::create(files.proof_file()).await.map_err(storage_error)?;
+    while let Some(chunk) = field.chunk().await.map_err(invalid)? {
+        file.write_all(&chunk).await.map_err(storage_error)?;
+    }
+    file.flush().await.map_err(storage_error)?;
+    Ok(files)
+}
+
+async fn read_form(mut multipart: Multipart) -> Result<Form, AppError> {
+    let mut form = Form::default();
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        let name = field.name().unwrap_or_default().to_string();
+        if matches!(name.as_str(), "proof" | "proof.bin") {
+            form.proof = Some(receive_proof(field).await?);
+            continue;
+        }
+        let bytes = field.bytes().await.map_err(invalid)?.to_vec();
+        let text = || String::from_utf8_lossy(&bytes).trim().to_string();
+        match name.as_str() {
+            "public" | "public.json" => form.public = Some(bytes),
+            "bundle" => form.bundle = Some(bytes),
+            "wasm" => form.wasm = Some(text()).filter(|wasm| !wasm.is_empty()),
+            "step_size" | "step" => form.step_size = Some(text()),
+            _ => {
+                return Err(AppError::new(ErrorCode::InvalidArguments, format!("Unexpected upload field {:?}", name)));
+            }
+        }
+    }
+    Ok(form)
+}
+
+async fn verify_upload(state: &AppState, form: Form) -> Result<VerificationRecord, AppError> {
+    let step_size: Option<u64> = match &form.step_size {
+        Some(step_size) => Some(step_size.parse().map_err(|_| {
+            AppError::new(ErrorCode::InvalidArguments, format!("step_size {:?} is not a number", step_size))
+        })?),
+        None => None,
+    };
+
+    let (files, upload) = match (form.bundle, form.proof, form.public) {
+        (Some(bundle), None, None) => {
+            let bundle: Value = serde_json::from_slice(&bundle)
+                .map_err(|e| AppError::new(ErrorCode::InvalidArguments, format!("Not a proof bundle: {}", e)))?;
+            let files = verifier::unpack(&bundle)?;
+            let metadata = &bundle["proof"]["metadata"];
+            let upload = UploadedProof {
+                source: "bundle".to_string(),
+                proof_hash: String::new(),
+                step_size: step_size.unwrap_or_else(|| verifier::bundle_step_size(&bundle)),
+                wasm: form.wasm.or_else(|| metadata["wasm_path"].as_str().map(str::to_string)),
+                original_proof_id: bundle["proof"]["id"].as_str().map(str::to_string),
+            };
+            (files, upload)
+        }
+        (None, Some(files), Some(public)) => {
+            files.write_public(&public)?;
+            let upload = UploadedProof {
+                source: "files".to_string(),
+                proof_hash: String::new(),
+                step_size: step_size.unwrap_or(DEFAULT_STEP_SIZE),
+                wasm: form.wasm,
+                original_proof_id: None,
+            };
+            (files, upload)
+        }
+        _ => {
+            return Err(AppError::new(ErrorCode::InvalidArguments, "Upload either proof and public, or a bundle"));
+        }
+    };
+    catalog::check_step_size(upload.step_size)?;
+    let proof_hash = verifier::sha256_file(&files.proof_file()).await
+        .map_err(|e| AppError::new(ErrorCode::StorageError, format!("Failed to read the uploaded proof: {}", e)))?;
+    let upload = UploadedProof { proof_hash, ..upload };
+
+    let _slot = state.verify_pool.slot().await;
+    let start_time = Instant::now();
+    let output = verifier::run(&state.sandbox, &state.zkengine_binary, upload.step_size, &files.proof_file(), &files.public_file()).await?;
+    let is_valid = output.status.success();
+    let verification = VerificationRecord {
+        id: Uuid::new_v4().to_string(),
+        proof_id: String::new(),
+        timestamp: Utc::now(),
+        is_valid,
+        verification_time_secs: start_time.elapsed().as_secs_f64(),
+        error: (!is_valid).then(|| String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        error_code: (!is_valid).then_some(ErrorCode::VerifierMismatch),
+        cached: false,
+        upload: Some(upload),
+        peers: Vec::new(),
+        receipt: None,
+    };
+    info!(
+        "Verified uploaded proof {} in {:.3}s: {}",
+        short_id(&verification.id),
+        verification.verification_time_secs,
+        if is_valid { "valid" } else { "INVALID" },
+    );
+
+    Ok(store_verification(state, verification).await)
+}
+
+#[tracing::instrument(name = "proof.verify_upload", skip_all)]
+pub async fn upload_handler(State(state): State<AppState>, multipart: Multipart) -> Response {
+    let result = match read_form(multipart).await {
+        Ok(form) => verify_upload(&state, form).await,
+        Err(error) => Err(error),
+    };
+    match result {
+        Ok(verification) => Json(json!({ "success": true, "verification": verification })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
@@ -0,0 +1,415 @@
+use axum::{
+    extract::{DefaultBodyLimit, State},
+    http::{Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::io::AsyncReadExt;
+use tower_http::{cors::CorsLayer, trace::{DefaultMakeSpan, TraceLayer}};
+use tracing::{error, info, warn, Level};
+use uuid::Uuid;
+
+use crate::{
+    auth::{self, Authenticator, Role},
+    cli,
+    errors::{AppError, ErrorCode},
+    outbound::{self, Integration},
+    listen, probes, request_id, sandbox, secrets, signing, telemetry, verify_command, workers, DEFAULT_STEP_SIZE,
+};
+
+// Verifier-only mode (`zkp-agentkit verifier`): an independent auditor node
+// that checks proofs made elsewhere and can't generate any. It needs only
+// the zkEngine binary; there is no LLM, job queue, proof store or wasm
+// directory.
+//
+//   POST /api/verify         {"bundle": {...}}  a bundle from GET /api/proofs/:id/export
+//                            {"path": "<dir>"}  a directory with proof.bin and public.json,
+//                                               under VERIFIER_ROOT (default: the proofs directory)
+//                            {"cid": "<cid>"}   a bundle fetched from IPFS_GATEWAY
+//                                               (default https://ipfs.io/ipfs)
+//   GET  /api/verifications  the latest results, newest first (VERIFIER_HISTORY, default 100)
+//   GET  /.well-known/proof-signing-key
+//   GET  /healthz, /readyz
+//
+// Every result is signed with the node's key (PROOF_SIGNING_KEY, see
+// signing.rs), so a server that asked it can show who verified what.
+//
+// With API_KEYS or JWT_ISSUER set, verifying takes the prover role and
+// listing results the viewer role. Bundles may be up to VERIFIER_MAX_BUNDLE_MB
+// (default 64).
+
+const CID_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Verifier {
+    zkengine_binary: String,
+    root: PathBuf,
+    ipfs_gateway: String,
+    max_bundle_bytes: usize,
+    sandbox: sandbox::Sandbox,
+    auth: Authenticator,
+    signer: signing::ProofSigner,
+    // VERIFY_WORKERS, see workers.rs
+    pool: workers::WorkerPool,
+    history: Mutex<VecDeque<Verification>>,
+    max_history: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Source {
+    Bundle(Value),
+    Path(String),
+    Cid(String),
+}
+
+#[derive(Serialize, Clone)]
+struct Verification {
+    id: String,
+    timestamp: DateTime<Utc>,
+    // bundle, path or cid
+    source: &'static str,
+    // The proof ID from the bundle, the directory or the CID
+    reference: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof_id: Option<String>,
+    // sha256 of proof.bin, hex
+    proof_hash: String,
+    is_valid: bool,
+    verification_time_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_code: Option<ErrorCode>,
+    signature: signing::VerificationSignature,
+}
+
+// A proof written out for zkEngine, removed once checked
+pub struct ProofFiles {
+    dir: PathBuf,
+    temporary: bool,
+}
+
+fn write_error(e: std::io::Error) -> AppError {
+    AppError::new(ErrorCode::StorageError, format!("Failed to write the proof files: {}", e))
+}
+
+impl ProofFiles {
+    // An empty scratch directory, for files written one at a time
+    pub fn temporary() -> Result<Self, AppError> {
+        let dir = std::env::temp_dir().join(format!("zkp-verifier-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).map_err(write_error)?;
+        Ok(ProofFiles { dir, temporary: true })
+    }
+
+    // Write a proof and its public outputs to a scratch directory
+    pub fn write(proof: &[u8], public: &[u8]) -> Result<Self, AppError> {
+        let files = Self::temporary()?;
+        std::fs::write(files.proof_file(), proof).map_err(write_error)?;
+        files.write_public(public)?;
+        Ok(files)
+    }
+
+    pub fn write_public(&self, public: &[u8]) -> Result<(), AppError> {
+        std::fs::write(self.public_file(), public).map_err(write_error)
+    }
+
+    pub fn proof_file(&self) -> PathBuf {
+        self.dir.join("proof.bin")
+    }
+
+    pub fn public_file(&self) -> PathBuf {
+        self.dir.join("public.json")
+    }
+}
+
+impl Drop for ProofFiles {
+    fn drop(&mut self) {
+        if self.temporary {
+            std::fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+// Proof files are hashed, uploaded and downloaded in chunks of this size, so
+// memory use stays flat however big the proof is. A multiple of 3, so that
+// base64-encoded chunks join up.
+pub const CHUNK_SIZE: usize = 3 * 64 * 1024;
+
+// Fill `buffer` from the file; fewer bytes only at the end of the file
+pub async fn read_chunk(file: &mut tokio::fs::File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]).await? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// sha256 of a file, hex, read a chunk at a time
+pub async fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        match read_chunk(&mut file, &mut buffer).await? {
+            0 => break,
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Run zkEngine's verifier; Err when it couldn't be run at all
+pub async fn run(sandbox: &sandbox::Sandbox, binary: &str, step_size: u64, proof_file: &Path, public_file: &Path) -> Result<std::process::Output, AppError> {
+    let mut cmd = verify_command(sandbox, binary, step_size, proof_file, public_file);
+    tokio::task::spawn_blocking(move || cmd.output())
+        .await
+        .map_err(|_| AppError::new(ErrorCode::Internal, "Internal error"))?
+        .map_err(|e| AppError::new(ErrorCode::ProverUnavailable, format!("Execution error: {}", e)))
+}
+
+// Unpack an export bundle's proof and public outputs
+pub fn unpack(bundle: &Value) -> Result<ProofFiles, AppError> {
+    let invalid = |message: &str| AppError::new(ErrorCode::InvalidArguments, format!("Not a proof bundle: {}", message));
+    if bundle["format"] != "bundle" {
+        return Err(invalid("format must be \"bundle\"; export with ?format=bundle"));
+    }
+    let encoded = bundle["proof_file"]["base64"].as_str().ok_or_else(|| invalid("proof_file.base64 is missing"))?;
+    let proof = base64::engine::general_purpose::STANDARD.decode(encoded).map_err(|_| invalid("proof_file.base64 is not base64"))?;
+    if let Some(expected) = bundle["proof_file"]["sha256"].as_str().filter(|hash| !hash.is_empty()) {
+        let actual = sha256_hex(&proof);
+        if actual != expected {
+            return Err(AppError::new(
+                ErrorCode::ArtifactTampered,
+                format!("proof.bin doesn't match the bundle's sha256 (expected {}, got {})", expected, actual),
+            ));
+        }
+    }
+    // Stored as parsed JSON, or as text when public.json wasn't JSON
+    let public = match &bundle["public_outputs"] {
+        Value::Null => return Err(invalid("public_outputs is missing")),
+        Value::String(text) => text.clone(),
+        outputs => outputs.to_string(),
+    };
+    ProofFiles::write(&proof, public.as_bytes())
+}
+
+// Step size recorded in an export bundle; zkEngine verifies with the one
+// the proof was made with
+pub fn bundle_step_size(bundle: &Value) -> u64 {
+    bundle["proof"]["metadata"]["step_size"].as_u64().unwrap_or(DEFAULT_STEP_SIZE)
+}
+
+impl Verifier {
+    fn from_env(paths: &cli::Paths, args: &cli::VerifierArgs, auth: Authenticator, signer: signing::ProofSigner) -> Self {
+        let number = |name: &str, default: usize| std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Verifier {
+            zkengine_binary: paths.zkengine_binary.clone(),
+            root: PathBuf::from(args.root.as_deref().unwrap_or(&paths.proofs_dir)),
+            ipfs_gateway: args.ipfs_gateway.trim_end_matches('/').to_string(),
+            max_bundle_bytes: number("VERIFIER_MAX_BUNDLE_MB", 64) * 1024 * 1024,
+            sandbox: sandbox::Sandbox::from_env(),
+            auth,
+            signer,
+            pool: workers::WorkerPool::verifiers(),
+            history: Mutex::new(VecDeque::new()),
+            max_history: number("VERIFIER_HISTORY", 100),
+        }
+    }
+
+    // A directory under the root; symlinks out of it are refused too
+    fn proof_dir(&self, path: &str) -> Result<ProofFiles, AppError> {
+        let not_found = || AppError::new(ErrorCode::NotFound, format!("No proof directory {} under {}", path, self.root.display()));
+        let root = self.root.canonicalize().map_err(|_| not_found())?;
+        let dir = root.join(path).canonicalize().map_err(|_| not_found())?;
+        if !dir.starts_with(&root) {
+            return Err(AppError::new(ErrorCode::Forbidden, format!("{} is outside {}", path, self.root.display())));
+        }
+        if !dir.join("proof.bin").is_file() {
+            return Err(AppError::new(ErrorCode::NotFound, format!("No proof.bin in {}", path)));
+        }
+        Ok(ProofFiles { dir, temporary: false })
+    }
+
+    async fn fetch_cid(&self, cid: &str) -> Result<Value, AppError> {
+        if cid.is_empty() || !cid.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(AppError::new(ErrorCode::InvalidArguments, format!("{:?} is not a CID", cid)));
+        }
+        let url = format!("{}/{}", self.ipfs_gateway, cid);
+        let unavailable = |e: String| AppError::new(ErrorCode::NotFound, format!("Failed to fetch {}: {}", url, e));
+        let response = outbound::client(Integration::Ipfs)
+            .get(&url)
+            .timeout(CID_FETCH_TIMEOUT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| unavailable(e.to_string()))?;
+        if response.content_length().is_some_and(|length| length as usize > self.max_bundle_bytes) {
+            return Err(AppError::new(ErrorCode::TooLarge, format!("{} is larger than VERIFIER_MAX_BUNDLE_MB", cid)));
+        }
+        let body = response.bytes().await.map_err(|e| unavailable(e.to_string()))?;
+        if body.len() > self.max_bundle_bytes {
+            return Err(AppError::new(ErrorCode::TooLarge, format!("{} is larger than VERIFIER_MAX_BUNDLE_MB", cid)));
+        }
+        serde_json::from_slice(&body).map_err(|e| AppError::new(ErrorCode::InvalidArguments, format!("{} is not a proof bundle: {}", cid, e)))
+    }
+
+    async fn verify(&self, source: Source) -> Result<Verification, AppError> {
+        let (name, reference, proof_id, step_size, files) = match source {
+            Source::Bundle(bundle) => {
+                let proof_id = bundle["proof"]["id"].as_str().map(str::to_string);
+                let files = unpack(&bundle)?;
+                ("bundle", proof_id.clone().unwrap_or_default(), proof_id, bundle_step_size(&bundle), files)
+            }
+            Source::Path(path) => {
+                let files = self.proof_dir(&path)?;
+                ("path", path, None, DEFAULT_STEP_SIZE, files)
+            }
+            Source::Cid(cid) => {
+                let bundle = self.fetch_cid(&cid).await?;
+                let proof_id = bundle["proof"]["id"].as_str().map(str::to_string);
+                ("cid", cid, proof_id, bundle_step_size(&bundle), unpack(&bundle)?)
+            }
+        };
+        let proof_hash = sha256_file(&files.proof_file()).await.unwrap_or_else(|_| sha256_hex(b""));
+
+        let _slot = self.pool.slot().await;
+        let start_time = Instant::now();
+        let output = run(&self.sandbox, &self.zkengine_binary, step_size, &files.proof_file(), &files.public_file()).await?;
+        let is_valid = output.status.success();
+        let error = (!is_valid).then(|| String::from_utf8_lossy(&output.stderr).trim().to_string());
+        let id = Uuid::new_v4().to_string();
+        let signature = self.signer.sign_verification(&id, &proof_hash, is_valid);
+        let verification = Verification {
+            id,
+            timestamp: Utc::now(),
+            source: name,
+            reference,
+            proof_id,
+            proof_hash,
+            is_valid,
+            verification_time_secs: start_time.elapsed().as_secs_f64(),
+            error,
+            error_code: (!is_valid).then_some(ErrorCode::VerifierMismatch),
+            signature,
+        };
+        info!(
+            "Verified {} {} in {:.3}s: {}",
+            verification.source,
+            verification.reference,
+            verification.verification_time_secs,
+            if is_valid { "valid" } else { "INVALID" },
+        );
+
+        let mut history = self.history.lock().unwrap();
+        history.push_front(verification.clone());
+        history.truncate(self.max_history);
+        Ok(verification)
+    }
+}
+
+async fn verify_handler(State(verifier): State<Arc<Verifier>>, Json(source): Json<Source>) -> Response {
+    match verifier.verify(source).await {
+        Ok(verification) => Json(json!({ "success": true, "verification": verification })).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn verifications_handler(State(verifier): State<Arc<Verifier>>) -> Json<Value> {
+    let verifications: Vec<Verification> = verifier.history.lock().unwrap().iter().cloned().collect();
+    Json(json!({ "success": true, "verifications": verifications }))
+}
+
+async fn public_key_handler(State(verifier): State<Arc<Verifier>>) -> Json<Value> {
+    Json(verifier.signer.public_key())
+}
+
+// Only the zkEngine binary is needed
+async fn readyz(State(verifier): State<Arc<Verifier>>) -> Response {
+    let ready = Path::new(&verifier.zkengine_binary).is_file();
+    let detail = if ready {
+        verifier.zkengine_binary.clone()
+    } else {
+        format!("zkEngine binary not found at {}", verifier.zkengine_binary)
+    };
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    let body = json!({
+        "status": if ready { "ready" } else { "not_ready" },
+        "checks": { "zkengine": { "ok": ready, "detail": detail } },
+    });
+    (status, Json(body)).into_response()
+}
+
+async fn auth_middleware<B>(State(verifier): State<Arc<Verifier>>, request: Request<B>, next: Next<B>) -> Response {
+    let required = match request.uri().path() {
+        "/api/verify" => Role::Prover,
+        path if path.starts_with("/api/") => Role::Viewer,
+        _ => return next.run(request).await,
+    };
+    match auth::authorize(&verifier.auth, &request, required).await {
+        Ok(_) => next.run(request).await,
+        Err(error) => error.into_response(),
+    }
+}
+
+pub async fn serve(paths: cli::Paths, args: cli::VerifierArgs) {
+    let telemetry = telemetry::init(false);
+    let listeners = match args.listen.clone() {
+        listeners if listeners.is_empty() => vec![listen::Listener::all(args.port)],
+        listeners => listeners,
+    };
+    let secrets = secrets::Secrets::from_env().await.unwrap_or_else(|e| {
+        error!("Invalid secrets configuration: {}", e);
+        std::process::exit(1);
+    });
+    secrets.spawn_refresh();
+    let authenticator = Authenticator::from_env(&secrets);
+    if !authenticator.is_enabled() {
+        warn!("Neither API_KEYS nor JWT_ISSUER set - anyone can submit proofs for verification");
+    }
+    let signer = signing::ProofSigner::from_env(&secrets);
+    let verifier = Arc::new(Verifier::from_env(&paths, &args, authenticator, signer));
+    if !Path::new(&verifier.zkengine_binary).is_file() {
+        warn!("zkEngine binary not found at {}; every verification will fail", verifier.zkengine_binary);
+    }
+    info!("Verifier only: proofs are checked, never generated (paths under {})", verifier.root.display());
+
+    let app = Router::new()
+        .route("/healthz", get(probes::healthz))
+        .route("/readyz", get(readyz))
+        .route("/.well-known/proof-signing-key", get(public_key_handler))
+        .route("/api/verify", post(verify_handler))
+        .route("/api/verifications", get(verifications_handler))
+        .layer(DefaultBodyLimit::max(verifier.max_bundle_bytes))
+        .layer(middleware::from_fn_with_state(verifier.clone(), auth_middleware))
+        .layer(CorsLayer::permissive())
+        .layer(middleware::from_fn(request_id::middleware))
+        .layer(TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::new().level(Level::INFO)))
+        .with_state(verifier);
+
+    if let Err(e) = listen::serve(app, listeners).await {
+        error!("Server failed: {}", e);
+        telemetry.shutdown();
+        std::process::exit(1);
+    }
+    telemetry.shutdown();
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::calculate_file_hash;
+
+// Results of zkEngine verify keyed by what was verified, so verifying an
+// untouched proof again answers at once instead of re-running a
+// multi-second verification. The key covers the bytes of proof.bin and
+// public.json and the step size; any change to them is a miss.
+//
+//   VERIFY_CACHE_SIZE   results kept, oldest evicted first (default 1024, 0 disables)
+//
+// Only verdicts are cached; failures to run zkEngine at all are not.
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Key {
+    proof_hash: String,
+    public_hash: String,
+    step_size: u64,
+}
+
+impl Key {
+    // `proof_hash` is proof.bin's, as the caller already hashed it to check
+    // for tampering; None when either file can't be read
+    pub async fn of(proof_hash: String, public_file: &Path, step_size: u64) -> Option<Self> {
+        let public_hash = calculate_file_hash(public_file).await;
+        if proof_hash == "error" || public_hash == "error" {
+            return None;
+        }
+        Some(Key { proof_hash, public_hash, step_size })
+    }
+}
+
+#[derive(Clone)]
+pub struct Verdict {
+    pub is_valid: bool,
+    // zkEngine's stderr when the proof is invalid
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct Entries {
+    verdicts: HashMap<Key, Verdict>,
+    // Insertion order, for eviction
+    order: VecDeque<Key>,
+}
+
+pub struct VerifyCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl VerifyCache {
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("VERIFY_CACHE_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or(1024);
+        VerifyCache { capacity, entries: Mutex::new(Entries::default()) }
+    }
+
+    pub fn get(&self, key: &Key) -> Option<Verdict> {
+        self.entries.lock().unwrap().verdicts.get(key).cloned()
+    }
+
+    pub fn insert(&self, key: Key, verdict: Verdict) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.verdicts.insert(key.clone(), verdict).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.verdicts.remove(&oldest);
+            }
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    outbound::{self, Integration},
+    protocol::{WsEvent, WsMessage},
+    short_id, AppState,
+};
+
+// Outbound webhooks, so external systems can react to proofs without
+// holding a WebSocket open. Registered by admins:
+//
+//   POST   /api/webhooks                  {"url": "https://...", "events": ["proof_complete"],
+//                                          "secret": "..."}; events and secret are optional
+//   GET    /api/webhooks
+//   DELETE /api/webhooks/:id
+//   GET    /api/webhooks/:id/deliveries   recent deliveries, newest first
+//
+// Events are proof_complete, proof_failed, proof_cancelled and
+// verification_complete (all four by default). Each delivery is a POST of
+// {"id": ..., "event": ..., "created_at": ..., "data": {...}}, where `data`
+// is the WebSocket event, with these headers:
+//
+//   X-Webhook-Event       the event name
+//   X-Webhook-Delivery    delivery ID, the same across retries
+//   X-Webhook-Signature   t=<unix time>,v1=<hex HMAC-SHA256 of "<t>.<body>">
+//
+// keyed with the webhook's secret, which is generated when none is given
+// and only shown in the registration response. Receivers should check the
+// signature and reject stale timestamps. Deliveries that fail (no 2xx
+// within 10 seconds) are retried with exponential backoff, up to
+// WEBHOOK_MAX_ATTEMPTS attempts in all (default 5). Only events raised on
+// this instance are delivered, so a cluster sends each one once.
+
+const WEBHOOKS_DB_FILE: &str = "./webhooks_db.json";
+const EVENTS: [&str; 4] = ["proof_complete", "proof_failed", "proof_cancelled", "verification_complete"];
+// Deliveries kept per webhook for the delivery log
+const DELIVERY_LOG_SIZE: usize = 100;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    secret: String,
+}
+
+impl Webhook {
+    // Everything but the secret
+    fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "url": self.url,
+            "events": self.events,
+            "description": self.description,
+            "created_at": self.created_at,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RegisterRequest {
+    url: String,
+    #[serde(default)]
+    events: Option<Vec<String>>,
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Attempt {
+    pub at: DateTime<Utc>,
+    // HTTP status, if the receiver answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Delivery {
+    pub id: String,
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_id: Option<String>,
+    pub state: DeliveryState,
+    pub attempts: Vec<Attempt>,
+}
+
+// Registered webhooks, persisted like templates, and their recent
+// deliveries, kept in memory
+pub struct WebhookStore {
+    hooks: Mutex<BTreeMap<String, Webhook>>,
+    deliveries: Mutex<HashMap<String, VecDeque<Delivery>>>,
+    client: reqwest::Client,
+}
+
+impl WebhookStore {
+    pub async fn load() -> Self {
+        let hooks = match tokio::fs::read_to_string(WEBHOOKS_DB_FILE).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Failed to load webhooks from {}: {}", WEBHOOKS_DB_FILE, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        WebhookStore {
+            hooks: Mutex::new(hooks),
+            deliveries: Mutex::new(HashMap::new()),
+            client: outbound::client(Integration::Webhooks),
+        }
+    }
+
+    // Deliver a published message to the webhooks that want it, in the
+    // background
+    pub fn dispatch(self: &Arc<Self>, msg: &WsMessage) {
+        let Some(data) = &msg.data else {
+            return;
+        };
+        let event = match data {
+            WsEvent::ProofComplete { .. } => "proof_complete",
+            WsEvent::ProofFailed { .. } => "proof_failed",
+            WsEvent::ProofCancelled { .. } => "proof_cancelled",
+            WsEvent::VerificationComplete { .. } => "verification_complete",
+            _ => return,
+        };
+        let proof_id = data.proof_id().map(str::to_string);
+        let data = serde_json::to_value(data).unwrap_or_default();
+        let store = self.clone();
+        tokio::spawn(async move {
+            let hooks: Vec<Webhook> = store.hooks.lock().await
+                .values()
+                .filter(|hook| hook.events.iter().any(|e| e == event))
+                .cloned()
+                .collect();
+            for hook in hooks {
+                let delivery = Delivery {
+                    id: Uuid::new_v4().to_string(),
+                    event: event.to_string(),
+                    proof_id: proof_id.clone(),
+                    state: DeliveryState::Pending,
+                    attempts: Vec::new(),
+                };
+                let body = json!({
+                    "id": delivery.id,
+                    "event": event,
+                    "created_at": Utc::now(),
+                    "data": data,
+                }).to_string();
+                store.record(&hook.id, delivery.clone()).await;
+                tokio::spawn(store.clone().deliver(hook, delivery, body));
+            }
+        });
+    }
+
+    async fn record(&self, hook_id: &str, delivery: Delivery) {
+        let mut deliveries = self.deliveries.lock().await;
+        let log = deliveries.entry(hook_id.to_string()).or_default();
+        match log.iter_mut().find(|d| d.id == delivery.id) {
+            Some(entry) => *entry = delivery,
+            None => {
+                log.push_front(delivery);
+                log.truncate(DELIVERY_LOG_SIZE);
+            }
+        }
+    }
+
+    // Send one delivery, retrying until it succeeds or runs out of attempts
+    async fn deliver(self: Arc<Self>, hook: Webhook, mut delivery: Delivery, body: String) {
+        let max_attempts: usize = std::env::var("WEBHOOK_MAX_ATTEMPTS").ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5)
+            .max(1);
+        for attempt in 1..=max_attempts {
+            // Deleted while waiting to retry
+            if attempt > 1 && !self.hooks.lock().await.contains_key(&hook.id) {
+                return;
+            }
+            let timestamp = Utc::now().timestamp();
+            let result = self.client.post(&hook.url)
+                .header("Content-Type", "application/json")
+                .header("X-Webhook-Event", &delivery.event)
+                .header("X-Webhook-Delivery", &delivery.id)
+                .header("X-Webhook-Signature", signature_header(&hook.secret, timestamp, &body))
+                .body(body.clone())
+                .timeout(DELIVERY_TIMEOUT)
+                .send()
+                .await;
+            let (status, error) = match result {
+                Ok(response) => (Some(response.status().as_u16()), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            delivery.attempts.push(Attempt { at: Utc::now(), status, error });
+            if status.is_some_and(|status| (200..300).contains(&status)) {
+                delivery.state = DeliveryState::Delivered;
+                self.record(&hook.id, delivery).await;
+                return;
+            }
+            if attempt == max_attempts {
+                break;
+            }
+            self.record(&hook.id, delivery.clone()).await;
+            // 5s, 10s, 20s, ... capped at ten minutes
+            tokio::time::sleep(Duration::from_secs((5u64 << (attempt - 1).min(7)).min(600))).await;
+        }
+        warn!(
+            "Webhook {} gave up on {} delivery {} after {} attempt(s)",
+            hook.id, delivery.event, short_id(&delivery.id), delivery.attempts.len()
+        );
+        delivery.state = DeliveryState::Failed;
+        self.record(&hook.id, delivery).await;
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn signature_header(secret: &str, timestamp: i64, body: &str) -> String {
+    format!("t={},v1={}", timestamp, sign(secret, timestamp, body))
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).expect("no randomness for a webhook secret");
+    format!("whsec_{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+async fn save(hooks: &BTreeMap<String, Webhook>) {
+    let result = match serde_json::to_string_pretty(hooks) {
+        Ok(json) => tokio::fs::write(WEBHOOKS_DB_FILE, json).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    if let Err(e) = result {
+        error!("Failed to save webhooks to disk: {}", e);
+    }
+}
+
+fn check_request(request: &RegisterRequest) -> Result<Vec<String>, AppError> {
+    let url = reqwest::Url::parse(request.url.trim())
+        .map_err(|e| AppError::new(ErrorCode::InvalidArguments, format!("Invalid webhook URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "Webhook URLs must be http or https"));
+    }
+    let events = match &request.events {
+        Some(events) if !events.is_empty() => events.iter().map(|e| e.trim().to_lowercase()).collect(),
+        _ => EVENTS.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+    };
+    if let Some(unknown) = events.iter().find(|e| !EVENTS.contains(&e.as_str())) {
+        return Err(AppError::new(
+            ErrorCode::InvalidArguments,
+            format!("Unknown event {}; use {}", unknown, EVENTS.join(", ")),
+        ));
+    }
+    if request.secret.as_ref().is_some_and(|secret| secret.len() < 16) {
+        return Err(AppError::new(ErrorCode::InvalidArguments, "Webhook secrets need at least 16 characters"));
+    }
+    Ok(events)
+}
+
+fn not_found(id: &str) -> Response {
+    AppError::new(ErrorCode::NotFound, format!("No webhook {}", id)).into_response()
+}
+
+pub async fn register_handler(State(state): State<AppState>, Json(request): Json<RegisterRequest>) -> Response {
+    let events = match check_request(&request) {
+        Ok(events) => events,
+        Err(error) => return error.into_response(),
+    };
+    let secret = request.secret.unwrap_or_else(generate_secret);
+    let hook = Webhook {
+        id: Uuid::new_v4().to_string(),
+        url: request.url.trim().to_string(),
+        events,
+        description: request.description,
+        created_at: Utc::now(),
+        secret: secret.clone(),
+    };
+    let mut hooks = state.webhooks.hooks.lock().await;
+    hooks.insert(hook.id.clone(), hook.clone());
+    save(&hooks).await;
+    info!(target: "audit", webhook_id = %hook.id, url = %hook.url, "Webhook registered");
+    Json(json!({ "success": true, "webhook": hook.summary(), "secret": secret })).into_response()
+}
+
+pub async fn list_handler(State(state): State<AppState>) -> Json<Value> {
+    let webhooks: Vec<Value> = state.webhooks.hooks.lock().await.values().map(Webhook::summary).collect();
+    Json(json!({ "success": true, "webhooks": webhooks }))
+}
+
+pub async fn delete_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let mut hooks = state.webhooks.hooks.lock().await;
+    let Some(hook) = hooks.remove(&id) else {
+        return not_found(&id);
+    };
+    save(&hooks).await;
+    drop(hooks);
+    state.webhooks.deliveries.lock().await.remove(&id);
+    info!(target: "audit", webhook_id = %hook.id, url = %hook.url, "Webhook deleted");
+    Json(json!({ "success": true, "id": hook.id })).into_response()
+}
+
+pub async fn deliveries_handler(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    if !state.webhooks.hooks.lock().await.contains_key(&id) {
+        return not_found(&id);
+    }
+    let deliveries: Vec<Delivery> = state.webhooks.deliveries.lock().await
+        .get(&id)
+        .map(|log| log.iter().cloned().collect())
+        .unwrap_or_default();
+    Json(json!({ "success": true, "webhook_id": id, "deliveries": deliveries })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test_secret";
+    const BODY: &str = r#"{"id":"1"}"#;
+
+    fn request(url: &str, events: Option<&[&str]>, secret: Option<&str>) -> RegisterRequest {
+        RegisterRequest {
+            url: url.to_string(),
+            events: events.map(|events| events.iter().map(|e| e.to_string()).collect()),
+            secret: secret.map(str::to_string),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn signatures_are_hmac_sha256_of_timestamp_and_body() {
+        assert_eq!(
+            signature_header(SECRET, 1_700_000_000, BODY),
+            "t=1700000000,v1=c10a092032b032422619ec21b90dd2523fd5b2be59ed619283f25aa7c1d57567",
+        );
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_body_and_secret() {
+        let signature = sign(SECRET, 1_700_000_000, BODY);
+        // A replay with a fresh timestamp doesn't carry over the signature
+        assert_ne!(sign(SECRET, 1_700_000_001, BODY), signature);
+        assert_ne!(sign(SECRET, 1_700_000_000, r#"{"id":"2"}"#), signature);
+        assert_ne!(sign("whsec_other_secret", 1_700_000_000, BODY), signature);
+    }
+
+    #[test]
+    fn registrations_are_checked() {
+        assert_eq!(check_request(&request("https://example.com/hook", None, None)).unwrap(), EVENTS);
+        assert_eq!(
+            check_request(&request("https://example.com/hook", Some(&[" Proof_Failed "]), None)).unwrap(),
+            ["proof_failed"],
+        );
+        let refused = [
+            request("ftp://example.com/hook", None, None),
+            request("not a url", None, None),
+            request("https://example.com/hook", Some(&["proof_started"]), None),
+            request("https://example.com/hook", None, Some("too-short")),
+        ];
+        for request in refused {
+            assert_eq!(check_request(&request).unwrap_err().code, ErrorCode::InvalidArguments);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_delivery_log_keeps_the_latest_state_newest_first() {
+        let store = WebhookStore { hooks: Default::default(), deliveries: Default::default(), client: reqwest::Client::new() };
+        let delivery = |id: &str, state| Delivery {
+            id: id.to_string(),
+            event: "proof_complete".to_string(),
+            proof_id: None,
+            state,
+            attempts: Vec::new(),
+        };
+        store.record("hook", delivery("first", DeliveryState::Pending)).await;
+        store.record("hook", delivery("second", DeliveryState::Pending)).await;
+        store.record("hook", delivery("first", DeliveryState::Delivered)).await;
+        for i in 0..DELIVERY_LOG_SIZE {
+            store.record("other", delivery(&i.to_string(), DeliveryState::Failed)).await;
+        }
+        store.record("other", delivery("latest", DeliveryState::Failed)).await;
+
+        let deliveries = store.deliveries.lock().await;
+        let log: Vec<(&str, DeliveryState)> = deliveries["hook"].iter().map(|d| (d.id.as_str(), d.state)).collect();
+        assert!(log == [("second", DeliveryState::Pending), ("first", DeliveryState::Delivered)]);
+        assert_eq!(deliveries["other"].len(), DELIVERY_LOG_SIZE);
+        assert_eq!(deliveries["other"][0].id, "latest");
+    }
+}
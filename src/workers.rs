@@ -0,0 +1,52 @@
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::info;
+
+// Bounded pools for zkEngine runs. Proving and verifying each get their own
+// number of slots, so a burst of verify requests can't hold up proof
+// generation or the other way round. A run waits for a free slot of its
+// kind before it is handed to the blocking thread pool.
+//
+//   PROVE_WORKERS    proofs generated at once (default: the number of CPUs)
+//   VERIFY_WORKERS   verifications run at once (default: half the CPUs, at least 1)
+
+pub struct WorkerPool {
+    name: &'static str,
+    size: usize,
+    slots: Arc<Semaphore>,
+}
+
+fn cpus() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+impl WorkerPool {
+    fn from_env(name: &'static str, variable: &str, default: usize) -> Self {
+        let size = std::env::var(variable).ok().and_then(|v| v.parse().ok()).filter(|&n| n > 0).unwrap_or(default);
+        WorkerPool { name, size, slots: Arc::new(Semaphore::new(size)) }
+    }
+
+    pub fn provers() -> Self {
+        Self::from_env("prove", "PROVE_WORKERS", cpus())
+    }
+
+    pub fn verifiers() -> Self {
+        Self::from_env("verify", "VERIFY_WORKERS", (cpus() / 2).max(1))
+    }
+
+    // Wait for a free slot; the run holds it until the permit is dropped
+    pub async fn slot(&self) -> OwnedSemaphorePermit {
+        if self.slots.available_permits() == 0 {
+            info!("All {} {} workers are busy; waiting for a slot", self.size, self.name);
+        }
+        self.slots.clone().acquire_owned().await.expect("worker pool semaphore is never closed")
+    }
+
+    pub fn status(&self) -> Value {
+        json!({
+            "size": self.size,
+            "busy": self.size - self.slots.available_permits(),
+        })
+    }
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tracing::{info, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    errors::{AppError, ErrorCode},
+    llm::ProofIntent, protocol::{WsEvent, WsMessage}, request_id, short_id, spawn_intent_proof, verify_proof_async,
+    AppState, ProofStatus,
+};
+
+// Multi-step chat workflows: "prove fibonacci of 20 and verify it" generates
+// the proof, waits for it, verifies it and reports both results. Each
+// workflow has an ID and publishes `workflow_update` events as it moves
+// through its stages.
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkflowStage {
+    Proving,
+    Verifying,
+    Complete,
+    Failed,
+}
+
+impl WorkflowStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkflowStage::Proving => "proving",
+            WorkflowStage::Verifying => "verifying",
+            WorkflowStage::Complete => "complete",
+            WorkflowStage::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Workflow {
+    pub id: String,
+    pub session_id: String,
+    pub function: String,
+    pub arguments: Vec<String>,
+    pub stage: WorkflowStage,
+    pub proof_id: Option<String>,
+    pub is_valid: Option<bool>,
+    pub error: Option<String>,
+    pub error_code: Option<ErrorCode>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Workflows are kept in memory; they only matter while a chat is waiting on them
+#[derive(Default)]
+pub struct WorkflowStore {
+    workflows: Mutex<HashMap<String, Workflow>>,
+}
+
+impl WorkflowStore {
+    pub async fn get(&self, id: &str) -> Option<Workflow> {
+        self.workflows.lock().await.get(id).cloned()
+    }
+}
+
+// Move a workflow to its next stage and tell the session about it
+async fn update(state: &AppState, workflow_id: &str, stage: WorkflowStage, message: String, apply: impl FnOnce(&mut Workflow)) {
+    let Some(workflow) = ({
+        let mut workflows = state.workflows.workflows.lock().await;
+        workflows.get_mut(workflow_id).map(|workflow| {
+            workflow.stage = stage;
+            workflow.updated_at = Utc::now();
+            apply(workflow);
+            workflow.clone()
+        })
+    }) else {
+        return;
+    };
+    info!("Workflow {} is {}", workflow_id, stage.name());
+    state.publish(WsMessage::event(message, WsEvent::WorkflowUpdate {
+        workflow_id: workflow.id,
+        stage: stage.name().to_string(),
+        proof_id: workflow.proof_id,
+        is_valid: workflow.is_valid,
+        error: workflow.error,
+        error_code: workflow.error_code,
+    }).for_session(Some(workflow.session_id)));
+}
+
+// Start proving; verification follows once the proof completes.
+// Returns the workflow ID.
+pub async fn prove_and_verify(state: &AppState, session_id: &str, intent: &ProofIntent) -> Result<String, AppError> {
+    let (proof_id, generation) = spawn_intent_proof(state, session_id, intent).await?;
+    let workflow_id = Uuid::new_v4().to_string();
+    let now = Utc::now();
+    state.workflows.workflows.lock().await.insert(workflow_id.clone(), Workflow {
+        id: workflow_id.clone(),
+        session_id: session_id.to_string(),
+        function: intent.function.clone(),
+        arguments: intent.arguments.clone(),
+        stage: WorkflowStage::Proving,
+        proof_id: Some(proof_id.clone()),
+        is_valid: None,
+        error: None,
+        error_code: None,
+        created_at: now,
+        updated_at: now,
+    });
+    update(
+        state,
+        &workflow_id,
+        WorkflowStage::Proving,
+        format!("Workflow {}: proving {}, then verifying it", short_id(&workflow_id), intent.function),
+        |_| {},
+    ).await;
+
+    let state = state.clone();
+    let session_id = session_id.to_string();
+    let id = workflow_id.clone();
+    tokio::spawn(request_id::inherit(async move {
+        let _ = generation.await;
+        let status = state.proof_store.read(&proof_id, |p| p.status.clone()).await;
+        match status {
+            Some(ProofStatus::Complete) => {}
+            status => {
+                let error = match status {
+                    Some(ProofStatus::Failed(error)) => error,
+                    Some(ProofStatus::Cancelled) => AppError::new(ErrorCode::ProofNotReady, "The proof was cancelled"),
+                    _ => AppError::proof_not_found(),
+                };
+                let message = format!("Workflow {} failed: proof {} did not complete", short_id(&id), short_id(&proof_id));
+                update(&state, &id, WorkflowStage::Failed, message, |w| {
+                    w.error = Some(error.message);
+                    w.error_code = Some(error.code);
+                }).await;
+                return;
+            }
+        }
+
+        update(
+            &state,
+            &id,
+            WorkflowStage::Verifying,
+            format!("Workflow {}: proof {} generated, verifying", short_id(&id), short_id(&proof_id)),
+            |_| {},
+        ).await;
+        let (is_valid, error, error_code) = match verify_proof_async(state.clone(), proof_id.clone(), Some(session_id)).await {
+            Ok(verification) => (verification.is_valid, verification.error, verification.error_code),
+            Err(error) => (false, Some(error.message), Some(error.code)),
+        };
+        let message = format!(
+            "Workflow {} complete: proof {} generated and {}",
+            short_id(&id),
+            short_id(&proof_id),
+            if is_valid { "verified as valid" } else { "failed verification" }
+        );
+        update(&state, &id, WorkflowStage::Complete, message, |w| {
+            w.is_valid = Some(is_valid);
+            w.error = error;
+            w.error_code = error_code;
+        }).await;
+    }.in_current_span()));
+
+    Ok(workflow_id)
+}
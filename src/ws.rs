@@ -0,0 +1,535 @@
+use axum::{
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Extension,
+};
+use flate2::{write::GzEncoder, Compression};
+use futures_util::{stream::SplitStream, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    time::Duration,
+};
+use tokio::sync::{mpsc, watch};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamMap,
+};
+use tracing::{info, info_span, warn, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthMessage, Identity},
+    catalog, chat_bridge,
+    errors::{AppError, ErrorCode},
+    events::{EventHub, Topic},
+    history,
+    i18n::Language,
+    origin::{self, Channel, Origin},
+    process_nl_command,
+    protocol::{WsEvent, WsMessage},
+    request_id, resolve_proposal,
+    rpc, AppState, ChatMessage, ChatSession,
+};
+
+// How long an unauthenticated socket may wait before sending its token
+const AUTH_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Messages at least this long are gzipped for clients that ask for it
+const COMPRESS_MIN_BYTES: usize = 1024;
+
+// Heartbeat settings, from WS_PING_INTERVAL_SECS and WS_IDLE_TIMEOUT_SECS
+#[derive(Clone, Copy)]
+pub struct WsConfig {
+    ping_interval: Duration,
+    idle_timeout: Duration,
+}
+
+impl WsConfig {
+    pub fn from_env() -> Self {
+        let secs = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        WsConfig {
+            ping_interval: Duration::from_secs(secs("WS_PING_INTERVAL_SECS", 30)),
+            idle_timeout: Duration::from_secs(secs("WS_IDLE_TIMEOUT_SECS", 90)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct WsParams {
+    // Opt in to global events (e.g. proofs started through the REST API)
+    #[serde(default)]
+    global: bool,
+    // API key or JWT; may instead be sent as the first message
+    token: Option<String>,
+    // Resume an earlier session after a reconnect, or pick a session ID
+    session_id: Option<String>,
+    // Replay buffered events after this sequence number
+    since: Option<u64>,
+    // Language for server messages (e.g. "es"); defaults to Accept-Language
+    lang: Option<String>,
+    // `gzip`: messages of COMPRESS_MIN_BYTES or more arrive as binary frames
+    // holding the gzipped JSON; shorter ones stay text. The axum WebSocket
+    // has no permessage-deflate, so proof lists and public outputs are
+    // compressed here instead.
+    compress: Option<WsCompression>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum WsCompression {
+    Gzip,
+}
+
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<WsParams>,
+    State(state): State<AppState>,
+    client: Option<Extension<Origin>>,
+    headers: HeaderMap,
+) -> Response {
+    if params.session_id.as_deref().is_some_and(|id| !valid_session_id(id)) {
+        return (StatusCode::BAD_REQUEST, "Invalid session_id").into_response();
+    }
+
+    // Resolve the identity up front when auth is off or the token is in the URL
+    let identity = if !state.auth.is_enabled() {
+        Some(Identity::anonymous())
+    } else if let Some(token) = &params.token {
+        match state.auth.authenticate(token).await {
+            Some(identity) => Some(identity),
+            None => {
+                warn!("Rejected WebSocket connection with invalid token");
+                return (StatusCode::UNAUTHORIZED, "Invalid API token").into_response();
+            }
+        }
+    } else {
+        None
+    };
+
+    let language = params.lang.as_deref()
+        .and_then(Language::parse)
+        .or_else(|| {
+            headers.get(header::ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(Language::from_accept_language)
+        });
+
+    let client = client.map(|Extension(client)| client);
+    ws.on_upgrade(move |socket| websocket_connection(socket, state, params, identity, client, language))
+        .into_response()
+}
+
+// Client-chosen session IDs: 8-64 letters, digits, '-' or '_', and not
+// one of the chat bridge's
+fn valid_session_id(id: &str) -> bool {
+    (8..=64).contains(&id.len())
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !chat_bridge::is_bridge_session(id)
+}
+
+// Wait for a `{"token": "..."}` handshake as the first message
+async fn authenticate_handshake(
+    state: &AppState,
+    receiver: &mut SplitStream<WebSocket>,
+) -> Option<Identity> {
+    let first = tokio::time::timeout(AUTH_HANDSHAKE_TIMEOUT, receiver.next()).await.ok()??;
+    let Ok(Message::Text(text)) = first else {
+        return None;
+    };
+    let auth_msg = serde_json::from_str::<AuthMessage>(&text).ok()?;
+    state.auth.authenticate(&auth_msg.token).await
+}
+
+// `{"proposal_id": "...", "approve": true}` answer to a proof proposal
+#[derive(Deserialize)]
+struct ProposalReply {
+    proposal_id: String,
+    approve: bool,
+}
+
+// Decide whether a broadcast message is meant for this socket
+fn should_deliver(msg: &WsMessage, session_id: &str, global: bool) -> bool {
+    match &msg.session_id {
+        Some(target) => target == session_id,
+        None => global,
+    }
+}
+
+// `{"subscribe": {...}}` / `{"unsubscribe": {...}}` control messages
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ControlMessage {
+    Subscribe(SubscriptionRequest),
+    Unsubscribe(SubscriptionRequest),
+}
+
+#[derive(Deserialize)]
+struct SubscriptionRequest {
+    #[serde(default)]
+    topics: Vec<Topic>,
+    proof_id: Option<String>,
+}
+
+// What a connection wants to receive. `topics: None` means every topic;
+// a non-empty `proof_ids` narrows proof/verification events to those proofs.
+#[derive(Serialize, Clone, Default)]
+struct Subscription {
+    topics: Option<HashSet<Topic>>,
+    proof_ids: HashSet<String>,
+}
+
+impl Subscription {
+    fn wants_topic(&self, topic: Topic) -> bool {
+        self.topics.as_ref().is_none_or(|topics| topics.contains(&topic))
+    }
+
+    fn matches(&self, msg: &WsMessage) -> bool {
+        let topic = Topic::of(msg);
+        if !self.wants_topic(topic) {
+            return false;
+        }
+        if topic == Topic::Chat || self.proof_ids.is_empty() {
+            return true;
+        }
+        msg.data.as_ref()
+            .and_then(WsEvent::proof_id)
+            .is_some_and(|id| self.proof_ids.contains(id))
+    }
+
+    fn apply(&mut self, control: ControlMessage) {
+        match control {
+            ControlMessage::Subscribe(req) => {
+                // The first explicit topic subscription narrows the default of "everything"
+                if !req.topics.is_empty() {
+                    self.topics.get_or_insert_with(HashSet::new).extend(req.topics);
+                }
+                self.proof_ids.extend(req.proof_id);
+            }
+            ControlMessage::Unsubscribe(req) => {
+                let topics = self.topics.get_or_insert_with(|| Topic::ALL.into_iter().collect());
+                for topic in &req.topics {
+                    topics.remove(topic);
+                }
+                if let Some(id) = req.proof_id {
+                    self.proof_ids.remove(&id);
+                }
+            }
+        }
+    }
+}
+
+// Keep one channel stream per subscribed topic
+fn sync_topic_streams(
+    streams: &mut StreamMap<Topic, BroadcastStream<WsMessage>>,
+    events: &EventHub,
+    subscription: &Subscription,
+) {
+    for topic in Topic::ALL {
+        if subscription.wants_topic(topic) {
+            if !streams.contains_key(&topic) {
+                streams.insert(topic, BroadcastStream::new(events.subscribe(topic)));
+            }
+        } else {
+            streams.remove(&topic);
+        }
+    }
+}
+
+fn to_frame<T: Serialize>(msg: &T) -> Message {
+    Message::Text(serde_json::to_string(msg).unwrap())
+}
+
+// A frame in the encoding the client asked for
+fn encode<T: Serialize>(msg: &T, compress: Option<WsCompression>) -> Message {
+    let text = serde_json::to_string(msg).unwrap();
+    if compress != Some(WsCompression::Gzip) || text.len() < COMPRESS_MIN_BYTES {
+        return Message::Text(text);
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    match encoder.write_all(text.as_bytes()).and_then(|()| encoder.finish()) {
+        Ok(gzipped) => Message::Binary(gzipped),
+        Err(_) => Message::Text(text),
+    }
+}
+
+async fn websocket_connection(
+    socket: WebSocket,
+    state: AppState,
+    params: WsParams,
+    identity: Option<Identity>,
+    client: Option<Origin>,
+    language: Option<Language>,
+) {
+    let (mut sender, mut receiver) = socket.split();
+
+    let identity = match identity {
+        Some(identity) => identity,
+        None => match authenticate_handshake(&state, &mut receiver).await {
+            Some(identity) => identity,
+            None => {
+                warn!("Closing unauthenticated WebSocket connection");
+                let rejection = WsMessage::event(
+                    "Authentication required",
+                    WsEvent::error(AppError::new(ErrorCode::Unauthorized, "unauthorized")),
+                );
+                sender.send(to_frame(&rejection)).await.ok();
+                sender.send(Message::Close(None)).await.ok();
+                return;
+            }
+        },
+    };
+
+    // Proofs asked for over this connection record who asked and from where
+    let origin = Origin::new(Channel::Chat).identity(&identity).client(client.as_ref());
+
+    // Every connection gets its own session so replies stay private;
+    // reconnecting clients may resume theirs to keep their conversation and
+    // receive replayed events
+    let session_id = params.session_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    {
+        let mut sessions = state.session_store.lock().await;
+        // Another client's session can only be taken over once nobody is
+        // connected to it and none of its events are left to replay
+        let owned_by_other = sessions.get(&session_id).is_some_and(|session| {
+            session.subject != identity.subject && (session.connections > 0 || state.events.has_session(&session_id))
+        });
+        match sessions.get_mut(&session_id) {
+            _ if owned_by_other => {
+                drop(sessions);
+                warn!("{} tried to join session {} owned by another client", identity.subject, session_id);
+                let rejection = WsMessage::event(
+                    "Session belongs to another client",
+                    WsEvent::error(AppError::new(ErrorCode::SessionForbidden, "session_forbidden")),
+                );
+                sender.send(to_frame(&rejection)).await.ok();
+                sender.send(Message::Close(None)).await.ok();
+                return;
+            }
+            Some(session) if session.subject == identity.subject => {
+                session.connections += 1;
+                session.role = identity.role;
+                if let Some(language) = language {
+                    session.language = language;
+                }
+            }
+            _ => {
+                sessions.insert(session_id.clone(), ChatSession {
+                    llm_session_id: history::conversation_id(&identity.subject, &session_id),
+                    subject: identity.subject.clone(),
+                    role: identity.role,
+                    connections: 1,
+                    language: language.unwrap_or_default(),
+                });
+            }
+        }
+    }
+    info!(
+        "WebSocket session {} connected as {} ({}, global events: {})",
+        session_id, identity.subject, identity.role.name(), params.global
+    );
+
+    // Subscribe to every topic channel until the client narrows it down
+    let (subscription_tx, mut subscription_rx) = watch::channel(Subscription::default());
+    let mut streams = StreamMap::new();
+    sync_topic_streams(&mut streams, &state.events, &subscription_rx.borrow());
+
+    // Send welcome message
+    let welcome = WsMessage::event(
+        format!(
+            "Connected to zkEngine Agent Kit! Available proofs: {}. Type 'help' for arguments and examples.",
+            catalog::names()
+        ),
+        WsEvent::Welcome { session_id: session_id.clone(), identity: identity.clone() },
+    );
+    let compress = params.compress;
+    sender.send(encode(&welcome, compress)).await.ok();
+
+    // Replay events missed while disconnected. We subscribed first, so
+    // anything published meanwhile is deduplicated by sequence number below.
+    let mut last_seq = 0;
+    if let Some(since) = params.since {
+        let missed: Vec<WsMessage> = state.events.since(since)
+            .into_iter()
+            .filter(|msg| should_deliver(msg, &session_id, params.global))
+            .filter(|msg| subscription_rx.borrow().matches(msg))
+            .collect();
+        info!("Replaying {} events since seq {} to session {}", missed.len(), since, session_id);
+        for msg in missed {
+            last_seq = msg.seq.unwrap_or(last_seq);
+            sender.send(encode(&msg, compress)).await.ok();
+        }
+    }
+
+    // Replies meant only for this socket, bypassing topic subscriptions
+    let (direct_tx, mut direct_rx) = mpsc::channel::<Message>(64);
+
+    // Spawn task to forward subscribed events addressed to this session
+    // and keep the connection alive with periodic pings
+    let send_session_id = session_id.clone();
+    let ws_config = state.ws_config;
+    let events = state.events.clone();
+    let mut send_task = tokio::spawn(async move {
+        let mut ping = tokio::time::interval(ws_config.ping_interval);
+        ping.tick().await;
+        // Highest sequence number delivered per topic. Each topic channel is
+        // ordered by seq, so anything at or below it is a duplicate.
+        let mut delivered: HashMap<Topic, u64> = Topic::ALL.into_iter().map(|t| (t, last_seq)).collect();
+        loop {
+            tokio::select! {
+                Some((topic, msg)) = streams.next() => {
+                    let batch = match msg {
+                        Ok(msg) => vec![msg],
+                        Err(BroadcastStreamRecvError::Lagged(dropped)) => {
+                            // Too slow to keep up: tell the client and fill the gap from the replay buffer
+                            let after = delivered[&topic];
+                            let missed: Vec<WsMessage> = events.since(after)
+                                .into_iter()
+                                .filter(|m| Topic::of(m) == topic)
+                                .collect();
+                            let complete = events.oldest_seq().is_none_or(|oldest| oldest <= after + 1);
+                            warn!(
+                                "WebSocket session {} lagged by {} {:?} events (resyncing {}, complete: {})",
+                                send_session_id, dropped, topic, missed.len(), complete
+                            );
+                            let notice = WsMessage::event(
+                                format!("Connection fell behind; {} events were dropped", dropped),
+                                WsEvent::Lagged { topic, dropped, resync_from: after, complete },
+                            );
+                            if sender.send(encode(&notice, compress)).await.is_err() {
+                                break;
+                            }
+                            missed
+                        }
+                    };
+                    let mut closed = false;
+                    for msg in batch {
+                        let seq = msg.seq.unwrap_or_default();
+                        if seq <= delivered[&topic]
+                            || !should_deliver(&msg, &send_session_id, params.global)
+                            || !subscription_rx.borrow().matches(&msg) {
+                            continue;
+                        }
+                        delivered.insert(topic, seq);
+                        if sender.send(encode(&msg, compress)).await.is_err() {
+                            closed = true;
+                            break;
+                        }
+                    }
+                    if closed {
+                        break;
+                    }
+                }
+                changed = subscription_rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    sync_topic_streams(&mut streams, &events, &subscription_rx.borrow_and_update());
+                }
+                Some(frame) = direct_rx.recv() => {
+                    if sender.send(frame).await.is_err() {
+                        break;
+                    }
+                }
+                _ = ping.tick() => {
+                    if sender.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    loop {
+        // Any inbound frame (including pongs) counts as activity
+        let next = tokio::select! {
+            next = tokio::time::timeout(ws_config.idle_timeout, receiver.next()) => next,
+            _ = &mut send_task => {
+                info!("WebSocket session {} writer closed", session_id);
+                break;
+            }
+        };
+        let msg = match next {
+            Err(_) => {
+                warn!("WebSocket session {} idle for {:?}, dropping", session_id, ws_config.idle_timeout);
+                break;
+            }
+            Ok(None) | Ok(Some(Err(_))) => break,
+            Ok(Some(Ok(msg))) => msg,
+        };
+        match msg {
+            Message::Text(text) => {
+                let request_id = request_id::generate();
+                let span = info_span!("ws.message", session_id = %session_id, request_id = %request_id);
+                request_id::scope(request_id, origin::scope(origin.clone(), async {
+                    let rpc_request = serde_json::from_str::<serde_json::Value>(&text)
+                        .ok()
+                        .filter(rpc::is_rpc);
+                    if let Some(request) = rpc_request {
+                        // JSON-RPC responses go straight back to the caller
+                        let rpc_origin = Origin { channel: Channel::JsonRpc, ..origin.clone() };
+                        if let Some(response) = origin::scope(rpc_origin, rpc::handle(&state, &session_id, request)).await {
+                            direct_tx.send(encode(&response, compress)).await.ok();
+                        }
+                    } else if let Ok(control) = serde_json::from_str::<ControlMessage>(&text) {
+                        subscription_tx.send_modify(|subscription| subscription.apply(control));
+                        let ack = WsMessage::event(
+                            String::new(),
+                            WsEvent::Subscription { subscription: serde_json::json!(*subscription_tx.borrow()) },
+                        );
+                        direct_tx.send(encode(&ack, compress)).await.ok();
+                    } else if let Ok(reply) = serde_json::from_str::<ProposalReply>(&text) {
+                        let response = resolve_proposal(&state, &session_id, Some(&reply.proposal_id), reply.approve).await;
+                        if !response.message.is_empty() || response.data.is_some() {
+                            state.publish(WsMessage::new(response.message, response.data).for_session(Some(session_id.clone())));
+                        }
+                    } else if let Ok(chat_msg) = serde_json::from_str::<ChatMessage>(&text) {
+                        info!("[{}] session {}: {}", identity.subject, session_id, chat_msg.message);
+                        if let Some(language) = chat_msg.language.as_deref().and_then(Language::parse) {
+                            if let Some(session) = state.session_store.lock().await.get_mut(&session_id) {
+                                session.language = language;
+                            }
+                        }
+                        let conversation_id = history::conversation_id(&identity.subject, &session_id);
+                        state.history.begin_turn(&conversation_id, &chat_msg.message).await;
+                        let response = process_nl_command(&state, &session_id, &chat_msg.message).await;
+                        if !response.message.is_empty() {
+                            state.history.record_response(&conversation_id, &response.message).await;
+                        }
+                        // Only send a message if there's content
+                        if !response.message.is_empty() || response.data.is_some() {
+                            let ws_msg = WsMessage::new(response.message, response.data)
+                                .for_session(Some(session_id.clone()));
+                            // Reply to the originating session only
+                            state.publish(ws_msg);
+                        }
+                    }
+                }).instrument(span))
+                .await
+            }
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+
+    // Aborting the writer drops its broadcast receiver
+    send_task.abort();
+    // A session nobody is connected to is kept while events for it can be
+    // replayed, so that only its owner can resume it and receive them
+    {
+        let mut sessions = state.session_store.lock().await;
+        if let Some(session) = sessions.get_mut(&session_id) {
+            session.connections -= 1;
+        }
+        sessions.retain(|id, session| session.connections > 0 || state.events.has_session(id));
+    }
+    info!("WebSocket session {} ({}) disconnected", session_id, identity.subject);
+}